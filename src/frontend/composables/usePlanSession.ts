@@ -46,7 +46,9 @@ export function usePlanSession({ workspace, adapter = tauriPlanSessionAdapter }:
   const completed = computed(() => snapshot.value?.summary.completed ?? 0)
   const total = computed(() => snapshot.value?.summary.total ?? 0)
   const allCompleted = computed(() => snapshot.value?.summary.all_completed ?? false)
-  const progressPercent = computed(() => total.value === 0 ? 0 : Math.round((completed.value / total.value) * 100))
+  const cancelled = computed(() => snapshot.value?.summary.cancelled ?? 0)
+  // 中文说明：已取消的步骤不再推进，进度按已完成与已取消合计。
+  const progressPercent = computed(() => total.value === 0 ? 0 : Math.round(((completed.value + cancelled.value) / total.value) * 100))
   const realtimeError = computed(() => eventError.value || watchError.value)
 
   function isCurrentWatch(generation: number): boolean {
@@ -194,6 +196,7 @@ export function usePlanSession({ workspace, adapter = tauriPlanSessionAdapter }:
 
   return {
     allCompleted,
+    cancelled,
     completed,
     eventError,
     items,
@@ -142,7 +142,9 @@ export const MCP_TOOLS_CONFIG: ToolPromptConfig[] = [
       ],
       howToUse: [
         '`set` 完整替换计划；`update` 只更新单项状态；`get` 查询；`clear` 清空',
-        '状态按 `pending -> in_progress -> completed` 单向推进，同一时间最多一个进行中步骤',
+        '状态按 `pending -> in_progress -> completed` 单向推进，同一时间最多一个进行中步骤（其上级项除外）',
+        '用 `parent_id` 拆分子步骤、`depends_on` 声明依赖；受阻时 `update` 为 `blocked` 并附 `reason`，放弃的步骤标记 `cancelled`',
+        '多个实例并行时，用 `plan_id` 维护各自的计划并传入 `agent_label`；修改时带上读到的 `revision`，版本冲突时基于返回的最新计划重试；`list` 查看全部计划，完成后 `archive` 归档',
       ],
     },
     ui: {
@@ -1,21 +1,48 @@
-export type PlanStatus = 'pending' | 'in_progress' | 'completed'
+export type PlanStatus = 'pending' | 'in_progress' | 'completed' | 'blocked' | 'cancelled'
 
 export interface PlanItem {
   id: string
   text: string
   status: PlanStatus
+  parent_id?: string
+  depends_on?: string[]
+  blocked_reason?: string
+}
+
+export interface PlanSubtreeSummary {
+  id: string
+  completed: number
+  total: number
+  all_completed: boolean
+  blocked: number
+  cancelled: number
 }
 
 export interface PlanSummary {
   completed: number
   total: number
   all_completed: boolean
+  blocked?: number
+  cancelled?: number
+  subtrees?: PlanSubtreeSummary[]
+}
+
+export interface PlanOverview {
+  plan_id: string
+  owner?: string | null
+  revision: number
+  archived_at?: string | null
+  summary: PlanSummary
 }
 
 export interface PlanSnapshot {
-  action: 'set' | 'update' | 'get' | 'clear'
+  action: 'set' | 'update' | 'get' | 'clear' | 'list' | 'archive'
   workspace: string
+  plan_id?: string
+  owner?: string | null
+  revision?: number
   changed: boolean
   items: PlanItem[]
   summary: PlanSummary
+  plans?: PlanOverview[]
 }
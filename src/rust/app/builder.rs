@@ -88,6 +88,13 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             crate::mcp::tools::acemcp::commands::debug_acemcp_search,
             crate::mcp::tools::acemcp::commands::get_sou_local_index_status,
             crate::mcp::tools::acemcp::commands::rebuild_sou_local_index,
+            crate::mcp::tools::acemcp::commands::explain_index_path,
+            crate::mcp::tools::acemcp::commands::export_index_snapshot,
+            crate::mcp::tools::acemcp::commands::import_index_snapshot,
+            crate::mcp::tools::acemcp::commands::acknowledge_secret_scan,
+            crate::mcp::tools::acemcp::commands::get_secret_audit_log,
+            crate::mcp::tools::acemcp::commands::get_acemcp_index_history,
+            crate::mcp::tools::acemcp::commands::collect_acemcp_index_garbage,
             crate::mcp::tools::acemcp::commands::execute_acemcp_tool,
             crate::mcp::tools::acemcp::commands::get_acemcp_index_status,
             crate::mcp::tools::acemcp::commands::get_all_acemcp_index_status,
@@ -175,6 +182,7 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             crate::network::commands::set_proxy_config,
             crate::network::commands::test_proxy_connection,
             crate::network::commands::detect_available_proxy,
+            crate::network::commands::get_resilience_diagnostics,
             // 图标工坊命令
             crate::mcp::tools::icon::commands::search_icons,
             crate::mcp::tools::icon::commands::get_icon_content,
@@ -1,10 +1,12 @@
 use crate::app::builder::run_tauri_app;
 use crate::config::load_standalone_config;
 use crate::log_important;
+use crate::mcp::tools::sou::eval;
 use crate::mcp::types::PopupRequest;
 use crate::mcp::utils::{generate_request_id, normalize_zhi_choices};
 use crate::telegram::handle_telegram_only_mcp_request;
 use anyhow::Result;
+use std::path::Path;
 
 /// 处理命令行参数
 pub fn handle_cli_args() -> Result<()> {
@@ -42,6 +44,9 @@ pub fn handle_cli_args() -> Result<()> {
                 // CLI 模式：解析参数并启动 GUI 交互
                 crate::log_important!(info, "进入CLI交互模式（--cli）");
                 handle_cli_mode(&args[2..])?;
+            } else if args[1] == "--sou-eval" {
+                crate::log_important!(info, "进入 sou 评测模式（--sou-eval）");
+                handle_sou_eval(&args[2..])?;
             } else if args[1] == "--icon-request" {
                 // 图标弹窗模式：从临时请求文件读取参数并启动 GUI（对齐 --mcp-request 协议）
                 if args.len() >= 3 {
@@ -249,6 +254,74 @@ fn handle_mcp_request(request_file: &str) -> Result<()> {
     Ok(())
 }
 
+/// 处理 sou 评测模式
+///
+/// 对指定项目逐个后端运行 JSONL 用例，输出 recall@k、MRR 与延迟
+fn handle_sou_eval(args: &[String]) -> Result<()> {
+    let mut project_root: Option<String> = None;
+    let mut cases_file: Option<String> = None;
+    let mut backends = "local".to_string();
+    let mut k = eval::DEFAULT_EVAL_K;
+    let mut json_output = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--project-root" if i + 1 < args.len() => {
+                project_root = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--cases" if i + 1 < args.len() => {
+                cases_file = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--backend" if i + 1 < args.len() => {
+                backends = args[i + 1].clone();
+                i += 2;
+            }
+            "--k" if i + 1 < args.len() => {
+                k = match args[i + 1].parse::<usize>() {
+                    Ok(value) if value > 0 => value,
+                    _ => {
+                        eprintln!("无效的 --k: {}", args[i + 1]);
+                        std::process::exit(2);
+                    }
+                };
+                i += 2;
+            }
+            "--json" => {
+                json_output = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("无效的命令行参数: {}", args[i]);
+                print_help();
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let (Some(project_root), Some(cases_file)) = (project_root, cases_file) else {
+        eprintln!("缺少必填参数: --project-root 与 --cases");
+        print_help();
+        std::process::exit(2);
+    };
+    let options = eval::EvalOptions {
+        project_root,
+        cases: eval::load_cases(Path::new(&cases_file))?,
+        backends: eval::parse_backends(&backends)?,
+        k,
+    };
+
+    let report = tokio::runtime::Runtime::new()?.block_on(eval::run(options));
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", eval::format_report(&report));
+    }
+    Ok(())
+}
+
 /// 处理图标弹窗请求
 ///
 /// 从临时请求文件读取 TuRequest 参数（对齐 --mcp-request 的临时文件协议），
@@ -300,6 +373,7 @@ fn print_help() {
     println!("  等一下 --mcp-request <文件>          处理 MCP 请求");
     println!("  等一下 --cli [选项]                  命令行独立调用 zhi 交互");
     println!("  等一下 --icon-request <文件>         处理图标弹窗请求（内部协议）");
+    println!("  等一下 --sou-eval [选项]             评测 sou 各后端检索质量");
     println!("  等一下 --help                       显示此帮助信息");
     println!("  等一下 --version                    显示版本信息");
     println!();
@@ -313,6 +387,15 @@ fn print_help() {
     println!("  --uiux-intent <值>                   none/beautify/page_refactor/uiux_search");
     println!("  --uiux-context-policy <值>           auto/force/forbid");
     println!("  --uiux-reason <内容>                  UI/UX 上下文追加原因");
+    println!();
+    println!("sou 评测选项:");
+    println!("  --project-root <路径>                必填，被评测的项目根目录");
+    println!(
+        "  --cases <文件>                       必填，JSONL 用例（每行 {{query, expected_paths}}）"
+    );
+    println!("  --backend <后端1,后端2>               评测后端（默认 local）");
+    println!("  --k <数量>                           recall@k / MRR 的截断位置（默认 10）");
+    println!("  --json                               以 JSON 输出报告");
 }
 
 /// 显示版本信息
@@ -0,0 +1,63 @@
+// 自建索引服务入口点（「三术索引」）
+// 用法：三术索引 --addr 127.0.0.1:8765 --db ./sanshu-index.db --token <TOKEN>
+//       [--embedding-url https://.../v1/embeddings --embedding-model <MODEL>]
+// embeddings 接口密钥通过环境变量 SANSHU_INDEX_EMBEDDING_KEY 传入，避免出现在进程参数中。
+use std::path::PathBuf;
+
+use sanshu::{
+    log_important,
+    mcp::tools::acemcp::index_server::{EmbeddingConfig, IndexServer, IndexServerOptions},
+    utils::auto_init_logger,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    auto_init_logger()?;
+
+    let mut addr = "127.0.0.1:8765".to_string();
+    let mut db_path = PathBuf::from("sanshu-index.db");
+    let mut token = std::env::var("SANSHU_INDEX_TOKEN").ok();
+    let mut embedding_url = None;
+    let mut embedding_model = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("参数 {} 缺少取值", name));
+        match arg.as_str() {
+            "--addr" => addr = value("--addr")?,
+            "--db" => db_path = PathBuf::from(value("--db")?),
+            "--token" => token = Some(value("--token")?),
+            "--embedding-url" => embedding_url = Some(value("--embedding-url")?),
+            "--embedding-model" => embedding_model = Some(value("--embedding-model")?),
+            "--help" | "-h" => {
+                println!(
+                    "用法: 三术索引 [--addr HOST:PORT] [--db PATH] [--token TOKEN] \
+                     [--embedding-url URL --embedding-model MODEL]"
+                );
+                return Ok(());
+            }
+            other => return Err(format!("未知参数: {}", other).into()),
+        }
+    }
+
+    let embedding = match (embedding_url, embedding_model) {
+        (Some(url), Some(model)) => Some(EmbeddingConfig {
+            url,
+            model,
+            api_key: std::env::var("SANSHU_INDEX_EMBEDDING_KEY").ok(),
+        }),
+        (None, None) => None,
+        _ => return Err("--embedding-url 与 --embedding-model 需同时提供".into()),
+    };
+
+    let server = IndexServer::bind(
+        &addr,
+        IndexServerOptions {
+            db_path,
+            token,
+            embedding,
+        },
+    )?;
+    log_important!(info, "自建索引服务已启动: {}", addr);
+    server.serve();
+    Ok(())
+}
@@ -143,12 +143,32 @@ pub struct McpConfig {
     pub acemcp_proxy_type: Option<String>,     // 代理类型: "http" | "https" | "socks5"
     pub acemcp_proxy_username: Option<String>, // 代理用户名（可选）
     pub acemcp_proxy_password: Option<String>, // 代理密码（可选）
+    #[serde(default)]
+    pub acemcp_remote_backend: Option<String>, // 远端索引后端: "ace"（默认）| "self_hosted"
+    #[serde(default)]
+    pub acemcp_index_concurrency: Option<u32>, // 所有进程合计的并发索引任务上限（默认 2）
+    #[serde(default)]
+    pub acemcp_upload_bandwidth_kbps: Option<u32>, // 每个进程的索引上传带宽上限 KB/s（为空不限速）
+    #[serde(default)]
+    pub acemcp_job_history_retention_days: Option<u32>, // 索引任务历史保留天数（默认 30）
+    // 上传前密钥扫描（ACE 上传与 fast_context 读文件共用）
+    #[serde(default)]
+    pub secret_scan_enabled: Option<bool>, // 是否启用（默认启用）
+    #[serde(default)]
+    pub secret_scan_policy: Option<String>, // "skip" | "redact"（默认）| "block"
+    #[serde(default)]
+    pub secret_scan_custom_patterns: Option<Vec<String>>, // 自定义密钥正则
+    #[serde(default)]
+    pub secret_scan_acknowledged_roots: Option<Vec<String>>, // 已确认首次上传密钥报告的项目根
     // Sou 多后端配置
     pub sou_default_backend: Option<String>, // "auto" | "ace" | "fast_context" | "local" | "both"
     pub sou_auto_order: Option<Vec<String>>, // auto 模式下的后端优先级
     pub sou_include_backend_headers: Option<bool>, // 是否在结果中标注后端来源
     pub sou_include_failed_backend_errors: Option<bool>, // 部分成功时是否附加失败后端诊断
     pub sou_local_enabled: Option<bool>,     // 是否启用 SQLite FTS5 / rg 本地兜底
+    /// 跨仓库检索的命名工作区组；sou 通过 workspace_group 参数按组名引用。
+    #[serde(default)]
+    pub sou_workspace_groups: Option<Vec<SouWorkspaceGroup>>,
     // Fast Context 配置
     pub fast_context_command: Option<String>, // 兼容旧配置：Rust 原生 fast-context 已不再使用
     pub fast_context_script_path: Option<String>, // 兼容旧配置：Rust 原生 fast-context 已不再使用
@@ -187,6 +207,37 @@ pub struct McpConfig {
     // Tavily AI 搜索配置
     /// Tavily API 密钥（必填，免费计划每月 1000 信用点）
     pub tavily_api_key: Option<String>,
+
+    // 记忆（ji）分层作用域
+    /// 团队共享记忆库，回忆时按列表顺序合并（先列出的优先）
+    #[serde(default)]
+    pub memory_team_stores: Option<Vec<MemoryTeamStore>>,
+}
+
+/// 团队共享记忆库：`path` 目录下直接存放 memories.json（如团队共用仓库中的目录）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MemoryTeamStore {
+    pub name: String,
+    pub path: String,
+}
+
+/// sou 工作区组：一组同时检索的项目根目录
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SouWorkspaceGroup {
+    pub name: String,
+    #[serde(default)]
+    pub roots: Vec<SouWorkspaceRoot>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SouWorkspaceRoot {
+    pub path: String,
+    /// 结果中标注的仓库名，缺省使用目录名
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 仅作用于该仓库的额外排除路径
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
 }
 
 // 自定义prompt结构
@@ -387,6 +438,14 @@ pub fn default_mcp_config() -> McpConfig {
         acemcp_proxy_type: None,
         acemcp_proxy_username: None,
         acemcp_proxy_password: None,
+        acemcp_remote_backend: None,        // 默认使用 ACE 协议
+        acemcp_index_concurrency: Some(2),  // 默认最多 2 个项目同时索引
+        acemcp_upload_bandwidth_kbps: None, // 默认不限速
+        acemcp_job_history_retention_days: Some(30),
+        secret_scan_enabled: Some(true),
+        secret_scan_policy: Some("redact".to_string()),
+        secret_scan_custom_patterns: None,
+        secret_scan_acknowledged_roots: None,
         // Sou 多后端默认配置
         sou_default_backend: Some("auto".to_string()),
         sou_auto_order: Some(vec![
@@ -397,6 +456,7 @@ pub fn default_mcp_config() -> McpConfig {
         sou_include_backend_headers: Some(true),
         sou_include_failed_backend_errors: Some(true),
         sou_local_enabled: Some(true),
+        sou_workspace_groups: None, // 默认不配置跨仓库工作区组
         // Fast Context 默认配置：协议与本地命令执行已迁移为 Rust 原生实现
         fast_context_command: Some("node".to_string()),
         fast_context_script_path: None,
@@ -429,6 +489,7 @@ pub fn default_mcp_config() -> McpConfig {
         icon_cache_expiry_minutes: None, // 默认 30 分钟
         // Tavily AI 搜索配置
         tavily_api_key: None, // 用户需配置 API Key
+        memory_team_stores: None,
     }
 }
 
@@ -293,6 +293,8 @@ pub struct MemoryEntryDto {
     pub content: String,
     pub category: String,
     pub created_at: String,
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 /// 记忆配置 DTO（用于前端交互）
@@ -349,6 +351,7 @@ pub async fn get_memory_list(project_path: String) -> Result<Vec<MemoryEntryDto>
             content: m.content.clone(),
             category: m.category.display_name().to_string(),
             created_at: m.created_at.to_rfc3339(),
+            pinned: m.pinned,
         })
         .collect();
 
@@ -422,6 +425,7 @@ pub async fn save_memory_config(
 pub async fn deduplicate_memories(project_path: String) -> Result<DedupResultDto, String> {
     let mut manager =
         MemoryManager::new(&project_path).map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    manager.set_change_context("user", None);
 
     let stats = manager
         .deduplicate_with_stats()
@@ -469,6 +473,7 @@ pub async fn preview_similarity(
 pub async fn delete_memory(project_path: String, memory_id: String) -> Result<String, String> {
     let mut manager =
         MemoryManager::new(&project_path).map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    manager.set_change_context("user", None);
 
     match manager.delete_memory(&memory_id) {
         Ok(Some(content)) => {
@@ -500,6 +505,7 @@ pub async fn apply_memory_cleanup(
 ) -> Result<CleanupApplyResult, String> {
     let mut manager =
         MemoryManager::new(&project_path).map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    manager.set_change_context("user", None);
 
     manager
         .apply_cleanup_plan(request)
@@ -525,6 +531,7 @@ pub async fn restore_memory_backup(
 ) -> Result<RestoreBackupResult, String> {
     let mut manager =
         MemoryManager::new(&project_path).map_err(|e| format!("创建记忆管理器失败: {}", e))?;
+    manager.set_change_context("user", None);
 
     manager
         .restore_backup(&file_name)
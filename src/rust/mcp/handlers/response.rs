@@ -342,6 +342,7 @@ fn build_structured_content(response: &McpResponse) -> Value {
                 "action": "记忆",
                 "category": block.normalized_memory_category().unwrap_or("context"),
                 "content": block.content.trim(),
+                "memory_source": "context_block",
                 "source": {
                     "kind": &block.kind,
                     "id": &block.source_id,
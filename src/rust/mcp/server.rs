@@ -238,7 +238,7 @@ impl ServerHandler for ZhiServer {
                 "properties": {
                     "action": {
                         "type": "string",
-                        "description": "操作类型：记忆(添加) | 回忆(查询) | 整理(去重) | 预览整理(候选预览) | 应用整理(按计划清理) | 备份列表 | 恢复备份 | 导出备份 | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆)"
+                        "description": "操作类型：记忆(添加) | 回忆(按任务查询相关记忆) | 整理(去重) | 预览整理(重复与归档候选) | 应用整理(按计划清理或归档) | 备份列表 | 恢复备份 | 导出备份 | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆) | 固定(固定/取消固定记忆) | 归档(归档/取消归档记忆) | 预览拉取(对比仓库共享记忆) | 拉取共享(导入 .sanshu/memory.jsonl) | 导出共享(写入 .sanshu/memory.jsonl) | 历史(单条记忆的版本记录) | 对比(两个版本的字段差异) | 撤销(恢复单条记忆到指定版本) | 导入规则(从 AGENTS.md 等规则文件导入) | 导出规则(写入 AGENTS.md 等规则文件)"
                     },
                     "project_path": {
                         "type": "string",
@@ -276,7 +276,69 @@ impl ServerHandler for ZhiServer {
                     },
                     "memory_id": {
                         "type": "string",
-                        "description": "记忆ID（删除操作时必需）"
+                        "description": "记忆ID（删除、固定、归档、历史、对比、撤销操作时必需）"
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "本次修改的原因（可选），记入变更日志"
+                    },
+                    "version": {
+                        "type": "integer",
+                        "description": "版本号（对比时为目标版本，默认最新；撤销时为要恢复到的版本，默认上一版本；0 表示第一次变更之前）"
+                    },
+                    "rule_files": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "规则文件（导入规则、导出规则时可选）：项目根目录下的相对路径，导出时也可用格式名 agents | claude | gemini | cursorrules | cursor | copilot | windsurf。导入时不传则读取项目中已存在的规则文件；导出时不传则只写入 AGENTS.md（项目只有 .cursor/rules/ 时为 .cursor/rules/sanshu-memory.mdc），其他文件须显式列出"
+                    },
+                    "from_version": {
+                        "type": "integer",
+                        "description": "对比的起始版本（对比操作时可选，默认目标版本的上一版本）"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "当前任务描述（回忆时建议提供）。按相关度返回记忆，固定记忆总是返回"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "回忆返回的非固定记忆条数上限（默认 20）"
+                    },
+                    "token_budget": {
+                        "type": "integer",
+                        "description": "回忆输出的估算 token 预算（默认 1500）"
+                    },
+                    "pinned": {
+                        "type": "boolean",
+                        "description": "是否固定记忆（记忆、固定操作时可选，固定操作默认 true）"
+                    },
+                    "scope": {
+                        "type": "string",
+                        "description": "记忆作用域：global（全局）| team 或 team:<名称>（团队）| project（项目，默认）| directory（project_path 所在子目录）。回忆时不传则按 子目录 > 项目 > 团队 > 全局 合并全部作用域"
+                    },
+                    "memory_source": {
+                        "type": "string",
+                        "description": "记忆来源（记忆操作时可选）：agent（默认）| user（用户在弹窗中要求记住）| context_block（memory_actions 中的条目，原样传入其 memory_source）| import"
+                    },
+                    "confidence": {
+                        "type": "number",
+                        "description": "记忆置信度 0~1（记忆操作时可选），默认按来源取值"
+                    },
+                    "expires_at": {
+                        "type": "string",
+                        "description": "过期时间（记忆操作时可选），RFC3339 或 YYYY-MM-DD；过期后不再参与回忆，整理预览会提议归档"
+                    },
+                    "archived": {
+                        "type": "boolean",
+                        "description": "是否归档（归档操作时可选，默认 true）；归档的记忆保留但不参与回忆"
+                    },
+                    "unused_days": {
+                        "type": "integer",
+                        "description": "超过该天数未被回忆的记忆提议归档（预览整理时可选，默认 90，0 表示不提议）"
+                    },
+                    "on_conflict": {
+                        "type": "string",
+                        "enum": ["supersede", "keep_both"],
+                        "description": "新记忆与已有记忆矛盾（如「使用 tab 缩进」与「使用 2 个空格缩进」）时的处理（记忆操作时可选）：不传则返回矛盾报告、不写入；supersede 取代并归档旧记忆；keep_both 两条都保留"
                     },
                     "threshold": {
                         "type": "number",
@@ -285,7 +347,7 @@ impl ServerHandler for ZhiServer {
                     "categories": {
                         "type": "array",
                         "items": { "type": "string" },
-                        "description": "清理分类过滤（预览整理时可选）"
+                        "description": "分类过滤（回忆、预览整理时可选）"
                     },
                     "include_cross_category": {
                         "type": "boolean",
@@ -295,6 +357,10 @@ impl ServerHandler for ZhiServer {
                         "type": "object",
                         "description": "应用整理计划（应用整理时必需）"
                     },
+                    "shared_pull": {
+                        "type": "object",
+                        "description": "共享记忆冲突处理（拉取共享时可选）：{\"resolutions\": [{\"id\": \"记忆ID\", \"keep\": \"local|shared\"}]}，未列出的冲突保持本地不变"
+                    },
                     "backup_file": {
                         "type": "string",
                         "description": "备份文件名（恢复备份/导出备份时必需）"
@@ -0,0 +1,162 @@
+// 内容定义分块（Content-Defined Chunking）
+// 以行为单位计算滚动哈希，在哈希命中掩码的位置切分 blob：
+// - 分块边界只取决于附近若干行的内容，文件头部插入/删除行不会让下游分块整体失效
+// - 分块后缀使用内容摘要（`#chunk-<hash8>`）而非序号，避免分块数量变化连带改名
+// - 不超过 max_lines 的文件保持单个 blob、路径不带后缀，与旧版本 blob 名称兼容
+
+use ring::digest::{digest, SHA256};
+
+use super::remote::BlobItem;
+
+/// 滚动哈希窗口（行数）
+const WINDOW_LINES: usize = 16;
+/// 多项式滚动哈希底数
+const ROLLING_BASE: u64 = 0x100000001b3;
+
+/// 按内容定义边界切分文件，分块行数落在 `[max_lines / 4, max_lines]` 区间内。
+pub(crate) fn split_content_defined(path: &str, content: &str, max_lines: usize) -> Vec<BlobItem> {
+    let max_lines = max_lines.max(1);
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    if lines.len() <= max_lines {
+        return vec![BlobItem {
+            path: path.to_string(),
+            content: content.to_string(),
+        }];
+    }
+
+    chunk_ranges(&lines, max_lines)
+        .into_iter()
+        .map(|(start, end)| {
+            let chunk_content = lines[start..end].concat();
+            BlobItem {
+                path: format!("{}#chunk-{}", path, short_digest(&chunk_content)),
+                content: chunk_content,
+            }
+        })
+        .collect()
+}
+
+/// 计算分块的行区间 `[start, end)`。
+fn chunk_ranges(lines: &[&str], max_lines: usize) -> Vec<(usize, usize)> {
+    let min_lines = (max_lines / 4).max(1);
+    // 期望平均分块约为 max_lines / 2：最小长度之后每行以 1/divisor 的概率成为边界
+    let divisor = (max_lines / 2).saturating_sub(min_lines).max(1) as u64;
+    let line_hashes = lines.iter().map(|line| line_hash(line)).collect::<Vec<_>>();
+    let base_pow = ROLLING_BASE.wrapping_pow(WINDOW_LINES as u32);
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut rolling = 0u64;
+    for (index, hash) in line_hashes.iter().enumerate() {
+        rolling = rolling.wrapping_mul(ROLLING_BASE).wrapping_add(*hash);
+        if index >= WINDOW_LINES {
+            rolling =
+                rolling.wrapping_sub(line_hashes[index - WINDOW_LINES].wrapping_mul(base_pow));
+        }
+        let length = index + 1 - start;
+        let at_boundary = length >= min_lines && mix(rolling).is_multiple_of(divisor);
+        if at_boundary || length >= max_lines {
+            ranges.push((start, index + 1));
+            start = index + 1;
+        }
+    }
+    if start < lines.len() {
+        ranges.push((start, lines.len()));
+    }
+    ranges
+}
+
+/// 行哈希忽略行尾差异，CRLF 与 LF 文件得到相同边界
+fn line_hash(line: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in line.trim_end_matches(['\r', '\n']).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// 打散低位，避免多项式哈希低位周期性导致边界聚集
+fn mix(mut value: u64) -> u64 {
+    value ^= value >> 33;
+    value = value.wrapping_mul(0xff51afd7ed558ccd);
+    value ^= value >> 33;
+    value
+}
+
+fn short_digest(content: &str) -> String {
+    hex::encode(&digest(&SHA256, content.as_bytes()).as_ref()[..4])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_file(lines: usize) -> String {
+        (0..lines)
+            .map(|index| {
+                format!(
+                    "let value_{} = compute({}, {});\n",
+                    index,
+                    index * 7,
+                    index % 13
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn small_files_keep_single_unsuffixed_blob() {
+        let blobs = split_content_defined("src/lib.rs", "fn main() {}\n", 800);
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(blobs[0].path, "src/lib.rs");
+    }
+
+    #[test]
+    fn chunks_cover_content_within_length_bounds() {
+        let content = sample_file(2000);
+        let blobs = split_content_defined("src/big.rs", &content, 200);
+        assert!(blobs.len() > 1);
+        assert_eq!(
+            blobs
+                .iter()
+                .map(|blob| blob.content.as_str())
+                .collect::<String>(),
+            content
+        );
+        for blob in &blobs[..blobs.len() - 1] {
+            let lines = blob.content.lines().count();
+            assert!((50..=200).contains(&lines), "chunk lines={}", lines);
+            assert!(blob.path.starts_with("src/big.rs#chunk-"));
+        }
+    }
+
+    #[test]
+    fn inserting_a_line_near_the_top_only_changes_nearby_chunks() {
+        let original = sample_file(3000);
+        let mut edited_lines = original.lines().map(str::to_string).collect::<Vec<_>>();
+        edited_lines.insert(5, "// inserted comment".to_string());
+        let edited = edited_lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+
+        let before = split_content_defined("src/big.rs", &original, 200);
+        let after = split_content_defined("src/big.rs", &edited, 200);
+        let before_paths = before
+            .iter()
+            .map(|blob| blob.path.as_str())
+            .collect::<std::collections::HashSet<_>>();
+        let changed = after
+            .iter()
+            .filter(|blob| !before_paths.contains(blob.path.as_str()))
+            .count();
+        assert!(after.len() > 10);
+        assert!(
+            changed <= 2,
+            "changed {} of {} chunks",
+            changed,
+            after.len()
+        );
+    }
+}
@@ -6,9 +6,9 @@ use std::time::Duration;
 use tauri::{AppHandle, Emitter, State};
 
 use super::types::{
-    DetectedProxy, ProjectFilesStatus, ProjectIndexStatus, ProjectWithNestedStatus,
-    ProjectsIndexStatus, ProxySpeedTestResult, SpeedTestMetric, SpeedTestProgress,
-    SpeedTestStageStatus,
+    DetectedProxy, ProjectFilesStatus, ProjectIndexStatus, ProjectScopeRiskLevel,
+    ProjectWithNestedStatus, ProjectsIndexStatus, ProxySpeedTestResult, SpeedTestMetric,
+    SpeedTestProgress, SpeedTestStageStatus,
 };
 use super::AcemcpTool;
 use crate::config::{save_config, AppState};
@@ -107,6 +107,33 @@ pub struct SaveAcemcpConfigArgs {
     pub proxy_username: Option<String>,
     #[serde(alias = "proxyPassword", alias = "proxy_password")]
     pub proxy_password: Option<String>,
+    /// 远端索引后端（ace / self_hosted）
+    #[serde(alias = "remoteBackend", alias = "remote_backend")]
+    pub remote_backend: Option<String>,
+    /// 跨项目并发索引任务上限
+    #[serde(alias = "indexConcurrency", alias = "index_concurrency")]
+    pub index_concurrency: Option<u32>,
+    /// 索引上传带宽上限（KB/s，0 表示不限速）
+    #[serde(alias = "uploadBandwidthKbps", alias = "upload_bandwidth_kbps")]
+    pub upload_bandwidth_kbps: Option<u32>,
+    /// 索引任务历史保留天数
+    #[serde(
+        alias = "jobHistoryRetentionDays",
+        alias = "job_history_retention_days"
+    )]
+    pub job_history_retention_days: Option<u32>,
+    /// 是否启用上传前密钥扫描
+    #[serde(alias = "secretScanEnabled", alias = "secret_scan_enabled")]
+    pub secret_scan_enabled: Option<bool>,
+    /// 疑似密钥处置策略: "skip" | "redact" | "block"
+    #[serde(alias = "secretScanPolicy", alias = "secret_scan_policy")]
+    pub secret_scan_policy: Option<String>,
+    /// 自定义密钥正则
+    #[serde(
+        alias = "secretScanCustomPatterns",
+        alias = "secret_scan_custom_patterns"
+    )]
+    pub secret_scan_custom_patterns: Option<Vec<String>>,
     /// 是否自动索引嵌套的 Git 子项目
     #[serde(alias = "indexNestedProjects", alias = "index_nested_projects")]
     pub index_nested_projects: Option<bool>,
@@ -129,6 +156,8 @@ pub struct SaveAcemcpConfigArgs {
     pub sou_include_failed_backend_errors: Option<bool>,
     #[serde(alias = "souLocalEnabled", alias = "sou_local_enabled")]
     pub sou_local_enabled: Option<bool>,
+    #[serde(alias = "souWorkspaceGroups", alias = "sou_workspace_groups")]
+    pub sou_workspace_groups: Option<Vec<crate::config::SouWorkspaceGroup>>,
     #[serde(alias = "uiuxKnowledgeBackend", alias = "uiux_knowledge_backend")]
     pub uiux_knowledge_backend: Option<String>,
     #[serde(alias = "fastContextCommand", alias = "fast_context_command")]
@@ -271,6 +300,14 @@ pub async fn save_acemcp_config(
         }
     };
 
+    // 拼写错误的远端后端直接拒绝保存，避免静默回落到 ACE
+    let remote_backend = args
+        .remote_backend
+        .as_deref()
+        .map(|value| super::remote::parse_remote_backend(Some(value)))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
     {
         let mut config = state
             .config
@@ -296,6 +333,43 @@ pub async fn save_acemcp_config(
         config.mcp_config.acemcp_proxy_type = args.proxy_type.clone();
         config.mcp_config.acemcp_proxy_username = args.proxy_username.clone();
         config.mcp_config.acemcp_proxy_password = args.proxy_password.clone();
+        if let Some(v) = remote_backend {
+            config.mcp_config.acemcp_remote_backend = Some(v);
+        }
+        if let Some(v) = args.index_concurrency {
+            config.mcp_config.acemcp_index_concurrency = Some(v.max(1));
+        }
+        if let Some(v) = args.upload_bandwidth_kbps {
+            config.mcp_config.acemcp_upload_bandwidth_kbps = (v > 0).then_some(v);
+        }
+        if let Some(v) = args.job_history_retention_days {
+            config.mcp_config.acemcp_job_history_retention_days = Some(v.max(1));
+        }
+        if let Some(v) = args.secret_scan_enabled {
+            config.mcp_config.secret_scan_enabled = Some(v);
+        }
+        if let Some(v) = args.secret_scan_policy {
+            config.mcp_config.secret_scan_policy = Some(
+                super::secret_scan::SecretPolicy::parse(Some(&v))
+                    .as_str()
+                    .to_string(),
+            );
+        }
+        if let Some(v) = args.secret_scan_custom_patterns {
+            let patterns = v
+                .into_iter()
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect::<Vec<_>>();
+            if let Some(invalid) = patterns
+                .iter()
+                .find(|pattern| regex::Regex::new(pattern).is_err())
+            {
+                return Err(format!("无效的自定义密钥正则: {}", invalid));
+            }
+            config.mcp_config.secret_scan_custom_patterns =
+                (!patterns.is_empty()).then_some(patterns);
+        }
         // 保存嵌套项目索引开关
         // 仅在前端显式传入时才覆盖，避免其他页面保存配置时将用户设置重置为默认值
         if let Some(v) = args.index_nested_projects {
@@ -320,6 +394,9 @@ pub async fn save_acemcp_config(
         if let Some(v) = args.sou_local_enabled {
             config.mcp_config.sou_local_enabled = Some(v);
         }
+        if let Some(groups) = args.sou_workspace_groups.clone() {
+            config.mcp_config.sou_workspace_groups = Some(groups);
+        }
         if let Some(v) = args.uiux_knowledge_backend.as_deref() {
             let normalized = v.trim().to_ascii_lowercase().replace('-', "_");
             if !matches!(normalized.as_str(), "auto" | "fast_context" | "local") {
@@ -1163,6 +1240,14 @@ pub struct AcemcpConfigResponse {
     pub proxy_type: String,
     pub proxy_username: String,
     pub proxy_password: String,
+    pub remote_backend: String,
+    pub index_concurrency: u32,
+    /// 0 表示不限速
+    pub upload_bandwidth_kbps: u32,
+    pub job_history_retention_days: u32,
+    pub secret_scan_enabled: bool,
+    pub secret_scan_policy: String,
+    pub secret_scan_custom_patterns: Vec<String>,
     /// 是否自动索引嵌套的 Git 子项目（默认启用）
     pub index_nested_projects: bool,
     pub sou_default_backend: String,
@@ -1170,6 +1255,7 @@ pub struct AcemcpConfigResponse {
     pub sou_include_backend_headers: bool,
     pub sou_include_failed_backend_errors: bool,
     pub sou_local_enabled: bool,
+    pub sou_workspace_groups: Vec<crate::config::SouWorkspaceGroup>,
     pub uiux_knowledge_backend: String,
     pub fast_context_command: String,
     pub fast_context_script_path: Option<String>,
@@ -1300,6 +1386,29 @@ pub async fn get_acemcp_config(state: State<'_, AppState>) -> Result<AcemcpConfi
             .acemcp_proxy_password
             .clone()
             .unwrap_or_default(),
+        remote_backend: super::remote::normalize_remote_backend(
+            config.mcp_config.acemcp_remote_backend.as_deref(),
+        ),
+        index_concurrency: config
+            .mcp_config
+            .acemcp_index_concurrency
+            .unwrap_or(super::scheduler::DEFAULT_INDEX_CONCURRENCY as u32),
+        upload_bandwidth_kbps: config.mcp_config.acemcp_upload_bandwidth_kbps.unwrap_or(0),
+        job_history_retention_days: config
+            .mcp_config
+            .acemcp_job_history_retention_days
+            .unwrap_or(super::job_history::DEFAULT_RETENTION_DAYS),
+        secret_scan_enabled: config.mcp_config.secret_scan_enabled.unwrap_or(true),
+        secret_scan_policy: super::secret_scan::SecretPolicy::parse(
+            config.mcp_config.secret_scan_policy.as_deref(),
+        )
+        .as_str()
+        .to_string(),
+        secret_scan_custom_patterns: config
+            .mcp_config
+            .secret_scan_custom_patterns
+            .clone()
+            .unwrap_or_default(),
         // 嵌套项目索引开关（默认启用）
         index_nested_projects: config
             .mcp_config
@@ -1326,6 +1435,11 @@ pub async fn get_acemcp_config(state: State<'_, AppState>) -> Result<AcemcpConfi
             .sou_include_failed_backend_errors
             .unwrap_or(true),
         sou_local_enabled: config.mcp_config.sou_local_enabled.unwrap_or(true),
+        sou_workspace_groups: config
+            .mcp_config
+            .sou_workspace_groups
+            .clone()
+            .unwrap_or_default(),
         uiux_knowledge_backend: config
             .mcp_config
             .uiux_knowledge_backend
@@ -1409,6 +1523,8 @@ pub async fn debug_acemcp_search(
     let req = SouRequest {
         project_root_path: project_root_path.clone(),
         query: query.clone(),
+        action: None,
+        path: None,
         backend,
         tree_depth: None,
         max_turns: None,
@@ -1416,6 +1532,11 @@ pub async fn debug_acemcp_search(
         max_commands: None,
         timeout_ms: None,
         exclude_paths: None,
+        workspace_group: None,
+        // 调试搜索用于观察真实后端耗时，总是绕过查询缓存
+        no_cache: Some(true),
+        context_lines: None,
+        max_output_chars: None,
     };
 
     // 调用搜索函数（日志会通过 log crate 输出到日志文件）
@@ -1524,6 +1645,51 @@ pub fn get_sou_local_index_status(
     crate::mcp::tools::sou::local::status(&project_root_path).map_err(|error| error.to_string())
 }
 
+/// 导出项目索引快照（相对路径 zip），供其他机器导入复用
+#[tauri::command]
+pub async fn export_index_snapshot(
+    project_root_path: String,
+    archive_path: String,
+) -> Result<super::snapshot::SnapshotExportResult, String> {
+    let config = AcemcpTool::get_acemcp_config()
+        .await
+        .map_err(|error| error.to_string())?;
+    super::snapshot::export_snapshot(&config, &project_root_path, &archive_path)
+        .await
+        .map_err(|error| error.to_string())
+}
+
+/// 导入项目索引快照；只复用内容哈希与工作区一致的文件
+#[tauri::command]
+pub async fn import_index_snapshot(
+    project_root_path: String,
+    archive_path: String,
+) -> Result<super::snapshot::SnapshotImportResult, String> {
+    let config = AcemcpTool::get_acemcp_config()
+        .await
+        .map_err(|error| error.to_string())?;
+    super::snapshot::import_snapshot(&config, &project_root_path, &archive_path)
+        .await
+        .map_err(|error| error.to_string())
+}
+
+/// why-not-indexed：解释路径在 ACE / local / fast_context 各后端为何（未）被收录
+#[tauri::command]
+pub async fn explain_index_path(
+    project_root_path: String,
+    path: String,
+    top_excluded: Option<usize>,
+) -> Result<crate::mcp::tools::sou::why_not_indexed::WhyNotIndexedReport, String> {
+    crate::mcp::tools::sou::why_not_indexed::explain(
+        &project_root_path,
+        &path,
+        None,
+        top_excluded.unwrap_or(crate::mcp::tools::sou::why_not_indexed::DEFAULT_TOP_EXCLUDED),
+    )
+    .await
+    .map_err(|error| error.to_string())
+}
+
 #[tauri::command]
 pub async fn rebuild_sou_local_index(
     project_root_path: String,
@@ -1583,6 +1749,8 @@ pub async fn execute_acemcp_tool(
             let req = SouRequest {
                 project_root_path,
                 query,
+                action: None,
+                path: None,
                 backend,
                 tree_depth: arguments
                     .get("tree_depth")
@@ -1604,6 +1772,19 @@ pub async fn execute_acemcp_tool(
                 exclude_paths: arguments
                     .get("exclude_paths")
                     .and_then(|v| serde_json::from_value::<Vec<String>>(v.clone()).ok()),
+                workspace_group: arguments
+                    .get("workspace_group")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string()),
+                no_cache: arguments.get("no_cache").and_then(|v| v.as_bool()),
+                context_lines: arguments
+                    .get("context_lines")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v.min(u8::MAX as u64) as u8),
+                max_output_chars: arguments
+                    .get("max_output_chars")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as usize),
             };
             match SouTool::search_context(req).await {
                 Ok(result) => {
@@ -2168,7 +2349,20 @@ pub async fn remove_acemcp_project_index(
     app: AppHandle,
 ) -> Result<String, String> {
     let normalized_root = normalize_path_key(&project_root_path);
-    let result = purge_project_index_records(&normalized_root, true)?;
+    let config = AcemcpTool::get_acemcp_config().await.ok();
+    let released = config
+        .as_ref()
+        .map(|config| super::mcp::current_scope_blob_names(config, &normalized_root))
+        .unwrap_or_default();
+    let mut result = purge_project_index_records(&normalized_root, true)?;
+    // 后端支持删除时，一并释放已无其他项目引用的远端 blob
+    if let Some(config) = config.as_ref() {
+        match super::mcp::release_unreferenced_blobs(config, released).await {
+            Ok(0) => {}
+            Ok(deleted) => result.push_str(&format!("，已删除 {} 个远端 blob", deleted)),
+            Err(error) => log::warn!("删除项目远端 blob 失败: {}", error),
+        }
+    }
     {
         let mut config = state
             .config
@@ -2203,6 +2397,68 @@ pub async fn remove_acemcp_project_index(
     Ok(result)
 }
 
+/// 索引垃圾回收：清理已不存在的项目记录与不再引用的 blob；默认仅预览（dry-run）。
+/// 不可访问的项目目录只有列入 `confirmed_missing_roots` 才会清理，避免误删暂未挂载的项目。
+#[tauri::command]
+pub async fn collect_acemcp_index_garbage(
+    dry_run: Option<bool>,
+    confirmed_missing_roots: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<super::mcp::IndexGcReport, String> {
+    let config = AcemcpTool::get_acemcp_config()
+        .await
+        .map_err(|error| error.to_string())?;
+    let report = super::mcp::collect_index_garbage(
+        &config,
+        dry_run.unwrap_or(true),
+        &confirmed_missing_roots.unwrap_or_default(),
+    )
+    .await
+    .map_err(|error| error.to_string())?;
+    let removed_roots = report
+        .projects
+        .iter()
+        .filter(|project| project.action == "missing_root")
+        .map(|project| project.project_root.clone())
+        .collect::<Vec<_>>();
+    if report.dry_run || removed_roots.is_empty() {
+        return Ok(report);
+    }
+    // 已删除的项目同时移出监听与范围确认列表
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|error| format!("获取配置失败: {}", error))?;
+        let keep = |paths: Option<Vec<String>>| {
+            paths.map(|paths| {
+                paths
+                    .into_iter()
+                    .filter(|path| {
+                        !removed_roots
+                            .iter()
+                            .any(|root| normalized_paths_equal(path, root))
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        config.mcp_config.acemcp_watched_projects =
+            keep(config.mcp_config.acemcp_watched_projects.take());
+        config.mcp_config.acemcp_confirmed_project_roots =
+            keep(config.mcp_config.acemcp_confirmed_project_roots.take());
+        config.mcp_config.secret_scan_acknowledged_roots =
+            keep(config.mcp_config.secret_scan_acknowledged_roots.take());
+    }
+    for root in &removed_roots {
+        let _ = super::watcher::get_watcher_manager().stop_watching(root);
+    }
+    save_config(&state, &app)
+        .await
+        .map_err(|error| format!("保存垃圾回收结果失败: {}", error))?;
+    Ok(report)
+}
+
 /// 确认异常路径确实是用户要索引的项目根目录。
 #[tauri::command]
 pub async fn confirm_acemcp_project_scope(
@@ -2239,6 +2495,106 @@ pub async fn confirm_acemcp_project_scope(
     Ok(format!("已确认项目索引范围: {}", normalized_root))
 }
 
+/// 确认首次上传前的疑似密钥报告，之后该项目按当前策略正常上传。
+#[tauri::command]
+pub async fn acknowledge_secret_scan(
+    project_root_path: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<String, String> {
+    let normalized_root = normalize_path_key(&project_root_path);
+    let report = AcemcpTool::get_index_status(normalized_root.clone())
+        .scope_risk
+        .filter(|risk| risk.level == ProjectScopeRiskLevel::SecretsDetected)
+        .map(|risk| risk.secret_findings)
+        .unwrap_or_default();
+    {
+        let mut config = state
+            .config
+            .lock()
+            .map_err(|error| format!("获取配置失败: {}", error))?;
+        let mut acknowledged = config
+            .mcp_config
+            .secret_scan_acknowledged_roots
+            .clone()
+            .unwrap_or_default();
+        if !acknowledged
+            .iter()
+            .any(|path| normalized_paths_equal(path, &normalized_root))
+        {
+            acknowledged.push(normalized_root.clone());
+        }
+        config.mcp_config.secret_scan_acknowledged_roots =
+            Some(normalize_project_list(acknowledged));
+    }
+    save_config(&state, &app)
+        .await
+        .map_err(|error| format!("保存密钥扫描确认失败: {}", error))?;
+    super::mcp::clear_project_scope_risk(&normalized_root)
+        .map_err(|error| format!("清理项目暂停状态失败: {}", error))?;
+    for file in report {
+        super::secret_scan::audit(super::secret_scan::SecretAuditEntry {
+            time: chrono::Utc::now(),
+            channel: super::secret_scan::CHANNEL_PREFLIGHT.to_string(),
+            project_root: normalized_root.clone(),
+            path: file.path,
+            action: "acknowledge".to_string(),
+            rules: file.rules,
+            lines: file.lines,
+        });
+    }
+    log_important!(info, "用户已确认首次上传密钥扫描报告: {}", normalized_root);
+    Ok(format!("已确认密钥扫描报告: {}", normalized_root))
+}
+
+/// 读取密钥扫描审计日志（新记录在前）
+#[tauri::command]
+pub fn get_secret_audit_log(
+    limit: Option<usize>,
+) -> Result<Vec<super::secret_scan::SecretAuditEntry>, String> {
+    Ok(super::secret_scan::read_audit_log(
+        limit.unwrap_or(200).clamp(1, 5000),
+    ))
+}
+
+/// 查询 ACE 索引任务历史（新记录在前）；`since` 为 RFC 3339 时间
+#[tauri::command]
+pub fn get_acemcp_index_history(
+    project_root_path: Option<String>,
+    status: Option<String>,
+    trigger: Option<String>,
+    since: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<super::job_history::IndexJobRecord>, String> {
+    let since = match since
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Some(value) => Some(
+            chrono::DateTime::parse_from_rfc3339(value)
+                .map_err(|e| format!("since 不是有效的 RFC 3339 时间: {}", e))?
+                .with_timezone(&chrono::Utc),
+        ),
+        None => None,
+    };
+    let keep = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    Ok(super::job_history::query_history(
+        &super::job_history::HistoryQuery {
+            project_root: keep(project_root_path)
+                .map(|root| super::scope_guard::normalize_root(&root)),
+            status: keep(status),
+            trigger: keep(trigger),
+            since,
+            limit: limit.unwrap_or(200).clamp(1, 5000),
+        },
+    ))
+}
+
 /// 检查指定目录是否存在
 #[tauri::command]
 pub fn check_directory_exists(directory_path: String) -> Result<bool, String> {
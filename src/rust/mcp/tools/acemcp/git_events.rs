@@ -0,0 +1,310 @@
+// Git 事件驱动的增量索引
+// 切换分支、rebase、reset 会在极短时间内改写大量工作区文件，单纯依赖文件事件防抖后
+// 只能整树重扫。这里识别 `.git/HEAD`、`ORIG_HEAD`、refs 的变化，用
+// `git diff --name-status` 计算新旧提交之间的精确变更集，交给索引流程按清单增量更新：
+// - 变更集按项目暂存在进程内，索引成功后才清除，失败时下次触发仍会带上
+// - 普通文件事件触发的增量任务会把暂存项标记为「需要整树扫描」，保证不漏文件
+
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use tokio::process::Command;
+
+use super::watcher::normalize_project_path;
+use crate::log_debug;
+
+/// 相对项目根的变更集合（正斜杠路径）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct GitChangeSet {
+    /// 新增、修改、重命名目标：需要重新读取
+    pub upserted: BTreeSet<String>,
+    /// 删除、重命名来源：从清单中移除
+    pub removed: BTreeSet<String>,
+}
+
+impl GitChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.upserted.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.upserted.len() + self.removed.len()
+    }
+
+    pub fn merge(&mut self, other: GitChangeSet) {
+        self.upserted.extend(other.upserted);
+        self.removed.extend(other.removed);
+    }
+}
+
+/// 判断事件路径是否为 git 元数据（HEAD / ORIG_HEAD / refs），返回所属仓库根目录
+///
+/// `.lock` 临时文件会在 git 写入完成后重命名为正式文件，这里只关注正式文件。
+pub(crate) fn git_metadata_repo_root(path: &Path) -> Option<String> {
+    let normalized = normalize_project_path(&path.to_string_lossy());
+    let (repo_root, rel) = normalized.split_once("/.git/")?;
+    if rel.ends_with(".lock") || repo_root.is_empty() {
+        return None;
+    }
+    let is_ref_change = matches!(rel, "HEAD" | "ORIG_HEAD" | "packed-refs")
+        || rel.starts_with("refs/heads/")
+        || rel.starts_with("refs/tags/");
+    is_ref_change.then(|| repo_root.to_string())
+}
+
+async fn run_git(repo_root: &str, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_root)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} 失败: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// 当前 HEAD 指向的提交；空仓库或非 git 目录返回 None
+pub(crate) async fn resolve_head(repo_root: &str) -> Option<String> {
+    let output = run_git(repo_root, &["rev-parse", "--verify", "-q", "HEAD"])
+        .await
+        .ok()?;
+    let head = String::from_utf8_lossy(&output).trim().to_string();
+    (!head.is_empty()).then_some(head)
+}
+
+/// 计算两个提交之间的文件变更（含重命名检测）
+pub(crate) async fn diff_commits(repo_root: &str, old: &str, new: &str) -> Result<GitChangeSet> {
+    let output = run_git(
+        repo_root,
+        &[
+            "diff",
+            "--name-status",
+            "-M",
+            "-z",
+            "--no-ext-diff",
+            old,
+            new,
+        ],
+    )
+    .await?;
+    Ok(parse_name_status_z(&String::from_utf8_lossy(&output)))
+}
+
+/// 解析 `git diff --name-status -z` 输出：`状态\0路径\0`，重命名/复制为 `状态\0源\0目标\0`
+fn parse_name_status_z(output: &str) -> GitChangeSet {
+    let mut changes = GitChangeSet::default();
+    let mut fields = output.split('\0').filter(|field| !field.is_empty());
+    while let Some(status) = fields.next() {
+        match status.chars().next() {
+            Some('R') => {
+                let (Some(from), Some(to)) = (fields.next(), fields.next()) else {
+                    break;
+                };
+                changes.removed.insert(from.to_string());
+                changes.upserted.insert(to.to_string());
+            }
+            Some('C') => {
+                let (Some(_), Some(to)) = (fields.next(), fields.next()) else {
+                    break;
+                };
+                changes.upserted.insert(to.to_string());
+            }
+            Some('D') => {
+                if let Some(path) = fields.next() {
+                    changes.removed.insert(path.to_string());
+                }
+            }
+            Some(_) => {
+                if let Some(path) = fields.next() {
+                    changes.upserted.insert(path.to_string());
+                }
+            }
+            None => break,
+        }
+    }
+    // 同一路径既被删除又被新增（如 A→B 与 C→A）时以新增为准
+    let upserted = changes.upserted.clone();
+    changes.removed.retain(|path| !upserted.contains(path));
+    changes
+}
+
+// ---------------- 待处理变更集 ----------------
+
+#[derive(Debug)]
+struct PendingChanges {
+    /// None 表示期间有无法精确描述的变更，需要整树扫描
+    changes: Option<GitChangeSet>,
+    generation: u64,
+}
+
+fn pending() -> &'static Mutex<HashMap<String, PendingChanges>> {
+    static PENDING: OnceLock<Mutex<HashMap<String, PendingChanges>>> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 记录一次触发：`Some` 合并 git 变更集，`None` 表示普通文件事件，后续需整树扫描
+pub(crate) fn record_pending(project_root: &str, changes: Option<GitChangeSet>) {
+    let mut pending = pending().lock().unwrap();
+    match pending.get_mut(project_root) {
+        Some(entry) => {
+            entry.generation += 1;
+            match (&mut entry.changes, changes) {
+                (Some(current), Some(changes)) => current.merge(changes),
+                (current, _) => *current = None,
+            }
+        }
+        // 没有暂存的 git 变更时普通事件无需记录，索引本来就会整树扫描
+        None => {
+            if let Some(changes) = changes {
+                pending.insert(
+                    project_root.to_string(),
+                    PendingChanges {
+                        changes: Some(changes),
+                        generation: 1,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// 索引开始时读取暂存变更集；返回的 generation 用于成功后清除
+pub(crate) fn snapshot_pending(project_root: &str) -> (u64, Option<GitChangeSet>) {
+    let pending = pending().lock().unwrap();
+    match pending.get(project_root) {
+        Some(entry) => (entry.generation, entry.changes.clone()),
+        None => (0, None),
+    }
+}
+
+/// 索引成功后清除暂存；期间又有新触发时保留，由下一轮处理合并后的集合
+pub(crate) fn clear_pending(project_root: &str, generation: u64) {
+    let mut pending = pending().lock().unwrap();
+    if pending
+        .get(project_root)
+        .is_some_and(|entry| entry.generation == generation)
+    {
+        pending.remove(project_root);
+        log_debug!("已清除 git 暂存变更集: project_root={}", project_root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn name_status_parsing_handles_renames_and_deletions() {
+        let output = "M\0src/lib.rs\0A\0src/new.rs\0D\0src/old.rs\0R087\0src/a.rs\0src/b.rs\0C100\0x.rs\0y.rs\0";
+        let changes = parse_name_status_z(output);
+        assert_eq!(
+            changes.upserted.into_iter().collect::<Vec<_>>(),
+            vec!["src/b.rs", "src/lib.rs", "src/new.rs", "y.rs"]
+        );
+        assert_eq!(
+            changes.removed.into_iter().collect::<Vec<_>>(),
+            vec!["src/a.rs", "src/old.rs"]
+        );
+    }
+
+    #[test]
+    fn metadata_paths_are_detected_per_repository() {
+        assert_eq!(
+            git_metadata_repo_root(&PathBuf::from("/repo/.git/HEAD")).as_deref(),
+            Some("/repo")
+        );
+        assert_eq!(
+            git_metadata_repo_root(&PathBuf::from("/repo/sub/.git/refs/heads/main")).as_deref(),
+            Some("/repo/sub")
+        );
+        assert!(git_metadata_repo_root(&PathBuf::from("/repo/.git/HEAD.lock")).is_none());
+        assert!(git_metadata_repo_root(&PathBuf::from("/repo/.git/index")).is_none());
+        assert!(git_metadata_repo_root(&PathBuf::from("/repo/.git/refs/remotes/o/main")).is_none());
+        assert!(git_metadata_repo_root(&PathBuf::from("/repo/src/HEAD")).is_none());
+    }
+
+    #[test]
+    fn plain_events_downgrade_pending_changes_to_full_scan() {
+        let root = "/test/git-events/pending";
+        let mut first = GitChangeSet::default();
+        first.upserted.insert("a.rs".to_string());
+        record_pending(root, Some(first));
+        let (generation, changes) = snapshot_pending(root);
+        assert_eq!(changes.map(|changes| changes.len()), Some(1));
+
+        record_pending(root, None);
+        let (next_generation, changes) = snapshot_pending(root);
+        assert!(changes.is_none());
+        // 期间有新触发，旧 generation 不能清除
+        clear_pending(root, generation);
+        assert_eq!(snapshot_pending(root).0, next_generation);
+        clear_pending(root, next_generation);
+        assert_eq!(snapshot_pending(root).0, 0);
+    }
+
+    #[tokio::test]
+    async fn diff_between_commits_reports_exact_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(args)
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            return;
+        }
+        let commit = |message: &str| {
+            git(&["add", "-A"])
+                && git(&[
+                    "-c",
+                    "user.name=t",
+                    "-c",
+                    "user.email=t@t",
+                    "commit",
+                    "-q",
+                    "-m",
+                    message,
+                ])
+        };
+        std::fs::write(dir.path().join("keep.rs"), "fn keep() {}\n").unwrap();
+        std::fs::write(dir.path().join("gone.rs"), "fn gone() {}\n").unwrap();
+        std::fs::write(
+            dir.path().join("moved.rs"),
+            "fn moved() {\n    let value = 1;\n    println!(\"{}\", value);\n}\n",
+        )
+        .unwrap();
+        assert!(commit("first"));
+        let old = resolve_head(&root).await.unwrap();
+
+        std::fs::write(dir.path().join("keep.rs"), "fn keep() { changed(); }\n").unwrap();
+        std::fs::remove_file(dir.path().join("gone.rs")).unwrap();
+        std::fs::rename(dir.path().join("moved.rs"), dir.path().join("renamed.rs")).unwrap();
+        assert!(commit("second"));
+        let new = resolve_head(&root).await.unwrap();
+
+        let changes = diff_commits(&root, &old, &new).await.unwrap();
+        assert_eq!(
+            changes.upserted.into_iter().collect::<Vec<_>>(),
+            vec!["keep.rs", "renamed.rs"]
+        );
+        assert_eq!(
+            changes.removed.into_iter().collect::<Vec<_>>(),
+            vec!["gone.rs", "moved.rs"]
+        );
+    }
+}
@@ -0,0 +1,806 @@
+// 自建索引服务（「三术索引」）
+// 与 remote::SelfHostedBackend 成对维护的最小参考实现：
+// - SQLite + FTS5 存储 blob，按请求携带的 blob 集合过滤后做 BM25 检索
+// - 可选接入 OpenAI 兼容的 embeddings 接口，对候选结果做向量重排
+// - 返回与 ACE formatted_retrieval 相同的 `Path:` 文本协议，sou 无需区分后端
+// - 服务可由多个客户端共用：按 client_id 记录 blob 引用，删除只释放调用方的引用，
+//   引用清零后才真正删除 blob
+
+use anyhow::{anyhow, Context, Result};
+use ring::digest::{digest, SHA256};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::mcp::{sha256_hex, strip_chunk_suffix};
+use super::remote::BlobItem;
+use crate::mcp::tools::sou::local::{build_search_text, extract_query_terms};
+
+/// 单次检索返回的最大片段数
+const MAX_SEARCH_RESULTS: usize = 10;
+/// FTS 候选上限；过滤 blob 集合与向量重排都在候选内进行
+const MAX_CANDIDATES: usize = 200;
+/// 参与向量重排的候选数量
+const RERANK_CANDIDATES: usize = 30;
+/// 片段窗口：以最佳匹配行为中心的行数
+const EXCERPT_LINES: usize = 30;
+/// 请求体上限，避免单次上传撑爆内存
+const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+/// 未携带 client_id 的请求与引用表出现前的 blob 记在该引用下，永不释放
+const LEGACY_CLIENT: &str = "legacy";
+
+/// OpenAI 兼容 embeddings 接口配置
+#[derive(Debug, Clone)]
+pub struct EmbeddingConfig {
+    pub url: String,
+    pub model: String,
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexServerOptions {
+    pub db_path: PathBuf,
+    /// 为空时不校验 Authorization，此时只允许监听本机回环地址
+    pub token: Option<String>,
+    pub embedding: Option<EmbeddingConfig>,
+}
+
+pub struct IndexServer {
+    server: Server,
+    store: Mutex<Connection>,
+    token: Option<String>,
+    embedding: Option<EmbeddingConfig>,
+}
+
+#[derive(Deserialize)]
+struct UploadRequest {
+    blobs: Vec<BlobItem>,
+    #[serde(default)]
+    client_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BlobNamesRequest {
+    blob_names: Vec<String>,
+    #[serde(default)]
+    client_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchRequest {
+    query: String,
+    #[serde(default)]
+    blob_names: Vec<String>,
+}
+
+struct Candidate {
+    path: String,
+    content: String,
+    lexical_score: f64,
+    embedding: Option<Vec<f32>>,
+    name: String,
+}
+
+impl IndexServer {
+    pub fn bind(addr: &str, options: IndexServerOptions) -> Result<Self> {
+        let token = options.token.filter(|token| !token.trim().is_empty());
+        if token.is_none() && !is_loopback(addr)? {
+            return Err(anyhow!(
+                "未配置 token 时只能监听本机回环地址（如 127.0.0.1），拒绝监听 {}",
+                addr
+            ));
+        }
+        let connection = open_store(&options.db_path)?;
+        let server =
+            Server::http(addr).map_err(|error| anyhow!("监听 {} 失败: {}", addr, error))?;
+        Ok(Self {
+            server,
+            store: Mutex::new(connection),
+            token,
+            embedding: options.embedding,
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// 阻塞处理请求，直到调用 `unblock`
+    pub fn serve(&self) {
+        for request in self.server.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    /// 让 `serve` 退出循环
+    pub fn unblock(&self) {
+        self.server.unblock();
+    }
+
+    fn handle(&self, mut request: Request) {
+        let (status, body) = if !self.authorized(&request) {
+            (401, json!({ "error": "unauthorized" }))
+        } else if request.method() != &Method::Post {
+            (405, json!({ "error": "method not allowed" }))
+        } else {
+            let path = request.url().split('?').next().unwrap_or("").to_string();
+            match read_body(&mut request).and_then(|body| self.route(&path, &body)) {
+                Ok(Some(value)) => (200, value),
+                Ok(None) => (404, json!({ "error": "not found" })),
+                Err(error) => {
+                    log::warn!("[index-server] 请求 {} 失败: {:#}", path, error);
+                    (400, json!({ "error": format!("{:#}", error) }))
+                }
+            }
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(json_header());
+        if let Err(error) = request.respond(response) {
+            log::warn!("[index-server] 写回响应失败: {}", error);
+        }
+    }
+
+    fn authorized(&self, request: &Request) -> bool {
+        let Some(token) = self.token.as_deref() else {
+            return true;
+        };
+        request.headers().iter().any(|header| {
+            header.field.equiv("Authorization") && bearer_matches(header.value.as_str(), token)
+        })
+    }
+
+    fn route(&self, path: &str, body: &[u8]) -> Result<Option<Value>> {
+        let value = match path {
+            "/v1/blobs/upload" => {
+                let request: UploadRequest = serde_json::from_slice(body)?;
+                json!({ "blob_names": self.upload(&request.blobs, request.client_id.as_deref())? })
+            }
+            "/v1/blobs/find-missing" => {
+                let request: BlobNamesRequest = serde_json::from_slice(body)?;
+                json!({ "missing": self.find_missing(&request.blob_names, request.client_id.as_deref())? })
+            }
+            "/v1/blobs/delete" => {
+                let request: BlobNamesRequest = serde_json::from_slice(body)?;
+                let client_id = request
+                    .client_id
+                    .as_deref()
+                    .filter(|client_id| {
+                        !client_id.trim().is_empty() && client_id.trim() != LEGACY_CLIENT
+                    })
+                    .ok_or_else(|| anyhow!("删除 blob 需要携带有效的 client_id"))?;
+                json!({ "deleted": self.delete(&request.blob_names, client_id)? })
+            }
+            "/v1/search" => {
+                let request: SearchRequest = serde_json::from_slice(body)?;
+                json!({ "formatted_retrieval": self.search(&request.query, &request.blob_names)? })
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    fn lock_store(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.store.lock().map_err(|_| anyhow!("索引存储锁已损坏"))
+    }
+
+    fn upload(&self, blobs: &[BlobItem], client_id: Option<&str>) -> Result<Vec<String>> {
+        let client_id = reference_owner(client_id);
+        let mut connection = self.lock_store()?;
+        let transaction = connection.transaction()?;
+        let mut names = Vec::with_capacity(blobs.len());
+        for blob in blobs {
+            let name = sha256_hex(&blob.path, &blob.content);
+            let exists = transaction
+                .query_row("SELECT 1 FROM blobs WHERE name = ?1", params![name], |_| {
+                    Ok(())
+                })
+                .optional()?
+                .is_some();
+            if !exists {
+                transaction.execute(
+                    "INSERT INTO blobs (name, path, content) VALUES (?1, ?2, ?3)",
+                    params![name, blob.path, blob.content],
+                )?;
+                transaction.execute(
+                    "INSERT INTO blobs_fts (name, search_text) VALUES (?1, ?2)",
+                    params![
+                        name,
+                        build_search_text(strip_chunk_suffix(&blob.path), &blob.content)
+                    ],
+                )?;
+            }
+            add_reference(&transaction, &name, client_id)?;
+            names.push(name);
+        }
+        transaction.commit()?;
+        Ok(names)
+    }
+
+    /// 已存在的 blob 会被客户端直接记为已确认，因此同时登记调用方的引用
+    fn find_missing(&self, blob_names: &[String], client_id: Option<&str>) -> Result<Vec<String>> {
+        let client_id = reference_owner(client_id);
+        let mut connection = self.lock_store()?;
+        let transaction = connection.transaction()?;
+        let mut missing = Vec::new();
+        {
+            let mut statement = transaction.prepare("SELECT 1 FROM blobs WHERE name = ?1")?;
+            for name in blob_names {
+                if statement.exists(params![name])? {
+                    add_reference(&transaction, name, client_id)?;
+                } else {
+                    missing.push(name.clone());
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(missing)
+    }
+
+    /// 释放 `client_id` 对这些 blob 的引用，返回引用清零后实际删除的 blob 数
+    fn delete(&self, blob_names: &[String], client_id: &str) -> Result<usize> {
+        let mut connection = self.lock_store()?;
+        let transaction = connection.transaction()?;
+        let mut deleted = 0;
+        for name in blob_names {
+            transaction.execute(
+                "DELETE FROM blob_refs WHERE name = ?1 AND client_id = ?2",
+                params![name, client_id],
+            )?;
+            let referenced = transaction
+                .query_row(
+                    "SELECT 1 FROM blob_refs WHERE name = ?1 LIMIT 1",
+                    params![name],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if referenced {
+                continue;
+            }
+            deleted += transaction.execute("DELETE FROM blobs WHERE name = ?1", params![name])?;
+            transaction.execute("DELETE FROM blobs_fts WHERE name = ?1", params![name])?;
+        }
+        transaction.commit()?;
+        Ok(deleted)
+    }
+
+    fn search(&self, query: &str, blob_names: &[String]) -> Result<String> {
+        let terms = extract_query_terms(query);
+        if terms.is_empty() || blob_names.is_empty() {
+            return Ok(String::new());
+        }
+        let mut candidates = self.lexical_candidates(&terms, blob_names)?;
+        if let Some(embedding) = &self.embedding {
+            // 向量重排失败时保留 BM25 顺序，不影响检索可用性
+            if let Err(error) = self.rerank(embedding, query, &mut candidates) {
+                log::warn!("[index-server] 向量重排失败，沿用 BM25 排序: {:#}", error);
+            }
+        }
+        candidates.truncate(MAX_SEARCH_RESULTS);
+        Ok(format_retrieval(&candidates, &terms))
+    }
+
+    /// 先把候选范围限定为请求的 blob 集合再排序截断：共享服务器上其他项目的 blob
+    /// 不会挤占本次请求的候选名额。
+    fn lexical_candidates(
+        &self,
+        terms: &[String],
+        blob_names: &[String],
+    ) -> Result<Vec<Candidate>> {
+        let match_query = terms
+            .iter()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let mut connection = self.lock_store()?;
+        // 中文说明：请求集合写入临时表，事务结束时回滚，不会残留到下一次查询。
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "CREATE TEMP TABLE IF NOT EXISTS requested_blobs (name TEXT PRIMARY KEY)",
+            [],
+        )?;
+        {
+            let mut insert =
+                transaction.prepare("INSERT OR IGNORE INTO requested_blobs(name) VALUES (?1)")?;
+            for name in blob_names {
+                insert.execute(params![name])?;
+            }
+        }
+        let mut statement = transaction.prepare(
+            "SELECT b.name, b.path, b.content, bm25(blobs_fts) AS score, b.embedding
+             FROM blobs_fts
+             JOIN requested_blobs r ON r.name = blobs_fts.name
+             JOIN blobs b ON b.name = blobs_fts.name
+             WHERE blobs_fts MATCH ?1
+             ORDER BY score
+             LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![match_query, MAX_CANDIDATES as i64], |row| {
+            Ok(Candidate {
+                name: row.get(0)?,
+                path: row.get(1)?,
+                content: row.get(2)?,
+                // bm25 越小越相关，取反后统一为越大越好
+                lexical_score: -row.get::<_, f64>(3)?,
+                embedding: row
+                    .get::<_, Option<Vec<u8>>>(4)?
+                    .map(|bytes| decode_vector(&bytes)),
+            })
+        })?;
+        let candidates = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(candidates)
+    }
+
+    fn rerank(
+        &self,
+        config: &EmbeddingConfig,
+        query: &str,
+        candidates: &mut Vec<Candidate>,
+    ) -> Result<()> {
+        let window = candidates.len().min(RERANK_CANDIDATES);
+        if window == 0 {
+            return Ok(());
+        }
+        let pending = candidates[..window]
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| candidate.embedding.is_none())
+            .map(|(index, candidate)| (index, embedding_input(candidate)))
+            .collect::<Vec<_>>();
+        let mut inputs = vec![query.to_string()];
+        inputs.extend(pending.iter().map(|(_, input)| input.clone()));
+        let mut vectors = request_embeddings(config, &inputs)?;
+        if vectors.len() != inputs.len() {
+            return Err(anyhow!(
+                "embeddings 返回数量不符: 期望 {}，实际 {}",
+                inputs.len(),
+                vectors.len()
+            ));
+        }
+        let query_vector = vectors.remove(0);
+
+        // 回写新计算的向量，后续检索直接复用
+        let connection = self.lock_store()?;
+        for ((index, _), vector) in pending.iter().zip(vectors) {
+            connection.execute(
+                "UPDATE blobs SET embedding = ?1 WHERE name = ?2",
+                params![encode_vector(&vector), candidates[*index].name],
+            )?;
+            candidates[*index].embedding = Some(vector);
+        }
+        drop(connection);
+
+        let max_lexical = candidates[..window]
+            .iter()
+            .map(|candidate| candidate.lexical_score)
+            .fold(f64::MIN, f64::max)
+            .max(f64::EPSILON);
+        let mut scored = candidates
+            .drain(..window)
+            .map(|candidate| {
+                let semantic = candidate
+                    .embedding
+                    .as_deref()
+                    .map(|vector| cosine(&query_vector, vector))
+                    .unwrap_or(0.0);
+                let score = 0.4 * (candidate.lexical_score / max_lexical) + 0.6 * semantic;
+                (score, candidate)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|left, right| right.0.total_cmp(&left.0));
+        let rest = std::mem::take(candidates);
+        candidates.extend(scored.into_iter().map(|(_, candidate)| candidate));
+        candidates.extend(rest);
+        Ok(())
+    }
+}
+
+fn open_store(db_path: &PathBuf) -> Result<Connection> {
+    if let Some(parent) = db_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("创建索引目录失败: {}", parent.display()))?;
+        }
+    }
+    let connection = Connection::open(db_path)
+        .with_context(|| format!("打开索引数据库失败: {}", db_path.display()))?;
+    connection.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         CREATE TABLE IF NOT EXISTS blobs (
+             name TEXT PRIMARY KEY,
+             path TEXT NOT NULL,
+             content TEXT NOT NULL,
+             embedding BLOB
+         );
+         CREATE VIRTUAL TABLE IF NOT EXISTS blobs_fts USING fts5(
+             name UNINDEXED,
+             search_text,
+             tokenize='unicode61 remove_diacritics 2'
+         );
+         CREATE TABLE IF NOT EXISTS blob_refs (
+             name TEXT NOT NULL,
+             client_id TEXT NOT NULL,
+             PRIMARY KEY (name, client_id)
+         ) WITHOUT ROWID;",
+    )?;
+    // 引用表出现前上传的 blob 无法得知被哪些客户端使用，记为 legacy 引用永久保留
+    connection.execute(
+        "INSERT OR IGNORE INTO blob_refs (name, client_id)
+         SELECT name, ?1 FROM blobs WHERE name NOT IN (SELECT name FROM blob_refs)",
+        params![LEGACY_CLIENT],
+    )?;
+    Ok(connection)
+}
+
+fn reference_owner(client_id: Option<&str>) -> &str {
+    client_id
+        .map(str::trim)
+        .filter(|client_id| !client_id.is_empty())
+        .unwrap_or(LEGACY_CLIENT)
+}
+
+fn add_reference(connection: &Connection, name: &str, client_id: &str) -> Result<()> {
+    connection.execute(
+        "INSERT OR IGNORE INTO blob_refs (name, client_id) VALUES (?1, ?2)",
+        params![name, client_id],
+    )?;
+    Ok(())
+}
+
+fn read_body(request: &mut Request) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_end(&mut body)
+        .context("读取请求体失败")?;
+    Ok(body)
+}
+
+fn json_header() -> Header {
+    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("静态响应头必然合法")
+}
+
+fn embedding_input(candidate: &Candidate) -> String {
+    let content = candidate.content.chars().take(4000).collect::<String>();
+    format!("{}\n{}", strip_chunk_suffix(&candidate.path), content)
+}
+
+fn request_embeddings(config: &EmbeddingConfig, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+    let payload = json!({ "model": config.model, "input": inputs });
+    let url = config.url.clone();
+    let api_key = config.api_key.clone();
+    // 服务循环运行在普通线程上，这里用临时运行时驱动异步 reqwest
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let value: Value = runtime.block_on(async move {
+        let mut request = reqwest::Client::new().post(&url).json(&payload);
+        if let Some(key) = api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request.send().await?.error_for_status()?;
+        Ok::<_, anyhow::Error>(response.json::<Value>().await?)
+    })?;
+    let data = value
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("embeddings 响应缺少 data"))?;
+    Ok(data
+        .iter()
+        .map(|item| {
+            item.get("embedding")
+                .and_then(Value::as_array)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(Value::as_f64)
+                        .map(|value| value as f32)
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+fn cosine(left: &[f32], right: &[f32]) -> f64 {
+    if left.len() != right.len() || left.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut left_norm, mut right_norm) = (0.0f64, 0.0f64, 0.0f64);
+    for (a, b) in left.iter().zip(right) {
+        dot += (*a as f64) * (*b as f64);
+        left_norm += (*a as f64).powi(2);
+        right_norm += (*b as f64).powi(2);
+    }
+    if left_norm == 0.0 || right_norm == 0.0 {
+        return 0.0;
+    }
+    dot / (left_norm.sqrt() * right_norm.sqrt())
+}
+
+fn format_retrieval(candidates: &[Candidate], terms: &[String]) -> String {
+    if candidates.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from("The following code sections were retrieved:\n");
+    for candidate in candidates {
+        output.push_str(&format!("Path: {}\n", strip_chunk_suffix(&candidate.path)));
+        output.push_str(&excerpt(&candidate.content, terms));
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str("...\n");
+    }
+    output
+}
+
+/// 以命中查询词最多的行为中心截取片段
+fn excerpt(content: &str, terms: &[String]) -> String {
+    let lines = content.lines().collect::<Vec<_>>();
+    if lines.len() <= EXCERPT_LINES {
+        return content.to_string();
+    }
+    let best = lines
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, line)| {
+            let lower = line.to_lowercase();
+            let hits = terms
+                .iter()
+                .filter(|term| lower.contains(term.as_str()))
+                .count();
+            // 命中数相同取靠前的行
+            (hits, std::cmp::Reverse(*index))
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+    let start = best.saturating_sub(EXCERPT_LINES / 2);
+    let end = (start + EXCERPT_LINES).min(lines.len());
+    let start = end.saturating_sub(EXCERPT_LINES);
+    lines[start..end].join("\n")
+}
+
+/// 地址解析出的全部 IP 均为回环地址
+fn is_loopback(addr: &str) -> Result<bool> {
+    let mut resolved = addr
+        .to_socket_addrs()
+        .with_context(|| format!("无法解析监听地址 {}", addr))?
+        .peekable();
+    if resolved.peek().is_none() {
+        return Ok(false);
+    }
+    Ok(resolved.all(|addr| addr.ip().is_loopback()))
+}
+
+/// 比较 `Authorization` 头与 `Bearer <token>`：两边先取 SHA-256 再逐字节异或累积，
+/// 比较耗时与令牌内容、公共前缀长度无关
+fn bearer_matches(value: &str, token: &str) -> bool {
+    let provided = digest(&SHA256, value.as_bytes());
+    let expected = digest(&SHA256, format!("Bearer {}", token).as_bytes());
+    provided
+        .as_ref()
+        .iter()
+        .zip(expected.as_ref())
+        .fold(0u8, |diff, (left, right)| diff | (left ^ right))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(dir: &tempfile::TempDir) -> IndexServer {
+        IndexServer::bind(
+            "127.0.0.1:0",
+            IndexServerOptions {
+                db_path: dir.path().join("index.db"),
+                token: None,
+                embedding: None,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn tokenless_server_only_binds_loopback_and_tokens_must_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = |token: Option<&str>| IndexServerOptions {
+            db_path: dir.path().join("index.db"),
+            token: token.map(str::to_string),
+            embedding: None,
+        };
+        assert!(IndexServer::bind("0.0.0.0:0", options(None)).is_err());
+        assert!(IndexServer::bind("0.0.0.0:0", options(Some(" "))).is_err());
+        assert!(IndexServer::bind("0.0.0.0:0", options(Some("secret"))).is_ok());
+        assert!(IndexServer::bind("127.0.0.1:0", options(None)).is_ok());
+
+        assert!(bearer_matches("Bearer secret", "secret"));
+        assert!(!bearer_matches("Bearer secreT", "secret"));
+        assert!(!bearer_matches("Bearer secret ", "secret"));
+        assert!(!bearer_matches("secret", "secret"));
+    }
+
+    fn blob(path: &str, content: &str) -> BlobItem {
+        BlobItem {
+            path: path.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn upload_search_and_delete_respect_requested_blob_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(&dir);
+        let names = server
+            .upload(
+                &[
+                    blob(
+                        "src/auth.rs",
+                        "fn refresh_token() {\n    rotate_session();\n}\n",
+                    ),
+                    blob("src/other.rs", "fn refresh_token_other() {}\n"),
+                ],
+                Some("client-a"),
+            )
+            .unwrap();
+        assert_eq!(
+            names[0],
+            sha256_hex(
+                "src/auth.rs",
+                "fn refresh_token() {\n    rotate_session();\n}\n"
+            )
+        );
+
+        let text = server.search("rotate_session", &names[..1]).unwrap();
+        assert!(text.contains("Path: src/auth.rs"));
+        assert!(!text.contains("src/other.rs"));
+
+        let missing = server
+            .find_missing(&[names[0].clone(), "unknown".to_string()], Some("client-a"))
+            .unwrap();
+        assert_eq!(missing, vec!["unknown".to_string()]);
+
+        assert_eq!(server.delete(&names[..1], "client-a").unwrap(), 1);
+        assert_eq!(
+            server.find_missing(&names[..1], Some("client-a")).unwrap(),
+            names[..1].to_vec()
+        );
+        assert!(server.search("rotate_session", &names).unwrap().is_empty());
+    }
+
+    #[test]
+    fn shared_blobs_are_deleted_only_after_every_client_releases_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(&dir);
+        let shared = server
+            .upload(
+                &[blob("src/shared.rs", "fn shared() {}\n")],
+                Some("client-a"),
+            )
+            .unwrap();
+        // 另一客户端经 find-missing 握手确认已存在的 blob，同样持有引用
+        assert!(server
+            .find_missing(&shared, Some("client-b"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(server.delete(&shared, "client-a").unwrap(), 0);
+        assert!(server
+            .find_missing(&shared, Some("client-a"))
+            .unwrap()
+            .is_empty());
+        // find-missing 也登记了 client-a 的引用，再次释放后只剩 client-b
+        assert_eq!(server.delete(&shared, "client-a").unwrap(), 0);
+        assert_eq!(server.delete(&shared, "client-b").unwrap(), 1);
+        assert_eq!(server.find_missing(&shared, None).unwrap(), shared);
+
+        // 未携带 client_id 的上传记为 legacy 引用，任何客户端都无法释放
+        let legacy = server
+            .upload(&[blob("src/legacy.rs", "fn legacy() {}\n")], None)
+            .unwrap();
+        assert_eq!(server.delete(&legacy, "client-a").unwrap(), 0);
+        assert!(server
+            .find_missing(&legacy, Some("client-a"))
+            .unwrap()
+            .is_empty());
+        assert!(server
+            .route(
+                "/v1/blobs/delete",
+                br#"{"blob_names":["x"],"client_id":"legacy"}"#
+            )
+            .is_err());
+        assert!(server
+            .route("/v1/blobs/delete", br#"{"blob_names":["x"]}"#)
+            .is_err());
+    }
+
+    #[test]
+    fn blobs_uploaded_before_reference_tracking_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("index.db");
+        {
+            let connection = open_store(&db_path).unwrap();
+            connection
+                .execute(
+                    "INSERT INTO blobs (name, path, content) VALUES ('old', 'a.rs', 'fn a() {}')",
+                    [],
+                )
+                .unwrap();
+            connection.execute("DELETE FROM blob_refs", []).unwrap();
+        }
+        let server = server(&dir);
+        assert_eq!(server.delete(&["old".to_string()], "client-a").unwrap(), 0);
+        assert!(server
+            .find_missing(&["old".to_string()], Some("client-a"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn candidates_are_limited_to_requested_blobs_before_ranking() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = server(&dir);
+        // 其他项目的 blob 词频更高、bm25 更优，数量超过候选上限
+        let others = (0..MAX_CANDIDATES + 50)
+            .map(|index| {
+                blob(
+                    &format!("other/{}.rs", index),
+                    "fn ledger_sync() { ledger_sync(); ledger_sync(); }\n",
+                )
+            })
+            .collect::<Vec<_>>();
+        server.upload(&others, None).unwrap();
+        let own_content = format!(
+            "// ledger_sync 的说明\n{}",
+            "fn unrelated() {}\n".repeat(200)
+        );
+        let own = server
+            .upload(&[blob("src/ledger.rs", &own_content)], None)
+            .unwrap();
+
+        let text = server.search("ledger_sync", &own).unwrap();
+        assert!(text.contains("Path: src/ledger.rs"), "{}", text);
+        assert!(!text.contains("other/"));
+    }
+
+    #[test]
+    fn excerpt_centers_on_best_matching_line_and_strips_chunk_suffix() {
+        let mut content = (0..100)
+            .map(|index| format!("line {}", index))
+            .collect::<Vec<_>>();
+        content[70] = "fn needle_target() {}".to_string();
+        let candidates = vec![Candidate {
+            name: "n".to_string(),
+            path: "src/big.rs#chunk2of3".to_string(),
+            content: content.join("\n"),
+            lexical_score: 1.0,
+            embedding: None,
+        }];
+        let text = format_retrieval(&candidates, &["needle_target".to_string()]);
+        assert!(text.contains("Path: src/big.rs\n"));
+        assert!(text.contains("fn needle_target"));
+        assert!(!text.contains("line 10\n"));
+        assert_eq!(cosine(&[1.0, 0.0], &[1.0, 0.0]), 1.0);
+        assert_eq!(decode_vector(&encode_vector(&[0.5, -2.0])), vec![0.5, -2.0]);
+    }
+}
@@ -0,0 +1,418 @@
+//! ACE 索引任务历史。
+//!
+//! `index_jobs.json` 每个项目只保留最近一次任务与最近 100 条事件，无法回答
+//! 「昨天那次索引为什么花了 20 分钟」。任务每次进入结束状态（完成、失败、暂停、
+//! 范围阻断）或被新一轮执行打断时，由 `jobs::update_job` 追加一条运行记录到
+//! `index_job_history.jsonl`，按保留天数与条数上限裁剪。
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+use super::jobs::{BatchFailure, IndexJob};
+
+pub(crate) const DEFAULT_RETENTION_DAYS: u32 = 30;
+/// 无论保留天数多长，最多保留的记录条数
+const MAX_HISTORY_RECORDS: usize = 5000;
+/// 每条记录最多保存的失败批次明细
+const MAX_BATCH_FAILURES_PER_RECORD: usize = 50;
+
+/// 触发索引任务的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IndexTrigger {
+    /// 搜索前自动补索引或范围变化后重建
+    Search,
+    /// GUI / MCP 手动触发
+    Manual,
+    /// 文件监听
+    Watcher,
+    /// git HEAD / ref 变化
+    Git,
+    /// 进程启动后恢复未完成任务
+    Resume,
+    /// 执行期间 ACE 配置变化，改为全量重建
+    ConfigChange,
+    /// 执行期间又收到索引请求，完成后接续
+    Followup,
+    /// 快照导入后补传
+    Snapshot,
+}
+
+impl IndexTrigger {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Search => "search",
+            Self::Manual => "manual",
+            Self::Watcher => "watcher",
+            Self::Git => "git",
+            Self::Resume => "resume",
+            Self::ConfigChange => "config_change",
+            Self::Followup => "followup",
+            Self::Snapshot => "snapshot",
+        }
+    }
+}
+
+/// 一次任务执行（从 queued 到结束状态）的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexJobRecord {
+    pub job_id: String,
+    pub project_root: String,
+    /// incremental | full
+    pub mode: String,
+    /// search | manual | watcher | git | resume | config_change | followup | snapshot
+    pub trigger: String,
+    /// completed | failed | paused | scope_blocked | interrupted
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// 各阶段累计耗时（queued / collecting / uploading）
+    pub phases_ms: BTreeMap<String, u64>,
+    pub total_blobs: usize,
+    pub completed_blobs: usize,
+    pub total_batches: usize,
+    pub completed_batches: usize,
+    /// 收到成功响应的批次所上传的 blob 内容字节数
+    pub bytes_sent: u64,
+    #[serde(default)]
+    pub batch_failures: Vec<BatchFailure>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl IndexJobRecord {
+    /// 由任务当前状态生成记录；`status` 为空时沿用任务状态。
+    pub(crate) fn from_job(
+        job: &IndexJob,
+        status: Option<&str>,
+        finished_at: DateTime<Utc>,
+    ) -> Self {
+        let started_at = job.run_started_at.unwrap_or(job.created_at);
+        let mut batch_failures = job.batch_failures.clone();
+        if batch_failures.len() > MAX_BATCH_FAILURES_PER_RECORD {
+            let drop_count = batch_failures.len() - MAX_BATCH_FAILURES_PER_RECORD;
+            batch_failures.drain(0..drop_count);
+        }
+        Self {
+            job_id: job.job_id.clone(),
+            project_root: job.project_root.clone(),
+            mode: job.mode.clone(),
+            trigger: job.trigger.clone().unwrap_or_else(|| "unknown".to_string()),
+            status: status.unwrap_or(&job.status).to_string(),
+            started_at,
+            finished_at,
+            duration_ms: (finished_at - started_at).num_milliseconds().max(0) as u64,
+            phases_ms: job.phase_durations_ms.clone(),
+            total_blobs: job.total_blobs,
+            completed_blobs: job.completed_blobs,
+            total_batches: job.total_batches,
+            completed_batches: job.completed_batches,
+            bytes_sent: job.bytes_sent,
+            batch_failures,
+            error: job.last_error.clone(),
+        }
+    }
+}
+
+/// 历史查询条件；字段为空表示不过滤
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HistoryQuery {
+    /// 已规范化的项目根
+    pub project_root: Option<String>,
+    pub status: Option<String>,
+    pub trigger: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+fn history_file() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let data_dir = home.join(".acemcp").join("data");
+    let _ = fs::create_dir_all(&data_dir);
+    data_dir.join("index_job_history.jsonl")
+}
+
+/// 历史文件的跨进程写锁。裁剪时历史文件会被整体替换，因此锁加在旁路的 `.lock` 文件上；
+/// 与 jobs 的 lease 一样按文件句柄加锁，同进程的并发写入同样互斥。
+fn lock_history(path: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path.with_extension("jsonl.lock"))?;
+    file.lock()?;
+    Ok(file)
+}
+
+fn retention_days() -> u32 {
+    crate::config::load_standalone_config()
+        .ok()
+        .and_then(|config| config.mcp_config.acemcp_job_history_retention_days)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+        .max(1)
+}
+
+/// 追加一条运行记录；写入失败只记录警告，不影响索引任务。
+pub(crate) fn record(entry: IndexJobRecord) {
+    if let Err(error) = append_record(&history_file(), &entry, retention_days(), Utc::now()) {
+        log::warn!("写入 ACE 索引任务历史失败: {}", error);
+    }
+}
+
+fn append_record(
+    path: &Path,
+    entry: &IndexJobRecord,
+    retention_days: u32,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let _lock = lock_history(path)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    drop(file);
+
+    // 只有存在过期或超额记录时才重写文件，多数追加不触发
+    let records = read_records(path);
+    let cutoff = now - Duration::days(i64::from(retention_days));
+    let expired = records
+        .iter()
+        .take_while(|record| record.finished_at < cutoff)
+        .count();
+    let overflow = records.len().saturating_sub(MAX_HISTORY_RECORDS);
+    let drop_count = expired.max(overflow);
+    if drop_count == 0 {
+        return Ok(());
+    }
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let mut tmp = NamedTempFile::new_in(dir)?;
+    for record in &records[drop_count..] {
+        writeln!(tmp, "{}", serde_json::to_string(record)?)?;
+    }
+    tmp.persist(path)?;
+    Ok(())
+}
+
+/// 按写入顺序（旧记录在前）读取；无法解析的行跳过。
+fn read_records(path: &Path) -> Vec<IndexJobRecord> {
+    let Ok(data) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    data.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// 查询历史记录（新记录在前）
+pub(crate) fn query_history(query: &HistoryQuery) -> Vec<IndexJobRecord> {
+    query_records(&history_file(), query)
+}
+
+fn query_records(path: &Path, query: &HistoryQuery) -> Vec<IndexJobRecord> {
+    read_records(path)
+        .into_iter()
+        .rev()
+        .filter(|record| {
+            query
+                .project_root
+                .as_deref()
+                .is_none_or(|root| record.project_root == root)
+        })
+        .filter(|record| {
+            query
+                .status
+                .as_deref()
+                .is_none_or(|status| record.status.eq_ignore_ascii_case(status))
+        })
+        .filter(|record| {
+            query
+                .trigger
+                .as_deref()
+                .is_none_or(|trigger| record.trigger.eq_ignore_ascii_case(trigger))
+        })
+        .filter(|record| query.since.is_none_or(|since| record.finished_at >= since))
+        .take(query.limit)
+        .collect()
+}
+
+/// 供 MCP 输出的文本摘要
+pub(crate) fn format_history(records: &[IndexJobRecord]) -> String {
+    if records.is_empty() {
+        return "暂无索引任务历史".to_string();
+    }
+    let mut lines = vec![format!("最近 {} 次索引任务（新记录在前）：", records.len())];
+    for record in records {
+        let phases = record
+            .phases_ms
+            .iter()
+            .map(|(phase, ms)| format!("{}={}", phase, format_duration_ms(*ms)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(format!(
+            "- {} [{}] {} / {} / {}，耗时 {}（{}），blob {}/{}，批次 {}/{}，发送 {}",
+            record.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            record.status,
+            record.project_root,
+            record.mode,
+            record.trigger,
+            format_duration_ms(record.duration_ms),
+            if phases.is_empty() {
+                "-".to_string()
+            } else {
+                phases
+            },
+            record.completed_blobs,
+            record.total_blobs,
+            record.completed_batches,
+            record.total_batches,
+            format_bytes(record.bytes_sent),
+        ));
+        for failure in &record.batch_failures {
+            lines.push(format!(
+                "    批次 {} 失败（{} 个 blob）: {}",
+                failure.batch, failure.failed_blobs, failure.error
+            ));
+        }
+        if let Some(error) = record.error.as_deref() {
+            lines.push(format!("    错误: {}", error));
+        }
+    }
+    lines.join("\n")
+}
+
+fn format_duration_ms(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m{}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{}B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1}KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1}MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_at(root: &str, trigger: &str, finished_at: DateTime<Utc>) -> IndexJobRecord {
+        IndexJobRecord {
+            job_id: "job".to_string(),
+            project_root: root.to_string(),
+            mode: "incremental".to_string(),
+            trigger: trigger.to_string(),
+            status: "completed".to_string(),
+            started_at: finished_at,
+            finished_at,
+            duration_ms: 0,
+            phases_ms: BTreeMap::new(),
+            total_blobs: 0,
+            completed_blobs: 0,
+            total_batches: 0,
+            completed_batches: 0,
+            bytes_sent: 0,
+            batch_failures: Vec::new(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn concurrent_appends_survive_pruning_rewrites() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("index_job_history.jsonl");
+        let now = Utc::now();
+        for _ in 0..50 {
+            let expired = now - Duration::days(40);
+            append_record(&path, &record_at("/old", "watcher", expired), 30, expired).unwrap();
+        }
+
+        let writers = (0..8)
+            .map(|writer| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        let root = format!("/w{}", writer);
+                        append_record(&path, &record_at(&root, "search", now), 30, now).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let records = read_records(&path);
+        assert_eq!(records.len(), 200);
+        assert!(records.iter().all(|record| record.project_root != "/old"));
+        assert!(!temp.path().join("index_job_history.jsonl.tmp").exists());
+    }
+
+    #[test]
+    fn append_prunes_expired_records_and_query_filters() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("index_job_history.jsonl");
+        let now = Utc::now();
+        append_record(
+            &path,
+            &record_at("/a", "watcher", now - Duration::days(40)),
+            30,
+            now - Duration::days(40),
+        )
+        .unwrap();
+        append_record(
+            &path,
+            &record_at("/a", "search", now - Duration::days(1)),
+            30,
+            now,
+        )
+        .unwrap();
+        append_record(&path, &record_at("/b", "watcher", now), 30, now).unwrap();
+
+        assert_eq!(read_records(&path).len(), 2);
+        let all = query_records(
+            &path,
+            &HistoryQuery {
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        assert_eq!(all[0].project_root, "/b");
+        let watcher = query_records(
+            &path,
+            &HistoryQuery {
+                trigger: Some("watcher".to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        assert_eq!(watcher.len(), 1);
+        assert_eq!(watcher[0].project_root, "/b");
+        let project_a = query_records(
+            &path,
+            &HistoryQuery {
+                project_root: Some("/a".to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+        );
+        assert_eq!(project_a.len(), 1);
+        assert_eq!(project_a[0].trigger, "search");
+    }
+}
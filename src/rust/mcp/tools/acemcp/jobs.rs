@@ -8,13 +8,15 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File, OpenOptions, TryLockError};
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 use tauri::{AppHandle, Emitter};
 use uuid::Uuid;
 
+use super::job_history::{self, IndexJobRecord};
+
 pub(crate) const JOB_QUEUED: &str = "queued";
 pub(crate) const JOB_COLLECTING: &str = "collecting";
 pub(crate) const JOB_UPLOADING: &str = "uploading";
@@ -72,6 +74,32 @@ pub(crate) struct IndexJob {
     pub updated_at: DateTime<Utc>,
     #[serde(default)]
     pub events: Vec<IndexJobEvent>,
+    /// 本轮执行的触发来源，见 `job_history::IndexTrigger`
+    #[serde(default)]
+    pub trigger: Option<String>,
+    /// 本轮执行进入 queued 的时间
+    #[serde(default)]
+    pub run_started_at: Option<DateTime<Utc>>,
+    /// 当前状态开始的时间，用于累计阶段耗时
+    #[serde(default)]
+    pub phase_started_at: Option<DateTime<Utc>>,
+    /// 本轮各阶段累计耗时（毫秒）
+    #[serde(default)]
+    pub phase_durations_ms: BTreeMap<String, u64>,
+    /// 本轮收到成功响应的批次所上传的 blob 内容字节数
+    #[serde(default)]
+    pub bytes_sent: u64,
+    /// 本轮失败批次明细
+    #[serde(default)]
+    pub batch_failures: Vec<BatchFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchFailure {
+    pub batch: usize,
+    pub failed_blobs: usize,
+    pub error: String,
+    pub at: DateTime<Utc>,
 }
 
 impl IndexJob {
@@ -101,6 +129,12 @@ impl IndexJob {
             created_at: now.clone(),
             updated_at: now,
             events: Vec::new(),
+            trigger: None,
+            run_started_at: Some(now),
+            phase_started_at: Some(now),
+            phase_durations_ms: BTreeMap::new(),
+            bytes_sent: 0,
+            batch_failures: Vec::new(),
         }
     }
 
@@ -110,6 +144,52 @@ impl IndexJob {
             JOB_QUEUED | JOB_COLLECTING | JOB_UPLOADING | JOB_PAUSED
         )
     }
+
+    /// 状态切换时累计上一阶段耗时；进入 queued 开始新一轮执行，
+    /// 进入结束状态时返回本轮的历史记录。
+    fn track_status_change(
+        &mut self,
+        previous_status: &str,
+        previous_trigger: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Vec<IndexJobRecord> {
+        let mut records = Vec::new();
+        if self.status == previous_status {
+            return records;
+        }
+        if matches!(previous_status, JOB_QUEUED | JOB_COLLECTING | JOB_UPLOADING) {
+            if let Some(started) = self.phase_started_at {
+                let elapsed = (now - started).num_milliseconds().max(0) as u64;
+                *self
+                    .phase_durations_ms
+                    .entry(previous_status.to_string())
+                    .or_default() += elapsed;
+            }
+        }
+        if self.status == JOB_QUEUED {
+            // 上一轮未走到结束状态（进程退出后恢复），先记为 interrupted
+            if self.run_started_at.is_some()
+                && matches!(previous_status, JOB_COLLECTING | JOB_UPLOADING)
+            {
+                let mut record = IndexJobRecord::from_job(self, Some("interrupted"), now);
+                record.trigger = previous_trigger.unwrap_or("unknown").to_string();
+                records.push(record);
+            }
+            self.run_started_at = Some(now);
+            self.phase_durations_ms.clear();
+            self.bytes_sent = 0;
+            self.batch_failures.clear();
+        } else if self.run_started_at.is_some()
+            && matches!(
+                self.status.as_str(),
+                JOB_COMPLETED | JOB_FAILED | JOB_PAUSED | JOB_SCOPE_BLOCKED
+            )
+        {
+            records.push(IndexJobRecord::from_job(self, None, now));
+        }
+        self.phase_started_at = Some(now);
+        records
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -281,7 +361,7 @@ pub(crate) fn update_job<F>(
 where
     F: FnOnce(&mut IndexJob),
 {
-    let updated = {
+    let (updated, history) = {
         let _guard = manifest_lock()
             .lock()
             .map_err(|_| anyhow::anyhow!("获取 ACE 索引任务清单锁失败"))?;
@@ -290,8 +370,15 @@ where
         let Some(job) = manifest.jobs.get_mut(project_root) else {
             return Ok(None);
         };
+        let previous_status = job.status.clone();
+        let previous_trigger = job.trigger.clone();
         updater(job);
         job.updated_at = Utc::now();
+        let history = job.track_status_change(
+            &previous_status,
+            previous_trigger.as_deref(),
+            job.updated_at,
+        );
         let event = IndexJobEvent {
             event_id: Uuid::new_v4().to_string(),
             job_id: job.job_id.clone(),
@@ -317,9 +404,12 @@ where
         }
         let updated = job.clone();
         save_manifest_unlocked(&manifest)?;
-        updated
+        (updated, history)
     };
 
+    for record in history {
+        job_history::record(record);
+    }
     publish_latest_event(&updated);
     Ok(Some(updated))
 }
@@ -337,19 +427,27 @@ pub(crate) fn remove_job(project_root: &str) -> Result<bool> {
     Ok(removed)
 }
 
-fn project_lease_path(project_root: &str) -> PathBuf {
+/// 跨进程 lease 文件目录；项目 lease 与调度器执行槽位都放在这里
+pub(crate) fn lease_dir() -> PathBuf {
     let jobs_path = home_index_jobs_file();
-    let lease_dir = jobs_path
+    jobs_path
         .parent()
         .unwrap_or_else(|| Path::new("."))
-        .join("index_job_leases");
+        .join("index_job_leases")
+}
+
+fn project_lease_path(project_root: &str) -> PathBuf {
     let digest = Md5::digest(project_root.as_bytes());
-    lease_dir.join(format!("{}.lock", hex::encode(digest)))
+    lease_dir().join(format!("{}.lock", hex::encode(digest)))
 }
 
 /// 尝试获取项目级 worker lease；`None` 表示另一个进程正在上传。
 pub(crate) fn try_acquire_project_lease(project_root: &str) -> Result<Option<ProjectLease>> {
-    let path = project_lease_path(project_root);
+    try_acquire_lease_file(&project_lease_path(project_root))
+}
+
+/// 对 lease 文件加非阻塞排他锁；`None` 表示已被其他进程（或本进程的其他句柄）持有。
+pub(crate) fn try_acquire_lease_file(path: &Path) -> Result<Option<ProjectLease>> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
@@ -357,12 +455,12 @@ pub(crate) fn try_acquire_project_lease(project_root: &str) -> Result<Option<Pro
         .read(true)
         .write(true)
         .create(true)
-        .open(&path)?;
+        .open(path)?;
     match file.try_lock() {
         Ok(()) => Ok(Some(ProjectLease { _file: file })),
         Err(TryLockError::WouldBlock) => Ok(None),
         Err(error) => Err(anyhow::anyhow!(
-            "获取 ACE 项目 worker lease 失败: path={}, error={}",
+            "获取 ACE lease 文件失败: path={}, error={}",
             path.display(),
             error
         )),
@@ -6,32 +6,39 @@ use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, OnceLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use encoding_rs::{GBK, UTF_8, WINDOWS_1252};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use reqwest::{Client, StatusCode};
+use reqwest::Client;
 use ring::digest::{Context as ShaContext, SHA256};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tauri::AppHandle;
 
+use super::chunking::split_content_defined;
+use super::git_events::{self, GitChangeSet};
+use super::job_history::IndexTrigger;
 use super::jobs::{
     self, IndexJob, JOB_COLLECTING, JOB_COMPLETED, JOB_FAILED, JOB_PAUSED, JOB_QUEUED,
     JOB_SCOPE_BLOCKED, JOB_UPLOADING,
 };
+use super::remote::{create_remote_backend, BlobItem, RemoteIndexBackend, RemoteIndexError};
+use super::scheduler::{self, IndexPriority};
 use super::scope_guard::{
     critical_path_risk, effective_exclude_patterns, is_confirmed_project_root,
-    preflight_project_scope,
+    preflight_project_scope, BUILTIN_EXCLUDE_PATTERNS, MAX_SCANNED_ENTRIES,
 };
+use super::secret_scan::{self, ScanDecision, SecretScanner, CHANNEL_ACE_UPLOAD};
 use super::types::{
     AcemcpConfig, AcemcpRequest, FileIndexStatus, FileIndexStatusKind, IndexStatus,
     NestedProjectInfo, ProjectFilesStatus, ProjectIndexStatus, ProjectScopeRisk,
-    ProjectWithNestedStatus, ProjectsIndexStatus,
+    ProjectScopeRiskLevel, ProjectWithNestedStatus, ProjectsIndexStatus,
 };
 use crate::log_debug;
 use crate::log_important;
+use crate::mcp::tools::sou::why_not_indexed::{ExcludedDirectory, RuleCheck};
+use crate::network::resilience::{self, RetryPolicy};
 // 代理模块（在 create_acemcp_client 中使用）
 
 /// Acemcp工具实现
@@ -89,14 +96,17 @@ impl AcemcpTool {
                 )
                 .await
             {
-                if get_project_status(&request.project_root_path)
-                    .scope_risk
-                    .is_some()
-                {
+                if let Some(risk) = get_project_status(&request.project_root_path).scope_risk {
+                    let message = if risk.level == ProjectScopeRiskLevel::SecretsDetected {
+                        format!(
+                            "代码搜索已暂停：{}请打开等一下窗口查看疑似密钥报告并确认后再上传。",
+                            risk.reason
+                        )
+                    } else {
+                        "代码搜索已暂停：项目路径或索引规模存在风险。请打开等一下窗口确认该项目路径，或移除错误的索引记录。".to_string()
+                    };
                     return Ok(CallToolResult {
-                        content: vec![Content::text(
-                            "代码搜索已暂停：项目路径或索引规模存在风险。请打开等一下窗口确认该项目路径，或移除错误的索引记录。",
-                        )],
+                        content: vec![Content::text(message)],
                         is_error: Some(true),
                         meta: None,
                         structured_content: None,
@@ -311,6 +321,8 @@ impl AcemcpTool {
                     true,
                     mode,
                     app,
+                    IndexPriority::Search,
+                    IndexTrigger::Manual,
                 )
                 .await?;
                 return Ok(format!("已提交后台索引任务: {:?}", launch));
@@ -335,6 +347,8 @@ impl AcemcpTool {
                     true,
                     mode,
                     app.clone(),
+                    IndexPriority::Backfill,
+                    IndexTrigger::Manual,
                 )
                 .await?;
                 launched.push((nested.relative_path.clone(), format!("{:?}", state)));
@@ -348,6 +362,8 @@ impl AcemcpTool {
                 true,
                 mode,
                 app,
+                IndexPriority::Search,
+                IndexTrigger::Manual,
             )
             .await?;
             Ok(format!("已提交后台索引任务: {:?}", state))
@@ -387,6 +403,10 @@ impl AcemcpTool {
             reconcile_project_status_with_job(status);
             enrich_project_scope_state(status);
         }
+        let upload_bandwidth_kbps = crate::config::load_standalone_config()
+            .ok()
+            .and_then(|config| config.mcp_config.acemcp_upload_bandwidth_kbps);
+        all_status.queue = Some(scheduler::queue_status(upload_bandwidth_kbps));
         all_status
     }
 
@@ -467,6 +487,12 @@ impl AcemcpTool {
             proxy_type: config.mcp_config.acemcp_proxy_type,
             proxy_username: config.mcp_config.acemcp_proxy_username,
             proxy_password: config.mcp_config.acemcp_proxy_password,
+            remote_backend: config.mcp_config.acemcp_remote_backend,
+            index_concurrency: config.mcp_config.acemcp_index_concurrency,
+            upload_bandwidth_kbps: config.mcp_config.acemcp_upload_bandwidth_kbps,
+            secret_scan_enabled: config.mcp_config.secret_scan_enabled,
+            secret_scan_policy: config.mcp_config.secret_scan_policy,
+            secret_scan_custom_patterns: config.mcp_config.secret_scan_custom_patterns,
         })
     }
 
@@ -695,6 +721,16 @@ pub(crate) fn build_index_scope_hash(config: &AcemcpConfig) -> Option<String> {
         "text_extensions": normalized_config_values(config.text_extensions.as_ref()),
         "exclude_patterns": normalized_config_values(Some(&effective_excludes)),
     });
+    let mut fingerprint = fingerprint;
+    // 只有切换到非默认后端时才写入签名，保持既有 ACE 项目的签名不变
+    let remote_backend = super::remote::normalize_remote_backend(config.remote_backend.as_deref());
+    if remote_backend != super::remote::REMOTE_BACKEND_ACE {
+        fingerprint["remote_backend"] = serde_json::Value::String(remote_backend);
+    }
+    // 密钥扫描改变上传内容（脱敏 / 跳过），非默认设置写入签名，改动后全量重扫
+    if let Some(secret_scan) = SecretScanner::from_acemcp_config(config).fingerprint() {
+        fingerprint["secret_scan"] = secret_scan;
+    }
     let mut ctx = ShaContext::new(&SHA256);
     ctx.update(fingerprint.to_string().as_bytes());
     Some(hex::encode(ctx.finish().as_ref()))
@@ -736,6 +772,12 @@ fn enrich_project_scope_state(status: &mut ProjectIndexStatus) {
                 proxy_type: config.mcp_config.acemcp_proxy_type,
                 proxy_username: config.mcp_config.acemcp_proxy_username,
                 proxy_password: config.mcp_config.acemcp_proxy_password,
+                remote_backend: config.mcp_config.acemcp_remote_backend,
+                index_concurrency: config.mcp_config.acemcp_index_concurrency,
+                upload_bandwidth_kbps: config.mcp_config.acemcp_upload_bandwidth_kbps,
+                secret_scan_enabled: config.mcp_config.secret_scan_enabled,
+                secret_scan_policy: config.mcp_config.secret_scan_policy,
+                secret_scan_custom_patterns: config.mcp_config.secret_scan_custom_patterns,
             })
         });
     let has_local_blobs = if status.project_root.is_empty() {
@@ -811,34 +853,58 @@ pub(crate) async fn ensure_project_scope_allowed(
     config: &AcemcpConfig,
     project_root: &str,
 ) -> anyhow::Result<bool> {
-    if is_confirmed_project_root(project_root) {
-        clear_project_scope_risk(project_root)?;
-        return Ok(true);
-    }
-    if let Some(risk) = critical_path_risk(project_root) {
-        record_project_scope_risk(project_root, risk)?;
-        return Ok(false);
-    }
-
-    let project_root = project_root.to_string();
-    let project_root_for_preflight = project_root.clone();
     let text_extensions = config.text_extensions.clone().unwrap_or_default();
     let exclude_patterns = effective_exclude_patterns(config.exclude_patterns.as_deref());
-    let risk = tokio::task::spawn_blocking(move || {
-        preflight_project_scope(
-            &project_root_for_preflight,
-            &text_extensions,
-            &exclude_patterns,
-        )
-    })
-    .await
-    .map_err(|error| anyhow::anyhow!("项目范围预检任务失败: {}", error))??;
 
-    if let Some(risk) = risk {
-        record_project_scope_risk(project_root.as_str(), risk)?;
-        return Ok(false);
+    if !is_confirmed_project_root(project_root) {
+        if let Some(risk) = critical_path_risk(project_root) {
+            record_project_scope_risk(project_root, risk)?;
+            return Ok(false);
+        }
+
+        let project_root_for_preflight = project_root.to_string();
+        let text_extensions = text_extensions.clone();
+        let exclude_patterns = exclude_patterns.clone();
+        let risk = tokio::task::spawn_blocking(move || {
+            preflight_project_scope(
+                &project_root_for_preflight,
+                &text_extensions,
+                &exclude_patterns,
+            )
+        })
+        .await
+        .map_err(|error| anyhow::anyhow!("项目范围预检任务失败: {}", error))??;
+
+        if let Some(risk) = risk {
+            record_project_scope_risk(project_root, risk)?;
+            return Ok(false);
+        }
+    }
+
+    // 首次上传前展示疑似密钥报告；已有上传记录或用户已确认的项目不再拦截
+    let scanner = SecretScanner::from_acemcp_config(config);
+    if scanner.is_enabled()
+        && !has_local_blob_names(project_root)
+        && !secret_scan::is_acknowledged_root(project_root)
+    {
+        let project_root_for_scan = project_root.to_string();
+        let risk = tokio::task::spawn_blocking(move || {
+            secret_scan::first_upload_report(
+                &scanner,
+                &project_root_for_scan,
+                &text_extensions,
+                &exclude_patterns,
+            )
+        })
+        .await
+        .map_err(|error| anyhow::anyhow!("首次上传密钥扫描任务失败: {}", error))??;
+
+        if let Some(risk) = risk {
+            record_project_scope_risk(project_root, risk)?;
+            return Ok(false);
+        }
     }
-    clear_project_scope_risk(project_root.as_str())?;
+    clear_project_scope_risk(project_root)?;
     Ok(true)
 }
 
@@ -869,6 +935,8 @@ async fn start_background_index(
         force,
         IndexJobMode::Incremental,
         None,
+        IndexPriority::Search,
+        IndexTrigger::Search,
     )
     .await
 }
@@ -879,11 +947,13 @@ async fn start_background_index_with_mode(
     force: bool,
     mode: IndexJobMode,
     app: Option<AppHandle>,
+    priority: IndexPriority,
+    trigger: IndexTrigger,
 ) -> anyhow::Result<BackgroundIndexLaunchState> {
     if !ensure_project_scope_allowed(config, project_root).await? {
         return Ok(BackgroundIndexLaunchState::ScopeBlocked);
     }
-    launch_index_worker(config, project_root, force, mode, app)
+    launch_index_worker(config, project_root, force, mode, app, priority, trigger)
 }
 
 /// 同步完成任务去重、检查点初始化并启动异步 worker。
@@ -894,6 +964,8 @@ fn launch_index_worker(
     force: bool,
     mode: IndexJobMode,
     app: Option<AppHandle>,
+    priority: IndexPriority,
+    trigger: IndexTrigger,
 ) -> anyhow::Result<BackgroundIndexLaunchState> {
     let normalized_root = normalize_project_path(
         &PathBuf::from(project_root)
@@ -930,6 +1002,8 @@ fn launch_index_worker(
             if force {
                 request_followup_index(&normalized_root, mode);
             }
+            // 排队中的同项目任务继承更高优先级，例如 watcher 任务等待期间用户发起搜索
+            scheduler::promote(&normalized_root, priority);
             return Ok(BackgroundIndexLaunchState::AlreadyRunning);
         }
     }
@@ -975,8 +1049,11 @@ fn launch_index_worker(
     let _ = jobs::update_job(
         &normalized_root,
         "queued",
-        Some("后台任务已排队".to_string()),
-        |job| job.status = JOB_QUEUED.to_string(),
+        Some(format!("后台任务已排队（{}）", trigger.as_str())),
+        |job| {
+            job.status = JOB_QUEUED.to_string();
+            job.trigger = Some(trigger.as_str().to_string());
+        },
     );
     let _ = update_project_status(project_root, |status| {
         status.status = IndexStatus::Indexing;
@@ -990,11 +1067,15 @@ fn launch_index_worker(
     let normalized_root_clone = normalized_root.clone();
     tokio::spawn(async move {
         let lease = lease;
+        // 全局调度：超出并发上限时保持 queued 状态等待槽位
+        scheduler::set_max_concurrency(config_clone.index_concurrency);
+        let permit = scheduler::acquire(&normalized_root_clone, priority).await;
         log_important!(
             info,
-            "后台索引任务启动: project_root={}, job_id={}",
+            "后台索引任务启动: project_root={}, job_id={}, priority={:?}",
             project_root_clone,
-            job_id
+            job_id,
+            priority
         );
         let task_succeeded = match update_index_with_mode(
             &config_clone,
@@ -1057,8 +1138,9 @@ fn launch_index_worker(
             let mut inflight = auto_index_inflight().lock().unwrap();
             inflight.remove(&normalized_root_clone);
         }
-        // 中文说明：当前 worker 已结束，先释放项目 lease，再尝试启动配置变更或后继任务。
+        // 中文说明：当前 worker 已结束，先释放项目 lease 与调度槽位，再尝试启动配置变更或后继任务。
         drop(lease);
+        drop(permit);
 
         // 中文说明：配置可能在任务执行期间被保存；旧任务退出后立即接续一次新签名的全量任务。
         if let Ok(latest_config) = AcemcpTool::get_acemcp_config().await {
@@ -1074,6 +1156,8 @@ fn launch_index_worker(
                     true,
                     IndexJobMode::Full,
                     None,
+                    IndexPriority::Watcher,
+                    IndexTrigger::ConfigChange,
                 )
                 .await;
             } else if task_succeeded {
@@ -1088,6 +1172,8 @@ fn launch_index_worker(
                         true,
                         rerun_mode,
                         None,
+                        IndexPriority::Watcher,
+                        IndexTrigger::Followup,
                     )
                     .await;
                 }
@@ -1159,6 +1245,8 @@ pub async fn resume_index_jobs() -> anyhow::Result<()> {
                 true,
                 IndexJobMode::Full,
                 None,
+                IndexPriority::Backfill,
+                IndexTrigger::Resume,
             )
             .await?;
             continue;
@@ -1169,6 +1257,8 @@ pub async fn resume_index_jobs() -> anyhow::Result<()> {
             false,
             IndexJobMode::from_str(&job.mode),
             None,
+            IndexPriority::Backfill,
+            IndexTrigger::Resume,
         )
         .await?;
     }
@@ -1176,16 +1266,26 @@ pub async fn resume_index_jobs() -> anyhow::Result<()> {
 }
 
 /// 文件监听只负责排队，上传由统一后台任务执行，确保同样具备批次断点与事件。
+/// `git_changes` 为 git 事件计算出的精确变更集，普通文件事件传 None（整树扫描）。
 pub(crate) async fn enqueue_incremental_index(
     config: &AcemcpConfig,
     project_root: &str,
+    git_changes: Option<GitChangeSet>,
 ) -> anyhow::Result<()> {
+    let trigger = if git_changes.is_some() {
+        IndexTrigger::Git
+    } else {
+        IndexTrigger::Watcher
+    };
+    git_events::record_pending(&normalize_project_path(project_root), git_changes);
     let _ = start_background_index_with_mode(
         config,
         project_root,
         true,
         IndexJobMode::Incremental,
         None,
+        IndexPriority::Watcher,
+        trigger,
     )
     .await?;
     Ok(())
@@ -1193,12 +1293,6 @@ pub(crate) async fn enqueue_incremental_index(
 
 // ---------------- 整合 temp 逻辑：索引、上传、检索 ----------------
 
-#[derive(Serialize, Deserialize, Clone)]
-struct BlobItem {
-    path: String,
-    content: String,
-}
-
 #[derive(Serialize, Deserialize, Default)]
 pub(crate) struct ProjectsFile(pub HashMap<String, Vec<String>>);
 
@@ -1299,6 +1393,62 @@ fn has_local_blob_names(project_root: &str) -> bool {
         .unwrap_or(false)
 }
 
+/// blob 指纹缓存：projects.json 的修改时间与大小不变时直接复用已计算的指纹
+#[derive(Default)]
+struct FingerprintCache {
+    signature: Option<(std::time::SystemTime, u64)>,
+    fingerprints: HashMap<String, Option<String>>,
+}
+
+static BLOB_FINGERPRINTS: OnceLock<Mutex<FingerprintCache>> = OnceLock::new();
+
+fn fingerprint_cache() -> &'static Mutex<FingerprintCache> {
+    BLOB_FINGERPRINTS.get_or_init(|| Mutex::new(FingerprintCache::default()))
+}
+
+/// 项目已确认 blob 集合的指纹；集合为空或项目未索引时返回 None。
+/// 供 sou 查询缓存判断 ACE 索引是否发生变化。
+pub(crate) fn project_blob_fingerprint(project_root: &str) -> Option<String> {
+    let normalized_root = normalize_project_path(
+        &PathBuf::from(project_root)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(project_root))
+            .to_string_lossy(),
+    );
+    let signature = fs::metadata(home_projects_file())
+        .ok()
+        .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+    let mut cache = fingerprint_cache().lock().ok()?;
+    // 中文说明：其他进程写入 projects.json 时靠修改时间与大小感知，本进程写入时直接清空。
+    if signature.is_none() || cache.signature != signature {
+        cache.signature = signature;
+        cache.fingerprints.clear();
+    }
+    if let Some(fingerprint) = cache.fingerprints.get(&normalized_root) {
+        return fingerprint.clone();
+    }
+    let fingerprint = compute_blob_fingerprint(&load_projects_file(), &normalized_root);
+    cache
+        .fingerprints
+        .insert(normalized_root, fingerprint.clone());
+    fingerprint
+}
+
+fn compute_blob_fingerprint(projects: &ProjectsFile, normalized_root: &str) -> Option<String> {
+    let blob_names = projects.0.get(normalized_root)?;
+    if blob_names.is_empty() {
+        return None;
+    }
+    let mut sorted = blob_names.iter().collect::<Vec<_>>();
+    sorted.sort();
+    let mut ctx = ShaContext::new(&SHA256);
+    for name in sorted {
+        ctx.update(name.as_bytes());
+        ctx.update(b"\n");
+    }
+    Some(hex::encode(&ctx.finish().as_ref()[..8]))
+}
+
 /// 规范化项目路径，去除 Windows 扩展路径前缀并统一使用正斜杠
 ///
 /// Windows 的 `canonicalize()` 会返回 `//?/C:/...` 或 `\\?\C:\...` 格式的路径，
@@ -1319,8 +1469,10 @@ fn normalize_project_path(path: &str) -> String {
     p.replace('\\', "/")
 }
 
+/// 远端索引请求统一走 resilience 层：指数退避 + jitter、遵循 Retry-After、按端点熔断
 async fn retry_request<F, Fut, T>(
-    mut f: F,
+    backend: &dyn RemoteIndexBackend,
+    f: F,
     max_retries: usize,
     base_delay_secs: f64,
 ) -> anyhow::Result<T>
@@ -1328,50 +1480,10 @@ where
     F: FnMut() -> Fut,
     Fut: std::future::Future<Output = anyhow::Result<T>>,
 {
-    let mut attempt = 0usize;
-    let mut last_error_str: Option<String> = None;
-
-    while attempt < max_retries {
-        match f().await {
-            Ok(v) => {
-                if attempt > 0 {
-                    log_debug!("请求在第{}次尝试后成功", attempt + 1);
-                }
-                return Ok(v);
-            }
-            Err(e) => {
-                last_error_str = Some(e.to_string());
-                attempt += 1;
-
-                // 检查是否为可重试的错误
-                let error_str = e.to_string();
-                let is_retryable = error_str.contains("timeout")
-                    || error_str.contains("connection")
-                    || error_str.contains("network")
-                    || error_str.contains("temporary");
-
-                if attempt >= max_retries || !is_retryable {
-                    log_debug!("请求失败，不再重试: {}", e);
-                    return Err(e);
-                }
-
-                let delay = base_delay_secs * 2f64.powi((attempt as i32) - 1);
-                let ms = (delay * 1000.0) as u64;
-                log_debug!(
-                    "请求失败，准备重试({}/{}), 等待 {}ms: {}",
-                    attempt,
-                    max_retries,
-                    ms,
-                    e
-                );
-                tokio::time::sleep(Duration::from_millis(ms)).await;
-            }
-        }
-    }
-
-    Err(last_error_str
-        .and_then(|s| anyhow::anyhow!(s).into())
-        .unwrap_or_else(|| anyhow::anyhow!("未知错误")))
+    let endpoint = resilience::endpoint_key(resilience::SERVICE_ACE, backend.base_url());
+    let base_delay_ms = (base_delay_secs * 1000.0) as u64;
+    let policy = RetryPolicy::new(max_retries, base_delay_ms, base_delay_ms * 30);
+    resilience::call(&endpoint, policy, f).await
 }
 
 pub(crate) fn home_projects_file() -> PathBuf {
@@ -1416,7 +1528,11 @@ fn load_projects_file() -> ProjectsFile {
 }
 
 fn save_projects_file(projects: &ProjectsFile) -> Result<()> {
-    write_json_atomically(&home_projects_file(), projects)
+    let result = write_json_atomically(&home_projects_file(), projects);
+    if let Ok(mut cache) = fingerprint_cache().lock() {
+        *cache = FingerprintCache::default();
+    }
+    result
 }
 
 fn load_json_with_backup<T>(path: &Path) -> T
@@ -1435,6 +1551,70 @@ where
     T::default()
 }
 
+/// 清单格式版本；分块算法变化时递增，旧清单整体失效
+const BLOB_MANIFEST_VERSION: u32 = 1;
+
+/// 项目级 blob 清单：记录每个文件上次成功索引时的大小、修改时间与 blob 名称
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BlobManifest {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    scope_hash: String,
+    #[serde(default)]
+    files: HashMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestEntry {
+    size: u64,
+    modified_ns: u64,
+    blob_names: Vec<String>,
+}
+
+impl BlobManifest {
+    fn new(scope_hash: String) -> Self {
+        Self {
+            version: BLOB_MANIFEST_VERSION,
+            scope_hash,
+            files: HashMap::new(),
+        }
+    }
+}
+
+fn blob_manifest_file(normalized_root: &str) -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let manifest_dir = home.join(".acemcp").join("data").join("manifests");
+    let _ = fs::create_dir_all(&manifest_dir);
+    let mut ctx = ShaContext::new(&SHA256);
+    ctx.update(normalized_root.as_bytes());
+    let digest = hex::encode(ctx.finish().as_ref());
+    manifest_dir.join(format!("{}.json", &digest[..16]))
+}
+
+/// 读取清单；版本或索引空间不一致时返回空清单，相当于全部文件重新读取
+fn load_blob_manifest(normalized_root: &str, scope_hash: &str) -> BlobManifest {
+    let manifest: BlobManifest = load_json_with_backup(&blob_manifest_file(normalized_root));
+    if manifest.version != BLOB_MANIFEST_VERSION || manifest.scope_hash != scope_hash {
+        return BlobManifest::new(scope_hash.to_string());
+    }
+    manifest
+}
+
+fn save_blob_manifest(normalized_root: &str, manifest: &BlobManifest) -> Result<()> {
+    write_json_atomically(&blob_manifest_file(normalized_root), manifest)
+}
+
+fn file_stamp(metadata: &fs::Metadata) -> (u64, u64) {
+    let modified_ns = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+    (metadata.len(), modified_ns)
+}
+
 /// 获取项目索引状态文件路径
 fn home_projects_status_file() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
@@ -1621,7 +1801,7 @@ fn read_file_with_encoding(path: &Path) -> Option<String> {
     Some(decoded.into_owned())
 }
 
-fn sha256_hex(path: &str, content: &str) -> String {
+pub(crate) fn sha256_hex(path: &str, content: &str) -> String {
     let mut ctx = ShaContext::new(&SHA256);
     // 先更新路径的哈希，再更新内容的哈希，与Python版本保持一致
     ctx.update(path.as_bytes());
@@ -1630,44 +1810,8 @@ fn sha256_hex(path: &str, content: &str) -> String {
     hex::encode(digest.as_ref())
 }
 
-/// 分割文件内容为多个 blob（如果超过最大行数）
-/// 与 Python 版本保持一致：chunk 索引从 1 开始
-fn split_content(path: &str, content: &str, max_lines: usize) -> Vec<BlobItem> {
-    let lines: Vec<&str> = content.split_inclusive('\n').collect();
-    let total_lines = lines.len();
-
-    // 如果文件在限制内，返回单个 blob
-    if total_lines <= max_lines {
-        return vec![BlobItem {
-            path: path.to_string(),
-            content: content.to_string(),
-        }];
-    }
-
-    // 计算需要的 chunk 数量
-    let num_chunks = (total_lines + max_lines - 1) / max_lines;
-    let mut blobs = Vec::new();
-
-    // 按 chunk 索引分割（从 0 开始，但显示时从 1 开始）
-    for chunk_idx in 0..num_chunks {
-        let start_line = chunk_idx * max_lines;
-        let end_line = usize::min(start_line + max_lines, total_lines);
-        let chunk_lines = &lines[start_line..end_line];
-        let chunk_content = chunk_lines.join("");
-
-        // chunk 编号从 1 开始（与 Python 版本保持一致）
-        let chunk_path = format!("{}#chunk{}of{}", path, chunk_idx + 1, num_chunks);
-        blobs.push(BlobItem {
-            path: chunk_path,
-            content: chunk_content,
-        });
-    }
-
-    blobs
-}
-
 // 去除 blob 路径中的 chunk 后缀，恢复文件级路径
-fn strip_chunk_suffix(path: &str) -> &str {
+pub(crate) fn strip_chunk_suffix(path: &str) -> &str {
     path.split("#chunk").next().unwrap_or(path)
 }
 
@@ -1741,12 +1885,22 @@ fn build_gitignore(root: &Path) -> Option<Gitignore> {
     None
 }
 
+/// 收集结果：需要上传核对的 blob、经清单复用而未读取的 blob 名称，以及本次扫描后的清单
+struct CollectedBlobs {
+    blobs: Vec<BlobItem>,
+    reused_blob_names: Vec<String>,
+    manifest: BlobManifest,
+}
+
 fn collect_blobs(
     root: &str,
     text_exts: &[String],
     exclude_patterns: &[String],
     max_lines_per_blob: usize,
-) -> anyhow::Result<Vec<BlobItem>> {
+    previous_manifest: &BlobManifest,
+    confirmed_blob_names: &HashSet<String>,
+    scanner: &SecretScanner,
+) -> anyhow::Result<CollectedBlobs> {
     let root_path = PathBuf::from(root);
     if !root_path.exists() {
         anyhow::bail!("项目根目录不存在: {}", root);
@@ -1774,11 +1928,15 @@ fn collect_blobs(
     };
 
     let mut out = Vec::new();
+    let mut reused_blob_names = Vec::new();
+    let mut manifest = BlobManifest::new(previous_manifest.scope_hash.clone());
     let gitignore = build_gitignore(&root_path);
     let mut dirs_stack = vec![root_path.clone()];
     let mut scanned_files = 0;
     let mut indexed_files = 0;
+    let mut reused_files = 0;
     let mut excluded_count = 0;
+    let mut blocked_files = Vec::new();
 
     while let Some(dir) = dirs_stack.pop() {
         let entries = match fs::read_dir(&dir) {
@@ -1844,10 +2002,48 @@ fn collect_blobs(
                 .unwrap_or(&p)
                 .to_string_lossy()
                 .replace('\\', "/");
+            let file_stamp = entry.metadata().ok().map(|metadata| file_stamp(&metadata));
+            // 大小与修改时间未变且 blob 均已确认时直接复用清单，不再读取与分块
+            if let (Some((size, modified_ns)), Some(previous)) =
+                (file_stamp, previous_manifest.files.get(&rel))
+            {
+                if previous.size == size
+                    && previous.modified_ns == modified_ns
+                    && !previous.blob_names.is_empty()
+                    && previous
+                        .blob_names
+                        .iter()
+                        .all(|name| confirmed_blob_names.contains(name))
+                {
+                    indexed_files += 1;
+                    reused_files += 1;
+                    reused_blob_names.extend(previous.blob_names.iter().cloned());
+                    manifest.files.insert(rel, previous.clone());
+                    continue;
+                }
+            }
             if let Some(content) = read_file_with_encoding(&p) {
-                let parts = split_content(&rel, &content, max_lines_per_blob);
+                let Some(content) =
+                    scan_before_upload(scanner, root, &rel, content, &mut blocked_files)
+                else {
+                    continue;
+                };
+                let parts = split_content_defined(&rel, &content, max_lines_per_blob);
                 let blob_count = parts.len();
                 indexed_files += 1;
+                if let Some((size, modified_ns)) = file_stamp {
+                    manifest.files.insert(
+                        rel.clone(),
+                        ManifestEntry {
+                            size,
+                            modified_ns,
+                            blob_names: parts
+                                .iter()
+                                .map(|blob| sha256_hex(&blob.path, &blob.content))
+                                .collect(),
+                        },
+                    );
+                }
                 out.extend(parts);
                 log_debug!(
                     "索引文件: path={}, content_length={}, blobs={}",
@@ -1863,121 +2059,559 @@ fn collect_blobs(
 
     log_important!(
         info,
-        "文件收集完成: 扫描文件数={}, 索引文件数={}, 生成blobs数={}, 排除文件/目录数={}",
+        "文件收集完成: 扫描文件数={}, 索引文件数={}, 清单复用文件数={}, 生成blobs数={}, 排除文件/目录数={}",
         scanned_files,
         indexed_files,
+        reused_files,
         out.len(),
         excluded_count
     );
-    Ok(out)
+    ensure_no_blocked_files(&blocked_files)?;
+    Ok(CollectedBlobs {
+        blobs: out,
+        reused_blob_names,
+        manifest,
+    })
 }
 
-/// 收集项目内所有可索引文件的索引状态
+/// 按 git 变更集增量收集：未变更文件直接沿用上次清单，只读取变更路径，不遍历目录树。
 ///
-/// 为避免引入新的持久化结构，这里通过重新扫描文件并复用与索引阶段相同的
-/// 路径规范化与分块逻辑，基于现有的 blob 哈希集合判断文件是否“已完全索引”。
-fn collect_file_statuses(
+/// 清单为空、存在未确认的 blob、变更涉及 `.gitignore` 或目录时返回 None，由调用方退回整树扫描。
+#[allow(clippy::too_many_arguments)]
+fn collect_blobs_targeted(
     root: &str,
     text_exts: &[String],
     exclude_patterns: &[String],
     max_lines_per_blob: usize,
-    existing_blob_names: &HashSet<String>,
-) -> anyhow::Result<Vec<FileIndexStatus>> {
-    let root_path = PathBuf::from(root);
-    if !root_path.exists() {
-        anyhow::bail!("项目根目录不存在: {}", root);
+    previous_manifest: &BlobManifest,
+    confirmed_blob_names: &HashSet<String>,
+    changes: &GitChangeSet,
+    scanner: &SecretScanner,
+) -> Option<anyhow::Result<CollectedBlobs>> {
+    if previous_manifest.files.is_empty()
+        || changes
+            .upserted
+            .iter()
+            .chain(changes.removed.iter())
+            .any(|rel| rel == ".gitignore" || rel.ends_with("/.gitignore"))
+    {
+        return None;
     }
-
-    // 构建排除模式的 GlobSet
+    let root_path = PathBuf::from(root);
     let exclude_globset = if exclude_patterns.is_empty() {
         None
     } else {
-        match build_exclude_globset(exclude_patterns) {
-            Ok(gs) => Some(gs),
-            Err(e) => {
-                log_debug!("构建排除模式失败，将使用简单匹配: {}", e);
-                None
-            }
-        }
+        build_exclude_globset(exclude_patterns).ok()
     };
-
     let gitignore = build_gitignore(&root_path);
-    let mut dirs_stack = vec![root_path.clone()];
-    let mut files_status = Vec::new();
 
-    while let Some(dir) = dirs_stack.pop() {
-        let entries = match fs::read_dir(&dir) {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
+    let mut manifest = previous_manifest.clone();
+    // 删除的可能是目录（来自文件事件），连同其下的清单项一起移除
+    for rel in changes.removed.iter().chain(changes.upserted.iter()) {
+        let prefix = format!("{}/", rel);
+        manifest
+            .files
+            .retain(|path, _| path != rel && !path.starts_with(&prefix));
+    }
+    if manifest.files.values().any(|entry| {
+        entry
+            .blob_names
+            .iter()
+            .any(|name| !confirmed_blob_names.contains(name))
+    }) {
+        return None;
+    }
+    let reused_files = manifest.files.len();
+    let reused_blob_names = manifest
+        .files
+        .values()
+        .flat_map(|entry| entry.blob_names.iter().cloned())
+        .collect::<Vec<_>>();
 
-        for entry in entries.flatten() {
-            let p = entry.path();
-            let Ok(file_type) = entry.file_type() else {
-                continue;
-            };
-            if file_type.is_symlink() {
+    let mut out = Vec::new();
+    let mut blocked_files = Vec::new();
+    for rel in &changes.upserted {
+        let p = root_path.join(rel);
+        let Ok(metadata) = fs::symlink_metadata(&p) else {
+            // 已不存在：视为删除
+            continue;
+        };
+        if metadata.is_dir() {
+            return None;
+        }
+        if !metadata.is_file() {
+            continue;
+        }
+        if let Some(gi) = &gitignore {
+            if gi.matched_path_or_any_parents(&p, false).is_ignore() {
                 continue;
             }
+        }
+        if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
+            continue;
+        }
+        let ext_ok = p
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|e| {
+                let dot = format!(".{}", e).to_lowercase();
+                text_exts.iter().any(|te| te.eq_ignore_ascii_case(&dot))
+            })
+            .unwrap_or(false);
+        if !ext_ok {
+            continue;
+        }
+        let Some(content) = read_file_with_encoding(&p) else {
+            log_debug!("无法读取文件: {:?}", p);
+            continue;
+        };
+        let Some(content) = scan_before_upload(scanner, root, rel, content, &mut blocked_files)
+        else {
+            continue;
+        };
+        let parts = split_content_defined(rel, &content, max_lines_per_blob);
+        let (size, modified_ns) = file_stamp(&metadata);
+        manifest.files.insert(
+            rel.clone(),
+            ManifestEntry {
+                size,
+                modified_ns,
+                blob_names: parts
+                    .iter()
+                    .map(|blob| sha256_hex(&blob.path, &blob.content))
+                    .collect(),
+            },
+        );
+        out.extend(parts);
+    }
 
-            // .gitignore 过滤
-            if let Some(gi) = &gitignore {
-                if gi
-                    .matched_path_or_any_parents(&p, file_type.is_dir())
-                    .is_ignore()
-                {
-                    continue;
-                }
-            }
+    log_important!(
+        info,
+        "Git 变更集收集完成: 根目录={}, 变更路径={}, 删除路径={}, 清单复用文件数={}, 生成blobs数={}",
+        root,
+        changes.upserted.len(),
+        changes.removed.len(),
+        reused_files,
+        out.len()
+    );
+    if let Err(error) = ensure_no_blocked_files(&blocked_files) {
+        return Some(Err(error));
+    }
+    Some(Ok(CollectedBlobs {
+        blobs: out,
+        reused_blob_names,
+        manifest,
+    }))
+}
 
-            if file_type.is_dir() {
-                if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
-                    continue;
-                }
-                dirs_stack.push(p);
-                continue;
-            }
-            if !file_type.is_file() {
-                continue;
-            }
+/// 上传前按密钥扫描策略处置文件内容；返回 None 表示该文件不上传
+fn scan_before_upload(
+    scanner: &SecretScanner,
+    root: &str,
+    rel: &str,
+    content: String,
+    blocked_files: &mut Vec<String>,
+) -> Option<String> {
+    match scanner.apply(CHANNEL_ACE_UPLOAD, root, rel, content) {
+        ScanDecision::Allow(content) => Some(content),
+        ScanDecision::Skipped => {
+            log_debug!("密钥扫描跳过文件: {}", rel);
+            None
+        }
+        ScanDecision::Blocked(rules) => {
+            blocked_files.push(format!("{}({})", rel, rules.join(",")));
+            None
+        }
+    }
+}
 
-            if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
-                continue;
-            }
+fn ensure_no_blocked_files(blocked_files: &[String]) -> anyhow::Result<()> {
+    if blocked_files.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "检测到疑似密钥，已按 block 策略中止上传: {}",
+        blocked_files.join(", ")
+    )
+}
 
-            // 扩展名过滤
-            let ext_ok = p
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|e| {
-                    let dot = format!(".{}", e).to_lowercase();
-                    text_exts.iter().any(|te| te.eq_ignore_ascii_case(&dot))
-                })
-                .unwrap_or(false);
+/// 单个被排除目录的体积统计上限（条目数），避免 node_modules 等巨型目录拖慢诊断
+const EXCLUDED_DIR_SIZE_ENTRY_LIMIT: usize = 200_000;
 
-            if !ext_ok {
-                continue;
-            }
+/// 返回命中路径的排除模式；语义与 `should_exclude` 一致：任一祖先或自身的相对路径、或任一路径段匹配即命中
+fn matching_exclude_pattern(exclude_patterns: &[String], relative: &str) -> Option<String> {
+    let segments = relative
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    exclude_patterns
+        .iter()
+        .find(|pattern| {
+            let Ok(glob) = Glob::new(pattern) else {
+                return false;
+            };
+            let matcher = glob.compile_matcher();
+            segments.iter().any(|segment| matcher.is_match(segment))
+                || (1..=segments.len()).any(|depth| matcher.is_match(segments[..depth].join("/")))
+        })
+        .cloned()
+}
 
-            let rel = p
-                .strip_prefix(&root_path)
-                .unwrap_or(&p)
-                .to_string_lossy()
-                .replace('\\', "/");
+fn exclude_pattern_source(pattern: &str) -> &'static str {
+    if BUILTIN_EXCLUDE_PATTERNS
+        .iter()
+        .any(|builtin| builtin.eq_ignore_ascii_case(pattern))
+    {
+        "内置排除项"
+    } else {
+        "acemcp_exclude_patterns"
+    }
+}
 
-            // 读取文件内容并根据分块结果计算 blob 哈希
-            if let Some(content) = read_file_with_encoding(&p) {
-                let blobs = split_content(&rel, &content, max_lines_per_blob);
-                if blobs.is_empty() {
-                    continue;
-                }
+/// why-not-indexed：按 `collect_blobs` 的过滤顺序逐条复核单个路径，并附带清单与文件监听状态
+pub(crate) fn explain_ace_path(config: &AcemcpConfig, root: &Path, path: &Path) -> Vec<RuleCheck> {
+    let text_exts = config.text_extensions.clone().unwrap_or_default();
+    let exclude_patterns = effective_exclude_patterns(config.exclude_patterns.as_deref());
+    let relative = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    let is_dir = path.is_dir();
+    let mut checks = Vec::new();
+
+    // 目录遍历不跟随符号链接
+    let mut current = root.to_path_buf();
+    let symlink = relative.split('/').find(|segment| {
+        current.push(segment);
+        fs::symlink_metadata(&current)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false)
+    });
+    checks.push(match symlink {
+        Some(segment) => RuleCheck::fail(
+            "符号链接",
+            format!("`{}` 是符号链接，ACE 收集时不跟随", segment),
+        ),
+        None => RuleCheck::pass("符号链接", "路径上没有符号链接"),
+    });
 
-                let mut all_indexed = true;
-                for blob in &blobs {
-                    let hash = sha256_hex(&blob.path, &blob.content);
-                    if !existing_blob_names.contains(&hash) {
-                        all_indexed = false;
-                        break;
+    checks.push(match build_gitignore(root) {
+        Some(gitignore) => match gitignore.matched_path_or_any_parents(path, is_dir) {
+            ignore::Match::Ignore(glob) => RuleCheck::fail(
+                ".gitignore",
+                format!(
+                    "规则 `{}` 命中（ACE 只读取项目根目录的 .gitignore）",
+                    glob.original()
+                ),
+            ),
+            _ => RuleCheck::pass(
+                ".gitignore",
+                "未命中项目根目录 .gitignore（子目录 .gitignore 不参与 ACE 过滤）",
+            ),
+        },
+        None => RuleCheck::pass(".gitignore", "项目根目录没有可用的 .gitignore"),
+    });
+
+    checks.push(
+        match matching_exclude_pattern(&exclude_patterns, &relative) {
+            Some(pattern) => RuleCheck::fail(
+                "exclude_patterns",
+                format!("命中 `{}`（{}）", pattern, exclude_pattern_source(&pattern)),
+            ),
+            None => RuleCheck::pass(
+                "exclude_patterns",
+                format!("未命中 {} 条排除模式", exclude_patterns.len()),
+            ),
+        },
+    );
+
+    if !is_dir {
+        let extension = path
+            .extension()
+            .and_then(|value| value.to_str())
+            .map(|value| format!(".{}", value.to_lowercase()));
+        checks.push(match extension {
+            Some(extension)
+                if text_exts
+                    .iter()
+                    .any(|ext| ext.eq_ignore_ascii_case(&extension)) =>
+            {
+                RuleCheck::pass(
+                    "text_extensions",
+                    format!("`{}` 在索引扩展名列表中", extension),
+                )
+            }
+            Some(extension) => RuleCheck::fail(
+                "text_extensions",
+                format!("`{}` 不在 acemcp_text_extensions 中", extension),
+            ),
+            None => RuleCheck::fail(
+                "text_extensions",
+                "文件没有扩展名，ACE 只收录列表中的扩展名",
+            ),
+        });
+        if path.is_file() && read_file_with_encoding(path).is_none() {
+            checks.push(RuleCheck::fail(
+                "文件读取",
+                "无法按 UTF-8 / GBK / Windows-1252 解码",
+            ));
+        }
+    }
+
+    let normalized_root = normalize_project_path(&root.to_string_lossy());
+    if let Some(scope_hash) = build_index_scope_hash(config) {
+        let manifest = load_blob_manifest(&normalized_root, &scope_hash);
+        let prefix = format!("{}/", relative);
+        let (files, blobs) = manifest
+            .files
+            .iter()
+            .filter(|(file, _)| **file == relative || file.starts_with(&prefix))
+            .fold((0usize, 0usize), |(files, blobs), (_, entry)| {
+                (files + 1, blobs + entry.blob_names.len())
+            });
+        checks.push(RuleCheck::info(
+            "索引清单",
+            if files > 0 {
+                format!("最近一次成功索引包含 {} 个文件、{} 个 blob", files, blobs)
+            } else if manifest.files.is_empty() {
+                "当前索引空间尚无 blob 清单（项目未完成过索引）".to_string()
+            } else {
+                "不在最近一次成功索引的 blob 清单中".to_string()
+            },
+        ));
+    }
+    let watcher_ignored =
+        super::watcher::PathFilter::new(&normalized_root, &exclude_patterns).should_ignore(path);
+    checks.push(RuleCheck::info(
+        "文件监听",
+        if watcher_ignored {
+            "修改不会触发自动增量索引"
+        } else {
+            "修改会触发自动增量索引"
+        },
+    ));
+    checks
+}
+
+/// 按 ACE 规则列出被排除目录（不再向下展开）的体积排行
+///
+/// 遍历与体积统计共用 `deadline`，超时或超过条目上限时返回已统计的部分，第二个返回值标记是否提前结束
+pub(crate) fn top_excluded_directories(
+    config: &AcemcpConfig,
+    root: &Path,
+    limit: usize,
+    deadline: Instant,
+) -> (Vec<ExcludedDirectory>, bool) {
+    let exclude_patterns = effective_exclude_patterns(config.exclude_patterns.as_deref());
+    let exclude_globset = build_exclude_globset(&exclude_patterns).ok();
+    let gitignore = build_gitignore(root);
+    let mut excluded = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    let mut scanned = 0usize;
+    let mut exhausted = false;
+    'walk: while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            scanned += 1;
+            if scanned > MAX_SCANNED_ENTRIES || Instant::now() >= deadline {
+                exhausted = true;
+                break 'walk;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if !file_type.is_dir() || file_type.is_symlink() {
+                continue;
+            }
+            let path = entry.path();
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let gitignore_rule = gitignore.as_ref().and_then(|gitignore| {
+                match gitignore.matched_path_or_any_parents(&path, true) {
+                    ignore::Match::Ignore(glob) => {
+                        Some(format!(".gitignore `{}`", glob.original()))
+                    }
+                    _ => None,
+                }
+            });
+            let rule = gitignore_rule.or_else(|| {
+                should_exclude(&path, root, exclude_globset.as_ref()).then(|| {
+                    match matching_exclude_pattern(&exclude_patterns, &relative) {
+                        Some(pattern) => {
+                            format!("{} `{}`", exclude_pattern_source(&pattern), pattern)
+                        }
+                        None => "exclude_patterns".to_string(),
+                    }
+                })
+            });
+            match rule {
+                Some(rule) => {
+                    let (bytes, files, truncated) = directory_size(&path, deadline);
+                    excluded.push(ExcludedDirectory {
+                        path: relative,
+                        bytes,
+                        files,
+                        rule,
+                        truncated,
+                    });
+                }
+                None => stack.push(path),
+            }
+        }
+    }
+    excluded.sort_by_key(|entry| std::cmp::Reverse(entry.bytes));
+    excluded.truncate(limit);
+    (excluded, exhausted)
+}
+
+/// 目录体积（不跟随符号链接），返回 (字节数, 文件数, 是否因条目上限或截止时间截断)
+fn directory_size(dir: &Path, deadline: Instant) -> (u64, u64, bool) {
+    let mut bytes = 0u64;
+    let mut files = 0u64;
+    let mut visited = 0usize;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&current) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            visited += 1;
+            if visited > EXCLUDED_DIR_SIZE_ENTRY_LIMIT || Instant::now() >= deadline {
+                return (bytes, files, true);
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if entry
+                .file_type()
+                .map(|kind| kind.is_symlink())
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            } else {
+                bytes += metadata.len();
+                files += 1;
+            }
+        }
+    }
+    (bytes, files, false)
+}
+
+/// 收集项目内所有可索引文件的索引状态
+///
+/// 为避免引入新的持久化结构，这里通过重新扫描文件并复用与索引阶段相同的
+/// 路径规范化与分块逻辑，基于现有的 blob 哈希集合判断文件是否“已完全索引”。
+fn collect_file_statuses(
+    root: &str,
+    text_exts: &[String],
+    exclude_patterns: &[String],
+    max_lines_per_blob: usize,
+    existing_blob_names: &HashSet<String>,
+) -> anyhow::Result<Vec<FileIndexStatus>> {
+    let root_path = PathBuf::from(root);
+    if !root_path.exists() {
+        anyhow::bail!("项目根目录不存在: {}", root);
+    }
+
+    // 构建排除模式的 GlobSet
+    let exclude_globset = if exclude_patterns.is_empty() {
+        None
+    } else {
+        match build_exclude_globset(exclude_patterns) {
+            Ok(gs) => Some(gs),
+            Err(e) => {
+                log_debug!("构建排除模式失败，将使用简单匹配: {}", e);
+                None
+            }
+        }
+    };
+
+    let gitignore = build_gitignore(&root_path);
+    let mut dirs_stack = vec![root_path.clone()];
+    let mut files_status = Vec::new();
+
+    while let Some(dir) = dirs_stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let p = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_symlink() {
+                continue;
+            }
+
+            // .gitignore 过滤
+            if let Some(gi) = &gitignore {
+                if gi
+                    .matched_path_or_any_parents(&p, file_type.is_dir())
+                    .is_ignore()
+                {
+                    continue;
+                }
+            }
+
+            if file_type.is_dir() {
+                if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
+                    continue;
+                }
+                dirs_stack.push(p);
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+
+            if should_exclude(&p, &root_path, exclude_globset.as_ref()) {
+                continue;
+            }
+
+            // 扩展名过滤
+            let ext_ok = p
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|e| {
+                    let dot = format!(".{}", e).to_lowercase();
+                    text_exts.iter().any(|te| te.eq_ignore_ascii_case(&dot))
+                })
+                .unwrap_or(false);
+
+            if !ext_ok {
+                continue;
+            }
+
+            let rel = p
+                .strip_prefix(&root_path)
+                .unwrap_or(&p)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            // 读取文件内容并根据分块结果计算 blob 哈希
+            if let Some(content) = read_file_with_encoding(&p) {
+                let blobs = split_content_defined(&rel, &content, max_lines_per_blob);
+                if blobs.is_empty() {
+                    continue;
+                }
+
+                let mut all_indexed = true;
+                for blob in &blobs {
+                    let hash = sha256_hex(&blob.path, &blob.content);
+                    if !existing_blob_names.contains(&hash) {
+                        all_indexed = false;
+                        break;
                     }
                 }
 
@@ -1987,21 +2621,711 @@ fn collect_file_statuses(
                     FileIndexStatusKind::Pending
                 };
 
-                files_status.push(FileIndexStatus {
-                    path: rel.clone(),
-                    status,
+                files_status.push(FileIndexStatus {
+                    path: rel.clone(),
+                    status,
+                });
+            } else {
+                // 无法读取内容时，保守地标记为 Pending，避免静默丢失
+                files_status.push(FileIndexStatus {
+                    path: rel.clone(),
+                    status: FileIndexStatusKind::Pending,
+                });
+            }
+        }
+    }
+
+    Ok(files_status)
+}
+
+/// find-missing 单次请求携带的 blob 名称上限
+const FIND_MISSING_BATCH: usize = 1000;
+
+/// 返回服务端已持有的 blob 名称；握手失败时返回空集合，退回全部上传。
+async fn find_present_blobs(
+    backend: &dyn RemoteIndexBackend,
+    entries: &[(String, BlobItem)],
+) -> HashSet<String> {
+    let names = entries
+        .iter()
+        .map(|(hash, _)| hash.clone())
+        .collect::<Vec<_>>();
+    find_present_blob_names(backend, &names).await
+}
+
+async fn find_present_blob_names(
+    backend: &dyn RemoteIndexBackend,
+    blob_names: &[String],
+) -> HashSet<String> {
+    let mut present = HashSet::new();
+    for chunk in blob_names.chunks(FIND_MISSING_BATCH) {
+        let names = chunk.to_vec();
+        match retry_request(backend, || backend.find_missing(&names), 3, 1.0).await {
+            Ok(missing) => {
+                let missing = missing.into_iter().collect::<HashSet<_>>();
+                present.extend(names.into_iter().filter(|name| !missing.contains(name)));
+            }
+            Err(error) => {
+                log::warn!(
+                    "find-missing 握手失败，剩余 blob 将全部上传: backend={}, error={}",
+                    backend.name(),
+                    error
+                );
+                break;
+            }
+        }
+    }
+    present
+}
+
+// ---------------- 索引快照 ----------------
+
+/// 跨机器可比较的索引空间签名：只包含影响 blob 名称与远端可见性的参数，不含 token 与本机路径
+pub(crate) fn portable_index_fingerprint(config: &AcemcpConfig) -> Option<String> {
+    let mut fingerprint = serde_json::json!({
+        "base_url": normalize_base_url(config.base_url.as_deref()?),
+        "remote_backend": super::remote::normalize_remote_backend(config.remote_backend.as_deref()),
+        "max_lines_per_blob": config.max_lines_per_blob.unwrap_or(800),
+        "text_extensions": normalized_config_values(config.text_extensions.as_ref()),
+        "exclude_patterns": normalized_config_values(Some(&effective_exclude_patterns(
+            config.exclude_patterns.as_deref(),
+        ))),
+    });
+    if let Some(secret_scan) = SecretScanner::from_acemcp_config(config).fingerprint() {
+        fingerprint["secret_scan"] = secret_scan;
+    }
+    let mut ctx = ShaContext::new(&SHA256);
+    ctx.update(fingerprint.to_string().as_bytes());
+    Some(hex::encode(ctx.finish().as_ref()))
+}
+
+/// 快照中的 ACE 状态：相对路径 → 该文件的 blob 名称
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct AceSnapshotState {
+    pub fingerprint: String,
+    pub files: std::collections::BTreeMap<String, Vec<String>>,
+}
+
+/// 导出已确认的 ACE 索引状态；只包含 blob 全部确认、且大小与修改时间仍与清单一致的文件
+pub(crate) fn export_ace_snapshot_state(
+    config: &AcemcpConfig,
+    project_root: &Path,
+) -> Option<AceSnapshotState> {
+    let fingerprint = portable_index_fingerprint(config)?;
+    let scope_hash = build_index_scope_hash(config)?;
+    let normalized_root = normalize_project_path(&project_root.to_string_lossy());
+    let confirmed = load_projects_file()
+        .0
+        .remove(&normalized_root)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    if confirmed.is_empty() {
+        return None;
+    }
+    let manifest = load_blob_manifest(&normalized_root, &scope_hash);
+    let files = manifest
+        .files
+        .into_iter()
+        .filter(|(relative, entry)| {
+            !entry.blob_names.is_empty()
+                && entry.blob_names.iter().all(|name| confirmed.contains(name))
+                && fs::metadata(project_root.join(relative))
+                    .map(|metadata| file_stamp(&metadata) == (entry.size, entry.modified_ns))
+                    .unwrap_or(false)
+        })
+        .map(|(relative, entry)| (relative, entry.blob_names))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    (!files.is_empty()).then_some(AceSnapshotState { fingerprint, files })
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AceSnapshotImport {
+    /// 内容一致且服务端仍持有全部 blob，直接确认的文件数
+    pub confirmed_files: usize,
+    /// 服务端缺少 blob，需要重新上传的文件数
+    pub reupload_files: usize,
+    /// 本地重新计算的 blob 名称与快照不一致，留给增量索引的文件数
+    pub mismatched_files: usize,
+    pub confirmed_blobs: usize,
+}
+
+/// 导入快照中的 ACE 状态。`files` 只应包含内容哈希已与工作区核对一致的文件。
+///
+/// blob 是否仍存在以服务端 find-missing 握手为准：存在的写入 projects.json 与清单，
+/// 缺失的留给随后启动的增量索引重新上传；工作区中快照未覆盖的文件同样由增量索引处理。
+pub(crate) async fn import_ace_snapshot_state(
+    config: &AcemcpConfig,
+    project_root: &Path,
+    files: std::collections::BTreeMap<String, Vec<String>>,
+) -> anyhow::Result<AceSnapshotImport> {
+    let scope_hash = require_index_scope_hash(config)?;
+    let backend = create_remote_backend(config, create_acemcp_client(config)?)?;
+    let chunking = SnapshotChunking {
+        max_lines_per_blob: (config.max_lines_per_blob.unwrap_or(800) as usize).max(1),
+        scanner: SecretScanner::from_acemcp_config(config),
+    };
+    let outcome = apply_ace_snapshot(
+        backend.as_ref(),
+        &scope_hash,
+        project_root,
+        &chunking,
+        files,
+    )
+    .await?;
+    let project_root_path = project_root.to_string_lossy().to_string();
+    log_important!(
+        info,
+        "ACE 快照导入: project_root={}, confirmed_files={}, reupload_files={}, mismatched_files={}, confirmed_blobs={}",
+        normalize_project_path(&project_root_path),
+        outcome.confirmed_files,
+        outcome.reupload_files,
+        outcome.mismatched_files,
+        outcome.confirmed_blobs
+    );
+    // 内容不同、服务端缺失或快照未覆盖的文件由增量索引补齐
+    if let Err(error) = start_background_index_with_mode(
+        config,
+        &project_root_path,
+        true,
+        IndexJobMode::Incremental,
+        None,
+        IndexPriority::Search,
+        IndexTrigger::Snapshot,
+    )
+    .await
+    {
+        log::warn!("快照导入后启动增量索引失败: {}", error);
+    }
+    Ok(outcome)
+}
+
+/// 本地重新计算 blob 名称所需的分块参数，与 `collect_blobs` 使用的一致
+struct SnapshotChunking {
+    max_lines_per_blob: usize,
+    scanner: SecretScanner,
+}
+
+impl SnapshotChunking {
+    /// 按 `collect_blobs` 的读取、密钥扫描与分块规则计算文件的 blob 名称；
+    /// 文件无法读取或被密钥扫描跳过、拦截时返回 None
+    fn blob_names(&self, root: &str, relative: &str, path: &Path) -> Option<Vec<String>> {
+        let content = read_file_with_encoding(path)?;
+        let mut blocked_files = Vec::new();
+        let content =
+            scan_before_upload(&self.scanner, root, relative, content, &mut blocked_files)?;
+        Some(
+            split_content_defined(relative, &content, self.max_lines_per_blob)
+                .iter()
+                .map(|blob| sha256_hex(&blob.path, &blob.content))
+                .collect(),
+        )
+    }
+}
+
+/// 以 find-missing 结果确认快照中的 blob，写入 projects.json、清单与索引空间
+///
+/// 快照中的 blob 名称只用于核对：按本地内容重新计算，一致的才向服务端确认，
+/// 避免把归档中任意的名称记为已上传
+async fn apply_ace_snapshot(
+    backend: &dyn RemoteIndexBackend,
+    scope_hash: &str,
+    project_root: &Path,
+    chunking: &SnapshotChunking,
+    files: std::collections::BTreeMap<String, Vec<String>>,
+) -> anyhow::Result<AceSnapshotImport> {
+    let project_root_path = project_root.to_string_lossy().to_string();
+    let normalized_root = normalize_project_path(&project_root_path);
+    if !auto_index_inflight()
+        .lock()
+        .unwrap()
+        .insert(normalized_root.clone())
+    {
+        anyhow::bail!("项目正在索引，请稍后再导入快照");
+    }
+    let result = async {
+        let _lease = jobs::try_acquire_project_lease(&normalized_root)?
+            .ok_or_else(|| anyhow::anyhow!("项目正在被其他进程索引，请稍后再导入快照"))?;
+        let mut outcome = AceSnapshotImport::default();
+        let mut verified = std::collections::BTreeMap::new();
+        for (relative, blob_names) in files {
+            let path = project_root.join(&relative);
+            // 工作区中已不存在或无法读取的文件不确认，也不计入重新上传
+            let Some(local) = chunking.blob_names(&project_root_path, &relative, &path) else {
+                continue;
+            };
+            if local == blob_names {
+                verified.insert(relative, blob_names);
+            } else {
+                outcome.mismatched_files += 1;
+            }
+        }
+        let mut names = verified.values().flatten().cloned().collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let present = find_present_blob_names(backend, &names).await;
+
+        // 索引空间与当前不一致时旧状态本就会被整体重建，直接以快照替换
+        let same_scope = get_project_status(&project_root_path)
+            .index_scope_hash
+            .as_deref()
+            == Some(scope_hash);
+        let mut manifest = if same_scope {
+            load_blob_manifest(&normalized_root, scope_hash)
+        } else {
+            BlobManifest::new(scope_hash.to_string())
+        };
+        let existing = if same_scope {
+            load_projects_file()
+                .0
+                .remove(&normalized_root)
+                .unwrap_or_default()
+                .into_iter()
+                .collect::<HashSet<_>>()
+        } else {
+            HashSet::new()
+        };
+        let mut confirmed_names = Vec::new();
+        for (relative, blob_names) in verified {
+            let Ok(metadata) = fs::metadata(project_root.join(&relative)) else {
+                continue;
+            };
+            if !blob_names.iter().all(|name| present.contains(name)) {
+                outcome.reupload_files += 1;
+                continue;
+            }
+            let (size, modified_ns) = file_stamp(&metadata);
+            confirmed_names.extend(blob_names.iter().cloned());
+            manifest.files.insert(
+                relative,
+                ManifestEntry {
+                    size,
+                    modified_ns,
+                    blob_names,
+                },
+            );
+            outcome.confirmed_files += 1;
+        }
+        let confirmed =
+            persist_confirmed_blob_names(&normalized_root, &existing, &confirmed_names)?;
+        outcome.confirmed_blobs = confirmed.len().saturating_sub(existing.len());
+        save_blob_manifest(&normalized_root, &manifest)?;
+        update_project_status(&project_root_path, |status| {
+            status.index_scope_hash = Some(scope_hash.to_string());
+        })?;
+        Ok::<_, anyhow::Error>(outcome)
+    }
+    .await;
+    auto_index_inflight()
+        .lock()
+        .unwrap()
+        .remove(&normalized_root);
+    result
+}
+
+// ---------------- 索引垃圾回收 ----------------
+
+/// 单次远端删除请求的 blob 数
+const GC_DELETE_BATCH: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexGcProject {
+    pub project_root: String,
+    /// unreachable_root | missing_root | pruned | clean | skipped
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub referenced_blobs: usize,
+    pub stale_blobs: usize,
+    /// 清单中对应文件已被删除的条目数
+    pub stale_manifest_files: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexGcReport {
+    pub dry_run: bool,
+    pub remote_backend: String,
+    pub remote_delete_supported: bool,
+    pub projects: Vec<IndexGcProject>,
+    pub stale_blobs: usize,
+    /// 已无任何项目引用、可请求远端删除的 blob 数
+    pub remote_deletable_blobs: usize,
+    pub remote_deleted_blobs: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_error: Option<String>,
+}
+
+impl IndexGcProject {
+    fn skipped(project_root: &str, reason: &str, referenced_blobs: usize) -> Self {
+        Self {
+            project_root: project_root.to_string(),
+            action: "skipped".to_string(),
+            reason: Some(reason.to_string()),
+            referenced_blobs,
+            stale_blobs: 0,
+            stale_manifest_files: 0,
+        }
+    }
+}
+
+struct ProjectGcPlan {
+    referenced: HashSet<String>,
+    stale: Vec<String>,
+    removed_files: Vec<String>,
+}
+
+/// 按清单计算项目当前树仍引用的 blob。
+///
+/// 文件已删除的清单条目连同其 blob 视为失效；文件仍在但内容已变化的条目保留到下次索引替换，
+/// 避免重建前检索丢失该文件。projects.json 中不属于任何清单条目的 blob（旧版本内容）一并失效。
+fn plan_project_gc(root: &Path, manifest: &BlobManifest, confirmed: &[String]) -> ProjectGcPlan {
+    let mut referenced = HashSet::new();
+    let mut removed_files = Vec::new();
+    for (relative, entry) in &manifest.files {
+        if fs::symlink_metadata(root.join(relative)).is_ok_and(|metadata| metadata.is_file()) {
+            referenced.extend(entry.blob_names.iter().cloned());
+        } else {
+            removed_files.push(relative.clone());
+        }
+    }
+    removed_files.sort();
+    let mut stale = confirmed
+        .iter()
+        .filter(|name| !referenced.contains(*name))
+        .cloned()
+        .collect::<Vec<_>>();
+    stale.sort();
+    stale.dedup();
+    ProjectGcPlan {
+        referenced,
+        stale,
+        removed_files,
+    }
+}
+
+/// 回收不再被引用的索引状态。
+///
+/// - 项目目录不可访问：可能已删除，也可能是暂未挂载的磁盘或网络路径。默认只报告（unreachable_root），
+///   其 blob 视为仍被引用；出现在 `confirmed_missing_roots` 中时才删除 projects.json、状态、任务与清单中的全部记录。
+/// - 项目仍在：按清单收缩 projects.json、清单与已完成任务中的 blob 名称。
+/// - 只有当前索引空间内、且已无任何项目引用的 blob 才会请求远端删除（需后端支持）。
+///   自建服务按客户端计数引用，删除只释放本机的引用。
+///
+/// `dry_run` 时只计算报告，不修改任何状态。
+pub(crate) async fn collect_index_garbage(
+    config: &AcemcpConfig,
+    dry_run: bool,
+    confirmed_missing_roots: &[String],
+) -> anyhow::Result<IndexGcReport> {
+    let current_scope_hash = build_index_scope_hash(config);
+    let backend = create_acemcp_client(config)
+        .and_then(|client| create_remote_backend(config, client))
+        .ok();
+    let mut report = IndexGcReport {
+        dry_run,
+        remote_backend: super::remote::normalize_remote_backend(config.remote_backend.as_deref()),
+        remote_delete_supported: backend
+            .as_ref()
+            .is_some_and(|backend| backend.supports_delete()),
+        ..Default::default()
+    };
+
+    let projects = load_projects_file();
+    let statuses = load_projects_status();
+    let mut roots = projects
+        .0
+        .keys()
+        .chain(statuses.projects.keys())
+        .cloned()
+        .collect::<Vec<_>>();
+    roots.sort();
+    roots.dedup();
+
+    let mut remote_candidates = HashSet::new();
+    let mut kept_blobs = HashSet::new();
+    for root in roots {
+        let confirmed = projects.0.get(&root).cloned().unwrap_or_default();
+        let saved_scope_hash = statuses
+            .projects
+            .get(&root)
+            .and_then(|status| status.index_scope_hash.clone());
+        let in_current_scope =
+            current_scope_hash.is_some() && saved_scope_hash == current_scope_hash;
+        let root_path = PathBuf::from(&root);
+
+        if !root_path.is_dir() {
+            let confirmed_missing = confirmed_missing_roots
+                .iter()
+                .any(|candidate| normalize_project_path(candidate) == root);
+            if !confirmed_missing {
+                kept_blobs.extend(confirmed.iter().cloned());
+                report.projects.push(IndexGcProject {
+                    project_root: root,
+                    action: "unreachable_root".to_string(),
+                    reason: Some(
+                        "项目目录不可访问（已删除或暂未挂载），确认后才会清理".to_string(),
+                    ),
+                    referenced_blobs: confirmed.len(),
+                    stale_blobs: 0,
+                    stale_manifest_files: 0,
                 });
+                continue;
+            }
+            if in_current_scope {
+                remote_candidates.extend(confirmed.iter().cloned());
+            }
+            if !dry_run {
+                remove_project_index_state(&root)?;
+            }
+            report.stale_blobs += confirmed.len();
+            report.projects.push(IndexGcProject {
+                project_root: root,
+                action: "missing_root".to_string(),
+                reason: Some("已确认项目目录不再使用".to_string()),
+                referenced_blobs: 0,
+                stale_blobs: confirmed.len(),
+                stale_manifest_files: 0,
+            });
+            continue;
+        }
+
+        let busy = auto_index_inflight().lock().unwrap().contains(&root)
+            || jobs::get_job(&root).is_some_and(|job| job.is_resumable());
+        let skip_reason = if busy {
+            Some("项目正在索引或存在未完成的索引任务")
+        } else if saved_scope_hash.is_none() {
+            Some("项目尚未完成过索引")
+        } else {
+            None
+        };
+        if let Some(reason) = skip_reason {
+            kept_blobs.extend(confirmed.iter().cloned());
+            report
+                .projects
+                .push(IndexGcProject::skipped(&root, reason, confirmed.len()));
+            continue;
+        }
+        let manifest = load_blob_manifest(&root, saved_scope_hash.as_deref().unwrap_or_default());
+        if manifest.files.is_empty() {
+            kept_blobs.extend(confirmed.iter().cloned());
+            report.projects.push(IndexGcProject::skipped(
+                &root,
+                "缺少 blob 清单，需先完成一次索引",
+                confirmed.len(),
+            ));
+            continue;
+        }
+
+        let plan = plan_project_gc(&root_path, &manifest, &confirmed);
+        let entry = IndexGcProject {
+            project_root: root.clone(),
+            action: if plan.stale.is_empty() && plan.removed_files.is_empty() {
+                "clean".to_string()
             } else {
-                // 无法读取内容时，保守地标记为 Pending，避免静默丢失
-                files_status.push(FileIndexStatus {
-                    path: rel.clone(),
-                    status: FileIndexStatusKind::Pending,
-                });
+                "pruned".to_string()
+            },
+            reason: None,
+            referenced_blobs: confirmed.len().saturating_sub(plan.stale.len()),
+            stale_blobs: plan.stale.len(),
+            stale_manifest_files: plan.removed_files.len(),
+        };
+        if !dry_run && entry.action == "pruned" {
+            match apply_project_gc(&root, manifest, &plan) {
+                Ok(true) => {}
+                Ok(false) => {
+                    kept_blobs.extend(confirmed.iter().cloned());
+                    report.projects.push(IndexGcProject::skipped(
+                        &root,
+                        "项目正在索引，稍后再试",
+                        confirmed.len(),
+                    ));
+                    continue;
+                }
+                Err(error) => {
+                    kept_blobs.extend(confirmed.iter().cloned());
+                    report.projects.push(IndexGcProject::skipped(
+                        &root,
+                        &format!("回收失败: {}", error),
+                        confirmed.len(),
+                    ));
+                    continue;
+                }
             }
         }
+        kept_blobs.extend(
+            confirmed
+                .iter()
+                .filter(|name| plan.referenced.contains(*name))
+                .cloned(),
+        );
+        if in_current_scope {
+            remote_candidates.extend(plan.stale.iter().cloned());
+        }
+        report.stale_blobs += plan.stale.len();
+        report.projects.push(entry);
     }
 
-    Ok(files_status)
+    // 内容相同的 blob 可能被多个项目共享，只删除已无任何项目引用的部分
+    let mut deletable = remote_candidates
+        .into_iter()
+        .filter(|name| !kept_blobs.contains(name))
+        .collect::<Vec<_>>();
+    deletable.sort();
+    report.remote_deletable_blobs = deletable.len();
+    if let Some(backend) = backend.filter(|backend| backend.supports_delete()) {
+        if !dry_run {
+            let (deleted, error) = delete_blobs_in_batches(backend.as_ref(), &deletable).await;
+            report.remote_deleted_blobs = deleted;
+            report.remote_error = error;
+        }
+    }
+
+    log_important!(
+        info,
+        "ACE 索引垃圾回收: dry_run={}, projects={}, stale_blobs={}, remote_deletable={}, remote_deleted={}",
+        dry_run,
+        report.projects.len(),
+        report.stale_blobs,
+        report.remote_deletable_blobs,
+        report.remote_deleted_blobs
+    );
+    Ok(report)
+}
+
+async fn delete_blobs_in_batches(
+    backend: &dyn RemoteIndexBackend,
+    blob_names: &[String],
+) -> (usize, Option<String>) {
+    let mut deleted = 0;
+    for chunk in blob_names.chunks(GC_DELETE_BATCH) {
+        let names = chunk.to_vec();
+        match retry_request(backend, || backend.delete_blobs(&names), 3, 1.0).await {
+            Ok(count) => deleted += count,
+            Err(error) => return (deleted, Some(error.to_string())),
+        }
+    }
+    (deleted, None)
+}
+
+/// 项目在当前索引空间内已确认的 blob；其他索引空间的 blob 不属于当前后端，不能删除
+pub(crate) fn current_scope_blob_names(
+    config: &AcemcpConfig,
+    normalized_root: &str,
+) -> Vec<String> {
+    let current_scope_hash = build_index_scope_hash(config);
+    if current_scope_hash.is_none()
+        || get_project_status(normalized_root).index_scope_hash != current_scope_hash
+    {
+        return Vec::new();
+    }
+    load_projects_file()
+        .0
+        .remove(normalized_root)
+        .unwrap_or_default()
+}
+
+/// 请求远端删除已无任何项目引用的 blob；后端不支持删除时返回 0
+pub(crate) async fn release_unreferenced_blobs(
+    config: &AcemcpConfig,
+    candidates: Vec<String>,
+) -> anyhow::Result<usize> {
+    let backend = create_remote_backend(config, create_acemcp_client(config)?)?;
+    if candidates.is_empty() || !backend.supports_delete() {
+        return Ok(0);
+    }
+    let referenced = load_projects_file()
+        .0
+        .into_values()
+        .flatten()
+        .collect::<HashSet<_>>();
+    let deletable = candidates
+        .into_iter()
+        .filter(|name| !referenced.contains(name))
+        .collect::<Vec<_>>();
+    match delete_blobs_in_batches(backend.as_ref(), &deletable).await {
+        (deleted, None) => Ok(deleted),
+        (deleted, Some(error)) => anyhow::bail!("已删除 {} 个远端 blob 后失败: {}", deleted, error),
+    }
+}
+
+/// 在项目互斥下收缩 projects.json、清单与已完成任务；项目正在索引时返回 false
+fn apply_project_gc(
+    normalized_root: &str,
+    mut manifest: BlobManifest,
+    plan: &ProjectGcPlan,
+) -> anyhow::Result<bool> {
+    if !auto_index_inflight()
+        .lock()
+        .unwrap()
+        .insert(normalized_root.to_string())
+    {
+        return Ok(false);
+    }
+    let result = (|| {
+        let Some(_lease) = jobs::try_acquire_project_lease(normalized_root)? else {
+            return Ok(false);
+        };
+        {
+            let _guard = projects_file_lock()
+                .lock()
+                .map_err(|_| anyhow::anyhow!("获取 projects.json 写入锁失败"))?;
+            let mut projects = load_projects_file();
+            if let Some(names) = projects.0.get_mut(normalized_root) {
+                names.retain(|name| plan.referenced.contains(name));
+                save_projects_file(&projects)?;
+            }
+        }
+        for relative in &plan.removed_files {
+            manifest.files.remove(relative);
+        }
+        save_blob_manifest(normalized_root, &manifest)?;
+        if jobs::get_job(normalized_root).is_some_and(|job| !job.is_resumable()) {
+            jobs::update_job(
+                normalized_root,
+                "gc",
+                Some(format!("已回收 {} 个失效 blob", plan.stale.len())),
+                |job| {
+                    job.uploaded_blob_names
+                        .retain(|name| plan.referenced.contains(name));
+                    job.completed_blob_hashes
+                        .retain(|name| plan.referenced.contains(name));
+                },
+            )?;
+        }
+        Ok::<_, anyhow::Error>(true)
+    })();
+    auto_index_inflight()
+        .lock()
+        .unwrap()
+        .remove(normalized_root);
+    result
+}
+
+/// 删除已不存在项目的全部本地索引记录
+fn remove_project_index_state(normalized_root: &str) -> anyhow::Result<()> {
+    {
+        let _guard = projects_file_lock()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("获取 projects.json 写入锁失败"))?;
+        let mut projects = load_projects_file();
+        if projects.0.remove(normalized_root).is_some() {
+            save_projects_file(&projects)?;
+        }
+    }
+    {
+        let _guard = projects_status_lock()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("获取 projects_status.json 写入锁失败"))?;
+        let mut all_status = load_projects_status();
+        if all_status.projects.remove(normalized_root).is_some() {
+            save_projects_status(&all_status)?;
+        }
+    }
+    jobs::remove_job(normalized_root)?;
+    let manifest_path = blob_manifest_file(normalized_root);
+    let _ = fs::remove_file(manifest_path.with_extension("json.bak"));
+    let _ = fs::remove_file(manifest_path);
+    Ok(())
 }
 
 fn calculate_index_progress(completed: usize, total: usize) -> u8 {
@@ -2025,12 +3349,7 @@ fn ensure_index_job(
             return Ok(job);
         }
     }
-    jobs::create_job(
-        normalized_root,
-        mode.as_str(),
-        scope_hash,
-        scope_hash,
-    )
+    jobs::create_job(normalized_root, mode.as_str(), scope_hash, scope_hash)
 }
 
 fn persist_confirmed_blob_names(
@@ -2080,6 +3399,12 @@ fn mark_index_job_error(
                 if !job.failed_batches.contains(&batch) {
                     job.failed_batches.push(batch);
                 }
+                job.batch_failures.push(jobs::BatchFailure {
+                    batch,
+                    failed_blobs,
+                    error: error_message.clone(),
+                    at: chrono::Utc::now(),
+                });
             }
         },
     );
@@ -2129,10 +3454,9 @@ async fn update_index_with_mode(
     if !has_scheme || !has_host {
         anyhow::bail!("无效的 base_url，请填写完整的 http(s)://host[:port] 格式");
     }
-    let token = config
-        .token
-        .clone()
-        .ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
+    if config.token.is_none() {
+        anyhow::bail!("未配置 token");
+    }
     let current_scope_hash = require_index_scope_hash(config)?;
     let batch_size = (config.batch_size.unwrap_or(10) as usize).max(1);
     let max_lines = (config.max_lines_per_blob.unwrap_or(800) as usize).max(1);
@@ -2171,8 +3495,65 @@ async fn update_index_with_mode(
         max_lines
     );
 
-    let blobs = match collect_blobs(project_root_path, &text_exts, &exclude_patterns, max_lines) {
-        Ok(blobs) if !blobs.is_empty() => blobs,
+    let projects = load_projects_file();
+    let mut existing_blob_names = projects
+        .0
+        .get(&normalized_root)
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let project_status = get_project_status(project_root_path);
+    let scope_changed = mode == IndexJobMode::Full
+        || is_index_scope_stale(
+            &project_status,
+            Some(current_scope_hash.as_str()),
+            !existing_blob_names.is_empty(),
+        );
+    if scope_changed {
+        log_important!(
+            info,
+            "检测到 ACE 索引空间或索引参数已变更，将按全量重建处理: project_root={}",
+            normalized_root
+        );
+        existing_blob_names.clear();
+    }
+
+    // 清单只复用仍在 projects.json 中确认过的 blob；全量重建时 existing 为空，自然全部重新读取。
+    let previous_manifest = load_blob_manifest(&normalized_root, &current_scope_hash);
+    // git 事件触发时按变更集增量收集，无法精确描述时退回整树扫描
+    let (pending_generation, pending_changes) = git_events::snapshot_pending(&normalized_root);
+    let scanner = SecretScanner::from_acemcp_config(config);
+    let targeted = pending_changes
+        .filter(|_| mode == IndexJobMode::Incremental && !scope_changed)
+        .and_then(|changes| {
+            collect_blobs_targeted(
+                project_root_path,
+                &text_exts,
+                &exclude_patterns,
+                max_lines,
+                &previous_manifest,
+                &existing_blob_names,
+                &changes,
+                &scanner,
+            )
+        });
+    let collected_result = match targeted {
+        Some(collected) => collected,
+        None => collect_blobs(
+            project_root_path,
+            &text_exts,
+            &exclude_patterns,
+            max_lines,
+            &previous_manifest,
+            &existing_blob_names,
+            &scanner,
+        ),
+    };
+    let collected = match collected_result {
+        Ok(collected) if !collected.blobs.is_empty() || !collected.reused_blob_names.is_empty() => {
+            collected
+        }
         Ok(_) => {
             let message = "未在项目中找到可索引的文本文件";
             mark_index_job_error(
@@ -2198,6 +3579,12 @@ async fn update_index_with_mode(
             return Err(error);
         }
     };
+    drop(previous_manifest);
+    let CollectedBlobs {
+        blobs,
+        reused_blob_names,
+        manifest: next_manifest,
+    } = collected;
 
     // 固定排序保证进程重启后重新分批时仍能稳定核对断点。
     let mut blob_entries = blobs
@@ -2205,36 +3592,14 @@ async fn update_index_with_mode(
         .map(|blob| (sha256_hex(&blob.path, &blob.content), blob))
         .collect::<Vec<_>>();
     blob_entries.sort_by(|left, right| left.0.cmp(&right.0));
-    let total_blobs = blob_entries.len();
-    let all_blob_hashes = blob_entries
+    // 内容定义分块可能在同一文件内产生完全相同的块，按名称去重后再计数。
+    blob_entries.dedup_by(|left, right| left.0 == right.0);
+    let mut all_blob_hashes = blob_entries
         .iter()
         .map(|(hash, _)| hash.clone())
         .collect::<HashSet<_>>();
-
-    let projects = load_projects_file();
-    let mut existing_blob_names = projects
-        .0
-        .get(&normalized_root)
-        .cloned()
-        .unwrap_or_default()
-        .into_iter()
-        .collect::<HashSet<_>>();
-    let project_status = get_project_status(project_root_path);
-    let scope_changed = mode == IndexJobMode::Full
-        || is_index_scope_stale(
-            &project_status,
-            Some(current_scope_hash.as_str()),
-            !existing_blob_names.is_empty(),
-        );
-    if scope_changed {
-        log_important!(
-            info,
-            "检测到 ACE 索引空间或索引参数已变更，将按全量重建处理: project_root={}",
-            normalized_root
-        );
-        existing_blob_names.clear();
-    }
-
+    all_blob_hashes.extend(reused_blob_names);
+    let total_blobs = all_blob_hashes.len();
     let checkpoint_hashes = job
         .completed_blob_hashes
         .iter()
@@ -2255,12 +3620,38 @@ async fn update_index_with_mode(
     completed_hashes.extend(checkpoint_hashes.iter().cloned());
     // 中文说明：原集合直接收缩并移动，避免百万级文件内容被完整 clone 两次。
     blob_entries.retain(|(hash, _)| !completed_hashes.contains(hash));
-    let new_entries = blob_entries;
+    let mut new_entries = blob_entries;
     let max_confirmed_batch_count = if checkpoint_hashes.is_empty() {
         0
     } else {
         (checkpoint_hashes.len() + batch_size - 1) / batch_size
     };
+
+    // 上传前先向服务端核对缺失 blob，已存在的内容（其他项目或历史版本上传过）直接确认。
+    let backend = create_remote_backend(config, create_acemcp_client(config)?)?;
+    let candidate_count = new_entries.len();
+    let present_hashes = find_present_blobs(backend.as_ref(), &new_entries).await;
+    if !present_hashes.is_empty() {
+        new_entries.retain(|(hash, _)| !present_hashes.contains(hash));
+    }
+    let mut checkpoint_hashes = checkpoint_hashes;
+    let mut checkpoint_names = checkpoint_names;
+    checkpoint_hashes.extend(present_hashes.iter().cloned());
+    checkpoint_names.extend(present_hashes.iter().cloned());
+    checkpoint_names.sort();
+    checkpoint_names.dedup();
+    completed_hashes.extend(present_hashes.iter().cloned());
+    log_important!(
+        info,
+        "ACE增量握手: candidates={}, already_present={}, to_upload={}, upload_bytes={}",
+        candidate_count,
+        present_hashes.len(),
+        new_entries.len(),
+        new_entries
+            .iter()
+            .map(|(_, blob)| blob.content.len())
+            .sum::<usize>()
+    );
     let completed_batch_count = job.completed_batches.min(max_confirmed_batch_count);
     let remaining_batches = (new_entries.len() + batch_size - 1) / batch_size;
     let total_batches = completed_batch_count.saturating_add(remaining_batches);
@@ -2295,7 +3686,6 @@ async fn update_index_with_mode(
         calculate_index_progress(completed_hashes.len(), total_blobs)
     );
 
-    let client = create_acemcp_client(config)?;
     let mut uploaded_this_run = Vec::new();
     for (batch_offset, batch_entries) in new_entries.chunks(batch_size).enumerate() {
         let batch_number = completed_batch_count + batch_offset + 1;
@@ -2307,7 +3697,6 @@ async fn update_index_with_mode(
             .iter()
             .map(|(hash, _)| hash.clone())
             .collect::<Vec<_>>();
-        let url = format!("{}/batch-upload", base_url);
         log_important!(
             info,
             "上传批次 {}/{}: project_root={}, blobs={}",
@@ -2316,39 +3705,13 @@ async fn update_index_with_mode(
             normalized_root,
             batch.len()
         );
-        let payload = serde_json::json!({"blobs": &batch});
-        let response = retry_request(
-            || async {
-                let response = client
-                    .post(&url)
-                    .header(AUTHORIZATION, format!("Bearer {}", token))
-                    .header(CONTENT_TYPE, "application/json")
-                    .json(&payload)
-                    .send()
-                    .await?;
-                let status = response.status();
-                if !status.is_success() {
-                    let body = response.text().await.unwrap_or_default();
-                    anyhow::bail!("HTTP {} {}", status, body);
-                }
-                Ok(response.json::<serde_json::Value>().await?)
-            },
-            3,
-            1.0,
-        )
-        .await;
+        let batch_bytes = batch.iter().map(|blob| blob.content.len()).sum::<usize>();
+        scheduler::throttle_upload(config.upload_bandwidth_kbps, batch_bytes).await;
+        let response =
+            retry_request(backend.as_ref(), || backend.upload_blobs(&batch), 3, 1.0).await;
 
         let returned_names = match response {
-            Ok(value) => value
-                .get("blob_names")
-                .and_then(|value| value.as_array())
-                .map(|values| {
-                    values
-                        .iter()
-                        .filter_map(|value| value.as_str().map(str::to_string))
-                        .collect::<Vec<_>>()
-                })
-                .filter(|names| !names.is_empty()),
+            Ok(names) => Some(names).filter(|names| !names.is_empty()),
             Err(error) => {
                 let error_message = error.to_string();
                 let auth_failure = is_ace_auth_failure_error(&error_message);
@@ -2430,6 +3793,7 @@ async fn update_index_with_mode(
             )),
             |job| {
                 job.status = JOB_UPLOADING.to_string();
+                job.bytes_sent += batch_bytes as u64;
                 if batch_fully_confirmed {
                     job.completed_batches = batch_number;
                 }
@@ -2566,6 +3930,12 @@ async fn update_index_with_mode(
         }
     });
 
+    // 清单只在全部 blob 确认后落盘，中途失败时下次仍按内容重新核对。
+    if let Err(error) = save_blob_manifest(&normalized_root, &next_manifest) {
+        log::warn!("保存 ACE blob 清单失败，下次索引将重新读取文件: {}", error);
+    }
+    git_events::clear_pending(&normalized_root, pending_generation);
+
     if is_first_success {
         let _ = write_index_memory_to_ji(project_root_path, config);
     }
@@ -2575,6 +3945,7 @@ async fn update_index_with_mode(
 
 /// 将索引配置信息写入 ji（记忆）工具
 fn write_index_memory_to_ji(project_root_path: &str, config: &AcemcpConfig) {
+    use super::super::memory::{LifecycleUpdate, MemorySource};
     use super::super::memory::MemoryCategory;
     use super::super::memory::MemoryManager;
 
@@ -2601,6 +3972,11 @@ fn write_index_memory_to_ji(project_root_path: &str, config: &AcemcpConfig) {
     // 写入记忆（add_memory 现在返回 Option<String>）
     match manager.add_memory(&memory_content, MemoryCategory::Context) {
         Ok(Some(id)) => {
+            let lifecycle = LifecycleUpdate {
+                source: Some(MemorySource::System),
+                ..Default::default()
+            };
+            let _ = manager.update_lifecycle(&id, &lifecycle);
             log_important!(info, "已将索引配置写入 ji 记忆: id={}", id);
         }
         Ok(None) => {
@@ -2619,14 +3995,12 @@ async fn search_only(
     project_root_path: &str,
     query: &str,
 ) -> anyhow::Result<String> {
-    let base_url = config
-        .base_url
-        .clone()
-        .ok_or_else(|| anyhow::anyhow!("未配置 base_url"))?;
-    let token = config
-        .token
-        .clone()
-        .ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
+    if config.base_url.is_none() {
+        anyhow::bail!("未配置 base_url");
+    }
+    if config.token.is_none() {
+        anyhow::bail!("未配置 token");
+    }
     let current_scope_hash = require_index_scope_hash(config)?;
 
     // 从 projects.json 读取已有的 blob 名称
@@ -2662,6 +4036,8 @@ async fn search_only(
             true,
             IndexJobMode::Full,
             None,
+            IndexPriority::Search,
+            IndexTrigger::Search,
         )
         .await?;
         let message = match launch_state {
@@ -2696,80 +4072,37 @@ async fn search_only(
 
     // 发起检索
     log_important!(info, "=== 开始代码检索（仅搜索模式） ===");
-    let search_url = format!("{}/agents/codebase-retrieval", base_url);
+    // 创建 HTTP 客户端（支持代理）
+    let backend = create_remote_backend(config, create_acemcp_client(config)?)?;
     log_important!(
         info,
-        "检索请求: url={}, 使用blobs数量={}, 查询内容={}",
-        search_url,
+        "检索请求: backend={}, 使用blobs数量={}, 查询内容={}",
+        backend.name(),
         blob_names.len(),
         query
     );
 
-    let payload = serde_json::json!({
-        "information_request": query,
-        "blobs": {"checkpoint_id": serde_json::Value::Null, "added_blobs": blob_names, "deleted_blobs": []},
-        "dialog": [],
-        "max_output_length": 0,
-        "disable_codebase_retrieval": false,
-        "enable_commit_retrieval": false,
-    });
-
-    // 创建 HTTP 客户端（支持代理）
-    let client = create_acemcp_client(config)?;
-    let value: serde_json::Value = retry_request(
-        || async {
-            let r = client
-                .post(&search_url)
-                .header(AUTHORIZATION, format!("Bearer {}", token))
-                .header(CONTENT_TYPE, "application/json")
-                .json(&payload)
-                .send()
-                .await?;
-
-            let status = r.status();
-            log_important!(info, "检索请求HTTP响应状态: {}", status);
-
-            if status == StatusCode::UNAUTHORIZED {
-                let body = r.text().await.unwrap_or_default();
-                log_important!(info, "检索请求遇到 ACE 认证失败: {}", body);
-                mark_project_auth_failure(project_root_path, Some(current_scope_hash.as_str()));
-                anyhow::bail!(ACE_AUTH_FAILURE_SEARCH_MESSAGE);
-            }
-
-            if !status.is_success() {
-                let body = r.text().await.unwrap_or_default();
-                anyhow::bail!("HTTP {} {}", status, body);
-            }
-
-            let v: serde_json::Value = r.json().await?;
-            // 只记录摘要，避免将 formatted_retrieval（可能包含大量代码片段）写入日志
-            let keys: Vec<String> = v
-                .as_object()
-                .map(|m| m.keys().cloned().collect())
-                .unwrap_or_default();
-            let formatted_len = v
-                .get("formatted_retrieval")
-                .and_then(|x| x.as_str())
-                .map(|s| s.len())
-                .unwrap_or(0);
-            log_important!(
-                info,
-                "检索响应摘要: keys={:?}, formatted_retrieval_len={}",
-                keys,
-                formatted_len
-            );
-            Ok(v)
-        },
+    let text = match retry_request(
+        backend.as_ref(),
+        || backend.search(query, &blob_names),
         3,
         2.0,
     )
-    .await?;
-
-    let text = value
-        .get("formatted_retrieval")
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string();
+    .await
+    {
+        Ok(text) => text,
+        Err(error) => {
+            if matches!(
+                error.downcast_ref::<RemoteIndexError>(),
+                Some(RemoteIndexError::Unauthorized(_))
+            ) {
+                log_important!(info, "检索请求遇到 ACE 认证失败: {}", error);
+                mark_project_auth_failure(project_root_path, Some(current_scope_hash.as_str()));
+                anyhow::bail!(ACE_AUTH_FAILURE_SEARCH_MESSAGE);
+            }
+            return Err(error);
+        }
+    };
 
     if text.is_empty() {
         log_important!(info, "搜索返回空结果");
@@ -2847,3 +4180,160 @@ fn create_acemcp_client(config: &AcemcpConfig) -> anyhow::Result<Client> {
         .build()
         .map_err(|e| anyhow::anyhow!("构建 HTTP 客户端失败: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::collections::BTreeMap;
+
+    /// 只回答 find-missing 的远端替身：`0` 之外的 blob 均视为服务端缺失
+    struct PresentBlobs(HashSet<String>);
+
+    #[async_trait]
+    impl RemoteIndexBackend for PresentBlobs {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        fn base_url(&self) -> &str {
+            "http://snapshot.test"
+        }
+
+        async fn upload_blobs(&self, _blobs: &[BlobItem]) -> Result<Vec<String>> {
+            anyhow::bail!("测试后端不接受上传")
+        }
+
+        async fn find_missing(&self, blob_names: &[String]) -> Result<Vec<String>> {
+            Ok(blob_names
+                .iter()
+                .filter(|name| !self.0.contains(*name))
+                .cloned()
+                .collect())
+        }
+
+        async fn search(&self, _query: &str, _blob_names: &[String]) -> Result<String> {
+            anyhow::bail!("测试后端不支持检索")
+        }
+
+        fn supports_delete(&self) -> bool {
+            false
+        }
+
+        async fn delete_blobs(&self, _blob_names: &[String]) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    fn test_chunking() -> SnapshotChunking {
+        SnapshotChunking {
+            max_lines_per_blob: 800,
+            scanner: SecretScanner::new(Some(false), None, &[]),
+        }
+    }
+
+    /// 清理测试写入 ~/.acemcp/data 的项目状态
+    fn forget_project(normalized_root: &str) {
+        {
+            let _guard = projects_file_lock().lock().unwrap();
+            let mut projects = load_projects_file();
+            if projects.0.remove(normalized_root).is_some() {
+                save_projects_file(&projects).unwrap();
+            }
+        }
+        {
+            let _guard = projects_status_lock().lock().unwrap();
+            let mut status = load_projects_status();
+            if status.projects.remove(normalized_root).is_some() {
+                save_projects_status(&status).unwrap();
+            }
+        }
+        let _ = fs::remove_file(blob_manifest_file(normalized_root));
+    }
+
+    #[tokio::test]
+    async fn snapshot_import_confirms_only_blobs_the_server_still_has() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/kept.rs"), "fn kept() {}\n").unwrap();
+        fs::write(root.join("src/missing.rs"), "fn missing() {}\n").unwrap();
+        fs::write(root.join("src/forged.rs"), "fn forged() {}\n").unwrap();
+        let root_path = root.to_string_lossy().to_string();
+        let normalized_root = normalize_project_path(&root_path);
+        let chunking = test_chunking();
+        let local = |relative: &str| {
+            chunking
+                .blob_names(&root_path, relative, &root.join(relative))
+                .unwrap()
+        };
+        let kept = local("src/kept.rs");
+        let files = BTreeMap::from([
+            ("src/kept.rs".to_string(), kept.clone()),
+            ("src/missing.rs".to_string(), local("src/missing.rs")),
+            // 与本地内容不符的名称即使服务端存在也不确认
+            ("src/forged.rs".to_string(), vec!["blob-forged".to_string()]),
+            // 工作区中已不存在的文件即使服务端仍有 blob 也不确认
+            (
+                "src/deleted.rs".to_string(),
+                vec!["blob-deleted".to_string()],
+            ),
+        ]);
+        let backend = PresentBlobs(
+            kept.iter()
+                .cloned()
+                .chain(["blob-forged".to_string(), "blob-deleted".to_string()])
+                .collect(),
+        );
+
+        let outcome =
+            apply_ace_snapshot(&backend, "snapshot-test-scope", &root, &chunking, files).await;
+        let confirmed = load_projects_file().0.remove(&normalized_root);
+        let manifest = load_blob_manifest(&normalized_root, "snapshot-test-scope");
+        let scope_hash = get_project_status(&root_path).index_scope_hash;
+        forget_project(&normalized_root);
+
+        let outcome = outcome.expect("快照导入应成功");
+        assert_eq!(outcome.confirmed_files, 1);
+        assert_eq!(outcome.reupload_files, 1);
+        assert_eq!(outcome.mismatched_files, 1);
+        assert_eq!(outcome.confirmed_blobs, kept.len());
+        assert_eq!(confirmed, Some(kept));
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            vec!["src/kept.rs"]
+        );
+        let metadata = fs::metadata(root.join("src/kept.rs")).unwrap();
+        let entry = &manifest.files["src/kept.rs"];
+        assert_eq!((entry.size, entry.modified_ns), file_stamp(&metadata));
+        assert_eq!(scope_hash.as_deref(), Some("snapshot-test-scope"));
+    }
+
+    #[tokio::test]
+    async fn snapshot_import_is_rejected_while_the_project_is_indexing() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let normalized_root = normalize_project_path(&root.to_string_lossy());
+        auto_index_inflight()
+            .lock()
+            .unwrap()
+            .insert(normalized_root.clone());
+
+        let result = apply_ace_snapshot(
+            &PresentBlobs(HashSet::new()),
+            "scope",
+            &root,
+            &test_chunking(),
+            BTreeMap::new(),
+        )
+        .await;
+        let still_inflight = auto_index_inflight()
+            .lock()
+            .unwrap()
+            .remove(&normalized_root);
+
+        assert!(result.is_err());
+        // 拒绝时不得清除正在运行的索引任务的占位
+        assert!(still_inflight);
+    }
+}
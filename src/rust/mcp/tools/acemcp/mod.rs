@@ -1,10 +1,18 @@
 // Acemcp工具模块
 // 用于代码库索引和语义搜索的MCP工具
 
+mod chunking;
 pub mod commands;
+mod git_events;
+pub mod index_server;
+pub mod job_history;
 pub mod jobs;
 pub mod mcp;
+pub mod remote;
+pub mod scheduler;
 pub mod scope_guard;
+pub mod secret_scan;
+pub mod snapshot;
 pub mod types;
 pub mod watcher;
 
@@ -0,0 +1,396 @@
+// 远端代码索引后端抽象
+// 索引流程（收集 blob → 分批上传 → 检索）与具体线上协议解耦：
+// - ace：现有 ACE / Augment 兼容协议（batch-upload、find-missing、codebase-retrieval）
+// - self_hosted：随本 crate 发布的自建索引服务（见 index_server 模块与「三术索引」二进制）
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::types::AcemcpConfig;
+use crate::log_important;
+use crate::network::resilience::UpstreamError;
+
+pub const REMOTE_BACKEND_ACE: &str = "ace";
+pub const REMOTE_BACKEND_SELF_HOSTED: &str = "self_hosted";
+
+/// 上传到远端索引的最小单元；`path` 可能带 `#chunk-<hash8>` 分块后缀。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BlobItem {
+    pub path: String,
+    pub content: String,
+}
+
+/// 远端后端返回的可分类错误；其余 HTTP 错误以 `UpstreamError` 返回，供重试与熔断判断。
+#[derive(Debug, thiserror::Error)]
+pub enum RemoteIndexError {
+    #[error("HTTP 401 认证失败: {0}")]
+    Unauthorized(String),
+}
+
+/// 远端索引后端：上传 blob、查询缺失 blob、检索与删除。
+/// blob 名称统一为本地计算的 `sha256(path + content)`，服务端须返回同名以便核对断点。
+#[async_trait]
+pub trait RemoteIndexBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 服务地址，用于区分熔断端点。
+    fn base_url(&self) -> &str;
+
+    /// 上传一批 blob，返回服务端确认的 blob 名称。
+    async fn upload_blobs(&self, blobs: &[BlobItem]) -> Result<Vec<String>>;
+
+    /// 返回服务端尚未持有的 blob 名称。
+    async fn find_missing(&self, blob_names: &[String]) -> Result<Vec<String>>;
+
+    /// 在给定 blob 集合内检索，返回 `Path:` 文本协议格式的结果。
+    async fn search(&self, query: &str, blob_names: &[String]) -> Result<String>;
+
+    /// 服务端是否支持删除 blob；不支持时垃圾回收只收缩本地状态。
+    fn supports_delete(&self) -> bool;
+
+    /// 删除不再引用的 blob，返回实际删除数量。
+    async fn delete_blobs(&self, blob_names: &[String]) -> Result<usize>;
+}
+
+/// 按配置创建远端后端；未配置 `remote_backend` 时沿用 ACE 协议。
+pub fn create_remote_backend(
+    config: &AcemcpConfig,
+    client: Client,
+) -> Result<Box<dyn RemoteIndexBackend>> {
+    let base_url = config
+        .base_url
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("未配置 base_url"))?;
+    let token = config
+        .token
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("未配置 token"))?;
+    match parse_remote_backend(config.remote_backend.as_deref())?.as_str() {
+        REMOTE_BACKEND_SELF_HOSTED => Ok(Box::new(SelfHostedBackend::new(base_url, token, client))),
+        _ => Ok(Box::new(AceBackend::new(base_url, token, client))),
+    }
+}
+
+/// 解析 `remote_backend`；未配置时为 ACE，无法识别的取值返回错误，避免拼写错误时静默连到 ACE
+pub fn parse_remote_backend(value: Option<&str>) -> Result<String> {
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(REMOTE_BACKEND_ACE.to_string());
+    };
+    match value.to_ascii_lowercase().replace('-', "_").as_str() {
+        "ace" => Ok(REMOTE_BACKEND_ACE.to_string()),
+        "self_hosted" | "selfhosted" | "sanshu" => Ok(REMOTE_BACKEND_SELF_HOSTED.to_string()),
+        _ => Err(anyhow::anyhow!(
+            "未知的远端后端: {}，可选值为 {} 或 {}",
+            value,
+            REMOTE_BACKEND_ACE,
+            REMOTE_BACKEND_SELF_HOSTED
+        )),
+    }
+}
+
+/// 用于展示与签名的后端名称；无法识别的取值记录告警后按 ACE 展示，实际创建后端时会报错
+pub fn normalize_remote_backend(value: Option<&str>) -> String {
+    parse_remote_backend(value).unwrap_or_else(|error| {
+        crate::log_important!(warn, "{}", error);
+        REMOTE_BACKEND_ACE.to_string()
+    })
+}
+
+async fn post_json(
+    client: &Client,
+    url: &str,
+    token: &str,
+    payload: &serde_json::Value,
+) -> Result<serde_json::Value> {
+    let response = client
+        .post(url)
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .header(CONTENT_TYPE, "application/json")
+        .json(payload)
+        .send()
+        .await?;
+    let status = response.status();
+    if status == StatusCode::UNAUTHORIZED {
+        let body = response.text().await.unwrap_or_default();
+        return Err(RemoteIndexError::Unauthorized(body).into());
+    }
+    if !status.is_success() {
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        return Err(UpstreamError::from_status(status, &headers, &body).into());
+    }
+    Ok(response.json::<serde_json::Value>().await?)
+}
+
+fn string_array(value: &serde_json::Value, key: &str) -> Option<Vec<String>> {
+    value
+        .get(key)
+        .and_then(|value| value.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect()
+        })
+}
+
+/// ACE / Augment 兼容协议。
+pub struct AceBackend {
+    base_url: String,
+    token: String,
+    client: Client,
+}
+
+impl AceBackend {
+    pub fn new(base_url: String, token: String, client: Client) -> Self {
+        Self {
+            base_url,
+            token,
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl RemoteIndexBackend for AceBackend {
+    fn name(&self) -> &'static str {
+        REMOTE_BACKEND_ACE
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn upload_blobs(&self, blobs: &[BlobItem]) -> Result<Vec<String>> {
+        let url = format!("{}/batch-upload", self.base_url);
+        let value = post_json(
+            &self.client,
+            &url,
+            &self.token,
+            &serde_json::json!({ "blobs": blobs }),
+        )
+        .await?;
+        Ok(string_array(&value, "blob_names").unwrap_or_default())
+    }
+
+    async fn find_missing(&self, blob_names: &[String]) -> Result<Vec<String>> {
+        let url = format!("{}/find-missing", self.base_url);
+        let value = post_json(
+            &self.client,
+            &url,
+            &self.token,
+            &serde_json::json!({ "mem_object_names": blob_names }),
+        )
+        .await?;
+        string_array(&value, "unknown_memory_names")
+            .ok_or_else(|| anyhow::anyhow!("find-missing 响应缺少 unknown_memory_names"))
+    }
+
+    async fn search(&self, query: &str, blob_names: &[String]) -> Result<String> {
+        let url = format!("{}/agents/codebase-retrieval", self.base_url);
+        let payload = serde_json::json!({
+            "information_request": query,
+            "blobs": {"checkpoint_id": serde_json::Value::Null, "added_blobs": blob_names, "deleted_blobs": []},
+            "dialog": [],
+            "max_output_length": 0,
+            "disable_codebase_retrieval": false,
+            "enable_commit_retrieval": false,
+        });
+        let value = post_json(&self.client, &url, &self.token, &payload).await?;
+        // 只记录摘要，避免将 formatted_retrieval（可能包含大量代码片段）写入日志
+        let keys: Vec<String> = value
+            .as_object()
+            .map(|map| map.keys().cloned().collect())
+            .unwrap_or_default();
+        let text = value
+            .get("formatted_retrieval")
+            .and_then(|value| value.as_str())
+            .unwrap_or("")
+            .to_string();
+        log_important!(
+            info,
+            "检索响应摘要: keys={:?}, formatted_retrieval_len={}",
+            keys,
+            text.len()
+        );
+        Ok(text)
+    }
+
+    fn supports_delete(&self) -> bool {
+        false
+    }
+
+    /// ACE 按请求携带的 blob 集合检索，未被引用的 blob 不参与结果，无需也无接口删除。
+    async fn delete_blobs(&self, _blob_names: &[String]) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+/// 自建索引服务协议（`/v1/...`），与 `index_server` 模块成对维护。
+/// 服务可由多台机器共用，上传、握手与删除都携带本机 `client_id`，服务端据此按客户端计数引用。
+pub struct SelfHostedBackend {
+    base_url: String,
+    token: String,
+    client: Client,
+    client_id: String,
+}
+
+impl SelfHostedBackend {
+    pub fn new(base_url: String, token: String, client: Client) -> Self {
+        Self {
+            base_url,
+            token,
+            client,
+            client_id: installation_client_id(),
+        }
+    }
+
+    async fn call(&self, path: &str, payload: serde_json::Value) -> Result<serde_json::Value> {
+        let url = format!("{}{}", self.base_url, path);
+        post_json(&self.client, &url, &self.token, &payload).await
+    }
+}
+
+#[async_trait]
+impl RemoteIndexBackend for SelfHostedBackend {
+    fn name(&self) -> &'static str {
+        REMOTE_BACKEND_SELF_HOSTED
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn upload_blobs(&self, blobs: &[BlobItem]) -> Result<Vec<String>> {
+        let value = self
+            .call(
+                "/v1/blobs/upload",
+                serde_json::json!({ "blobs": blobs, "client_id": self.client_id }),
+            )
+            .await?;
+        Ok(string_array(&value, "blob_names").unwrap_or_default())
+    }
+
+    async fn find_missing(&self, blob_names: &[String]) -> Result<Vec<String>> {
+        let value = self
+            .call(
+                "/v1/blobs/find-missing",
+                serde_json::json!({ "blob_names": blob_names, "client_id": self.client_id }),
+            )
+            .await?;
+        string_array(&value, "missing")
+            .ok_or_else(|| anyhow::anyhow!("find-missing 响应缺少 missing"))
+    }
+
+    async fn search(&self, query: &str, blob_names: &[String]) -> Result<String> {
+        let value = self
+            .call(
+                "/v1/search",
+                serde_json::json!({ "query": query, "blob_names": blob_names }),
+            )
+            .await?;
+        Ok(value
+            .get("formatted_retrieval")
+            .and_then(|value| value.as_str())
+            .unwrap_or("")
+            .to_string())
+    }
+
+    fn supports_delete(&self) -> bool {
+        true
+    }
+
+    async fn delete_blobs(&self, blob_names: &[String]) -> Result<usize> {
+        let value = self
+            .call(
+                "/v1/blobs/delete",
+                serde_json::json!({ "blob_names": blob_names, "client_id": self.client_id }),
+            )
+            .await?;
+        Ok(value
+            .get("deleted")
+            .and_then(|value| value.as_u64())
+            .unwrap_or(0) as usize)
+    }
+}
+
+/// 本机安装的客户端标识，持久化在 ~/.acemcp/data/client_id；
+/// 无法持久化时本进程使用临时标识，对应引用永不释放，只会多保留 blob。
+fn installation_client_id() -> String {
+    static CLIENT_ID: OnceLock<String> = OnceLock::new();
+    CLIENT_ID
+        .get_or_init(|| {
+            let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+            let data_dir = home.join(".acemcp").join("data");
+            let _ = fs::create_dir_all(&data_dir);
+            load_or_create_client_id(&data_dir.join("client_id")).unwrap_or_else(|error| {
+                log::warn!("读取客户端标识失败，本进程使用临时标识: {}", error);
+                uuid::Uuid::new_v4().to_string()
+            })
+        })
+        .clone()
+}
+
+fn load_or_create_client_id(path: &Path) -> Result<String> {
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    if let Some(client_id) = read(path) {
+        return Ok(client_id);
+    }
+    let client_id = uuid::Uuid::new_v4().to_string();
+    // 先写入同目录的临时文件，再以不覆盖的方式改名：其他进程要么看不到文件，要么读到完整标识；
+    // 并发启动的多个进程只有一个改名成功，其余读取同一标识
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(client_id.as_bytes())?;
+    match file.persist_noclobber(path) {
+        Ok(_) => Ok(client_id),
+        Err(error) if error.error.kind() == ErrorKind::AlreadyExists => {
+            read(path).ok_or_else(|| anyhow::anyhow!("客户端标识文件为空: {}", path.display()))
+        }
+        Err(error) => Err(error.error.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_backend_defaults_to_ace_and_accepts_aliases() {
+        assert_eq!(normalize_remote_backend(None), REMOTE_BACKEND_ACE);
+        assert_eq!(
+            normalize_remote_backend(Some("Self-Hosted")),
+            REMOTE_BACKEND_SELF_HOSTED
+        );
+        assert_eq!(parse_remote_backend(Some(" ")).unwrap(), REMOTE_BACKEND_ACE);
+        assert!(parse_remote_backend(Some("self_hostd")).is_err());
+        assert_eq!(
+            normalize_remote_backend(Some("self_hostd")),
+            REMOTE_BACKEND_ACE
+        );
+    }
+
+    #[test]
+    fn client_id_is_created_once_and_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client_id");
+        let first = load_or_create_client_id(&path).unwrap();
+        assert!(!first.is_empty());
+        assert_eq!(load_or_create_client_id(&path).unwrap(), first);
+        assert_eq!(fs::read_to_string(&path).unwrap(), first);
+        // 临时文件已改名为标识文件，不留残余
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
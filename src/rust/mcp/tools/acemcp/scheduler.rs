@@ -0,0 +1,413 @@
+// ACE 索引任务全局调度
+// 单项目去重与断点由 jobs / launch_index_worker 负责，这里只控制跨项目的执行顺序：
+// - 并发上限：进程内先排队，再用 lease 目录下的槽位文件在所有 MCP / GUI 进程间限流
+// - 优先级：搜索触发 > 文件监听 > 嵌套子项目补齐（只在本进程的队列内生效）
+// - 同优先级按「最久未被调度的项目优先」轮转，避免单个项目反复插队
+// - 上传带宽上限：本进程所有 worker 共享一个令牌桶
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use super::jobs::{self, ProjectLease};
+use crate::log_important;
+
+/// 未配置时的默认并发上限
+pub const DEFAULT_INDEX_CONCURRENCY: usize = 2;
+/// 跨进程槽位全部被占用时的重试间隔
+const SLOT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 索引任务优先级，数值越大越先执行
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexPriority {
+    /// 嵌套子项目补齐、进程启动后恢复的任务
+    Backfill = 0,
+    /// 文件监听防抖后的增量刷新
+    Watcher = 1,
+    /// 搜索或用户手动触发，调用方正在等待结果
+    Search = 2,
+}
+
+/// 单个排队或执行中的任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexQueueEntry {
+    pub project_root: String,
+    pub priority: IndexPriority,
+    /// 入队（或开始执行）至今的毫秒数
+    pub elapsed_ms: u64,
+}
+
+/// 调度器快照，随 get_all_acemcp_index_status 返回给前端
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct IndexQueueStatus {
+    pub max_concurrency: usize,
+    /// 上传带宽上限（KB/s），为空表示不限速
+    pub upload_bandwidth_kbps: Option<u32>,
+    pub running: Vec<IndexQueueEntry>,
+    /// 按调度顺序排列的等待任务
+    pub queued: Vec<IndexQueueEntry>,
+}
+
+struct Waiter {
+    project_root: String,
+    priority: IndexPriority,
+    seq: u64,
+    enqueued_at: Instant,
+    notify: oneshot::Sender<()>,
+}
+
+struct Running {
+    priority: IndexPriority,
+    started_at: Instant,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    max_concurrency: usize,
+    running: HashMap<String, Running>,
+    waiting: Vec<Waiter>,
+    /// 项目最近一次获得槽位的序号，用于同优先级轮转
+    last_served: HashMap<String, u64>,
+    tick: u64,
+}
+
+impl SchedulerState {
+    fn limit(&self) -> usize {
+        self.max_concurrency.max(1)
+    }
+
+    /// 选出下一个应获得槽位的等待者下标
+    fn next_waiter(&self) -> Option<usize> {
+        self.waiting
+            .iter()
+            .enumerate()
+            .max_by(|(_, left), (_, right)| {
+                left.priority
+                    .cmp(&right.priority)
+                    // 同优先级时最久未被调度的项目优先，再按入队顺序
+                    .then_with(|| {
+                        self.served_at(&right.project_root)
+                            .cmp(&self.served_at(&left.project_root))
+                    })
+                    .then_with(|| right.seq.cmp(&left.seq))
+            })
+            .map(|(index, _)| index)
+    }
+
+    fn served_at(&self, project_root: &str) -> u64 {
+        self.last_served.get(project_root).copied().unwrap_or(0)
+    }
+
+    fn grant(&mut self, project_root: &str, priority: IndexPriority) {
+        self.tick += 1;
+        self.last_served.insert(project_root.to_string(), self.tick);
+        self.running.insert(
+            project_root.to_string(),
+            Running {
+                priority,
+                started_at: Instant::now(),
+            },
+        );
+    }
+
+    /// 在有空闲槽位时唤醒等待者；接收端已丢弃的等待者直接跳过
+    fn dispatch(&mut self) {
+        while self.running.len() < self.limit() {
+            let Some(index) = self.next_waiter() else {
+                return;
+            };
+            let waiter = self.waiting.swap_remove(index);
+            self.grant(&waiter.project_root, waiter.priority);
+            if waiter.notify.send(()).is_err() {
+                self.running.remove(&waiter.project_root);
+            }
+        }
+    }
+}
+
+fn scheduler() -> &'static Mutex<SchedulerState> {
+    static SCHEDULER: OnceLock<Mutex<SchedulerState>> = OnceLock::new();
+    SCHEDULER.get_or_init(|| {
+        Mutex::new(SchedulerState {
+            max_concurrency: DEFAULT_INDEX_CONCURRENCY,
+            ..Default::default()
+        })
+    })
+}
+
+/// 执行槽位；析构时归还并唤醒下一个任务
+pub(crate) struct SchedulerPermit {
+    project_root: String,
+    /// 跨进程槽位文件锁；lease 目录不可用时为空，退化为仅进程内限流
+    _slot: Option<ProjectLease>,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        if let Ok(mut state) = scheduler().lock() {
+            state.running.remove(&self.project_root);
+            state.dispatch();
+        }
+    }
+}
+
+/// 更新并发上限；调小时不打断执行中的任务，只影响后续调度
+pub(crate) fn set_max_concurrency(limit: Option<u32>) {
+    let limit = limit
+        .map(|value| value as usize)
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_INDEX_CONCURRENCY);
+    if let Ok(mut state) = scheduler().lock() {
+        state.max_concurrency = limit;
+        state.dispatch();
+    }
+}
+
+/// 等待执行槽位。队列为空且未达上限时立即返回。
+pub(crate) async fn acquire(project_root: &str, priority: IndexPriority) -> SchedulerPermit {
+    let receiver = {
+        let mut state = scheduler().lock().unwrap();
+        if state.running.len() < state.limit() && state.waiting.is_empty() {
+            state.grant(project_root, priority);
+            None
+        } else {
+            let (notify, receiver) = oneshot::channel();
+            state.tick += 1;
+            let seq = state.tick;
+            state.waiting.push(Waiter {
+                project_root: project_root.to_string(),
+                priority,
+                seq,
+                enqueued_at: Instant::now(),
+                notify,
+            });
+            log_important!(
+                info,
+                "ACE索引任务排队: project_root={}, priority={:?}, running={}, queued={}",
+                project_root,
+                priority,
+                state.running.len(),
+                state.waiting.len()
+            );
+            Some(receiver)
+        }
+    };
+    if let Some(receiver) = receiver {
+        // 发送端只会在授予槽位后触发；调度器随进程存在，不会提前丢弃
+        let _ = receiver.await;
+    }
+    // 先构造 permit：等待跨进程槽位期间任务被取消时，进程内槽位也能随析构归还
+    let mut permit = SchedulerPermit {
+        project_root: project_root.to_string(),
+        _slot: None,
+    };
+    permit._slot = acquire_process_slot(project_root).await;
+    permit
+}
+
+/// 在 lease 目录的 `slot-<n>.lock` 中占用一个空闲槽位，所有进程合计不超过并发上限。
+async fn acquire_process_slot(project_root: &str) -> Option<ProjectLease> {
+    let slot_dir = jobs::lease_dir().join("slots");
+    let mut logged = false;
+    loop {
+        let limit = scheduler()
+            .lock()
+            .map(|state| state.limit())
+            .unwrap_or(DEFAULT_INDEX_CONCURRENCY);
+        match try_acquire_slot(&slot_dir, limit) {
+            Ok(Some(slot)) => return Some(slot),
+            Ok(None) => {
+                if !logged {
+                    log_important!(
+                        info,
+                        "ACE索引任务等待其他进程释放槽位: project_root={}, max_concurrency={}",
+                        project_root,
+                        limit
+                    );
+                    logged = true;
+                }
+                tokio::time::sleep(SLOT_POLL_INTERVAL).await;
+            }
+            Err(error) => {
+                log_important!(
+                    warn,
+                    "ACE索引跨进程槽位不可用，仅按进程内并发限制执行: {}",
+                    error
+                );
+                return None;
+            }
+        }
+    }
+}
+
+fn try_acquire_slot(slot_dir: &Path, limit: usize) -> anyhow::Result<Option<ProjectLease>> {
+    for slot in 0..limit.max(1) {
+        if let Some(lease) =
+            jobs::try_acquire_lease_file(&slot_dir.join(format!("slot-{}.lock", slot)))?
+        {
+            return Ok(Some(lease));
+        }
+    }
+    Ok(None)
+}
+
+/// 提升排队中任务的优先级（例如 watcher 任务尚未执行时用户发起了搜索）
+pub(crate) fn promote(project_root: &str, priority: IndexPriority) {
+    if let Ok(mut state) = scheduler().lock() {
+        for waiter in state
+            .waiting
+            .iter_mut()
+            .filter(|waiter| waiter.project_root == project_root)
+        {
+            waiter.priority = waiter.priority.max(priority);
+        }
+    }
+}
+
+pub fn queue_status(upload_bandwidth_kbps: Option<u32>) -> IndexQueueStatus {
+    let Ok(state) = scheduler().lock() else {
+        return IndexQueueStatus::default();
+    };
+    let mut running = state
+        .running
+        .iter()
+        .map(|(project_root, running)| IndexQueueEntry {
+            project_root: project_root.clone(),
+            priority: running.priority,
+            elapsed_ms: running.started_at.elapsed().as_millis() as u64,
+        })
+        .collect::<Vec<_>>();
+    running.sort_by(|left, right| left.project_root.cmp(&right.project_root));
+
+    // 按实际调度顺序输出等待队列
+    let mut order = (0..state.waiting.len()).collect::<Vec<_>>();
+    order.sort_by(|left, right| {
+        let (left, right) = (&state.waiting[*left], &state.waiting[*right]);
+        right
+            .priority
+            .cmp(&left.priority)
+            .then_with(|| {
+                state
+                    .served_at(&left.project_root)
+                    .cmp(&state.served_at(&right.project_root))
+            })
+            .then_with(|| left.seq.cmp(&right.seq))
+    });
+    let queued = order
+        .into_iter()
+        .map(|index| {
+            let waiter = &state.waiting[index];
+            IndexQueueEntry {
+                project_root: waiter.project_root.clone(),
+                priority: waiter.priority,
+                elapsed_ms: waiter.enqueued_at.elapsed().as_millis() as u64,
+            }
+        })
+        .collect();
+    IndexQueueStatus {
+        max_concurrency: state.limit(),
+        upload_bandwidth_kbps: upload_bandwidth_kbps.filter(|value| *value > 0),
+        running,
+        queued,
+    }
+}
+
+/// 共享令牌桶：按上传字节数预约发送时间，所有 worker 合计不超过带宽上限
+pub(crate) async fn throttle_upload(upload_bandwidth_kbps: Option<u32>, bytes: usize) {
+    let Some(kbps) = upload_bandwidth_kbps.filter(|value| *value > 0) else {
+        return;
+    };
+    static NEXT_FREE: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+    let delay = {
+        let mut next_free = NEXT_FREE.get_or_init(|| Mutex::new(None)).lock().unwrap();
+        let now = Instant::now();
+        let start = next_free.filter(|instant| *instant > now).unwrap_or(now);
+        let cost = Duration::from_secs_f64(bytes as f64 / (kbps as f64 * 1024.0));
+        *next_free = Some(start + cost);
+        start - now
+    };
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waiter(
+        project_root: &str,
+        priority: IndexPriority,
+        seq: u64,
+    ) -> (Waiter, oneshot::Receiver<()>) {
+        let (notify, receiver) = oneshot::channel();
+        (
+            Waiter {
+                project_root: project_root.to_string(),
+                priority,
+                seq,
+                enqueued_at: Instant::now(),
+                notify,
+            },
+            receiver,
+        )
+    }
+
+    #[test]
+    fn dispatch_prefers_priority_then_least_recently_served_project() {
+        let mut state = SchedulerState {
+            max_concurrency: 1,
+            ..Default::default()
+        };
+        state.grant("/a", IndexPriority::Watcher);
+        state.running.clear();
+
+        let mut receivers = Vec::new();
+        for (project, priority, seq) in [
+            ("/a", IndexPriority::Watcher, 10),
+            ("/b", IndexPriority::Watcher, 11),
+            ("/c", IndexPriority::Backfill, 12),
+            ("/d", IndexPriority::Search, 13),
+        ] {
+            let (waiter, receiver) = waiter(project, priority, seq);
+            state.waiting.push(waiter);
+            receivers.push(receiver);
+        }
+
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            state.dispatch();
+            let served = state.running.keys().next().cloned().unwrap();
+            state.running.clear();
+            order.push(served);
+        }
+        // /a 刚被调度过，同为 watcher 时让位给 /b
+        assert_eq!(order, vec!["/d", "/b", "/a", "/c"]);
+    }
+
+    #[test]
+    fn process_slots_are_limited_across_lease_holders() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = try_acquire_slot(dir.path(), 2).unwrap();
+        let second = try_acquire_slot(dir.path(), 2).unwrap();
+        assert!(first.is_some() && second.is_some());
+        // 文件锁按句柄互斥，其他进程同样拿不到第三个槽位
+        assert!(try_acquire_slot(dir.path(), 2).unwrap().is_none());
+        drop(first);
+        assert!(try_acquire_slot(dir.path(), 2).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn bandwidth_cap_delays_following_uploads() {
+        let started = Instant::now();
+        throttle_upload(Some(1024), 64 * 1024).await;
+        throttle_upload(Some(1024), 64 * 1024).await;
+        // 第一批立即发送，第二批需等待第一批占用的约 62ms
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        throttle_upload(None, usize::MAX).await;
+    }
+}
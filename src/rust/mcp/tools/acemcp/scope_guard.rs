@@ -81,6 +81,7 @@ impl ProjectScopeAssessment {
             project_markers: self.project_markers,
             requires_secondary_confirmation: false,
             detected_at: chrono::Utc::now(),
+            secret_findings: Vec::new(),
         }
     }
 }
@@ -158,6 +159,7 @@ pub(crate) fn critical_path_risk(project_root: &str) -> Option<ProjectScopeRisk>
         project_markers: detect_root_markers(&root_path),
         requires_secondary_confirmation: true,
         detected_at: chrono::Utc::now(),
+        secret_findings: Vec::new(),
     })
 }
 
@@ -270,7 +272,7 @@ fn preflight_with_limits(
     Ok(None)
 }
 
-fn build_gitignore(root: &Path) -> Option<Gitignore> {
+pub(super) fn build_gitignore(root: &Path) -> Option<Gitignore> {
     let mut builder = GitignoreBuilder::new(root);
     let path = root.join(".gitignore");
     if !path.exists() || builder.add(path).is_some() {
@@ -279,7 +281,7 @@ fn build_gitignore(root: &Path) -> Option<Gitignore> {
     builder.build().ok()
 }
 
-fn has_text_extension(path: &Path, text_extensions: &[String]) -> bool {
+pub(super) fn has_text_extension(path: &Path, text_extensions: &[String]) -> bool {
     path.extension()
         .and_then(|value| value.to_str())
         .map(|extension| {
@@ -310,7 +312,7 @@ fn detect_root_markers(root: &Path) -> Vec<String> {
     markers
 }
 
-fn paths_equal(left: &str, right: &str) -> bool {
+pub(super) fn paths_equal(left: &str, right: &str) -> bool {
     if cfg!(windows) {
         left.eq_ignore_ascii_case(right)
     } else {
@@ -0,0 +1,416 @@
+// 索引快照导出 / 导入
+// 新成员首次索引耗时很长，而索引状态（projects.json、blob 清单、本地 FTS 库）都以本机绝对路径为键。
+// 快照把项目的索引状态打包为只含相对路径的 zip：
+// - snapshot.json：文件内容哈希清单 + 已确认的 ACE blob 名称
+// - local/index.sqlite3：本地 FTS 索引副本
+// 导入时逐文件核对内容哈希，只复用一致的部分，其余文件照常重新上传 / 重新索引。
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use ring::digest::{Context as ShaContext, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+use super::mcp::{self, AceSnapshotImport, AceSnapshotState};
+use super::types::AcemcpConfig;
+use crate::log_important;
+use crate::mcp::tools::sou::local::{self, LocalIndexStatus};
+
+/// 快照格式版本；结构不兼容时递增
+const SNAPSHOT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "snapshot.json";
+const LOCAL_DB_ENTRY: &str = "local/index.sqlite3";
+/// snapshot.json 解压上限，防止损坏或恶意归档占满内存
+const MAX_MANIFEST_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotManifest {
+    version: u32,
+    created_at: DateTime<Utc>,
+    /// 导出时的项目目录名，仅用于展示
+    project_name: String,
+    /// 相对路径 → 内容哈希；只包含导出时与索引状态一致的文件
+    files: BTreeMap<String, SnapshotFile>,
+    ace: Option<AceSnapshotState>,
+    /// 本地索引副本收录的相对路径
+    local_files: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotFile {
+    sha256: String,
+    size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotExportResult {
+    pub archive_path: String,
+    pub files: usize,
+    pub ace_files: usize,
+    pub local_files: usize,
+    pub archive_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotImportResult {
+    pub files: usize,
+    /// 内容哈希与工作区一致
+    pub matched_files: usize,
+    /// 内容已变化，需要重新索引
+    pub changed_files: usize,
+    /// 工作区中已不存在
+    pub missing_files: usize,
+    pub ace: Option<AceSnapshotImport>,
+    pub ace_skipped_reason: Option<String>,
+    pub local: Option<LocalIndexStatus>,
+    pub local_skipped_reason: Option<String>,
+}
+
+/// 文件内容哈希核对结果
+#[derive(Debug, Default)]
+struct Verification {
+    matched: HashSet<String>,
+    changed: usize,
+    missing: usize,
+}
+
+fn canonical_root(project_root: &str) -> Result<PathBuf> {
+    let root = PathBuf::from(project_root)
+        .canonicalize()
+        .with_context(|| format!("项目路径无效: {}", project_root))?;
+    if !root.is_dir() {
+        anyhow::bail!("项目路径不是目录: {}", root.display());
+    }
+    Ok(root)
+}
+
+fn file_sha256(path: &Path) -> Option<(String, u64)> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut ctx = ShaContext::new(&SHA256);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        size += read as u64;
+        ctx.update(&buffer[..read]);
+    }
+    Some((hex::encode(ctx.finish().as_ref()), size))
+}
+
+fn hash_files<'a>(
+    root: &Path,
+    relatives: impl IntoIterator<Item = &'a String>,
+) -> BTreeMap<String, SnapshotFile> {
+    relatives
+        .into_iter()
+        .filter_map(|relative| {
+            let (sha256, size) = file_sha256(&root.join(relative))?;
+            Some((relative.clone(), SnapshotFile { sha256, size }))
+        })
+        .collect()
+}
+
+fn verify_files(root: &Path, files: &BTreeMap<String, SnapshotFile>) -> Verification {
+    let mut verification = Verification::default();
+    for (relative, expected) in files {
+        // 归档来自其他机器，拒绝越出项目目录的路径
+        if Path::new(relative).is_absolute() || relative.split('/').any(|part| part == "..") {
+            verification.missing += 1;
+            continue;
+        }
+        match file_sha256(&root.join(relative)) {
+            Some((sha256, size)) if sha256 == expected.sha256 && size == expected.size => {
+                verification.matched.insert(relative.clone());
+            }
+            Some(_) => verification.changed += 1,
+            None => verification.missing += 1,
+        }
+    }
+    verification
+}
+
+fn write_archive(
+    archive_path: &Path,
+    manifest: &SnapshotManifest,
+    local_db: Option<&Path>,
+) -> Result<()> {
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = archive_path.with_extension(format!("zip.tmp-{}", uuid::Uuid::new_v4()));
+    let result = (|| -> Result<()> {
+        let mut writer = zip::ZipWriter::new(fs::File::create(&tmp_path)?);
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(true);
+        writer.start_file(MANIFEST_ENTRY, options)?;
+        writer.write_all(&serde_json::to_vec(manifest)?)?;
+        if let Some(local_db) = local_db {
+            writer.start_file(LOCAL_DB_ENTRY, options)?;
+            std::io::copy(&mut fs::File::open(local_db)?, &mut writer)?;
+        }
+        writer.finish()?;
+        Ok(())
+    })();
+    if let Err(error) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(error);
+    }
+    fs::rename(&tmp_path, archive_path)
+        .with_context(|| format!("写入快照失败: {}", archive_path.display()))
+}
+
+/// 读取快照清单，并把本地索引解压到 `extract_dir`
+fn read_archive(
+    archive_path: &Path,
+    extract_dir: &Path,
+) -> Result<(SnapshotManifest, Option<PathBuf>)> {
+    let file = fs::File::open(archive_path)
+        .with_context(|| format!("无法打开快照: {}", archive_path.display()))?;
+    let mut archive = zip::ZipArchive::new(file).context("无法读取快照归档")?;
+    let manifest: SnapshotManifest = {
+        let entry = archive
+            .by_name(MANIFEST_ENTRY)
+            .context("快照缺少 snapshot.json")?;
+        let mut data = Vec::new();
+        entry.take(MAX_MANIFEST_BYTES).read_to_end(&mut data)?;
+        serde_json::from_slice(&data).context("snapshot.json 格式无效")?
+    };
+    if manifest.version != SNAPSHOT_VERSION {
+        anyhow::bail!(
+            "不支持的快照版本 {}（当前版本 {}）",
+            manifest.version,
+            SNAPSHOT_VERSION
+        );
+    }
+    let local_db = match archive.by_name(LOCAL_DB_ENTRY) {
+        Ok(mut entry) => {
+            let path = extract_dir.join("index.sqlite3");
+            std::io::copy(&mut entry, &mut fs::File::create(&path)?)?;
+            Some(path)
+        }
+        Err(_) => None,
+    };
+    Ok((manifest, local_db))
+}
+
+/// 导出项目索引快照到 `archive_path`
+pub(crate) async fn export_snapshot(
+    config: &AcemcpConfig,
+    project_root: &str,
+    archive_path: &str,
+) -> Result<SnapshotExportResult> {
+    let root = canonical_root(project_root)?;
+    let archive_path = PathBuf::from(archive_path);
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || {
+        let temp = tempfile::tempdir().context("创建临时目录失败")?;
+        let local_db = temp.path().join("index.sqlite3");
+        let local_files = match local::export_snapshot_database(&root, &local_db) {
+            Ok(files) => files,
+            Err(error) => {
+                log::warn!("导出本地索引失败，快照将不包含本地索引: {}", error);
+                None
+            }
+        };
+        let mut ace = mcp::export_ace_snapshot_state(&config, &root);
+        let mut relatives = ace
+            .as_ref()
+            .map(|ace| ace.files.keys().cloned().collect::<HashSet<_>>())
+            .unwrap_or_default();
+        relatives.extend(local_files.iter().flatten().cloned());
+        if relatives.is_empty() {
+            anyhow::bail!("项目尚无可导出的索引状态，请先完成一次索引");
+        }
+        let files = hash_files(&root, &relatives);
+        if let Some(ace) = ace.as_mut() {
+            ace.files.retain(|relative, _| files.contains_key(relative));
+        }
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_VERSION,
+            created_at: Utc::now(),
+            project_name: root
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            files,
+            ace,
+            local_files,
+        };
+        write_archive(
+            &archive_path,
+            &manifest,
+            manifest.local_files.as_ref().map(|_| local_db.as_path()),
+        )?;
+        let result = SnapshotExportResult {
+            archive_path: archive_path.to_string_lossy().replace('\\', "/"),
+            files: manifest.files.len(),
+            ace_files: manifest.ace.as_ref().map_or(0, |ace| ace.files.len()),
+            local_files: manifest.local_files.as_ref().map_or(0, Vec::len),
+            archive_bytes: fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0),
+        };
+        log_important!(
+            info,
+            "索引快照已导出: project_root={}, archive={}, files={}, ace_files={}, local_files={}",
+            root.display(),
+            result.archive_path,
+            result.files,
+            result.ace_files,
+            result.local_files
+        );
+        Ok(result)
+    })
+    .await
+    .context("导出快照任务异常")?
+}
+
+/// 从快照导入项目索引状态；只复用内容哈希与工作区一致的文件
+pub(crate) async fn import_snapshot(
+    config: &AcemcpConfig,
+    project_root: &str,
+    archive_path: &str,
+) -> Result<SnapshotImportResult> {
+    let root = canonical_root(project_root)?;
+    let archive_path = PathBuf::from(archive_path);
+    let temp = tempfile::tempdir().context("创建临时目录失败")?;
+    let extract_dir = temp.path().to_path_buf();
+    let blocking_root = root.clone();
+    let (manifest, local_db, verification) = tokio::task::spawn_blocking(move || {
+        let (manifest, local_db) = read_archive(&archive_path, &extract_dir)?;
+        let verification = verify_files(&blocking_root, &manifest.files);
+        Ok::<_, anyhow::Error>((manifest, local_db, verification))
+    })
+    .await
+    .context("读取快照任务异常")??;
+
+    let mut result = SnapshotImportResult {
+        files: manifest.files.len(),
+        matched_files: verification.matched.len(),
+        changed_files: verification.changed,
+        missing_files: verification.missing,
+        ace: None,
+        ace_skipped_reason: None,
+        local: None,
+        local_skipped_reason: None,
+    };
+
+    match (manifest.local_files.as_ref(), local_db) {
+        (Some(_), Some(local_db)) => {
+            let verified = verification.matched.clone();
+            let local_root = root.clone();
+            match tokio::task::spawn_blocking(move || {
+                local::import_snapshot_database(&local_root, &local_db, &verified)
+            })
+            .await
+            .context("导入本地索引任务异常")?
+            {
+                Ok(status) => result.local = Some(status),
+                Err(error) => result.local_skipped_reason = Some(error.to_string()),
+            }
+        }
+        _ => result.local_skipped_reason = Some("快照不包含本地索引".to_string()),
+    }
+
+    match manifest.ace {
+        None => result.ace_skipped_reason = Some("快照不包含 ACE 索引状态".to_string()),
+        Some(ace)
+            if mcp::portable_index_fingerprint(config).as_deref() != Some(&ace.fingerprint) =>
+        {
+            result.ace_skipped_reason = Some(
+                "ACE 地址、后端或索引参数（扩展名、排除规则、分块行数）与导出方不一致".to_string(),
+            );
+        }
+        Some(ace) => {
+            let files = ace
+                .files
+                .into_iter()
+                .filter(|(relative, _)| verification.matched.contains(relative))
+                .collect::<BTreeMap<_, _>>();
+            match mcp::import_ace_snapshot_state(config, &root, files).await {
+                Ok(outcome) => result.ace = Some(outcome),
+                Err(error) => result.ace_skipped_reason = Some(error.to_string()),
+            }
+        }
+    }
+
+    log_important!(
+        info,
+        "索引快照已导入: project_root={}, files={}, matched={}, changed={}, missing={}",
+        root.display(),
+        result.files,
+        result.matched_files,
+        result.changed_files,
+        result.missing_files
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_round_trip_verifies_content_hashes() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("project");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("src/b.rs"), "fn b() {}\n").unwrap();
+        fs::write(root.join("src/c.rs"), "fn c() {}\n").unwrap();
+        let relatives = ["src/a.rs", "src/b.rs", "src/c.rs"].map(String::from);
+        let mut ace = AceSnapshotState {
+            fingerprint: "fp".to_string(),
+            ..Default::default()
+        };
+        ace.files
+            .insert("src/a.rs".to_string(), vec!["blob-a".to_string()]);
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_VERSION,
+            created_at: Utc::now(),
+            project_name: "project".to_string(),
+            files: hash_files(&root, &relatives),
+            ace: Some(ace),
+            local_files: None,
+        };
+        let archive = temp.path().join("out/snapshot.zip");
+        write_archive(&archive, &manifest, None).unwrap();
+
+        fs::write(root.join("src/b.rs"), "fn b() { changed(); }\n").unwrap();
+        fs::remove_file(root.join("src/c.rs")).unwrap();
+        let extract = tempfile::tempdir().unwrap();
+        let (read, local_db) = read_archive(&archive, extract.path()).unwrap();
+        assert!(local_db.is_none());
+        assert_eq!(read.ace.unwrap().files["src/a.rs"], vec!["blob-a"]);
+
+        let verification = verify_files(&root, &read.files);
+        assert_eq!(
+            verification.matched,
+            HashSet::from(["src/a.rs".to_string()])
+        );
+        assert_eq!(verification.changed, 1);
+        assert_eq!(verification.missing, 1);
+    }
+
+    #[test]
+    fn paths_escaping_the_project_are_never_verified() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("project");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(temp.path().join("secret.txt"), "x").unwrap();
+        let files = hash_files(temp.path(), &["secret.txt".to_string()]);
+        let escaped = files
+            .into_values()
+            .map(|file| ("../secret.txt".to_string(), file))
+            .collect();
+        let verification = verify_files(&root, &escaped);
+        assert!(verification.matched.is_empty());
+        assert_eq!(verification.missing, 1);
+    }
+}
@@ -43,6 +43,18 @@ pub struct AcemcpConfig {
     pub proxy_username: Option<String>,
     /// 代理密码（可选）
     pub proxy_password: Option<String>,
+    /// 远端索引后端: "ace"（默认）| "self_hosted"
+    pub remote_backend: Option<String>,
+    /// 跨项目同时执行的索引任务上限（默认 2）
+    pub index_concurrency: Option<u32>,
+    /// 所有索引任务合计的上传带宽上限（KB/s），为空或 0 表示不限速
+    pub upload_bandwidth_kbps: Option<u32>,
+    /// 是否在上传前扫描疑似密钥（默认启用）
+    pub secret_scan_enabled: Option<bool>,
+    /// 命中疑似密钥时的处置策略: "skip" | "redact"（默认）| "block"
+    pub secret_scan_policy: Option<String>,
+    /// 自定义密钥正则，有捕获组时只处理第 1 组
+    pub secret_scan_custom_patterns: Option<Vec<String>>,
 }
 
 /// 索引状态枚举
@@ -69,6 +81,8 @@ pub enum ProjectScopeRiskLevel {
     CriticalPath,
     /// 候选文件数量或体积超过有界预检阈值。
     ExcessiveScale,
+    /// 首次上传前检测到疑似密钥。
+    SecretsDetected,
 }
 
 /// 项目索引范围风险诊断，供后端持久化并由 GUI 明确展示原因。
@@ -83,6 +97,9 @@ pub struct ProjectScopeRisk {
     pub project_markers: Vec<String>,
     pub requires_secondary_confirmation: bool,
     pub detected_at: DateTime<Utc>,
+    /// 疑似密钥所在文件（仅 SecretsDetected），不含密钥原文
+    #[serde(default)]
+    pub secret_findings: Vec<super::secret_scan::SecretFileReport>,
 }
 
 /// 项目索引状态信息
@@ -176,6 +193,9 @@ impl Default for ProjectIndexStatus {
 pub struct ProjectsIndexStatus {
     /// 项目路径 -> 索引状态
     pub projects: HashMap<String, ProjectIndexStatus>,
+    /// 全局调度队列快照，仅在查询时填充，不写入状态文件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<super::scheduler::IndexQueueStatus>,
 }
 
 /// 单个文件的索引状态
@@ -9,6 +9,9 @@
 //    `first_event_at` 维护"最大等待时间"门限，防止用户持续小写入永远触发不了。
 // 4. 嵌套项目场景：当变更不属于任何子项目但仍在父项目根之内（且未被排除），
 //    把父项目本身加入待索引列表——之前直接吞掉导致父项目长期不更新。
+// 5. Git 事件：`.git/HEAD`、`ORIG_HEAD`、refs 变化说明发生了切换分支 / rebase 等操作，
+//    flush 时用 `git diff --name-status` 计算新旧 HEAD 之间的精确变更集，
+//    索引只处理这些路径，不再整树重扫（见 git_events 模块）。
 
 use anyhow::Result;
 use globset::{Glob, GlobSet, GlobSetBuilder};
@@ -19,6 +22,7 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use super::git_events::{self, GitChangeSet};
 use super::mcp::{ensure_project_scope_allowed, should_skip_auto_index_for_auth_failure};
 use super::scope_guard::effective_exclude_patterns;
 use super::types::AcemcpConfig;
@@ -51,6 +55,15 @@ pub(crate) fn normalize_project_path(path: &str) -> String {
     p.replace('\\', "/")
 }
 
+/// 监听回调推送给防抖任务的信号
+#[derive(Debug)]
+enum WatchSignal {
+    /// 通过路径过滤的工作区文件变更
+    Path(PathBuf),
+    /// git HEAD / refs 变化，携带仓库根目录（规范化路径）
+    GitRefs(String),
+}
+
 /// 嵌套项目监听信息
 #[derive(Debug, Clone)]
 struct NestedWatchInfo {
@@ -340,7 +353,7 @@ impl WatcherManager {
         let filter = PathFilter::new(&normalized_root, &exclude_patterns);
 
         // 事件通道：closure → debounce task
-        let (tx, rx) = mpsc::unbounded_channel::<PathBuf>();
+        let (tx, rx) = mpsc::unbounded_channel::<WatchSignal>();
 
         // 创建 notify 原生监听器（不再用 notify_debouncer_full）
         // closure 内做路径过滤，过滤掉的事件不会进入下游 → 不会重置 debounce 计时器
@@ -352,11 +365,16 @@ impl WatcherManager {
                         return;
                     }
                     for p in &event.paths {
+                        // `.git` 目录整体被路径过滤排除，需在过滤前识别 refs 变化
+                        if let Some(repo_root) = git_events::git_metadata_repo_root(p) {
+                            let _ = tx.send(WatchSignal::GitRefs(repo_root));
+                            continue;
+                        }
                         if filter_for_handler.should_ignore(p) {
                             continue;
                         }
                         // unbounded：失败说明接收端已关闭，监听器即将销毁
-                        let _ = tx.send(p.clone());
+                        let _ = tx.send(WatchSignal::Path(p.clone()));
                     }
                 }
                 Err(e) => {
@@ -571,7 +589,7 @@ fn is_meaningful_event_kind(kind: &EventKind) -> bool {
 /// - 静默期 quiet_dur：从最后一次事件起静默达到该时长后 flush
 /// - 最大等待 max_wait_dur：自第一次事件起累积超过该时长强制 flush
 async fn debounce_and_index_loop(
-    mut rx: mpsc::UnboundedReceiver<PathBuf>,
+    mut rx: mpsc::UnboundedReceiver<WatchSignal>,
     mut cancel_rx: tokio::sync::oneshot::Receiver<()>,
     parent_root: String,
     config_fallback: AcemcpConfig,
@@ -584,6 +602,16 @@ async fn debounce_and_index_loop(
     let mut seen: HashSet<String> = HashSet::new();
     let mut first_event_at: Option<Instant> = None;
     let mut last_event_at: Option<Instant> = None;
+    // 发生 refs 变化、待计算变更集的仓库，以及各仓库上次索引时的 HEAD
+    let mut git_dirty_repos: HashSet<String> = HashSet::new();
+    let mut git_heads: HashMap<String, String> = HashMap::new();
+    for repo_root in std::iter::once(parent_root.as_str())
+        .chain(nested_infos.iter().map(|info| info.absolute_path.as_str()))
+    {
+        if let Some(head) = git_events::resolve_head(repo_root).await {
+            git_heads.insert(repo_root.to_string(), head);
+        }
+    }
 
     loop {
         // 计算下一次轮询要等多久：
@@ -608,12 +636,19 @@ async fn debounce_and_index_loop(
                 break;
             }
 
-            maybe_path = rx.recv() => {
-                match maybe_path {
-                    Some(p) => {
-                        let key = normalize_project_path(&p.to_string_lossy());
-                        if seen.insert(key) {
-                            buffer.push(p);
+            maybe_signal = rx.recv() => {
+                match maybe_signal {
+                    Some(signal) => {
+                        match signal {
+                            WatchSignal::Path(p) => {
+                                let key = normalize_project_path(&p.to_string_lossy());
+                                if seen.insert(key) {
+                                    buffer.push(p);
+                                }
+                            }
+                            WatchSignal::GitRefs(repo_root) => {
+                                git_dirty_repos.insert(repo_root);
+                            }
                         }
                         let now = Instant::now();
                         if first_event_at.is_none() {
@@ -637,28 +672,91 @@ async fn debounce_and_index_loop(
                     .map(|t| now.saturating_duration_since(t) >= max_wait_dur)
                     .unwrap_or(false);
 
-                if (quiet_ok || max_ok) && !buffer.is_empty() {
+                if (quiet_ok || max_ok) && (!buffer.is_empty() || !git_dirty_repos.is_empty()) {
                     let paths: Vec<PathBuf> = std::mem::take(&mut buffer);
+                    let dirty_repos = std::mem::take(&mut git_dirty_repos);
                     seen.clear();
                     first_event_at = None;
                     last_event_at = None;
 
+                    let git_changes =
+                        collect_git_changes(&parent_root, &nested_infos, dirty_repos, &mut git_heads)
+                            .await;
                     log_important!(
                         info,
-                        "触发自动索引更新: parent_root={}, paths={}, reason={}, sample={:?}",
+                        "触发自动索引更新: parent_root={}, paths={}, git_repos={}, reason={}, sample={:?}",
                         parent_root,
                         paths.len(),
+                        git_changes.len(),
                         if max_ok { "max_wait" } else { "quiet" },
                         summarize_changed_paths(&parent_root, &paths, 8)
                     );
 
-                    flush_index(&parent_root, &paths, &nested_infos, &config_fallback).await;
+                    flush_index(&parent_root, &paths, &nested_infos, &config_fallback, &git_changes)
+                        .await;
                 }
             }
         }
     }
 }
 
+/// 计算发生 refs 变化的仓库自上次索引以来的变更集
+/// - 返回值的 key 为项目根目录；value 为 None 表示无法计算 diff，需要整树扫描
+/// - 只处理父项目与已识别的嵌套子项目，其余仓库的工作区变更仍按文件事件路由
+async fn collect_git_changes(
+    parent_root: &str,
+    nested_infos: &[NestedWatchInfo],
+    dirty_repos: HashSet<String>,
+    git_heads: &mut HashMap<String, String>,
+) -> HashMap<String, Option<GitChangeSet>> {
+    let mut changes = HashMap::new();
+    for repo_root in dirty_repos {
+        let is_project = repo_root == parent_root
+            || nested_infos
+                .iter()
+                .any(|info| info.absolute_path == repo_root);
+        if !is_project {
+            continue;
+        }
+        let Some(new_head) = git_events::resolve_head(&repo_root).await else {
+            continue;
+        };
+        let previous = git_heads.insert(repo_root.clone(), new_head.clone());
+        let repo_changes = match previous {
+            Some(old_head) if old_head == new_head => continue,
+            Some(old_head) => {
+                match git_events::diff_commits(&repo_root, &old_head, &new_head).await {
+                    // 树未变化（如 commit --amend 只改说明）时无需索引
+                    Ok(set) if set.is_empty() => continue,
+                    Ok(set) => {
+                        log_important!(
+                            info,
+                            "检测到 Git 操作: repo={}, {}..{}, changed={}",
+                            repo_root,
+                            &old_head[..old_head.len().min(8)],
+                            &new_head[..new_head.len().min(8)],
+                            set.len()
+                        );
+                        Some(set)
+                    }
+                    Err(e) => {
+                        log_debug!(
+                            "计算 git 变更集失败，将整树扫描: repo={}, error={}",
+                            repo_root,
+                            e
+                        );
+                        None
+                    }
+                }
+            }
+            // 之前没有提交（空仓库）时无法 diff
+            None => None,
+        };
+        changes.insert(repo_root, repo_changes);
+    }
+    changes
+}
+
 /// 可选 sleep：当 timeout 为 None 时永久挂起，由 select 的其他分支唤醒
 async fn sleep_optional(timeout: Option<Duration>) {
     match timeout {
@@ -673,9 +771,18 @@ async fn flush_index(
     changed_paths: &[PathBuf],
     nested_infos: &[NestedWatchInfo],
     config_fallback: &AcemcpConfig,
+    git_changes: &HashMap<String, Option<GitChangeSet>>,
 ) {
-    let projects_to_index =
-        WatcherManager::determine_affected_projects(parent_root, changed_paths, nested_infos);
+    let mut projects_to_index = if changed_paths.is_empty() {
+        Vec::new()
+    } else {
+        WatcherManager::determine_affected_projects(parent_root, changed_paths, nested_infos)
+    };
+    for project in git_changes.keys() {
+        if !projects_to_index.contains(project) {
+            projects_to_index.push(project.clone());
+        }
+    }
 
     if projects_to_index.is_empty() {
         log_debug!("无需索引的项目: parent_root={}", parent_root);
@@ -708,7 +815,23 @@ async fn flush_index(
             continue;
         }
 
-        match super::mcp::enqueue_incremental_index(&latest_config, &project_path).await {
+        // git 变更集与同一窗口内落在该项目的文件事件合并，索引只处理这些路径
+        let project_changes = git_changes
+            .get(&project_path)
+            .cloned()
+            .flatten()
+            .map(|mut set| {
+                set.upserted.extend(project_relative_paths(
+                    parent_root,
+                    &project_path,
+                    changed_paths,
+                    nested_infos,
+                ));
+                set
+            });
+        match super::mcp::enqueue_incremental_index(&latest_config, &project_path, project_changes)
+            .await
+        {
             Ok(()) => {
                 log_important!(
                     info,
@@ -730,6 +853,33 @@ async fn flush_index(
     }
 }
 
+/// 路由到指定项目的变更路径（相对项目根）
+fn project_relative_paths(
+    parent_root: &str,
+    project_root: &str,
+    changed_paths: &[PathBuf],
+    nested_infos: &[NestedWatchInfo],
+) -> Vec<String> {
+    let prefix = format!("{}/", project_root.trim_end_matches('/'));
+    changed_paths
+        .iter()
+        .filter(|path| {
+            WatcherManager::determine_affected_projects(
+                parent_root,
+                std::slice::from_ref(*path),
+                nested_infos,
+            )
+            .iter()
+            .any(|project| project == project_root)
+        })
+        .filter_map(|path| {
+            normalize_project_path(&path.to_string_lossy())
+                .strip_prefix(&prefix)
+                .map(str::to_string)
+        })
+        .collect()
+}
+
 fn summarize_changed_paths(
     project_root: &str,
     changed_paths: &[PathBuf],
@@ -9,6 +9,10 @@ use std::time::Duration;
 
 use super::types::{Context7Config, Context7Request, SearchResponse, SearchResult};
 use crate::log_debug;
+use crate::network::resilience::{self, RetryPolicy};
+
+/// 免费模式限流较严，Retry-After 超过 15s 时直接返回错误
+const CONTEXT7_RETRY_POLICY: RetryPolicy = RetryPolicy::new(3, 500, 15_000);
 use crate::log_important;
 
 /// Context7 工具实现
@@ -140,8 +144,10 @@ impl Context7Tool {
             req_builder = req_builder.query(&[("page", page.to_string())]);
         }
 
-        // 发送请求
-        let response = req_builder.send().await?;
+        // 发送请求（429/5xx 按 Retry-After 或退避重试，连续失败后熔断）
+        let endpoint = resilience::endpoint_key(resilience::SERVICE_CONTEXT7, &url);
+        let response =
+            resilience::send_with_retry(&endpoint, CONTEXT7_RETRY_POLICY, req_builder).await?;
         let status = response.status();
 
         log_debug!("Context7 响应状态: {}", status);
@@ -272,7 +278,9 @@ impl Context7Tool {
             req_builder = req_builder.header(AUTHORIZATION, format!("Bearer {}", api_key));
         }
 
-        let response = req_builder.send().await?;
+        let endpoint = resilience::endpoint_key(resilience::SERVICE_CONTEXT7, &url);
+        let response =
+            resilience::send_with_retry(&endpoint, CONTEXT7_RETRY_POLICY, req_builder).await?;
         let status = response.status();
 
         if !status.is_success() {
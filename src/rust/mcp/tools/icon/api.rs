@@ -12,6 +12,7 @@ use super::types::{
     IconItem, IconSearchRequest, IconSearchResult, IconfontApiResponse, IconfontIcon,
 };
 use crate::log_debug;
+use crate::network::resilience::{self, RetryPolicy, UpstreamError};
 
 // ============ 常量定义 ============
 
@@ -24,7 +25,7 @@ const DEFAULT_CACHE_EXPIRY_SECS: u64 = 30 * 60;
 /// HTTP 请求超时时间
 const REQUEST_TIMEOUT_SECS: u64 = 30;
 
-/// 最大重试次数
+/// 最大尝试次数
 const MAX_RETRIES: usize = 3;
 
 // ============ 缓存结构 ============
@@ -239,28 +240,15 @@ pub async fn search_icons(request: IconSearchRequest) -> Result<IconSearchResult
     Ok(search_result)
 }
 
-/// 带重试的搜索请求
+/// 带重试的搜索请求：网络错误、429/5xx 走共享的退避与熔断策略，业务错误不重试
 async fn retry_search_request(params: &HashMap<&str, String>) -> Result<IconfontApiResponse> {
     let client = create_http_client()?;
-    let mut last_error = None;
-
-    for attempt in 0..MAX_RETRIES {
-        if attempt > 0 {
-            // 指数退避
-            let delay = Duration::from_millis(100 * (2_u64.pow(attempt as u32)));
-            tokio::time::sleep(delay).await;
-            log_debug!("图标搜索重试第 {} 次", attempt + 1);
-        }
-
-        match execute_search_request(&client, params).await {
-            Ok(response) => return Ok(response),
-            Err(e) => {
-                last_error = Some(e);
-            }
-        }
-    }
-
-    Err(last_error.unwrap_or_else(|| anyhow!("搜索请求失败，已达到最大重试次数")))
+    let endpoint = resilience::endpoint_key(resilience::SERVICE_ICONFONT, ICONFONT_SEARCH_API);
+    let policy = RetryPolicy::new(MAX_RETRIES, 200, 5_000);
+    resilience::call(&endpoint, policy, || {
+        execute_search_request(&client, params)
+    })
+    .await
 }
 
 /// 执行单次搜索请求
@@ -274,17 +262,26 @@ async fn execute_search_request(
         .send()
         .await
         .map_err(|e| {
-            if e.is_timeout() {
-                anyhow!("请求超时")
+            let message = if e.is_timeout() {
+                "请求超时".to_string()
             } else if e.is_connect() {
-                anyhow!("网络连接失败")
+                "网络连接失败".to_string()
             } else {
-                anyhow!("请求失败: {}", e)
+                format!("请求失败: {}", e)
+            };
+            UpstreamError {
+                message,
+                ..UpstreamError::from_reqwest(&e)
             }
         })?;
 
     if !response.status().is_success() {
-        return Err(anyhow!("API 返回错误状态码: {}", response.status()));
+        let failure = UpstreamError::from_status(response.status(), response.headers(), "");
+        return Err(UpstreamError {
+            message: format!("API 返回错误状态码: {}", response.status()),
+            ..failure
+        }
+        .into());
     }
 
     let api_response: IconfontApiResponse = response
@@ -1,6 +1,6 @@
 //! 记忆历史清理模块
 //!
-//! 提供只预览、不改写正文的本地相似度清理能力。
+//! 提供只预览、不改写正文的本地相似度清理能力，以及对过期、长期未使用记忆的归档提议。
 
 use std::collections::{HashMap, HashSet};
 
@@ -8,6 +8,7 @@ use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use super::contradiction::contradicts;
 use super::similarity::TextSimilarity;
 use super::types::{MemoryCategory, MemoryEntry};
 
@@ -15,6 +16,9 @@ fn default_cleanup_threshold() -> f64 {
     0.55
 }
 
+/// 超过该天数未被回忆（从未回忆时按创建时间计）的记忆提议归档
+pub const DEFAULT_UNUSED_DAYS: u32 = 90;
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CleanupPreviewRequest {
     /// 本地相似度阈值，达到后进入候选清理组
@@ -26,6 +30,9 @@ pub struct CleanupPreviewRequest {
     /// 是否允许跨分类合并；默认关闭以降低误删风险
     #[serde(default)]
     pub include_cross_category: bool,
+    /// 未使用天数阈值，默认 90；为 0 时不提议归档未使用的记忆（过期记忆仍会提议）
+    #[serde(default)]
+    pub unused_days: Option<u32>,
 }
 
 impl Default for CleanupPreviewRequest {
@@ -34,6 +41,7 @@ impl Default for CleanupPreviewRequest {
             threshold: default_cleanup_threshold(),
             categories: Vec::new(),
             include_cross_category: false,
+            unused_days: None,
         }
     }
 }
@@ -69,12 +77,27 @@ pub struct CleanupGroup {
     pub entries: Vec<CleanupGroupEntry>,
 }
 
+/// 归档候选：过期（expired）或长期未被回忆（unused）
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveCandidate {
+    pub entry: CleanupGroupEntry,
+    pub reason: String,
+    pub source: String,
+    pub confidence: f64,
+    pub recall_count: u64,
+    pub last_recalled_at: Option<String>,
+    pub expires_at: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct CleanupPreviewResult {
     pub original_count: usize,
     pub candidate_group_count: usize,
     pub estimated_removed_count: usize,
     pub groups: Vec<CleanupGroup>,
+    /// 建议归档的记忆，按置信度从低到高排列；不与 `groups` 中默认删除的条目重复
+    #[serde(default)]
+    pub archive_candidates: Vec<ArchiveCandidate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -90,6 +113,9 @@ pub struct CleanupApplyRequest {
     pub auto_backup: bool,
     #[serde(default)]
     pub groups: Vec<CleanupApplyGroup>,
+    /// 需要归档的记忆 id（来自预览中的 `archive_candidates`）
+    #[serde(default)]
+    pub archive_ids: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
@@ -98,6 +124,8 @@ pub struct CleanupApplyResult {
     pub removed_count: usize,
     pub remaining_count: usize,
     pub removed_ids: Vec<String>,
+    #[serde(default)]
+    pub archived_ids: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -149,16 +177,85 @@ impl DisjointSet {
     }
 }
 
-/// 生成历史清理预览，只返回候选组，不修改任何记忆。
+/// 生成历史清理预览，只返回候选组与归档候选，不修改任何记忆。
 pub fn preview_cleanup(
     entries: &[MemoryEntry],
     request: &CleanupPreviewRequest,
+) -> CleanupPreviewResult {
+    let mut result = preview_duplicate_groups(entries, request);
+    let delete_ids = result
+        .groups
+        .iter()
+        .flat_map(|group| group.default_delete_ids.iter().map(String::as_str))
+        .collect::<HashSet<_>>();
+    result.archive_candidates = archive_candidates(entries, request, Utc::now())
+        .into_iter()
+        .filter(|candidate| !delete_ids.contains(candidate.entry.id.as_str()))
+        .collect();
+    result
+}
+
+/// 归档候选：未归档、未固定的记忆中已过期或超过 `unused_days` 未被回忆的条目
+fn archive_candidates(
+    entries: &[MemoryEntry],
+    request: &CleanupPreviewRequest,
+    now: DateTime<Utc>,
+) -> Vec<ArchiveCandidate> {
+    let category_filter = build_category_filter(&request.categories);
+    let unused_days = request.unused_days.unwrap_or(DEFAULT_UNUSED_DAYS);
+    let unused_before = now - chrono::Duration::days(i64::from(unused_days));
+
+    let mut candidates = entries
+        .iter()
+        .filter(|entry| !entry.lifecycle.archived && !entry.pinned)
+        .filter(|entry| {
+            category_filter
+                .as_ref()
+                .map(|set| set.contains(&entry.category))
+                .unwrap_or(true)
+        })
+        .filter_map(|entry| {
+            let lifecycle = &entry.lifecycle;
+            let last_used = lifecycle.last_recalled_at.unwrap_or(entry.created_at);
+            let reason = if lifecycle.is_expired(now) {
+                "expired"
+            } else if unused_days > 0 && last_used < unused_before {
+                "unused"
+            } else {
+                return None;
+            };
+            Some(ArchiveCandidate {
+                entry: CleanupGroupEntry::from(entry),
+                reason: reason.to_string(),
+                source: lifecycle.source.as_str().to_string(),
+                confidence: lifecycle.effective_confidence(),
+                recall_count: lifecycle.recall_count,
+                last_recalled_at: lifecycle.last_recalled_at.map(|at| at.to_rfc3339()),
+                expires_at: lifecycle.expires_at.map(|at| at.to_rfc3339()),
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| {
+        a.confidence
+            .partial_cmp(&b.confidence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.recall_count.cmp(&b.recall_count))
+            .then_with(|| a.entry.id.cmp(&b.entry.id))
+    });
+    candidates
+}
+
+/// 近义重复候选组
+fn preview_duplicate_groups(
+    entries: &[MemoryEntry],
+    request: &CleanupPreviewRequest,
 ) -> CleanupPreviewResult {
     let threshold = request.threshold.clamp(0.0, 1.0);
     let category_filter = build_category_filter(&request.categories);
     let candidates: Vec<CandidateEntry<'_>> = entries
         .iter()
         .enumerate()
+        .filter(|(_, entry)| !entry.lifecycle.archived)
         .filter(|(_, entry)| {
             category_filter
                 .as_ref()
@@ -190,7 +287,9 @@ pub fn preview_cleanup(
                 &candidates[i].entry.content,
                 &candidates[j].entry.content,
             );
-            if similarity >= threshold {
+            if similarity >= threshold
+                && !contradicts(&candidates[i].entry.content, &candidates[j].entry.content)
+            {
                 dsu.union(i, j);
                 pair_scores.insert((i, j), similarity);
             }
@@ -255,6 +354,7 @@ pub fn preview_cleanup(
         candidate_group_count: groups.len(),
         estimated_removed_count,
         groups,
+        ..CleanupPreviewResult::default()
     }
 }
 
@@ -334,6 +434,8 @@ mod tests {
             category,
             created_at: now,
             updated_at: now,
+            pinned: false,
+            lifecycle: Default::default(),
         }
     }
 
@@ -351,6 +453,7 @@ mod tests {
                 threshold: 0.55,
                 categories: vec!["规范".to_string()],
                 include_cross_category: false,
+                unused_days: None,
             },
         );
 
@@ -0,0 +1,424 @@
+//! 记忆矛盾检测
+//!
+//! 去重与同类 upsert 只看措辞是否相近，「使用 tab 缩进」与「使用 2 个空格缩进」会同时保留，
+//! 代理拿到互相冲突的指令。本模块按子句做启发式检测：
+//! - 极性相反：去掉否定词后主题相同，但一方否定（「运行 cargo fmt」与「不要运行 cargo fmt」）
+//! - 取值不同：同一枚举组（缩进、包管理器、引号等）取值互斥，或主题相同但数值不同
+//!
+//! 启发式只用于提示，最终由调用方选择取代（supersede）或并存（keep_both）。
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::recall::tokenize;
+use super::similarity::TextSimilarity;
+use super::types::MemoryEntry;
+
+/// 去掉否定词与数字后，子句主题相似度（词集合 Jaccard）的下限
+const TOPIC_THRESHOLD: f64 = 0.6;
+/// 枚举取值互斥时，去掉取值词后主题词的重合度（交集 / 较小集合）下限，
+/// 避免「提交信息使用中文」与「代码注释使用英文」这类不同主题被误判
+const VALUE_TOPIC_THRESHOLD: f64 = 0.5;
+
+/// 中文否定词，按长度从长到短匹配
+const CJK_NEGATIONS: &[&str] = &[
+    "不允许",
+    "不需要",
+    "不要",
+    "不得",
+    "不能",
+    "不用",
+    "不必",
+    "不再",
+    "无需",
+    "禁止",
+    "严禁",
+    "避免",
+    "别",
+    "勿",
+    "不",
+];
+/// 「不」开头但不表示否定的常见词
+const CJK_NEGATION_EXCEPTIONS: &[&str] = &[
+    "不同", "不错", "不过", "不仅", "不只", "不但", "不论", "不管", "不少",
+];
+const ASCII_NEGATIONS: &[&str] = &[
+    "do not", "dont", "never", "avoid", "not", "no", "disable", "without",
+];
+
+/// 互斥取值组：组内每个取值可有多个别名
+const VALUE_GROUPS: &[(&str, &[&[&str]])] = &[
+    (
+        "缩进",
+        &[&["tab", "tabs", "制表符"], &["space", "spaces", "空格"]],
+    ),
+    ("包管理器", &[&["npm"], &["pnpm"], &["yarn"], &["bun"]]),
+    (
+        "引号",
+        &[
+            &["single quote", "single quotes", "单引号"],
+            &["double quote", "double quotes", "双引号"],
+        ],
+    ),
+    ("语言", &[&["chinese", "中文"], &["english", "英文"]]),
+    (
+        "命名风格",
+        &[
+            &["camelcase", "小驼峰"],
+            &["pascalcase", "大驼峰"],
+            &["snake_case", "snakecase", "蛇形"],
+            &["kebabcase", "短横线"],
+        ],
+    ),
+    ("换行符", &[&["lf"], &["crlf"]]),
+];
+
+/// 与新内容矛盾的已有记忆
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContradictionConflict {
+    pub id: String,
+    pub content: String,
+    pub category: String,
+    /// polarity（极性相反）| value（取值不同）
+    pub kind: String,
+    pub detail: String,
+}
+
+/// 新增记忆与已有记忆矛盾时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 不写入，返回矛盾报告由调用方确认
+    Report,
+    /// 写入新记忆，归档矛盾的旧记忆并记录 `supersedes`
+    Supersede,
+    /// 两条都保留
+    KeepBoth,
+}
+
+impl ConflictPolicy {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value.map(|value| value.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("report") => Some(Self::Report),
+            Some("supersede") | Some("取代") => Some(Self::Supersede),
+            Some("keep_both") | Some("keep") | Some("并存") => Some(Self::KeepBoth),
+            _ => None,
+        }
+    }
+}
+
+/// 拆好的子句：原文、极性、主题词集合、枚举取值与数值
+struct Clause {
+    text: String,
+    negative: bool,
+    topic: HashSet<String>,
+    /// 去掉枚举取值词后的主题词
+    value_topic: HashSet<String>,
+    values: Vec<(usize, HashSet<usize>)>,
+    numbers: Vec<String>,
+}
+
+/// 检测新内容与未归档、未过期的已有记忆之间的矛盾
+pub fn detect_contradictions(
+    content: &str,
+    existing: &[MemoryEntry],
+    now: DateTime<Utc>,
+) -> Vec<ContradictionConflict> {
+    let new_clauses = clauses(content);
+    if new_clauses.is_empty() {
+        return Vec::new();
+    }
+    existing
+        .iter()
+        .filter(|entry| entry.is_active(now))
+        .filter_map(|entry| {
+            let (kind, detail) = compare_clauses(&new_clauses, &clauses(&entry.content))?;
+            Some(ContradictionConflict {
+                id: entry.id.clone(),
+                content: entry.content.clone(),
+                category: entry.category.display_name().to_string(),
+                kind: kind.to_string(),
+                detail,
+            })
+        })
+        .collect()
+}
+
+/// 两段内容是否矛盾；供去重与清理预览避免把矛盾的记忆当作重复合并
+pub fn contradicts(left: &str, right: &str) -> bool {
+    compare_clauses(&clauses(left), &clauses(right)).is_some()
+}
+
+fn compare_clauses(left: &[Clause], right: &[Clause]) -> Option<(&'static str, String)> {
+    for a in left {
+        for b in right {
+            if let Some(found) = compare_clause(a, b) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+fn compare_clause(a: &Clause, b: &Clause) -> Option<(&'static str, String)> {
+    let same_topic = jaccard(&a.topic, &b.topic) >= TOPIC_THRESHOLD;
+    if a.negative != b.negative {
+        // 一方否定、另一方肯定时取值不同反而一致（「不要用 npm」与「用 pnpm」）
+        return same_topic.then(|| {
+            (
+                "polarity",
+                format!("「{}」与「{}」肯定/否定相反", a.text, b.text),
+            )
+        });
+    }
+
+    for (group, a_values) in &a.values {
+        let Some((_, b_values)) = b.values.iter().find(|(other, _)| other == group) else {
+            continue;
+        };
+        if a_values.is_disjoint(b_values)
+            && overlap(&a.value_topic, &b.value_topic) >= VALUE_TOPIC_THRESHOLD
+        {
+            let (name, options) = VALUE_GROUPS[*group];
+            return Some((
+                "value",
+                format!(
+                    "{}取值不同：{} vs {}",
+                    name,
+                    describe_values(options, a_values),
+                    describe_values(options, b_values)
+                ),
+            ));
+        }
+    }
+
+    if same_topic && !a.numbers.is_empty() && !b.numbers.is_empty() && a.numbers != b.numbers {
+        return Some((
+            "value",
+            format!(
+                "「{}」与「{}」数值不同：{} vs {}",
+                a.text,
+                b.text,
+                a.numbers.join("/"),
+                b.numbers.join("/")
+            ),
+        ));
+    }
+    None
+}
+
+fn describe_values(options: &[&[&str]], values: &HashSet<usize>) -> String {
+    let mut indexes = values.iter().copied().collect::<Vec<_>>();
+    indexes.sort_unstable();
+    indexes
+        .into_iter()
+        .map(|index| options[index][0])
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 按标点拆成子句；小数点不作为分隔
+fn clauses(content: &str) -> Vec<Clause> {
+    let lower = content.to_lowercase();
+    let chars = lower.chars().collect::<Vec<_>>();
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    for (index, ch) in chars.iter().enumerate() {
+        let is_decimal_point = *ch == '.'
+            && index > 0
+            && chars[index - 1].is_ascii_digit()
+            && chars
+                .get(index + 1)
+                .is_some_and(|next| next.is_ascii_digit());
+        if !is_decimal_point
+            && matches!(
+                ch,
+                '，' | ',' | '；' | ';' | '。' | '.' | '！' | '!' | '？' | '?' | '\n'
+            )
+        {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(*ch);
+        }
+    }
+    parts.push(current);
+
+    parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .map(|part| analyze_clause(&part))
+        .collect()
+}
+
+fn analyze_clause(text: &str) -> Clause {
+    let normalized = TextSimilarity::normalize(text);
+    let mut stripped = normalized.clone();
+    let mut negative = false;
+
+    for marker in ASCII_NEGATIONS {
+        while let Some(position) = find_word(&stripped, marker) {
+            stripped.replace_range(position..position + marker.len(), " ");
+            negative = true;
+        }
+    }
+    let mut cjk_stripped = String::with_capacity(stripped.len());
+    let mut rest = stripped.as_str();
+    'outer: while !rest.is_empty() {
+        if !CJK_NEGATION_EXCEPTIONS
+            .iter()
+            .any(|word| rest.starts_with(word))
+        {
+            for marker in CJK_NEGATIONS {
+                if let Some(after) = rest.strip_prefix(marker) {
+                    negative = true;
+                    cjk_stripped.push(' ');
+                    rest = after;
+                    continue 'outer;
+                }
+            }
+        }
+        let ch = rest.chars().next().expect("rest 非空");
+        cjk_stripped.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    let numbers = extract_numbers(&cjk_stripped);
+    let topic_text = cjk_stripped
+        .chars()
+        .map(|ch| if ch.is_ascii_digit() { ' ' } else { ch })
+        .collect::<String>();
+
+    Clause {
+        text: text.to_string(),
+        negative,
+        topic: tokenize(&topic_text).into_iter().collect(),
+        value_topic: tokenize(&strip_value_aliases(&topic_text))
+            .into_iter()
+            .collect(),
+        values: extract_values(text),
+        numbers,
+    }
+}
+
+fn strip_value_aliases(text: &str) -> String {
+    let mut stripped = text.to_string();
+    for alias in VALUE_GROUPS
+        .iter()
+        .flat_map(|(_, options)| options.iter())
+        .flat_map(|aliases| aliases.iter())
+    {
+        if alias.is_ascii() {
+            while let Some(position) = find_word(&stripped, alias) {
+                stripped.replace_range(position..position + alias.len(), " ");
+            }
+        } else {
+            stripped = stripped.replace(alias, " ");
+        }
+    }
+    stripped
+}
+
+fn extract_values(text: &str) -> Vec<(usize, HashSet<usize>)> {
+    VALUE_GROUPS
+        .iter()
+        .enumerate()
+        .filter_map(|(group, (_, options))| {
+            let matched = options
+                .iter()
+                .enumerate()
+                .filter(|(_, aliases)| {
+                    aliases.iter().any(|alias| {
+                        if alias.is_ascii() {
+                            find_word(text, alias).is_some()
+                        } else {
+                            text.contains(alias)
+                        }
+                    })
+                })
+                .map(|(index, _)| index)
+                .collect::<HashSet<_>>();
+            (!matched.is_empty()).then_some((group, matched))
+        })
+        .collect()
+}
+
+fn extract_numbers(text: &str) -> Vec<String> {
+    let mut numbers = Vec::new();
+    let mut current = String::new();
+    for ch in text.chars() {
+        if ch.is_ascii_digit() || (ch == '.' && !current.is_empty()) {
+            current.push(ch);
+        } else if !current.is_empty() {
+            numbers.push(current.trim_end_matches('.').to_string());
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        numbers.push(current.trim_end_matches('.').to_string());
+    }
+    numbers.sort();
+    numbers
+}
+
+/// 查找 ASCII 单词（前后不是字母或数字）
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    text.match_indices(word)
+        .map(|(index, _)| index)
+        .find(|index| {
+            let before = text[..*index].chars().next_back();
+            let after = text[index + word.len()..].chars().next();
+            !before.is_some_and(|ch| ch.is_ascii_alphanumeric())
+                && !after.is_some_and(|ch| ch.is_ascii_alphanumeric())
+        })
+}
+
+fn jaccard(left: &HashSet<String>, right: &HashSet<String>) -> f64 {
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+    let intersection = left.intersection(right).count() as f64;
+    let union = left.union(right).count() as f64;
+    intersection / union
+}
+
+fn overlap(left: &HashSet<String>, right: &HashSet<String>) -> f64 {
+    let smaller = left.len().min(right.len());
+    if smaller == 0 {
+        // 只剩取值词（如「tabs」与「2 spaces」）时视为同一主题
+        return if left.len() == right.len() { 1.0 } else { 0.0 };
+    }
+    left.intersection(right).count() as f64 / smaller as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_opposite_polarity_and_different_values() {
+        assert!(contradicts(
+            "提交前运行 cargo fmt",
+            "提交前不要运行 cargo fmt"
+        ));
+        assert!(contradicts(
+            "Use tabs for indentation",
+            "Use 2 spaces for indentation"
+        ));
+        assert!(contradicts("缩进使用 2 个空格", "缩进使用 4 个空格"));
+        assert!(contradicts("安装依赖使用 pnpm", "安装依赖使用 npm"));
+    }
+
+    #[test]
+    fn consistent_statements_are_not_flagged() {
+        assert!(!contradicts("使用 pnpm，不要使用 npm", "使用 pnpm"));
+        assert!(!contradicts("不要使用 npm", "使用 pnpm"));
+        assert!(!contradicts(
+            "回复使用中文",
+            "数据库迁移必须使用 sqlx migrate"
+        ));
+        assert!(!contradicts("不同模块分开提交", "提交信息使用中文"));
+        assert!(!contradicts("提交信息使用中文", "代码注释使用英文"));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 提供记忆条目的去重检测和批量去重功能
 
+use super::contradiction::contradicts;
 use super::similarity::TextSimilarity;
 use super::types::MemoryEntry;
 
@@ -75,6 +76,10 @@ impl MemoryDeduplicator {
         for entry in existing {
             // 使用增强版算法，包含子串检测
             let similarity = TextSimilarity::calculate_enhanced(new_content, &entry.content);
+            // 措辞相近但互相矛盾（如缩进 2 / 4 个空格）的不算重复
+            if similarity >= self.threshold && contradicts(new_content, &entry.content) {
+                continue;
+            }
             if similarity > max_similarity {
                 max_similarity = similarity;
                 if similarity >= self.threshold {
@@ -109,10 +114,12 @@ impl MemoryDeduplicator {
         for entry in entries {
             let mut is_dup = false;
 
-            for kept in &result {
+            for kept in result.iter_mut() {
                 // 使用增强版算法，包含子串检测
                 let similarity = TextSimilarity::calculate_enhanced(&entry.content, &kept.content);
-                if similarity >= self.threshold {
+                if similarity >= self.threshold && !contradicts(&entry.content, &kept.content) {
+                    // 被移除的重复项若已固定，由保留项继承固定状态
+                    kept.pinned |= entry.pinned;
                     is_dup = true;
                     break;
                 }
@@ -144,7 +151,7 @@ impl MemoryDeduplicator {
     pub fn is_duplicate(&self, new_content: &str, existing: &[MemoryEntry]) -> bool {
         for entry in existing {
             let similarity = TextSimilarity::calculate_enhanced(new_content, &entry.content);
-            if similarity >= self.threshold {
+            if similarity >= self.threshold && !contradicts(new_content, &entry.content) {
                 return true;
             }
         }
@@ -166,6 +173,8 @@ mod tests {
             category: MemoryCategory::Rule,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            pinned: false,
+            lifecycle: Default::default(),
         }
     }
 
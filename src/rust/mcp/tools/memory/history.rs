@@ -0,0 +1,315 @@
+//! 记忆变更日志
+//!
+//! 每个记忆库在 `memories.db` 的 `memory_history` 表中记录每次新增、更新、删除，包括整理、去重、
+//! 拉取共享和撤销，每条保存变更前后的完整条目以及操作者和原因。变更记录与条目在同一事务中写入，
+//! 保存失败时两者都不落盘。旧版的 `history.jsonl` 在打开记忆库时一次性导入。单条记忆的版本号按其变更记录的顺序从 1 开始计数，
+//! 版本 0 表示第一次变更之前的状态。
+//!
+//! 回忆产生的使用统计（`last_recalled_at`、`recall_count`）和仅 `updated_at` 的变化不记录。
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::types::MemoryEntry;
+use crate::log_debug;
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Add,
+    Update,
+    Delete,
+}
+
+/// 变更日志中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryChange {
+    pub change_id: String,
+    pub at: DateTime<Utc>,
+    pub memory_id: String,
+    pub kind: ChangeKind,
+    /// 触发变更的操作：upsert | pin | lifecycle | archive | supersede | delete | cleanup |
+    /// deduplicate | startup-dedup | shared-pull | restore | undo
+    pub operation: String,
+    /// agent（ji）| user（桌面端）| system（启动去重、sou 自动写入等）
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<MemoryEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<MemoryEntry>,
+}
+
+/// 记录变更时的操作者与原因
+#[derive(Debug, Clone)]
+pub struct ChangeContext {
+    pub actor: String,
+    pub reason: Option<String>,
+}
+
+impl Default for ChangeContext {
+    fn default() -> Self {
+        Self {
+            actor: "system".to_string(),
+            reason: None,
+        }
+    }
+}
+
+/// 单条记忆的一个版本
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryVersion {
+    pub version: usize,
+    pub change_id: String,
+    pub at: String,
+    pub kind: ChangeKind,
+    pub operation: String,
+    pub actor: String,
+    pub reason: Option<String>,
+    /// 该版本的内容；删除后为空
+    pub content: Option<String>,
+}
+
+/// 两个版本之间变化的字段
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionDiff {
+    pub memory_id: String,
+    pub from_version: usize,
+    pub to_version: usize,
+    pub changes: Vec<FieldDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UndoResult {
+    pub memory_id: String,
+    /// 恢复到的版本号
+    pub restored_version: usize,
+    /// removed（撤销新增）| restored（恢复已删除的记忆）| reverted（回退内容）
+    pub action: String,
+    pub content: Option<String>,
+}
+
+/// 对比两次保存之间的条目，生成变更记录
+pub fn collect_changes(
+    previous: &HashMap<String, MemoryEntry>,
+    current: &[MemoryEntry],
+    operation: &str,
+    context: &ChangeContext,
+    now: DateTime<Utc>,
+) -> Vec<MemoryChange> {
+    let change =
+        |memory_id: &str, kind, before: Option<&MemoryEntry>, after: Option<&MemoryEntry>| {
+            MemoryChange {
+                change_id: uuid::Uuid::new_v4().to_string(),
+                at: now,
+                memory_id: memory_id.to_string(),
+                kind,
+                operation: operation.to_string(),
+                actor: context.actor.clone(),
+                reason: context.reason.clone(),
+                before: before.cloned(),
+                after: after.cloned(),
+            }
+        };
+
+    let mut changes = Vec::new();
+    for entry in current {
+        match previous.get(&entry.id) {
+            None => changes.push(change(&entry.id, ChangeKind::Add, None, Some(entry))),
+            Some(old) if tracked_state(old) != tracked_state(entry) => changes.push(change(
+                &entry.id,
+                ChangeKind::Update,
+                Some(old),
+                Some(entry),
+            )),
+            Some(_) => {}
+        }
+    }
+    let remaining = current
+        .iter()
+        .map(|entry| entry.id.as_str())
+        .collect::<std::collections::HashSet<_>>();
+    let mut deleted = previous
+        .values()
+        .filter(|entry| !remaining.contains(entry.id.as_str()))
+        .collect::<Vec<_>>();
+    deleted.sort_by(|a, b| a.id.cmp(&b.id));
+    for entry in deleted {
+        changes.push(change(&entry.id, ChangeKind::Delete, Some(entry), None));
+    }
+    changes
+}
+
+/// 参与版本比较的字段：去掉使用统计与 `updated_at`
+fn tracked_state(entry: &MemoryEntry) -> serde_json::Value {
+    let mut entry = entry.clone();
+    entry.updated_at = entry.created_at;
+    entry.lifecycle.last_recalled_at = None;
+    entry.lifecycle.recall_count = 0;
+    serde_json::to_value(&entry).unwrap_or_default()
+}
+
+/// 两个状态是否相同（忽略使用统计与 `updated_at`）
+pub fn same_state(left: Option<&MemoryEntry>, right: Option<&MemoryEntry>) -> bool {
+    match (left, right) {
+        (Some(left), Some(right)) => tracked_state(left) == tracked_state(right),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// 读取旧版 `history.jsonl` 中的全部变更，供导入 SQLite 使用；无法解析的行跳过
+pub fn read_change_file(path: &Path) -> Result<Vec<MemoryChange>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("读取记忆变更日志失败: {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<MemoryChange>(line) {
+            Ok(change) => Some(change),
+            Err(error) => {
+                log_debug!("跳过无法解析的记忆变更记录: {}", error);
+                None
+            }
+        })
+        .collect())
+}
+
+pub fn versions(changes: &[MemoryChange]) -> Vec<MemoryVersion> {
+    changes
+        .iter()
+        .enumerate()
+        .map(|(index, change)| MemoryVersion {
+            version: index + 1,
+            change_id: change.change_id.clone(),
+            at: change.at.to_rfc3339(),
+            kind: change.kind,
+            operation: change.operation.clone(),
+            actor: change.actor.clone(),
+            reason: change.reason.clone(),
+            content: change.after.as_ref().map(|entry| entry.content.clone()),
+        })
+        .collect()
+}
+
+/// 指定版本的条目状态；版本 0 为第一次变更之前
+pub fn state_at(changes: &[MemoryChange], version: usize) -> Result<Option<&MemoryEntry>> {
+    if version > changes.len() {
+        return Err(anyhow::anyhow!(
+            "版本 {} 不存在，当前最新版本为 {}",
+            version,
+            changes.len()
+        ));
+    }
+    Ok(match version {
+        0 => changes.first().and_then(|change| change.before.as_ref()),
+        _ => changes[version - 1].after.as_ref(),
+    })
+}
+
+/// 逐字段对比两个状态；不存在的一侧以空值表示
+pub fn diff_states(before: Option<&MemoryEntry>, after: Option<&MemoryEntry>) -> Vec<FieldDiff> {
+    let fields = |entry: Option<&MemoryEntry>| -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("content", entry.map(|entry| entry.content.clone())),
+            (
+                "category",
+                entry.map(|entry| entry.category.display_name().to_string()),
+            ),
+            ("pinned", entry.map(|entry| entry.pinned.to_string())),
+            (
+                "source",
+                entry.map(|entry| entry.lifecycle.source.as_str().to_string()),
+            ),
+            (
+                "confidence",
+                entry.and_then(|entry| entry.lifecycle.confidence.map(|value| value.to_string())),
+            ),
+            (
+                "expires_at",
+                entry.and_then(|entry| entry.lifecycle.expires_at.map(|at| at.to_rfc3339())),
+            ),
+            (
+                "archived",
+                entry.map(|entry| entry.lifecycle.archived.to_string()),
+            ),
+            (
+                "supersedes",
+                entry
+                    .map(|entry| entry.lifecycle.supersedes.join(", "))
+                    .filter(|value| !value.is_empty()),
+            ),
+        ]
+    };
+    fields(before)
+        .into_iter()
+        .zip(fields(after))
+        .filter(|((_, left), (_, right))| left != right)
+        .map(|((field, before), (_, after))| FieldDiff {
+            field: field.to_string(),
+            before,
+            after,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::types::test_entry;
+
+    #[test]
+    fn collect_changes_ignores_usage_stats() {
+        let old = test_entry("a", "使用 pnpm");
+        let previous = HashMap::from([("a".to_string(), old.clone())]);
+        let mut recalled = old.clone();
+        recalled.lifecycle.recall_count = 3;
+        recalled.updated_at = Utc::now() + chrono::Duration::seconds(5);
+        let context = ChangeContext::default();
+
+        assert!(collect_changes(&previous, &[recalled], "upsert", &context, Utc::now()).is_empty());
+
+        let mut edited = old.clone();
+        edited.content = "使用 pnpm 安装依赖".to_string();
+        let added = test_entry("b", "回复使用中文");
+        let changes = collect_changes(&previous, &[edited, added], "upsert", &context, Utc::now());
+        let kinds = changes
+            .iter()
+            .map(|change| (change.memory_id.as_str(), change.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![("a", ChangeKind::Update), ("b", ChangeKind::Add)]
+        );
+
+        let changes = collect_changes(&previous, &[], "delete", &context, Utc::now());
+        assert_eq!(changes[0].kind, ChangeKind::Delete);
+        assert_eq!(
+            diff_states(changes[0].before.as_ref(), changes[0].after.as_ref())[0],
+            FieldDiff {
+                field: "content".to_string(),
+                before: Some("使用 pnpm".to_string()),
+                after: None,
+            }
+        );
+    }
+}
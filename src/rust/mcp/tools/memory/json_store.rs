@@ -0,0 +1,239 @@
+//! JSON 记忆存储（团队库）
+//!
+//! 团队库通常放在团队共用的 Git 仓库或网络共享目录中。SQLite 的 WAL 依赖共享内存，
+//! 在网络文件系统上不可靠，二进制的 `memories.db` 也无法在 Git 中合并，因此团队库继续使用
+//! `memories.json`，变更日志追加到同目录的 `history.jsonl`。
+//!
+//! 写入时重新读取文件，只合并与上次加载相比发生变化的条目与元数据，再通过临时文件整体替换；
+//! 要修改或删除的条目、要修改的元数据已被其他进程改动时返回错误且不写入任何内容，
+//! 与 `sqlite_store::save` 一致。
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tempfile::NamedTempFile;
+
+use super::history::{self, MemoryChange};
+use super::types::{MemoryEntry, MemoryStore, StoreSnapshot};
+
+pub const STORE_FILE: &str = "memories.json";
+
+pub fn store_path(memory_dir: &Path) -> PathBuf {
+    memory_dir.join(STORE_FILE)
+}
+
+fn history_path(memory_dir: &Path) -> PathBuf {
+    memory_dir.join(history::HISTORY_FILE)
+}
+
+/// 加载整个记忆库；文件不存在时返回 `None`
+pub fn load(memory_dir: &Path) -> Result<Option<MemoryStore>> {
+    let path = store_path(memory_dir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("读取记忆库失败: {}", path.display()))?;
+    let store = serde_json::from_str::<MemoryStore>(&content)
+        .with_context(|| format!("解析记忆库失败: {}", path.display()))?;
+    Ok(Some(store))
+}
+
+/// 写入自 `previous` 以来发生变化的条目与存储元数据，再追加变更记录 `changes`
+///
+/// 条目写入失败时不追加变更记录；变更记录追加失败时返回错误，记忆本身已保存。
+pub fn save(
+    memory_dir: &Path,
+    store: &MemoryStore,
+    previous: &StoreSnapshot,
+    changes: &[MemoryChange],
+) -> Result<()> {
+    let mut merged = load(memory_dir)?.unwrap_or_else(|| MemoryStore {
+        entries: Vec::new(),
+        ..store.clone()
+    });
+    merge_meta(&mut merged, store, previous)?;
+    let entries = &mut merged.entries;
+
+    for entry in &store.entries {
+        match previous.entries.get(&entry.id) {
+            Some(old) if serde_json::to_value(old)? == serde_json::to_value(entry)? => continue,
+            Some(old) => ensure_unchanged(entries, old)?,
+            None => {}
+        }
+        match entries.iter_mut().find(|current| current.id == entry.id) {
+            Some(current) => *current = entry.clone(),
+            None => entries.push(entry.clone()),
+        }
+    }
+
+    let remaining = store
+        .entries
+        .iter()
+        .map(|entry| entry.id.as_str())
+        .collect::<HashSet<_>>();
+    for old in previous.entries.values() {
+        if remaining.contains(old.id.as_str()) {
+            continue;
+        }
+        // 其他进程已删除的条目无需再删，删除结果一致
+        if !entries.iter().any(|entry| entry.id == old.id) {
+            continue;
+        }
+        ensure_unchanged(entries, old)?;
+        entries.retain(|entry| entry.id != old.id);
+    }
+
+    let path = store_path(memory_dir);
+    let mut file = NamedTempFile::new_in(memory_dir)
+        .with_context(|| format!("创建临时文件失败: {}", memory_dir.display()))?;
+    file.write_all(serde_json::to_string_pretty(&merged)?.as_bytes())?;
+    file.persist(&path)
+        .with_context(|| format!("写入记忆库失败: {}", path.display()))?;
+
+    append_changes(memory_dir, changes).context("记忆已保存，但写入变更日志失败")
+}
+
+/// 把自 `previous` 以来发生变化的元数据写入 `merged`；文件中的值须与上次加载时相同
+fn merge_meta(
+    merged: &mut MemoryStore,
+    store: &MemoryStore,
+    previous: &StoreSnapshot,
+) -> Result<()> {
+    let stored = merged.meta_values()?;
+    for ((key, value), (_, current)) in store.meta_values()?.into_iter().zip(stored) {
+        let expected = previous.meta.get(key);
+        if expected == Some(&value) {
+            continue;
+        }
+        if expected.is_some_and(|expected| *expected != current) {
+            return Err(anyhow::anyhow!(
+                "记忆库元数据 {} 已被其他进程修改，请重新执行本次操作",
+                key
+            ));
+        }
+        match key {
+            "version" => merged.version = store.version.clone(),
+            "project_path" => merged.project_path = store.project_path.clone(),
+            "last_dedup_at" => merged.last_dedup_at = store.last_dedup_at,
+            "config" => merged.config = store.config.clone(),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// 文件中的条目须与上次加载时相同（忽略使用统计与 `updated_at`）；已被其他进程删除也视为冲突，
+/// 否则修改会让已删除的记忆重新出现
+fn ensure_unchanged(entries: &[MemoryEntry], expected: &MemoryEntry) -> Result<()> {
+    let stored = entries.iter().find(|entry| entry.id == expected.id);
+    if !history::same_state(stored, Some(expected)) {
+        return Err(anyhow::anyhow!(
+            "记忆 {} 已被其他进程修改，请重新执行本次操作",
+            expected.id
+        ));
+    }
+    Ok(())
+}
+
+fn append_changes(memory_dir: &Path, changes: &[MemoryChange]) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut buffer = String::new();
+    for change in changes {
+        buffer.push_str(&serde_json::to_string(change)?);
+        buffer.push('\n');
+    }
+    let path = history_path(memory_dir);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("打开记忆变更日志失败: {}", path.display()))?;
+    file.write_all(buffer.as_bytes())
+        .with_context(|| format!("写入记忆变更日志失败: {}", path.display()))?;
+    Ok(())
+}
+
+/// 某条记忆的变更记录，按写入顺序
+pub fn read_changes(memory_dir: &Path, memory_id: &str) -> Result<Vec<MemoryChange>> {
+    let path = history_path(memory_dir);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(history::read_change_file(&path)?
+        .into_iter()
+        .filter(|change| change.memory_id == memory_id)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::types::test_entry;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn concurrent_writers_merge_and_reject_stale_edits() {
+        let dir = TempDir::new().unwrap();
+        let store = MemoryStore {
+            entries: vec![test_entry("a", "使用 pnpm 安装依赖")],
+            ..Default::default()
+        };
+        save(dir.path(), &store, &StoreSnapshot::default(), &[]).unwrap();
+
+        let base = StoreSnapshot::of(&store).unwrap();
+        let mut first = load(dir.path()).unwrap().unwrap();
+        let mut second = load(dir.path()).unwrap().unwrap();
+        first.entries.push(test_entry("b", "回复使用中文"));
+        second
+            .entries
+            .push(test_entry("c", "数据库迁移必须使用 sqlx migrate"));
+        save(dir.path(), &first, &base, &[]).unwrap();
+        save(dir.path(), &second, &base, &[]).unwrap();
+
+        let ids = load(dir.path())
+            .unwrap()
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert!(!dir.path().join("memories.db").exists());
+
+        let mut edited = first.clone();
+        edited.entries[0].content = "使用 npm 安装依赖".to_string();
+        let context = history::ChangeContext::default();
+        let changes = history::collect_changes(
+            &StoreSnapshot::of(&first).unwrap().entries,
+            &edited.entries,
+            "upsert",
+            &context,
+            Utc::now(),
+        );
+        save(
+            dir.path(),
+            &edited,
+            &StoreSnapshot::of(&first).unwrap(),
+            &changes,
+        )
+        .unwrap();
+        assert_eq!(read_changes(dir.path(), "a").unwrap().len(), 1);
+
+        let mut stale = second.clone();
+        stale.entries[0].pinned = true;
+        assert!(save(
+            dir.path(),
+            &stale,
+            &StoreSnapshot::of(&second).unwrap(),
+            &[]
+        )
+        .is_err());
+    }
+}
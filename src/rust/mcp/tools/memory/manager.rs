@@ -3,11 +3,11 @@
 //! 核心记忆管理功能，包括：
 //! - 记忆的添加、查询
 //! - 启动时自动迁移和去重
-//! - JSON 格式存储
+//! - SQLite 存储（见 `sqlite_store`），备份导出为 JSON
 
 use anyhow::{Context, Result};
 use chrono::Utc;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -15,10 +15,26 @@ use super::cleanup::{
     parse_backup_timestamp, preview_cleanup, BackupInfo, CleanupApplyRequest, CleanupApplyResult,
     CleanupPreviewRequest, CleanupPreviewResult, RestoreBackupResult,
 };
+use super::contradiction::{detect_contradictions, ConflictPolicy, ContradictionConflict};
 use super::dedup::MemoryDeduplicator;
+use super::history::{self, ChangeContext, MemoryChange, MemoryVersion, UndoResult, VersionDiff};
+use super::json_store;
 use super::migration::MemoryMigrator;
+use super::recall::{recall_with_hits, RecallRequest, RecallResult, RecalledMemory};
+use super::rule_files::{
+    self, ImportedRule, RuleExportResult, RuleFileExport, RuleFileFormat, RuleFileImport,
+    RuleImportResult, SkippedRule,
+};
+use super::scopes::{self, MemoryScope};
+use super::shared::{
+    self, SharedExportResult, SharedPullPreview, SharedPullRequest, SharedPullResult,
+};
 use super::similarity::TextSimilarity;
-use super::types::{MemoryCategory, MemoryConfig, MemoryEntry, MemoryStore};
+use super::sqlite_store;
+use super::types::{
+    LifecycleUpdate, MemoryCategory, MemoryConfig, MemoryEntry, MemoryLifecycle, MemorySource,
+    MemoryStore, StoreSnapshot,
+};
 use crate::log_debug;
 
 /// 记忆管理器
@@ -29,12 +45,29 @@ pub struct MemoryManager {
     store: MemoryStore,
     /// 是否为非 Git 项目（降级模式）
     is_non_git_project: bool,
+    /// 上次加载或保存后的条目与元数据，用于生成下一次的变更记录并只写入变化的部分
+    committed: StoreSnapshot,
+    /// 变更日志中记录的操作者与原因
+    change_context: ChangeContext,
+    /// 存储格式
+    backend: StoreBackend,
+}
+
+/// 记忆库的存储格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreBackend {
+    /// `memories.db`（见 `sqlite_store`），用于本机上的全局、项目、子目录记忆库
+    Sqlite,
+    /// `memories.json`（见 `json_store`），用于团队库：共享仓库或网络共享目录上 WAL 不可靠
+    Json,
 }
 
 /// 路径规范化结果
 struct NormalizeResult {
     /// 规范化后的路径
     path: PathBuf,
+    /// 规范化后的请求目录（未回退到 Git 根目录）
+    requested: PathBuf,
     /// 是否为非 Git 项目
     is_non_git: bool,
 }
@@ -55,27 +88,91 @@ pub enum AddOutcome {
         similarity: f64,
         matched_content: Option<String>,
     },
+    /// 与已有记忆矛盾，未写入；需调用方选择取代或并存后重试
+    Conflict(Vec<ContradictionConflict>),
+}
+
+/// `upsert_memory_with_policy` 的结果
+#[derive(Debug, Clone)]
+pub struct UpsertReport {
+    pub outcome: AddOutcome,
+    /// 被新记忆取代并归档的矛盾记忆 id
+    pub superseded_ids: Vec<String>,
+    /// 选择并存时保留的矛盾记忆
+    pub kept_conflicts: Vec<ContradictionConflict>,
 }
 
 impl MemoryManager {
-    /// 存储文件名
-    const STORE_FILE: &'static str = "memories.json";
     /// 运行时自动备份目录名
     const BACKUP_DIR: &'static str = "back";
     /// 最多保留的自动备份数量
     const MAX_BACKUPS: usize = 10;
 
-    /// 创建新的记忆管理器
+    /// 创建新的记忆管理器（项目作用域）
     ///
     /// 自动执行：
     /// 1. 路径规范化和验证（支持非 Git 项目降级）
     /// 2. 旧格式迁移（如果需要）
     /// 3. 启动时去重（如果配置启用）
     pub fn new(project_path: &str) -> Result<Self> {
-        // 规范化项目路径（支持非 Git 项目降级）
-        let normalize_result = Self::normalize_project_path(project_path)?;
-        let memory_dir = normalize_result.path.join(".sanshu-memory");
+        Self::for_scope(project_path, &MemoryScope::Project)
+    }
 
+    /// 打开指定作用域的记忆库
+    ///
+    /// - 全局、团队作用域不依赖 `project_path`
+    /// - 子目录作用域使用 `project_path` 本身（须位于项目根目录之下）
+    pub fn for_scope(project_path: &str, scope: &MemoryScope) -> Result<Self> {
+        let backend = match scope {
+            MemoryScope::Team(_) => StoreBackend::Json,
+            _ => StoreBackend::Sqlite,
+        };
+        let (store_root, memory_dir, is_non_git) = match scope {
+            MemoryScope::Global => {
+                let memory_dir = scopes::global_memory_dir()?;
+                (memory_dir.clone(), memory_dir, false)
+            }
+            MemoryScope::Team(name) => {
+                let store = scopes::resolve_team_store(name.as_deref())?;
+                let memory_dir = PathBuf::from(store.path.trim());
+                (memory_dir.clone(), memory_dir, false)
+            }
+            MemoryScope::Project => {
+                // 规范化项目路径（支持非 Git 项目降级）
+                let normalize_result = Self::normalize_project_path(project_path)?;
+                let memory_dir = normalize_result.path.join(".sanshu-memory");
+                (
+                    normalize_result.path,
+                    memory_dir,
+                    normalize_result.is_non_git,
+                )
+            }
+            MemoryScope::Directory => {
+                let normalize_result = Self::normalize_project_path(project_path)?;
+                if normalize_result.requested == normalize_result.path {
+                    return Err(anyhow::anyhow!(
+                        "子目录作用域需要 project_path 指向项目根目录下的子目录，当前即为项目根目录: {}",
+                        Self::clean_display_path(&normalize_result.path)
+                    ));
+                }
+                let memory_dir = normalize_result.requested.join(".sanshu-memory");
+                (
+                    normalize_result.requested,
+                    memory_dir,
+                    normalize_result.is_non_git,
+                )
+            }
+        };
+        Self::open(store_root, memory_dir, is_non_git, backend)
+    }
+
+    /// 打开 `memory_dir` 处的记忆库；`store_root` 为记忆库所属的目录，用于显示
+    fn open(
+        store_root: PathBuf,
+        memory_dir: PathBuf,
+        is_non_git: bool,
+        backend: StoreBackend,
+    ) -> Result<Self> {
         // 创建记忆目录
         fs::create_dir_all(&memory_dir).map_err(|e| {
             anyhow::anyhow!(
@@ -85,7 +182,7 @@ impl MemoryManager {
             )
         })?;
 
-        let project_path_str = Self::clean_display_path(&normalize_result.path);
+        let project_path_str = Self::clean_display_path(&store_root);
 
         // 检查是否需要迁移
         if MemoryMigrator::needs_migration(&memory_dir) {
@@ -105,24 +202,44 @@ impl MemoryManager {
             }
         }
 
-        // 加载或创建存储
-        let store_path = memory_dir.join(Self::STORE_FILE);
-        let mut store = if store_path.exists() {
-            let content = fs::read_to_string(&store_path)?;
-            serde_json::from_str(&content).unwrap_or_else(|e| {
-                log_debug!("解析存储文件失败，使用默认值: {}", e);
-                MemoryStore {
-                    project_path: project_path_str.clone(),
-                    ..Default::default()
+        let loaded = match backend {
+            StoreBackend::Sqlite => {
+                // v2.0 的 memories.json 一次性迁移到 SQLite；失败时不继续，避免新建空库后旧数据无法再迁移
+                if MemoryMigrator::needs_store_migration(&memory_dir) {
+                    if let Some(result) = MemoryMigrator::migrate_to_sqlite(&memory_dir)? {
+                        log_debug!(
+                            "已迁移到 SQLite: {} 条记忆，原文件备份为 {}",
+                            result.entries_count,
+                            result.backup_file
+                        );
+                    }
                 }
-            })
-        } else {
-            MemoryStore {
-                project_path: project_path_str.clone(),
-                ..Default::default()
+
+                // 旧版 history.jsonl 一次性导入变更日志表
+                let imported = sqlite_store::import_history_file(&memory_dir)
+                    .context("导入旧版记忆变更日志失败")?;
+                if imported > 0 {
+                    log_debug!("已导入 {} 条记忆变更记录", imported);
+                }
+                sqlite_store::load(&memory_dir)?
             }
+            StoreBackend::Json => json_store::load(&memory_dir)?,
         };
 
+        // 加载或创建存储；新建的存储没有已写入的元数据，首次保存时全部写入
+        let created = loaded.is_none();
+        let mut store = loaded.unwrap_or_else(|| MemoryStore {
+            project_path: project_path_str.clone(),
+            ..Default::default()
+        });
+
+        let committed = if created {
+            StoreSnapshot::default()
+        } else {
+            StoreSnapshot::of(&store)?
+        };
+        let mut removed = 0;
+
         // 如果配置启用了启动时去重，执行去重
         if store.config.dedup_on_startup && !store.entries.is_empty() {
             let dedup = MemoryDeduplicator::new(store.config.similarity_threshold);
@@ -137,17 +254,24 @@ impl MemoryManager {
                 );
                 store.last_dedup_at = Utc::now();
             }
+            removed = stats.removed_count;
             store.entries = deduped;
         }
 
-        let manager = Self {
+        let mut manager = Self {
             memory_dir,
             store,
-            is_non_git_project: normalize_result.is_non_git,
+            is_non_git_project: is_non_git,
+            committed,
+            change_context: ChangeContext::default(),
+            backend,
         };
 
-        // 保存存储
-        manager.save_store()?;
+        // 新建存储或启动去重移除了条目时保存，移除的条目记入变更日志；
+        // 没有变化时不写入，避免每次打开都占用写锁
+        if created || removed > 0 {
+            manager.commit("startup-dedup")?;
+        }
 
         Ok(manager)
     }
@@ -197,10 +321,12 @@ impl MemoryManager {
             category,
             created_at: now,
             updated_at: now,
+            pinned: false,
+            lifecycle: MemoryLifecycle::default(),
         };
 
         self.store.entries.push(entry);
-        self.save_store()?;
+        self.commit("add")?;
 
         log_debug!("已添加记忆: {} ({:?})", id, category);
         Ok(Some(id))
@@ -229,21 +355,131 @@ impl MemoryManager {
     /// 3. 否则 → 新增。
     ///
     /// 相比 [`add_memory`]，本方法从源头抑制「同一条规则的不同表述」堆积。
+    /// 与已有记忆矛盾时不写入，返回 [`AddOutcome::Conflict`]。
     pub fn upsert_memory(&mut self, content: &str, category: MemoryCategory) -> Result<AddOutcome> {
+        Ok(self
+            .upsert_memory_with_policy(content, category, ConflictPolicy::Report)?
+            .outcome)
+    }
+
+    /// 带矛盾处理策略的 upsert
+    ///
+    /// 先检测新内容与未归档记忆是否矛盾（见 [`super::contradiction`]）：
+    /// - `Report`：存在矛盾时不写入，返回矛盾报告
+    /// - `Supersede`：写入新记忆，归档矛盾的旧记忆，并在新记忆的 `supersedes` 中记录
+    /// - `KeepBoth`：两条都保留
+    ///
+    /// 矛盾的记忆不参与后续的重复判断与同类更新，避免新规则被当作旧规则的改写。
+    pub fn upsert_memory_with_policy(
+        &mut self,
+        content: &str,
+        category: MemoryCategory,
+        policy: ConflictPolicy,
+    ) -> Result<UpsertReport> {
         let content = content.trim();
         if content.is_empty() {
             return Err(anyhow::anyhow!("记忆内容不能为空"));
         }
 
+        let conflicts =
+            detect_contradictions(content, &self.similarity_candidates(content), Utc::now());
+        if !conflicts.is_empty() && policy == ConflictPolicy::Report {
+            log_debug!("记忆矛盾: 与 {} 条已有记忆矛盾，等待确认", conflicts.len());
+            return Ok(UpsertReport {
+                outcome: AddOutcome::Conflict(conflicts),
+                superseded_ids: Vec::new(),
+                kept_conflicts: Vec::new(),
+            });
+        }
+        let outcome = self.upsert_excluding(content, category, &conflicts)?;
+        let mut report = UpsertReport {
+            outcome,
+            superseded_ids: Vec::new(),
+            kept_conflicts: Vec::new(),
+        };
+        match policy {
+            ConflictPolicy::Supersede => {
+                if let AddOutcome::Added(id) | AddOutcome::Updated { id, .. } = &report.outcome {
+                    report.superseded_ids = self.supersede(id, &conflicts)?;
+                }
+            }
+            _ => report.kept_conflicts = conflicts,
+        }
+        Ok(report)
+    }
+
+    /// 归档矛盾的旧记忆并记录到新记忆的 `supersedes`
+    fn supersede(
+        &mut self,
+        new_id: &str,
+        conflicts: &[ContradictionConflict],
+    ) -> Result<Vec<String>> {
+        let now = Utc::now();
+        let ids = conflicts
+            .iter()
+            .map(|conflict| conflict.id.clone())
+            .collect::<Vec<_>>();
+        for entry in self
+            .store
+            .entries
+            .iter_mut()
+            .filter(|entry| ids.contains(&entry.id))
+        {
+            entry.lifecycle.set_archived(true, now);
+        }
+        if let Some(entry) = self
+            .store
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == new_id)
+        {
+            for id in &ids {
+                if !entry.lifecycle.supersedes.contains(id) {
+                    entry.lifecycle.supersedes.push(id.clone());
+                }
+            }
+        }
+        if !ids.is_empty() {
+            self.commit("supersede")?;
+            log_debug!("记忆 {} 取代了矛盾记忆: {:?}", new_id, ids);
+        }
+        Ok(ids)
+    }
+
+    /// 去重、同类更新或新增；`conflicts` 中的记忆不参与比较
+    fn upsert_excluding(
+        &mut self,
+        content: &str,
+        category: MemoryCategory,
+        conflicts: &[ContradictionConflict],
+    ) -> Result<AddOutcome> {
+        let excluded = conflicts
+            .iter()
+            .map(|conflict| conflict.id.as_str())
+            .collect::<HashSet<_>>();
+
+        let similar = self.similarity_candidates(content);
+
         // 1) 全局去重检查（≥ 去重阈值视为重复，静默拒绝）
         if self.store.config.enable_dedup {
             let dedup = MemoryDeduplicator::new(self.store.config.similarity_threshold);
-            let dup_info = dedup.check_duplicate(content, &self.store.entries);
+            let candidates = similar
+                .iter()
+                .filter(|entry| !excluded.contains(entry.id.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            let dup_info = dedup.check_duplicate(content, &candidates);
             if dup_info.is_duplicate {
                 log_debug!(
                     "记忆去重: 相似度 {:.1}%，静默拒绝",
                     dup_info.similarity * 100.0
                 );
+                // 再次记录已归档的记忆，视为重新启用
+                if let Some(matched_id) = dup_info.matched_id.as_deref() {
+                    if self.set_archived(matched_id, false)? {
+                        log_debug!("重复记忆已取消归档: id={}", matched_id);
+                    }
+                }
                 return Ok(AddOutcome::Duplicate {
                     similarity: dup_info.similarity,
                     matched_content: dup_info.matched_content,
@@ -255,9 +491,16 @@ impl MemoryManager {
         //    [upsert_threshold, similarity_threshold) 区间的最相似条目
         let upsert_threshold = self.store.config.upsert_threshold;
         if upsert_threshold < self.store.config.similarity_threshold {
+            let similar_ids = similar
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .collect::<HashSet<_>>();
             let mut best: Option<(usize, f64)> = None;
             for (idx, entry) in self.store.entries.iter().enumerate() {
-                if entry.category != category {
+                if entry.category != category
+                    || excluded.contains(entry.id.as_str())
+                    || !similar_ids.contains(entry.id.as_str())
+                {
                     continue;
                 }
                 let sim = TextSimilarity::calculate_enhanced(content, &entry.content);
@@ -276,7 +519,8 @@ impl MemoryManager {
                 entry.content = content.to_string();
                 entry.content_normalized = TextSimilarity::normalize(content);
                 entry.updated_at = Utc::now();
-                self.save_store()?;
+                entry.lifecycle.set_archived(false, entry.updated_at);
+                self.commit("upsert")?;
                 log_debug!(
                     "记忆同类更新(upsert): id={}, 相似度 {:.1}%",
                     id,
@@ -300,13 +544,306 @@ impl MemoryManager {
             category,
             created_at: now,
             updated_at: now,
+            pinned: false,
+            lifecycle: MemoryLifecycle::default(),
         };
         self.store.entries.push(entry);
-        self.save_store()?;
+        self.commit("upsert")?;
         log_debug!("已新增记忆: {} ({:?})", id, category);
         Ok(AddOutcome::Added(id))
     }
 
+    /// 设置记忆的固定状态，返回是否找到该记忆
+    pub fn set_pinned(&mut self, memory_id: &str, pinned: bool) -> Result<bool> {
+        let Some(entry) = self
+            .store
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == memory_id)
+        else {
+            return Ok(false);
+        };
+        if entry.pinned != pinned {
+            entry.pinned = pinned;
+            entry.updated_at = Utc::now();
+            self.commit("pin")?;
+        }
+        Ok(true)
+    }
+
+    /// 应用调用方给出的来源、置信度与过期时间，返回是否找到该记忆
+    pub fn update_lifecycle(&mut self, memory_id: &str, update: &LifecycleUpdate) -> Result<bool> {
+        let Some(entry) = self
+            .store
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == memory_id)
+        else {
+            return Ok(false);
+        };
+        let before = entry.lifecycle.clone();
+        if let Some(source) = update.source {
+            entry.lifecycle.source = source;
+        }
+        if let Some(confidence) = update.confidence {
+            entry.lifecycle.confidence = Some(confidence.clamp(0.0, 1.0));
+        }
+        if let Some(expires_at) = update.expires_at {
+            entry.lifecycle.expires_at = Some(expires_at);
+        }
+        if entry.lifecycle != before {
+            self.commit("lifecycle")?;
+        }
+        Ok(true)
+    }
+
+    /// 设置记忆的归档状态，返回状态是否发生变化
+    pub fn set_archived(&mut self, memory_id: &str, archived: bool) -> Result<bool> {
+        let Some(entry) = self
+            .store
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == memory_id)
+        else {
+            return Ok(false);
+        };
+        if !entry.lifecycle.set_archived(archived, Utc::now()) {
+            return Ok(false);
+        }
+        self.commit("archive")?;
+        Ok(true)
+    }
+
+    pub fn contains(&self, memory_id: &str) -> bool {
+        self.store.entries.iter().any(|entry| entry.id == memory_id)
+    }
+
+    /// 记录回忆结果中返回的记忆，更新最近回忆时间与次数；不属于本记忆库的 id 忽略
+    pub fn mark_recalled(&mut self, memory_ids: &[String]) -> Result<usize> {
+        let ids = memory_ids
+            .iter()
+            .map(String::as_str)
+            .collect::<HashSet<_>>();
+        let now = Utc::now();
+        let mut updated = 0usize;
+        for entry in self
+            .store
+            .entries
+            .iter_mut()
+            .filter(|entry| ids.contains(entry.id.as_str()))
+        {
+            entry.lifecycle.last_recalled_at = Some(now);
+            entry.lifecycle.recall_count += 1;
+            updated += 1;
+        }
+        if updated > 0 {
+            // 使用统计不记入变更日志，但要同步到快照，被删除或撤销时才能保留
+            self.commit("recall")?;
+        }
+        Ok(updated)
+    }
+
+    /// 按当前任务检索记忆（回忆），仅限当前记忆库
+    ///
+    /// 有 query 时先用全文索引找出命中任一词项的记忆，只对这些记忆计算排序得分
+    pub fn recall(&self, request: &RecallRequest) -> RecallResult {
+        let hits = self.search_hits(std::slice::from_ref(&self.memory_dir), request);
+        let now = Utc::now();
+        let memories = self
+            .store
+            .entries
+            .iter()
+            .filter(|entry| entry.is_active(now))
+            .cloned()
+            .map(|entry| scopes::ScopedMemory::new(entry, scopes::SCOPE_PROJECT))
+            .collect::<Vec<_>>();
+        recall_with_hits(&memories, request, hits.as_ref())
+    }
+
+    /// 各层全文索引命中的记忆 id；无 query、某层没有索引或全部未命中时返回 `None`（全量排序）
+    fn search_hits(
+        &self,
+        memory_dirs: &[PathBuf],
+        request: &RecallRequest,
+    ) -> Option<HashSet<String>> {
+        let query = request.query.as_deref()?.trim();
+        if query.is_empty() {
+            return None;
+        }
+        let mut hits = HashSet::new();
+        for memory_dir in memory_dirs {
+            match sqlite_store::search(memory_dir, query) {
+                Ok(Some(layer_hits)) => hits.extend(layer_hits),
+                Ok(None) => return None,
+                Err(error) => {
+                    log_debug!("全文检索记忆失败，改为全量排序: {}", error);
+                    return None;
+                }
+            }
+        }
+        (!hits.is_empty()).then_some(hits)
+    }
+
+    /// 与 `content` 共享 n-gram 签名的记忆，用于重复、同类更新与矛盾判断；无法使用索引时返回全部
+    fn similarity_candidates(&self, content: &str) -> Vec<MemoryEntry> {
+        match sqlite_store::similarity_candidates(
+            &self.memory_dir,
+            content,
+            sqlite_store::MAX_SIMILARITY_CANDIDATES,
+        ) {
+            Ok(Some(ids)) => self
+                .store
+                .entries
+                .iter()
+                .filter(|entry| ids.contains(&entry.id))
+                .cloned()
+                .collect(),
+            Ok(None) => self.store.entries.clone(),
+            Err(error) => {
+                log_debug!("查询相似度候选失败，改为全量比较: {}", error);
+                self.store.entries.clone()
+            }
+        }
+    }
+
+    /// 跨作用域回忆：子目录 > 项目 > 团队 > 全局
+    ///
+    /// `self` 为项目作用域的管理器，`project_path` 用于定位子目录记忆层。
+    /// 其他作用域只读加载，不会因为回忆而创建目录或写入文件。
+    pub fn recall_layered(
+        &self,
+        project_path: &str,
+        request: &RecallRequest,
+    ) -> Result<RecallResult> {
+        let sources = self.layer_sources(project_path)?;
+        Ok(self.recall_from_layers(&sources, request))
+    }
+
+    /// 跨作用域回忆涉及的记忆层（作用域, 记忆目录），按优先级从高到低排列
+    fn layer_sources(&self, project_path: &str) -> Result<Vec<(String, PathBuf)>> {
+        let normalize_result = Self::normalize_project_path(project_path)?;
+        let mut sources =
+            scopes::directory_layers(&normalize_result.path, &normalize_result.requested);
+        sources.push((scopes::SCOPE_PROJECT.to_string(), self.memory_dir.clone()));
+        for store in scopes::team_stores() {
+            sources.push((
+                scopes::team_scope(&store.name),
+                PathBuf::from(store.path.trim()),
+            ));
+        }
+        if let Ok(global_dir) = scopes::global_memory_dir() {
+            sources.push((scopes::SCOPE_GLOBAL.to_string(), global_dir));
+        }
+        Ok(sources)
+    }
+
+    fn recall_from_layers(
+        &self,
+        sources: &[(String, PathBuf)],
+        request: &RecallRequest,
+    ) -> RecallResult {
+        let layers = sources
+            .iter()
+            .map(|(scope, memory_dir)| {
+                let entries = if scope == scopes::SCOPE_PROJECT {
+                    self.store.entries.clone()
+                } else {
+                    scopes::read_layer_entries(scope, memory_dir)
+                };
+                (scope.clone(), entries)
+            })
+            .collect::<Vec<_>>();
+        let memory_dirs = sources
+            .iter()
+            .map(|(_, memory_dir)| memory_dir.clone())
+            .collect::<Vec<_>>();
+
+        let merged = scopes::merge_layers(layers, self.store.config.upsert_threshold);
+        if merged.duplicates > 0 {
+            log_debug!("跨作用域合并: 折叠 {} 条重复记忆", merged.duplicates);
+        }
+        // 团队库为 JSON 存储，没有全文索引；有团队层时全量排序
+        let hits = if sources
+            .iter()
+            .any(|(scope, _)| scopes::is_team_scope(scope))
+        {
+            None
+        } else {
+            self.search_hits(&memory_dirs, request)
+        };
+        recall_with_hits(&merged.memories, request, hits.as_ref())
+    }
+
+    /// 记录跨作用域回忆返回的记忆：按来源作用域分组，使用统计写回各自的记忆库
+    ///
+    /// 返回更新的条数；某一层写入失败时继续处理其余层，最后返回第一个错误
+    pub fn mark_recalled_layered(
+        &mut self,
+        project_path: &str,
+        recalled: &[RecalledMemory],
+    ) -> Result<usize> {
+        let sources = self.layer_sources(project_path)?;
+        self.mark_recalled_in_layers(&sources, recalled)
+    }
+
+    fn mark_recalled_in_layers(
+        &mut self,
+        sources: &[(String, PathBuf)],
+        recalled: &[RecalledMemory],
+    ) -> Result<usize> {
+        let mut by_scope: HashMap<&str, Vec<String>> = HashMap::new();
+        for entry in recalled {
+            by_scope
+                .entry(entry.scope.as_str())
+                .or_default()
+                .push(entry.id.clone());
+        }
+
+        let mut updated = 0usize;
+        let mut first_error = None;
+        for (scope, memory_dir) in sources {
+            let Some(ids) = by_scope.get(scope.as_str()) else {
+                continue;
+            };
+            let result = if scope == scopes::SCOPE_PROJECT {
+                self.mark_recalled(ids)
+            } else {
+                // 子目录层的记忆库位于该子目录下；全局、团队层以记忆目录本身为根
+                let store_root = if scopes::is_directory_scope(scope) {
+                    memory_dir
+                        .parent()
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|| memory_dir.clone())
+                } else {
+                    memory_dir.clone()
+                };
+                let backend = if scopes::is_team_scope(scope) {
+                    StoreBackend::Json
+                } else {
+                    StoreBackend::Sqlite
+                };
+                Self::open(
+                    store_root,
+                    memory_dir.clone(),
+                    self.is_non_git_project,
+                    backend,
+                )
+                .and_then(|mut manager| manager.mark_recalled(ids))
+                .with_context(|| format!("{}记忆库", scopes::scope_display(scope)))
+            };
+            match result {
+                Ok(count) => updated += count,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(updated),
+        }
+    }
+
     /// 手动执行去重
     ///
     /// 返回移除的记忆数量
@@ -319,7 +856,7 @@ impl MemoryManager {
         }
         self.store.entries = deduped;
         self.store.last_dedup_at = Utc::now();
-        self.save_store()?;
+        self.commit("deduplicate")?;
 
         log_debug!("手动去重完成: 移除 {} 条重复记忆", stats.removed_count);
         Ok(stats.removed_count)
@@ -336,7 +873,7 @@ impl MemoryManager {
         }
         self.store.entries = deduped;
         self.store.last_dedup_at = Utc::now();
-        self.save_store()?;
+        self.commit("deduplicate")?;
 
         log_debug!("手动去重完成: 移除 {} 条重复记忆", stats.removed_count);
         Ok(stats)
@@ -355,7 +892,7 @@ impl MemoryManager {
             self.create_backup("delete")?;
             self.store.entries.remove(idx);
             log_debug!("已删除记忆: {}", memory_id);
-            self.save_store()?;
+            self.commit("delete")?;
             Ok(Some(deleted_content))
         } else {
             Ok(None) // 未找到该 ID
@@ -402,12 +939,26 @@ impl MemoryManager {
             }
         }
 
-        if remove_ids.is_empty() {
+        let mut archive_ids = HashSet::new();
+        for archive_id in &request.archive_ids {
+            if !existing_ids.contains(archive_id) {
+                return Err(anyhow::anyhow!(
+                    "清理计划已过期：归档项不存在 ({})",
+                    archive_id
+                ));
+            }
+            if !remove_ids.contains(archive_id) {
+                archive_ids.insert(archive_id.clone());
+            }
+        }
+
+        if remove_ids.is_empty() && archive_ids.is_empty() {
             return Ok(CleanupApplyResult {
                 backup_file: None,
                 removed_count: 0,
                 remaining_count: self.store.entries.len(),
                 removed_ids: Vec::new(),
+                archived_ids: Vec::new(),
             });
         }
 
@@ -426,27 +977,238 @@ impl MemoryManager {
                 true
             }
         });
-        self.store.last_dedup_at = Utc::now();
-        self.save_store()?;
+        let now = Utc::now();
+        let mut archived_ids = Vec::new();
+        for entry in self
+            .store
+            .entries
+            .iter_mut()
+            .filter(|entry| archive_ids.contains(&entry.id))
+        {
+            if entry.lifecycle.set_archived(true, now) {
+                archived_ids.push(entry.id.clone());
+            }
+        }
+        if !removed_ids.is_empty() {
+            self.store.last_dedup_at = now;
+        }
+        self.commit("cleanup")?;
 
         Ok(CleanupApplyResult {
             backup_file,
             removed_count: removed_ids.len(),
             remaining_count: self.store.entries.len(),
             removed_ids,
+            archived_ids,
         })
     }
 
-    /// 创建当前 memories.json 的运行时备份。
+    /// 仓库内共享记忆文件路径（`<项目根>/.sanshu/memory.jsonl`）
+    pub fn shared_file_path(&self) -> PathBuf {
+        shared::shared_file_path(self.memory_dir.parent().unwrap_or(&self.memory_dir))
+    }
+
+    /// 预览从共享文件拉取，不修改任何记忆
+    pub fn preview_shared_pull(&self) -> Result<SharedPullPreview> {
+        let path = self.shared_file_path();
+        let shared_file = shared::read_shared_file(&path)?;
+        Ok(shared::preview_pull(
+            &self.store.entries,
+            &shared_file,
+            &Self::clean_display_path(&path),
+        ))
+    }
+
+    /// 从共享文件拉取：新增条目直接导入，冲突按 `request.resolutions` 处理，未处理的保持本地不变
+    pub fn apply_shared_pull(&mut self, request: &SharedPullRequest) -> Result<SharedPullResult> {
+        let path = self.shared_file_path();
+        let shared_file = shared::read_shared_file(&path)?;
+        shared::ensure_parseable(&shared_file, &path)?;
+
+        let mut entries = self.store.entries.clone();
+        let mut result = SharedPullResult::default();
+        if shared::apply_pull(&mut entries, &shared_file, request, Utc::now(), &mut result)? {
+            result.backup_file = Some(self.create_backup("shared-pull")?.file_name);
+            self.store.entries = entries;
+            self.commit("shared-pull")?;
+        }
+        log_debug!(
+            "共享记忆拉取完成: 新增 {}，采用共享 {}，保留本地 {}，未处理冲突 {}",
+            result.added_ids.len(),
+            result.replaced_ids.len(),
+            result.kept_local_ids.len(),
+            result.unresolved_ids.len()
+        );
+        Ok(result)
+    }
+
+    /// 从代理规则文件导入记忆
+    ///
+    /// `paths` 为项目根目录下的相对路径，为空时导入所有已存在的已知规则文件。
+    /// 每条经过与「记忆」相同的重复、同类更新与矛盾检测；矛盾的条目不导入，由调用方处理。
+    pub fn import_rule_files(&mut self, paths: &[String]) -> Result<RuleImportResult> {
+        let project_root = self.project_root();
+        let files = if paths.is_empty() {
+            rule_files::discover_rule_files(&project_root)
+        } else {
+            paths
+                .iter()
+                .map(|path| rule_files::resolve_rule_path(&project_root, path))
+                .collect::<Result<Vec<_>>>()?
+        };
+        if files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "项目根目录下没有找到规则文件（AGENTS.md、CLAUDE.md、.cursorrules 等）"
+            ));
+        }
+
+        let mut parsed = Vec::new();
+        let mut result = RuleImportResult::default();
+        for path in &files {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("读取规则文件失败: {}", path.display()))?;
+            let display = path
+                .strip_prefix(&project_root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let rules = rule_files::parse_rule_file(&content);
+            result.files.push(RuleFileImport {
+                path: display.clone(),
+                format: RuleFileFormat::detect(path),
+                parsed_count: rules.len(),
+            });
+            parsed.extend(rules.into_iter().map(|rule| (display.clone(), rule)));
+        }
+        if parsed.is_empty() {
+            return Ok(result);
+        }
+
+        result.backup_file = Some(self.create_backup("rule-import")?.file_name);
+        let import_source = LifecycleUpdate {
+            source: Some(MemorySource::Import),
+            ..Default::default()
+        };
+        for (source_file, rule) in parsed {
+            let outcome = self
+                .upsert_memory_with_policy(&rule.content, rule.category, ConflictPolicy::Report)?
+                .outcome;
+            let imported = |id: String| ImportedRule {
+                id,
+                content: rule.content.clone(),
+                category: rule.category.display_name().to_string(),
+                source_file: source_file.clone(),
+            };
+            match outcome {
+                AddOutcome::Added(id) => {
+                    self.update_lifecycle(&id, &import_source)?;
+                    result.added.push(imported(id));
+                }
+                AddOutcome::Updated { id, .. } => result.updated.push(imported(id)),
+                AddOutcome::Duplicate {
+                    matched_content, ..
+                } => result.skipped.push(SkippedRule {
+                    content: rule.content.clone(),
+                    source_file,
+                    reason: "duplicate".to_string(),
+                    detail: matched_content,
+                }),
+                AddOutcome::Conflict(conflicts) => result.skipped.push(SkippedRule {
+                    content: rule.content.clone(),
+                    source_file,
+                    reason: "conflict".to_string(),
+                    detail: Some(
+                        conflicts
+                            .iter()
+                            .map(|conflict| format!("{}（ID: {}）", conflict.detail, conflict.id))
+                            .collect::<Vec<_>>()
+                            .join("；"),
+                    ),
+                }),
+            }
+        }
+        log_debug!(
+            "规则文件导入完成: 新增 {}，更新 {}，跳过 {}",
+            result.added.len(),
+            result.updated.len(),
+            result.skipped.len()
+        );
+        Ok(result)
+    }
+
+    /// 把记忆导出到代理规则文件中的导出区块
+    ///
+    /// `targets` 为格式名（agents、claude、cursor 等）或项目根目录下的相对路径；
+    /// 为空时只写入一个由三术管理的文件：项目使用 Cursor 规则目录且没有 AGENTS.md 时为
+    /// `.cursor/rules/sanshu-memory.mdc`，否则为 AGENTS.md。其他规则文件须显式指定。
+    pub fn export_rule_files(&self, targets: &[String]) -> Result<RuleExportResult> {
+        let project_root = self.project_root();
+        let targets = if targets.is_empty() {
+            vec![project_root.join(Self::default_rule_export_format(&project_root).default_path())]
+        } else {
+            targets
+                .iter()
+                .map(|target| match RuleFileFormat::parse(target) {
+                    Some(format) => Ok(project_root.join(format.default_path())),
+                    None => rule_files::resolve_rule_path(&project_root, target),
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let entries = rule_files::exportable_entries(&self.store.entries);
+        let mut result = RuleExportResult::default();
+        for path in targets {
+            let format = RuleFileFormat::detect(&path);
+            let status = rule_files::write_export(&path, &entries, format)?;
+            result.files.push(RuleFileExport {
+                path: path
+                    .strip_prefix(&project_root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                format,
+                entry_count: entries.len(),
+                status: status.to_string(),
+            });
+        }
+        Ok(result)
+    }
+
+    fn default_rule_export_format(project_root: &Path) -> RuleFileFormat {
+        let agents = project_root.join(RuleFileFormat::Agents.default_path());
+        if !agents.is_file() && project_root.join(".cursor").join("rules").is_dir() {
+            RuleFileFormat::CursorMdc
+        } else {
+            RuleFileFormat::Agents
+        }
+    }
+
+    fn project_root(&self) -> PathBuf {
+        self.memory_dir
+            .parent()
+            .unwrap_or(&self.memory_dir)
+            .to_path_buf()
+    }
+
+    /// 导出到共享文件：本地新增或较新的条目写入，共享文件中较新的冲突条目跳过
+    pub fn export_shared(&self) -> Result<SharedExportResult> {
+        let path = self.shared_file_path();
+        let shared_file = shared::read_shared_file(&path)?;
+        shared::ensure_parseable(&shared_file, &path)?;
+
+        let (lines, mut result) = shared::merge_for_export(&self.store.entries, &shared_file);
+        if !result.added_ids.is_empty() || !result.updated_ids.is_empty() || !path.exists() {
+            shared::write_shared_file(&path, &lines)?;
+        }
+        result.shared_file = Self::clean_display_path(&path);
+        Ok(result)
+    }
+
+    /// 把当前记忆库导出为 JSON 运行时备份。
     pub fn create_backup(&self, reason: &str) -> Result<BackupInfo> {
         let backup_dir = self.backup_dir();
         fs::create_dir_all(&backup_dir)?;
 
-        let source = self.store_path();
-        if !source.exists() {
-            self.save_store()?;
-        }
-
         let now = Utc::now();
         let file_name = format!(
             "{}-{:03}.memories.json",
@@ -454,13 +1216,9 @@ impl MemoryManager {
             now.timestamp_subsec_millis()
         );
         let backup_path = backup_dir.join(&file_name);
-        fs::copy(&source, &backup_path).with_context(|| {
-            format!(
-                "创建记忆备份失败: {} -> {}",
-                source.display(),
-                backup_path.display()
-            )
-        })?;
+        let json = serde_json::to_string_pretty(&self.store)?;
+        fs::write(&backup_path, json)
+            .with_context(|| format!("创建记忆备份失败: {}", backup_path.display()))?;
         log_debug!("已创建记忆备份({}): {}", reason, backup_path.display());
 
         self.prune_backups(Self::MAX_BACKUPS)?;
@@ -508,7 +1266,7 @@ impl MemoryManager {
 
         let safety_backup = self.create_backup("restore")?;
         self.store = restored_store;
-        self.save_store()?;
+        self.commit("restore")?;
 
         Ok(RestoreBackupResult {
             restored_file: file_name.to_string(),
@@ -517,10 +1275,6 @@ impl MemoryManager {
         })
     }
 
-    fn store_path(&self) -> PathBuf {
-        self.memory_dir.join(Self::STORE_FILE)
-    }
-
     fn backup_dir(&self) -> PathBuf {
         self.memory_dir.join(Self::BACKUP_DIR)
     }
@@ -650,11 +1404,148 @@ impl MemoryManager {
         self.save_store()
     }
 
-    /// 保存存储到文件
-    fn save_store(&self) -> Result<()> {
-        let store_path = self.memory_dir.join(Self::STORE_FILE);
-        let json = serde_json::to_string_pretty(&self.store)?;
-        fs::write(&store_path, json)?;
+    /// 设置后续变更记录的操作者（agent | user | system）与原因
+    pub fn set_change_context(&mut self, actor: &str, reason: Option<String>) {
+        self.change_context = ChangeContext {
+            actor: actor.to_string(),
+            reason: reason.filter(|reason| !reason.trim().is_empty()),
+        };
+    }
+
+    /// 某条记忆的全部版本，按时间顺序
+    pub fn history(&self, memory_id: &str) -> Result<Vec<MemoryVersion>> {
+        Ok(history::versions(&self.entry_changes(memory_id)?))
+    }
+
+    /// 对比两个版本；缺省时对比最新版本与上一版本
+    pub fn diff_versions(
+        &self,
+        memory_id: &str,
+        from_version: Option<usize>,
+        to_version: Option<usize>,
+    ) -> Result<VersionDiff> {
+        let changes = self.entry_changes(memory_id)?;
+        let to_version = to_version.unwrap_or(changes.len());
+        let from_version = from_version.unwrap_or(to_version.saturating_sub(1));
+        let before = history::state_at(&changes, from_version)?;
+        let after = history::state_at(&changes, to_version)?;
+        Ok(VersionDiff {
+            memory_id: memory_id.to_string(),
+            from_version,
+            to_version,
+            changes: history::diff_states(before, after),
+        })
+    }
+
+    /// 撤销单条记忆的变更：缺省撤销最近一次，指定 `to_version` 时恢复到该版本
+    ///
+    /// 只改动这一条记忆，不影响其他记忆；撤销本身也会记入变更日志，可以再次撤销。
+    /// 使用统计沿用当前值。
+    pub fn undo(&mut self, memory_id: &str, to_version: Option<usize>) -> Result<UndoResult> {
+        let changes = self.entry_changes(memory_id)?;
+        let restored_version = to_version.unwrap_or(changes.len().saturating_sub(1));
+        let target = history::state_at(&changes, restored_version)?.cloned();
+        let index = self
+            .store
+            .entries
+            .iter()
+            .position(|entry| entry.id == memory_id);
+        let current = index.map(|index| &self.store.entries[index]);
+        if history::same_state(current, target.as_ref()) {
+            return Err(anyhow::anyhow!(
+                "记忆 {} 当前已是版本 {} 的状态，无需撤销",
+                memory_id,
+                restored_version
+            ));
+        }
+
+        let action = match (index, target) {
+            (Some(index), None) => {
+                self.store.entries.remove(index);
+                "removed"
+            }
+            (None, Some(mut entry)) => {
+                // 已删除的记忆沿用删除前的使用统计
+                if let Some(deleted) = changes.last().and_then(|change| change.before.as_ref()) {
+                    entry.lifecycle.last_recalled_at = deleted.lifecycle.last_recalled_at;
+                    entry.lifecycle.recall_count = deleted.lifecycle.recall_count;
+                }
+                entry.updated_at = Utc::now();
+                self.store.entries.push(entry);
+                "restored"
+            }
+            (Some(index), Some(mut entry)) => {
+                let current = &self.store.entries[index];
+                entry.lifecycle.last_recalled_at = current.lifecycle.last_recalled_at;
+                entry.lifecycle.recall_count = current.lifecycle.recall_count;
+                entry.updated_at = Utc::now();
+                self.store.entries[index] = entry;
+                "reverted"
+            }
+            (None, None) => unreachable!("same_state 已排除两侧都不存在"),
+        };
+        let context = self.change_context.clone();
+        if context.reason.is_none() {
+            self.change_context.reason = Some(format!("撤销到版本 {}", restored_version));
+        }
+        let committed = self.commit("undo");
+        self.change_context = context;
+        committed?;
+        log_debug!("记忆 {} 已撤销到版本 {}", memory_id, restored_version);
+
+        Ok(UndoResult {
+            memory_id: memory_id.to_string(),
+            restored_version,
+            action: action.to_string(),
+            content: self
+                .store
+                .entries
+                .iter()
+                .find(|entry| entry.id == memory_id)
+                .map(|entry| entry.content.clone()),
+        })
+    }
+
+    fn entry_changes(&self, memory_id: &str) -> Result<Vec<MemoryChange>> {
+        let changes = match self.backend {
+            StoreBackend::Sqlite => sqlite_store::read_changes(&self.memory_dir, memory_id)?,
+            StoreBackend::Json => json_store::read_changes(&self.memory_dir, memory_id)?,
+        };
+        if changes.is_empty() {
+            return Err(anyhow::anyhow!("记忆 {} 没有变更记录", memory_id));
+        }
+        Ok(changes)
+    }
+
+    /// 保存存储，自上次提交以来的条目变化在同一事务中写入变更日志
+    fn commit(&mut self, operation: &str) -> Result<()> {
+        let changes = history::collect_changes(
+            &self.committed.entries,
+            &self.store.entries,
+            operation,
+            &self.change_context,
+            Utc::now(),
+        );
+        self.save_with_changes(&changes)
+    }
+
+    /// 把自上次保存以来变化的条目写入 SQLite 记忆库
+    ///
+    /// 其他进程在此期间新增的记忆不受影响；要修改的记忆已被其他进程改动时返回错误
+    fn save_store(&mut self) -> Result<()> {
+        self.save_with_changes(&[])
+    }
+
+    fn save_with_changes(&mut self, changes: &[MemoryChange]) -> Result<()> {
+        match self.backend {
+            StoreBackend::Sqlite => {
+                sqlite_store::save(&self.memory_dir, &self.store, &self.committed, changes)?
+            }
+            StoreBackend::Json => {
+                json_store::save(&self.memory_dir, &self.store, &self.committed, changes)?
+            }
+        }
+        self.committed = StoreSnapshot::of(&self.store)?;
         Ok(())
     }
 
@@ -725,6 +1616,7 @@ impl MemoryManager {
         if let Some(git_root) = Self::find_git_root(&canonical_path) {
             Ok(NormalizeResult {
                 path: git_root,
+                requested: canonical_path,
                 is_non_git: false,
             })
         } else {
@@ -734,7 +1626,8 @@ impl MemoryManager {
                 Self::clean_display_path(&canonical_path)
             );
             Ok(NormalizeResult {
-                path: canonical_path,
+                path: canonical_path.clone(),
+                requested: canonical_path,
                 is_non_git: true,
             })
         }
@@ -799,6 +1692,7 @@ pub struct MemoryStats {
 #[cfg(test)]
 mod tests {
     use super::super::cleanup::CleanupApplyGroup;
+    use super::super::history::ChangeKind;
     use super::*;
     use tempfile::TempDir;
 
@@ -824,6 +1718,8 @@ mod tests {
             category,
             created_at: now,
             updated_at: now,
+            pinned: false,
+            lifecycle: MemoryLifecycle::default(),
         }
     }
 
@@ -943,6 +1839,171 @@ mod tests {
         assert_eq!(m.get_all_memories().len(), 2);
     }
 
+    #[test]
+    fn test_upsert_reports_and_supersedes_contradiction() {
+        let (_dir, mut m) = make_manager();
+
+        let old_id = match m
+            .upsert_memory("缩进使用 2 个空格", MemoryCategory::Rule)
+            .unwrap()
+        {
+            AddOutcome::Added(id) => id,
+            other => panic!("首次应新增: {:?}", other),
+        };
+        let r = m
+            .upsert_memory("缩进使用 4 个空格", MemoryCategory::Rule)
+            .unwrap();
+        match r {
+            AddOutcome::Conflict(conflicts) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].id, old_id);
+                assert_eq!(conflicts[0].kind, "value");
+            }
+            other => panic!("矛盾内容应返回冲突报告: {:?}", other),
+        }
+        assert_eq!(m.get_all_memories().len(), 1, "冲突报告不应写入");
+
+        let report = m
+            .upsert_memory_with_policy(
+                "缩进使用 4 个空格",
+                MemoryCategory::Rule,
+                ConflictPolicy::Supersede,
+            )
+            .unwrap();
+        let AddOutcome::Added(new_id) = report.outcome else {
+            panic!("取代时应新增而非并入旧记忆: {:?}", report.outcome);
+        };
+        assert_eq!(report.superseded_ids, vec![old_id.clone()]);
+        let entries = m.get_all_memories();
+        let old = entries.iter().find(|entry| entry.id == old_id).unwrap();
+        let new = entries.iter().find(|entry| entry.id == new_id).unwrap();
+        assert!(old.lifecycle.archived);
+        assert_eq!(new.lifecycle.supersedes, vec![old_id]);
+    }
+
+    #[test]
+    fn test_history_records_versions_and_undo_single_entry() {
+        let (_dir, mut m) = make_manager();
+        m.set_change_context("agent", Some("初次记录".to_string()));
+
+        let id = match m
+            .upsert_memory("前端组件统一使用 Naive UI", MemoryCategory::Pattern)
+            .unwrap()
+        {
+            AddOutcome::Added(id) => id,
+            other => panic!("首次应新增: {:?}", other),
+        };
+        m.upsert_memory("配置数据库连接池大小为 20", MemoryCategory::Rule)
+            .unwrap();
+        m.set_change_context("user", None);
+        m.set_pinned(&id, true).unwrap();
+        m.mark_recalled(std::slice::from_ref(&id)).unwrap();
+        m.delete_memory(&id).unwrap();
+
+        let versions = m.history(&id).unwrap();
+        let summary = versions
+            .iter()
+            .map(|version| {
+                (
+                    version.kind,
+                    version.operation.as_str(),
+                    version.actor.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Add, "upsert", "agent"),
+                (ChangeKind::Update, "pin", "user"),
+                (ChangeKind::Delete, "delete", "user"),
+            ]
+        );
+        assert_eq!(versions[0].reason.as_deref(), Some("初次记录"));
+
+        let diff = m.diff_versions(&id, Some(1), Some(2)).unwrap();
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].field, "pinned");
+
+        let undo = m.undo(&id, None).unwrap();
+        assert_eq!(undo.action, "restored");
+        assert_eq!(undo.restored_version, 2);
+        let restored = m
+            .get_all_memories()
+            .into_iter()
+            .find(|entry| entry.id == id)
+            .unwrap();
+        assert!(restored.pinned);
+        assert_eq!(restored.lifecycle.recall_count, 1);
+        assert_eq!(m.get_all_memories().len(), 2, "撤销不影响其他记忆");
+
+        let undo = m.undo(&id, Some(0)).unwrap();
+        assert_eq!(undo.action, "removed");
+        assert_eq!(m.history(&id).unwrap().len(), 5);
+        assert!(m.undo(&id, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_import_and_export_rule_files() {
+        let (dir, mut m) = make_manager();
+        fs::write(
+            dir.path().join("AGENTS.md"),
+            "# AGENTS\n\n## 规范\n\n- 提交前运行 cargo fmt\n- 不要修改 Cargo.lock\n\n## 偏好\n\n- 提交信息使用中文\n",
+        )
+        .unwrap();
+        m.upsert_memory("提交前运行 cargo fmt", MemoryCategory::Rule)
+            .unwrap();
+
+        let imported = m.import_rule_files(&[]).unwrap();
+        assert_eq!(imported.files.len(), 1);
+        assert_eq!(imported.files[0].parsed_count, 3);
+        assert_eq!(imported.added.len(), 2);
+        assert_eq!(imported.skipped.len(), 1);
+        assert_eq!(imported.skipped[0].reason, "duplicate");
+        assert!(m
+            .get_all_memories()
+            .iter()
+            .any(|entry| entry.content == "提交信息使用中文"
+                && entry.category == MemoryCategory::Preference
+                && entry.lifecycle.source == MemorySource::Import));
+
+        let exported = m.export_rule_files(&["claude".to_string()]).unwrap();
+        assert_eq!(exported.files[0].path, "CLAUDE.md");
+        assert_eq!(exported.files[0].status, "created");
+        assert_eq!(exported.files[0].entry_count, 3);
+        let exported = m.export_rule_files(&["CLAUDE.md".to_string()]).unwrap();
+        assert_eq!(exported.files[0].status, "unchanged");
+        assert!(m.export_rule_files(&["../AGENTS.md".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_export_rule_files_defaults_to_one_managed_file() {
+        let (dir, mut m) = make_manager();
+        m.upsert_memory("提交前运行 cargo fmt", MemoryCategory::Rule)
+            .unwrap();
+        let claude = dir.path().join("CLAUDE.md");
+        fs::write(&claude, "# 手写规则\n").unwrap();
+        let cursor_rules = dir.path().join(".cursor").join("rules");
+        fs::create_dir_all(&cursor_rules).unwrap();
+        fs::write(cursor_rules.join("team.mdc"), "- 使用 pnpm\n").unwrap();
+
+        // 没有 AGENTS.md 但有 Cursor 规则目录：只写入三术自己的 .mdc
+        let exported = m.export_rule_files(&[]).unwrap();
+        assert_eq!(exported.files.len(), 1);
+        assert_eq!(exported.files[0].path, ".cursor/rules/sanshu-memory.mdc");
+        assert_eq!(fs::read_to_string(&claude).unwrap(), "# 手写规则\n");
+        assert_eq!(
+            fs::read_to_string(cursor_rules.join("team.mdc")).unwrap(),
+            "- 使用 pnpm\n"
+        );
+
+        fs::write(dir.path().join("AGENTS.md"), "# AGENTS\n").unwrap();
+        let exported = m.export_rule_files(&[]).unwrap();
+        assert_eq!(exported.files.len(), 1);
+        assert_eq!(exported.files[0].path, "AGENTS.md");
+        assert_eq!(fs::read_to_string(&claude).unwrap(), "# 手写规则\n");
+    }
+
     #[test]
     fn test_cleanup_apply_creates_backup_and_removes_selected() {
         let (_dir, mut m) = make_manager();
@@ -957,6 +2018,7 @@ mod tests {
             threshold: 0.55,
             categories: vec!["规范".to_string()],
             include_cross_category: false,
+            unused_days: None,
         });
         assert_eq!(preview.candidate_group_count, 1);
         let group = &preview.groups[0];
@@ -969,6 +2031,7 @@ mod tests {
                     keep_id: group.recommended_keep_id.clone(),
                     delete_ids: group.default_delete_ids.clone(),
                 }],
+                archive_ids: Vec::new(),
             })
             .unwrap();
 
@@ -978,6 +2041,140 @@ mod tests {
         assert_eq!(m.list_backups().unwrap().len(), 1);
     }
 
+    #[test]
+    fn test_cleanup_proposes_and_archives_unused_and_expired() {
+        let (_dir, mut m) = make_manager();
+        let mut stale = make_entry(
+            "stale",
+            "旧的部署地址是 10.0.0.8",
+            MemoryCategory::Context,
+            0,
+        );
+        stale.created_at = Utc::now() - chrono::Duration::days(120);
+        let mut expired = make_entry("expired", "本周冻结主分支", MemoryCategory::Context, 0);
+        expired.lifecycle.expires_at = Some(Utc::now() - chrono::Duration::hours(1));
+        let mut recalled = make_entry(
+            "recalled",
+            "数据库使用 PostgreSQL",
+            MemoryCategory::Context,
+            0,
+        );
+        recalled.created_at = Utc::now() - chrono::Duration::days(120);
+        m.store.entries = vec![stale, expired, recalled];
+        m.mark_recalled(&["recalled".to_string()]).unwrap();
+
+        let preview = m.preview_cleanup(CleanupPreviewRequest::default());
+        let mut proposed = preview
+            .archive_candidates
+            .iter()
+            .map(|candidate| (candidate.entry.id.as_str(), candidate.reason.as_str()))
+            .collect::<Vec<_>>();
+        proposed.sort();
+        assert_eq!(proposed, vec![("expired", "expired"), ("stale", "unused")]);
+
+        let result = m
+            .apply_cleanup_plan(CleanupApplyRequest {
+                auto_backup: true,
+                groups: Vec::new(),
+                archive_ids: vec!["stale".to_string(), "expired".to_string()],
+            })
+            .unwrap();
+        assert_eq!(result.archived_ids.len(), 2);
+        assert_eq!(m.get_all_memories().len(), 3);
+
+        let recalled = m.recall(&RecallRequest {
+            query: None,
+            categories: Vec::new(),
+            limit: 10,
+            token_budget: 1000,
+        });
+        assert_eq!(recalled.entries.len(), 1);
+        assert_eq!(recalled.entries[0].id, "recalled");
+    }
+
+    #[test]
+    fn test_layered_recall_marks_usage_in_each_scope_store() {
+        let (_dir, mut m) = make_manager();
+        m.store.entries = vec![make_entry(
+            "local",
+            "项目使用 pnpm 安装依赖",
+            MemoryCategory::Rule,
+            0,
+        )];
+        m.save_store().unwrap();
+
+        let global_dir = TempDir::new().unwrap();
+        let global_memory_dir = global_dir.path().to_path_buf();
+        let mut global = MemoryManager::open(
+            global_memory_dir.clone(),
+            global_memory_dir.clone(),
+            false,
+            StoreBackend::Sqlite,
+        )
+        .unwrap();
+        let mut shared = make_entry("shared", "回复一律使用中文", MemoryCategory::Preference, 0);
+        shared.created_at = Utc::now() - chrono::Duration::days(120);
+        global.store.entries = vec![shared];
+        global.save_store().unwrap();
+
+        let sources = vec![
+            (scopes::SCOPE_PROJECT.to_string(), m.memory_dir.clone()),
+            (scopes::SCOPE_GLOBAL.to_string(), global_memory_dir.clone()),
+        ];
+        let recalled = m.recall_from_layers(
+            &sources,
+            &RecallRequest {
+                query: None,
+                categories: Vec::new(),
+                limit: 10,
+                token_budget: 1000,
+            },
+        );
+        assert_eq!(recalled.entries.len(), 2);
+        let updated = m
+            .mark_recalled_in_layers(&sources, &recalled.entries)
+            .unwrap();
+        assert_eq!(updated, 2);
+        assert_eq!(m.store.entries[0].lifecycle.recall_count, 1);
+
+        // 全局记忆的使用统计写回全局库，清理预览不会再把它当作长期未使用
+        let global = MemoryManager::open(
+            global_memory_dir.clone(),
+            global_memory_dir,
+            false,
+            StoreBackend::Sqlite,
+        )
+        .unwrap();
+        assert_eq!(global.store.entries[0].lifecycle.recall_count, 1);
+        let preview = global.preview_cleanup(CleanupPreviewRequest::default());
+        assert!(preview.archive_candidates.is_empty());
+    }
+
+    #[test]
+    fn test_two_managers_on_same_store_keep_both_additions() {
+        let (dir, mut first) = make_manager();
+        let path = dir.path().to_string_lossy().to_string();
+        let mut second = MemoryManager::new(&path).unwrap();
+
+        first
+            .upsert_memory("使用 pnpm 安装依赖", MemoryCategory::Rule)
+            .unwrap();
+        second
+            .upsert_memory("数据库迁移必须使用 sqlx migrate", MemoryCategory::Rule)
+            .unwrap();
+
+        let reopened = MemoryManager::new(&path).unwrap();
+        assert_eq!(reopened.get_all_memories().len(), 2);
+        let recalled = reopened.recall(&RecallRequest {
+            query: Some("新增数据库迁移脚本".to_string()),
+            categories: Vec::new(),
+            limit: 10,
+            token_budget: 1000,
+        });
+        assert_eq!(recalled.entries.len(), 1);
+        assert_eq!(recalled.omitted_irrelevant, 1);
+    }
+
     #[test]
     fn test_backup_retention_keeps_latest_ten() {
         let (_dir, m) = make_manager();
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use rmcp::model::{CallToolResult, Content, ErrorData as McpError};
 
-use super::{CleanupPreviewRequest, MemoryCategory, MemoryManager};
+use super::recall::{format_recall, DEFAULT_RECALL_LIMIT, DEFAULT_RECALL_TOKEN_BUDGET};
+use super::{CleanupPreviewRequest, MemoryCategory, MemoryManager, MemoryScope, RecallRequest};
+use super::{ConflictPolicy, ContradictionConflict, LifecycleUpdate, MemorySource, UpsertReport};
 use crate::mcp::{
     utils::{project_path_error, validate_project_path},
     JiyiRequest,
@@ -34,15 +36,22 @@ impl MemoryTool {
             )).into());
         }
 
+        let scope = MemoryScope::parse(request.scope.as_deref()).map_err(|e| {
+            log_important!(warn, "[ji] 作用域解析失败: {}", e);
+            McpError::invalid_params(e.to_string(), None)
+        })?;
+
         // 创建记忆管理器（会自动执行迁移和启动时去重）
         // 支持非 Git 项目降级模式
         let start = std::time::Instant::now();
-        let mut manager = MemoryManager::new(&request.project_path).map_err(|e| {
+        let mut manager = MemoryManager::for_scope(&request.project_path, &scope).map_err(|e| {
             log_important!(error, "[ji] 创建记忆管理器失败: {}", e);
             McpError::internal_error(format!("创建记忆管理器失败: {}", e), None)
         })?;
+        manager.set_change_context("agent", request.reason.clone());
         log_debug!(
-            "[ji] 记忆管理器创建完成: elapsed={}ms, is_non_git={}",
+            "[ji] 记忆管理器创建完成: scope={:?}, elapsed={}ms, is_non_git={}",
+            scope,
             start.elapsed().as_millis(),
             manager.is_non_git_project()
         );
@@ -80,8 +89,39 @@ impl MemoryTool {
                     request.content.len()
                 );
 
-                // 添加记忆（方案 B：带同类 upsert 语义）
-                match manager.upsert_memory(&request.content, category) {
+                let lifecycle = lifecycle_update(&request)?;
+                let policy =
+                    ConflictPolicy::parse(request.on_conflict.as_deref()).ok_or_else(|| {
+                        McpError::invalid_params(
+                            format!(
+                                "未知的矛盾处理方式: {}。支持: supersede | keep_both",
+                                request.on_conflict.as_deref().unwrap_or_default()
+                            ),
+                            None,
+                        )
+                    })?;
+
+                // 添加记忆（方案 B：带同类 upsert 语义），先检测与已有记忆是否矛盾
+                let report = manager.upsert_memory_with_policy(&request.content, category, policy);
+                let conflict_hint = report.as_ref().map(conflict_hint).unwrap_or_default();
+                let outcome = report.map(|report| report.outcome);
+                if let Ok(super::AddOutcome::Added(id) | super::AddOutcome::Updated { id, .. }) =
+                    &outcome
+                {
+                    if let Err(e) = manager.update_lifecycle(id, &lifecycle) {
+                        log_important!(warn, "[ji] 记录生命周期信息失败: id={}, error={}", id, e);
+                    }
+                }
+                if let (
+                    Some(pinned),
+                    Ok(super::AddOutcome::Added(id) | super::AddOutcome::Updated { id, .. }),
+                ) = (request.pinned, &outcome)
+                {
+                    if let Err(e) = manager.set_pinned(id, pinned) {
+                        log_important!(warn, "[ji] 设置固定状态失败: id={}, error={}", id, e);
+                    }
+                }
+                match outcome {
                     Ok(super::AddOutcome::Added(id)) => {
                         log_important!(
                             info,
@@ -90,10 +130,12 @@ impl MemoryTool {
                             category
                         );
                         format!(
-                            "✅ 记忆已添加，ID: {}\n📝 内容: {}\n📂 分类: {}{}{}",
+                            "✅ 记忆已添加，ID: {}\n📝 内容: {}\n📂 分类: {}{}{}{}{}",
                             id,
                             request.content,
                             category.display_name(),
+                            scope_hint(&scope),
+                            conflict_hint,
                             index_hint,
                             non_git_hint
                         )
@@ -111,12 +153,13 @@ impl MemoryTool {
                             category
                         );
                         format!(
-                            "🔄 已更新同类记忆（相似度 {:.1}%），ID: {}\n📝 新内容: {}\n📝 原内容: {}\n📂 分类: {}{}{}",
+                            "🔄 已更新同类记忆（相似度 {:.1}%），ID: {}\n📝 新内容: {}\n📝 原内容: {}\n📂 分类: {}{}{}{}",
                             similarity * 100.0,
                             id,
                             request.content,
                             old_content,
                             category.display_name(),
+                            conflict_hint,
                             index_hint,
                             non_git_hint
                         )
@@ -140,6 +183,14 @@ impl MemoryTool {
                             non_git_hint
                         )
                     }
+                    Ok(super::AddOutcome::Conflict(conflicts)) => {
+                        log_important!(
+                            info,
+                            "[ji] 记忆与已有记忆矛盾，未写入: conflicts={}",
+                            conflicts.len()
+                        );
+                        format_conflicts(&request.content, &conflicts)
+                    }
                     Err(e) => {
                         log_important!(error, "[ji] 添加记忆失败: {}", e);
                         return Err(McpError::internal_error(
@@ -150,11 +201,121 @@ impl MemoryTool {
                 }
             }
             "回忆" => {
-                log_debug!("[ji] 执行回忆操作");
-                let info = manager.get_project_info();
-                log_important!(info, "[ji] 回忆完成: info_len={}", info.len());
+                let recall_request = RecallRequest {
+                    query: request.query.clone(),
+                    categories: request
+                        .categories
+                        .iter()
+                        .map(|category| MemoryCategory::from_str(category))
+                        .collect(),
+                    limit: request.limit.unwrap_or(DEFAULT_RECALL_LIMIT).max(1),
+                    token_budget: request
+                        .token_budget
+                        .unwrap_or(DEFAULT_RECALL_TOKEN_BUDGET)
+                        .max(1),
+                };
+                log_debug!(
+                    "[ji] 执行回忆操作: query_len={}, categories={:?}, limit={}, token_budget={}",
+                    recall_request.query.as_deref().map_or(0, str::len),
+                    recall_request.categories,
+                    recall_request.limit,
+                    recall_request.token_budget
+                );
+                // 未指定作用域时合并全部作用域；指定时只回忆该作用域
+                let recalled = if request.scope.is_some() {
+                    manager.recall(&recall_request)
+                } else {
+                    manager
+                        .recall_layered(&request.project_path, &recall_request)
+                        .map_err(|e| {
+                            log_important!(error, "[ji] 回忆失败: {}", e);
+                            McpError::internal_error(format!("回忆失败: {}", e), None)
+                        })?
+                };
+                let has_query = recall_request
+                    .query
+                    .as_deref()
+                    .is_some_and(|query| !query.trim().is_empty());
+                let info = format_recall(&recalled, has_query);
+                let marked = if request.scope.is_some() {
+                    let recalled_ids = recalled
+                        .entries
+                        .iter()
+                        .map(|entry| entry.id.clone())
+                        .collect::<Vec<_>>();
+                    manager.mark_recalled(&recalled_ids)
+                } else {
+                    manager.mark_recalled_layered(&request.project_path, &recalled.entries)
+                };
+                if let Err(e) = marked {
+                    log_debug!("[ji] 记录回忆使用统计失败（不影响回忆）: {}", e);
+                }
+                log_important!(
+                    info,
+                    "[ji] 回忆完成: returned={}, omitted={}, estimated_tokens={}",
+                    recalled.entries.len(),
+                    recalled.omitted(),
+                    recalled.estimated_tokens
+                );
                 format!("{}{}{}", info, index_hint, non_git_hint)
             }
+            "归档" => {
+                let memory_id = request.memory_id.as_deref().ok_or_else(|| {
+                    log_important!(warn, "[ji] 归档失败: 缺少 memory_id");
+                    McpError::invalid_params("缺少 memory_id 参数".to_string(), None)
+                })?;
+                let archived = request.archived.unwrap_or(true);
+                if !manager.contains(memory_id) {
+                    format!("⚠️ 未找到指定 ID 的记忆: {}", memory_id)
+                } else {
+                    let changed = manager.set_archived(memory_id, archived).map_err(|e| {
+                        log_important!(error, "[ji] 更新归档状态失败: {}", e);
+                        McpError::internal_error(format!("更新归档状态失败: {}", e), None)
+                    })?;
+                    log_important!(
+                        info,
+                        "[ji] 归档状态已更新: id={}, archived={}, changed={}",
+                        memory_id,
+                        archived,
+                        changed
+                    );
+                    if archived {
+                        format!("🗄️ 已归档记忆，回忆时不再返回\n🆔 ID: {}", memory_id)
+                    } else {
+                        format!("✅ 已取消归档记忆\n🆔 ID: {}", memory_id)
+                    }
+                }
+            }
+            "固定" => {
+                let memory_id = request.memory_id.as_deref().ok_or_else(|| {
+                    log_important!(warn, "[ji] 固定失败: 缺少 memory_id");
+                    McpError::invalid_params("缺少 memory_id 参数".to_string(), None)
+                })?;
+                let pinned = request.pinned.unwrap_or(true);
+                match manager.set_pinned(memory_id, pinned) {
+                    Ok(true) => {
+                        log_important!(
+                            info,
+                            "[ji] 固定状态已更新: id={}, pinned={}",
+                            memory_id,
+                            pinned
+                        );
+                        if pinned {
+                            format!("📌 已固定记忆，回忆时总是返回\n🆔 ID: {}", memory_id)
+                        } else {
+                            format!("✅ 已取消固定记忆\n🆔 ID: {}", memory_id)
+                        }
+                    }
+                    Ok(false) => format!("⚠️ 未找到指定 ID 的记忆: {}", memory_id),
+                    Err(e) => {
+                        log_important!(error, "[ji] 更新固定状态失败: {}", e);
+                        return Err(McpError::internal_error(
+                            format!("更新固定状态失败: {}", e),
+                            None,
+                        ));
+                    }
+                }
+            }
             // === 新增: 整理 (执行去重) ===
             "整理" => {
                 log_debug!("[ji] 执行整理（去重）操作");
@@ -199,6 +360,7 @@ impl MemoryTool {
                     threshold,
                     categories: request.categories.clone(),
                     include_cross_category: request.include_cross_category,
+                    unused_days: request.unused_days,
                 });
                 format!(
                     "🔎 历史清理预览\n{}",
@@ -220,6 +382,65 @@ impl MemoryTool {
                     serde_json::to_string_pretty(&result).unwrap_or_default()
                 )
             }
+            "预览拉取" | "拉取共享" | "导出共享" => {
+                if scope != MemoryScope::Project {
+                    return Err(McpError::invalid_params(
+                        "共享记忆文件仅支持项目作用域".to_string(),
+                        None,
+                    ));
+                }
+                shared_memory_action(&mut manager, &request)?
+            }
+            "历史" | "对比" | "撤销" => history_action(&mut manager, &request)?,
+            "导入规则" | "导出规则" => {
+                if scope != MemoryScope::Project {
+                    return Err(McpError::invalid_params(
+                        "规则文件导入导出仅支持项目作用域".to_string(),
+                        None,
+                    ));
+                }
+                if request.action == "导入规则" {
+                    let result = manager
+                        .import_rule_files(&request.rule_files)
+                        .map_err(|e| {
+                            log_important!(error, "[ji] 导入规则文件失败: {}", e);
+                            McpError::internal_error(format!("导入规则文件失败: {}", e), None)
+                        })?;
+                    log_important!(
+                        info,
+                        "[ji] 规则文件导入完成: files={}, added={}, updated={}, skipped={}",
+                        result.files.len(),
+                        result.added.len(),
+                        result.updated.len(),
+                        result.skipped.len()
+                    );
+                    let conflict_hint = if result
+                        .skipped
+                        .iter()
+                        .any(|rule| rule.reason == "conflict")
+                    {
+                        "\n⚠️ 部分条目与已有记忆矛盾，未导入。确认后可用「记忆」操作带 on_conflict 单独写入"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        "📥 规则文件已导入\n{}{}",
+                        serde_json::to_string_pretty(&result).unwrap_or_default(),
+                        conflict_hint
+                    )
+                } else {
+                    let result = manager
+                        .export_rule_files(&request.rule_files)
+                        .map_err(|e| {
+                            log_important!(error, "[ji] 导出规则文件失败: {}", e);
+                            McpError::internal_error(format!("导出规则文件失败: {}", e), None)
+                        })?;
+                    format!(
+                        "📤 记忆已导出到规则文件（仅替换 sanshu-memory 标记之间的区块）\n{}",
+                        serde_json::to_string_pretty(&result).unwrap_or_default()
+                    )
+                }
+            }
             "备份列表" => {
                 let backups = manager.list_backups().map_err(|e| {
                     log_important!(error, "[ji] 读取备份列表失败: {}", e);
@@ -266,7 +487,15 @@ impl MemoryTool {
                             "id": m.id,
                             "content": m.content,
                             "category": m.category.display_name(),
-                            "created_at": m.created_at.to_rfc3339()
+                            "created_at": m.created_at.to_rfc3339(),
+                            "pinned": m.pinned,
+                            "source": m.lifecycle.source.as_str(),
+                            "confidence": m.lifecycle.effective_confidence(),
+                            "expires_at": m.lifecycle.expires_at.map(|at| at.to_rfc3339()),
+                            "archived": m.lifecycle.archived,
+                            "last_recalled_at": m.lifecycle.last_recalled_at.map(|at| at.to_rfc3339()),
+                            "recall_count": m.lifecycle.recall_count,
+                            "supersedes": m.lifecycle.supersedes
                         })
                     })
                     .collect();
@@ -295,13 +524,16 @@ impl MemoryTool {
                 log_debug!("[ji] 执行预览相似: content_len={}", request.content.len());
                 let dedup =
                     super::dedup::MemoryDeduplicator::new(manager.config().similarity_threshold);
-                let dup_info = dedup.check_duplicate(
+                let entries = manager
+                    .get_all_memories()
+                    .iter()
+                    .map(|e| (*e).clone())
+                    .collect::<Vec<_>>();
+                let dup_info = dedup.check_duplicate(&request.content, &entries);
+                let contradictions = super::contradiction::detect_contradictions(
                     &request.content,
-                    &manager
-                        .get_all_memories()
-                        .iter()
-                        .map(|e| (*e).clone())
-                        .collect::<Vec<_>>(),
+                    &entries,
+                    chrono::Utc::now(),
                 );
 
                 log_important!(
@@ -317,7 +549,8 @@ impl MemoryTool {
                     "similarity_value": dup_info.similarity,
                     "threshold": manager.config().similarity_threshold,
                     "matched_id": dup_info.matched_id,
-                    "matched_content": dup_info.matched_content
+                    "matched_content": dup_info.matched_content,
+                    "contradictions": contradictions
                 });
 
                 if dup_info.is_duplicate {
@@ -439,7 +672,7 @@ impl MemoryTool {
             _ => {
                 log_important!(warn, "[ji] 未知操作类型: {}", request.action);
                 return Err(McpError::invalid_params(
-                    format!("未知的操作类型: {}。支持的操作: 记忆 | 回忆 | 整理 | 预览整理 | 应用整理 | 备份列表 | 恢复备份 | 导出备份 | 列表 | 预览相似 | 配置 | 删除", request.action),
+                    format!("未知的操作类型: {}。支持的操作: 记忆 | 回忆 | 整理 | 预览整理 | 应用整理 | 备份列表 | 恢复备份 | 导出备份 | 列表 | 预览相似 | 配置 | 删除 | 固定 | 归档 | 预览拉取 | 拉取共享 | 导出共享 | 历史 | 对比 | 撤销 | 导入规则 | 导出规则", request.action),
                     None
                 ));
             }
@@ -455,6 +688,224 @@ impl MemoryTool {
     }
 }
 
+/// 单条记忆的版本历史、版本对比与撤销
+fn history_action(manager: &mut MemoryManager, request: &JiyiRequest) -> Result<String, McpError> {
+    let memory_id = request.memory_id.as_deref().ok_or_else(|| {
+        log_important!(warn, "[ji] {}失败: 缺少 memory_id", request.action);
+        McpError::invalid_params("缺少 memory_id 参数".to_string(), None)
+    })?;
+    let version = request.version.map(|version| version as usize);
+    let failed = |e: anyhow::Error| {
+        log_debug!("[ji] {}失败: {}", request.action, e);
+        McpError::invalid_params(format!("{}失败: {}", request.action, e), None)
+    };
+    match request.action.as_str() {
+        "历史" => {
+            let versions = manager.history(memory_id).map_err(failed)?;
+            Ok(format!(
+                "📜 记忆 {} 共 {} 个版本\n{}",
+                memory_id,
+                versions.len(),
+                serde_json::to_string_pretty(&versions).unwrap_or_default()
+            ))
+        }
+        "对比" => {
+            let from_version = request.from_version.map(|version| version as usize);
+            let diff = manager
+                .diff_versions(memory_id, from_version, version)
+                .map_err(failed)?;
+            Ok(format!(
+                "🔍 版本 {} → {}\n{}",
+                diff.from_version,
+                diff.to_version,
+                serde_json::to_string_pretty(&diff).unwrap_or_default()
+            ))
+        }
+        _ => {
+            let result = manager.undo(memory_id, version).map_err(failed)?;
+            log_important!(
+                info,
+                "[ji] 撤销完成: id={}, restored_version={}, action={}",
+                result.memory_id,
+                result.restored_version,
+                result.action
+            );
+            Ok(format!(
+                "↩️ 已撤销，记忆恢复到版本 {}\n{}",
+                result.restored_version,
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            ))
+        }
+    }
+}
+
+/// 仓库内共享记忆文件（`.sanshu/memory.jsonl`）的预览、拉取与导出
+fn shared_memory_action(
+    manager: &mut MemoryManager,
+    request: &JiyiRequest,
+) -> Result<String, McpError> {
+    match request.action.as_str() {
+        "预览拉取" => {
+            let preview = manager.preview_shared_pull().map_err(|e| {
+                log_important!(error, "[ji] 预览共享记忆失败: {}", e);
+                McpError::internal_error(format!("预览共享记忆失败: {}", e), None)
+            })?;
+            log_important!(
+                info,
+                "[ji] 共享记忆预览: new={}, conflicts={}, invalid_lines={}",
+                preview.new_entries.len(),
+                preview.conflicts.len(),
+                preview.invalid_lines.len()
+            );
+            Ok(format!(
+                "🔎 共享记忆拉取预览\n{}",
+                serde_json::to_string_pretty(&preview).unwrap_or_default()
+            ))
+        }
+        "拉取共享" => {
+            let pull_request = request.shared_pull.clone().unwrap_or_default();
+            let result = manager.apply_shared_pull(&pull_request).map_err(|e| {
+                log_important!(error, "[ji] 拉取共享记忆失败: {}", e);
+                McpError::internal_error(format!("拉取共享记忆失败: {}", e), None)
+            })?;
+            let unresolved_hint = if result.unresolved_ids.is_empty() {
+                ""
+            } else {
+                "\n⚠️ 存在未处理的冲突，本地内容未改动。请用「预览拉取」查看后通过 shared_pull.resolutions 选择 local 或 shared"
+            };
+            Ok(format!(
+                "✅ 共享记忆已拉取\n{}{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default(),
+                unresolved_hint
+            ))
+        }
+        _ => {
+            let result = manager.export_shared().map_err(|e| {
+                log_important!(error, "[ji] 导出共享记忆失败: {}", e);
+                McpError::internal_error(format!("导出共享记忆失败: {}", e), None)
+            })?;
+            log_important!(
+                info,
+                "[ji] 共享记忆导出完成: added={}, updated={}, skipped={}",
+                result.added_ids.len(),
+                result.updated_ids.len(),
+                result.skipped_conflict_ids.len()
+            );
+            let skipped_hint = if result.skipped_conflict_ids.is_empty() {
+                ""
+            } else {
+                "\n⚠️ 部分条目在共享文件中有更新的版本，已跳过。请先「拉取共享」处理冲突"
+            };
+            Ok(format!(
+                "📤 共享记忆已导出，请随代码一起提交\n{}{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default(),
+                skipped_hint
+            ))
+        }
+    }
+}
+
+/// 解析「记忆」操作的来源、置信度与过期时间
+fn lifecycle_update(request: &JiyiRequest) -> Result<LifecycleUpdate, McpError> {
+    let source = match request.memory_source.as_deref() {
+        Some(value) => Some(MemorySource::parse(value).ok_or_else(|| {
+            McpError::invalid_params(
+                format!(
+                    "未知的记忆来源: {}。支持: agent | user | context_block | import",
+                    value
+                ),
+                None,
+            )
+        })?),
+        // 未指定时新增条目默认为 agent，同类更新保留原来源
+        None => None,
+    };
+    let expires_at = match request.expires_at.as_deref().map(str::trim) {
+        Some(value) if !value.is_empty() => Some(parse_expires_at(value).ok_or_else(|| {
+            McpError::invalid_params(
+                format!("过期时间格式错误: {}，应为 RFC3339 或 YYYY-MM-DD", value),
+                None,
+            )
+        })?),
+        _ => None,
+    };
+    Ok(LifecycleUpdate {
+        source,
+        confidence: request.confidence,
+        expires_at,
+    })
+}
+
+/// YYYY-MM-DD 按当天 UTC 零点处理
+fn parse_expires_at(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    if let Ok(at) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|at| at.and_utc())
+}
+
+/// 矛盾报告：未写入，请调用方确认后带 `on_conflict` 重试
+fn format_conflicts(content: &str, conflicts: &[ContradictionConflict]) -> String {
+    let lines = conflicts
+        .iter()
+        .map(|conflict| {
+            format!(
+                "- [{}] {}（ID: {}）\n  {}",
+                conflict.category, conflict.content, conflict.id, conflict.detail
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "⚠️ 新记忆与 {} 条已有记忆矛盾，未写入\n📝 内容: {}\n{}\n💡 请确认后重新调用：on_conflict=supersede 取代并归档旧记忆，on_conflict=keep_both 两条都保留",
+        conflicts.len(),
+        content,
+        lines
+    )
+}
+
+/// 新增或更新后附加的矛盾处理结果
+fn conflict_hint(report: &UpsertReport) -> String {
+    let mut hint = String::new();
+    if !report.superseded_ids.is_empty() {
+        hint.push_str(&format!(
+            "\n🗄️ 已取代并归档矛盾记忆: {}",
+            report.superseded_ids.join(", ")
+        ));
+    }
+    if !report.kept_conflicts.is_empty() {
+        hint.push_str(&format!(
+            "\n⚠️ 与 {} 条记忆矛盾，已按 keep_both 并存: {}",
+            report.kept_conflicts.len(),
+            report
+                .kept_conflicts
+                .iter()
+                .map(|conflict| conflict.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    hint
+}
+
+/// 非项目作用域时在新增结果中注明写入位置
+fn scope_hint(scope: &MemoryScope) -> String {
+    match scope {
+        MemoryScope::Project => String::new(),
+        MemoryScope::Global => "\n🌐 作用域: 全局".to_string(),
+        MemoryScope::Team(name) => format!(
+            "\n👥 作用域: 团队{}",
+            name.as_deref()
+                .map(|name| format!(" {}", name))
+                .unwrap_or_default()
+        ),
+        MemoryScope::Directory => "\n📁 作用域: 子目录".to_string(),
+    }
+}
+
 /// 检查 sou 工具是否启用
 fn is_sou_enabled() -> bool {
     match crate::config::load_standalone_config() {
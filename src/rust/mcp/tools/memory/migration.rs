@@ -1,15 +1,19 @@
 //! 记忆格式迁移模块
 //!
-//! 将旧版 Markdown 格式迁移到新版 JSON 格式
+//! - 将旧版 Markdown 格式迁移到 v2.0 JSON 格式
+//! - 将 v2.0 JSON 存储（`memories.json`）一次性迁移到 SQLite（`memories.db`）
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::Utc;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use super::dedup::MemoryDeduplicator;
 use super::similarity::TextSimilarity;
-use super::types::{MemoryCategory, MemoryConfig, MemoryEntry, MemoryStore};
+use super::sqlite_store;
+use super::types::{
+    MemoryCategory, MemoryConfig, MemoryEntry, MemoryLifecycle, MemorySource, MemoryStore,
+};
 use crate::log_debug;
 
 /// 迁移结果统计
@@ -27,6 +31,15 @@ pub struct MigrationResult {
     pub backed_up_files: Vec<String>,
 }
 
+/// JSON 存储迁移到 SQLite 的结果
+#[derive(Debug, Clone, Default)]
+pub struct StoreMigrationResult {
+    /// 写入 SQLite 的条目数
+    pub entries_count: usize,
+    /// 原 memories.json 的备份路径（相对记忆目录）
+    pub backup_file: String,
+}
+
 /// 记忆格式迁移器
 pub struct MemoryMigrator;
 
@@ -45,14 +58,17 @@ impl MemoryMigrator {
     /// 备份目录名
     const BACKUP_DIR: &'static str = "backup";
 
+    /// 迁移到 SQLite 后 memories.json 的备份文件名
+    const JSON_BACKUP_FILE: &'static str = "memories.v2.json";
+
     /// 检查是否需要迁移
     ///
-    /// 如果存在旧版 MD 文件且不存在新版 JSON 文件，则需要迁移
+    /// 如果存在旧版 MD 文件且不存在新版 JSON 文件或 SQLite 库，则需要迁移
     pub fn needs_migration(memory_dir: &Path) -> bool {
         let store_path = memory_dir.join(Self::STORE_FILE);
 
         // 如果已存在新版文件，不需要迁移
-        if store_path.exists() {
+        if store_path.exists() || sqlite_store::exists(memory_dir) {
             return false;
         }
 
@@ -153,6 +169,99 @@ impl MemoryMigrator {
         Ok(result)
     }
 
+    /// 检查是否需要把 v2.0 JSON 存储迁移到 SQLite
+    ///
+    /// 存在 memories.json 且不存在 memories.db 时需要迁移
+    pub fn needs_store_migration(memory_dir: &Path) -> bool {
+        memory_dir.join(Self::STORE_FILE).is_file() && !sqlite_store::exists(memory_dir)
+    }
+
+    /// 把 memories.json 迁移到 SQLite
+    ///
+    /// 1. 取得记忆目录的迁移锁（`memories.db.lock`），并在锁内重新检查是否仍需迁移；
+    ///    其他进程已完成迁移时返回 `None`
+    /// 2. 读取 memories.json（无法解析时视为空库，原文件仍会保留在备份中）
+    /// 3. 在临时文件 `memories.db.migrating` 中写入整个库，完成后改名为 memories.db；
+    ///    失败时只删除本次创建的临时文件，下次启动重试
+    /// 4. 把 memories.json 移到 backup/memories.v2.json
+    pub fn migrate_to_sqlite(memory_dir: &Path) -> Result<Option<StoreMigrationResult>> {
+        let _lock = Self::lock_store_migration(memory_dir)?;
+        if !Self::needs_store_migration(memory_dir) {
+            return Ok(None);
+        }
+
+        let store_path = memory_dir.join(Self::STORE_FILE);
+        let content = fs::read_to_string(&store_path)
+            .with_context(|| format!("读取存储文件失败: {}", store_path.display()))?;
+        let store = serde_json::from_str::<MemoryStore>(&content).unwrap_or_else(|e| {
+            log_debug!("解析存储文件失败，迁移为空库: {}", e);
+            MemoryStore::default()
+        });
+
+        // 临时库只由持有迁移锁的进程创建，残留的临时库来自中断的迁移，可以删除
+        let temp_path = memory_dir.join(format!("{}.migrating", sqlite_store::STORE_DB_FILE));
+        Self::remove_database_files(&temp_path);
+        if let Err(error) = sqlite_store::create_at(&temp_path, &store) {
+            Self::remove_database_files(&temp_path);
+            return Err(error.context("写入 SQLite 记忆库失败"));
+        }
+        if sqlite_store::exists(memory_dir) {
+            // 不经迁移锁创建的库（如用户手动放入）不覆盖
+            Self::remove_database_files(&temp_path);
+            return Err(anyhow::anyhow!(
+                "迁移期间 {} 已被创建，保留现有记忆库与 {}",
+                sqlite_store::STORE_DB_FILE,
+                Self::STORE_FILE
+            ));
+        }
+        let db_path = sqlite_store::db_path(memory_dir);
+        if let Err(error) = fs::rename(&temp_path, &db_path) {
+            Self::remove_database_files(&temp_path);
+            return Err(anyhow::Error::new(error)
+                .context(format!("启用 SQLite 记忆库失败: {}", db_path.display())));
+        }
+        log_debug!(
+            "已迁移 {} 条记忆到 {}",
+            store.entries.len(),
+            sqlite_store::STORE_DB_FILE
+        );
+
+        let backup_dir = memory_dir.join(Self::BACKUP_DIR);
+        fs::create_dir_all(&backup_dir)?;
+        let backup_path = backup_dir.join(Self::JSON_BACKUP_FILE);
+        fs::rename(&store_path, &backup_path)
+            .with_context(|| format!("备份 memories.json 失败: {}", backup_path.display()))?;
+        log_debug!("已备份: {} -> {}", Self::STORE_FILE, backup_path.display());
+
+        Ok(Some(StoreMigrationResult {
+            entries_count: store.entries.len(),
+            backup_file: format!("{}/{}", Self::BACKUP_DIR, Self::JSON_BACKUP_FILE),
+        }))
+    }
+
+    /// 独占的迁移锁，随返回的文件关闭而释放
+    fn lock_store_migration(memory_dir: &Path) -> Result<fs::File> {
+        let path = memory_dir.join(format!("{}.lock", sqlite_store::STORE_DB_FILE));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("打开迁移锁失败: {}", path.display()))?;
+        file.lock()
+            .with_context(|| format!("获取迁移锁失败: {}", path.display()))?;
+        Ok(file)
+    }
+
+    fn remove_database_files(path: &Path) {
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let mut file = path.as_os_str().to_owned();
+            file.push(suffix);
+            let _ = fs::remove_file(PathBuf::from(file));
+        }
+    }
+
     /// 解析旧版 MD 文件
     fn parse_md_file(path: &Path, category: MemoryCategory) -> Result<Vec<MemoryEntry>> {
         let content = fs::read_to_string(path)?;
@@ -171,6 +280,8 @@ impl MemoryMigrator {
                         category,
                         created_at: Utc::now(),
                         updated_at: Utc::now(),
+                        pinned: false,
+                        lifecycle: MemoryLifecycle::from_source(MemorySource::Import),
                     };
                     entries.push(entry);
                 }
@@ -229,4 +340,68 @@ mod tests {
         assert_eq!(entries[1].content, "规则二");
         assert_eq!(entries[2].content, "规则三");
     }
+
+    #[test]
+    fn test_migrate_json_store_to_sqlite() {
+        let temp_dir = TempDir::new().unwrap();
+        let memory_dir = temp_dir.path();
+        let mut store = MemoryStore::default();
+        let md_path = temp_dir.path().join("rules.md");
+        fs::write(&md_path, "- 使用 pnpm\n- 回复使用中文\n").unwrap();
+        store.entries = MemoryMigrator::parse_md_file(&md_path, MemoryCategory::Rule).unwrap();
+        fs::remove_file(&md_path).unwrap();
+        store.config.similarity_threshold = 0.8;
+        fs::write(
+            memory_dir.join("memories.json"),
+            serde_json::to_string_pretty(&store).unwrap(),
+        )
+        .unwrap();
+        assert!(MemoryMigrator::needs_store_migration(memory_dir));
+
+        let result = MemoryMigrator::migrate_to_sqlite(memory_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.entries_count, 2);
+        assert!(!memory_dir.join("memories.json").exists());
+        assert!(memory_dir.join(&result.backup_file).is_file());
+        assert!(!MemoryMigrator::needs_store_migration(memory_dir));
+
+        let loaded = sqlite_store::load(memory_dir).unwrap().unwrap();
+        assert_eq!(loaded.entries.len(), 2);
+        assert_eq!(loaded.entries[0].content, "使用 pnpm");
+        assert_eq!(loaded.config.similarity_threshold, 0.8);
+        assert!(!memory_dir.join("memories.db.migrating").exists());
+        assert!(!memory_dir.join("memories.db-wal").exists());
+    }
+
+    #[test]
+    fn test_store_migration_never_replaces_an_existing_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let memory_dir = temp_dir.path();
+        fs::write(
+            memory_dir.join("memories.json"),
+            serde_json::to_string(&MemoryStore::default()).unwrap(),
+        )
+        .unwrap();
+
+        // 其他进程已完成迁移：锁内重新检查后不做任何事
+        let existing = MemoryStore {
+            project_path: "existing".to_string(),
+            ..Default::default()
+        };
+        sqlite_store::create_at(&sqlite_store::db_path(memory_dir), &existing).unwrap();
+        assert!(MemoryMigrator::migrate_to_sqlite(memory_dir)
+            .unwrap()
+            .is_none());
+        assert!(memory_dir.join("memories.json").is_file());
+        let loaded = sqlite_store::load(memory_dir).unwrap().unwrap();
+        assert_eq!(loaded.project_path, "existing");
+
+        // 迁移失败（目标路径被目录占用）时只清理临时库，不碰已有的 memories.db
+        fs::create_dir(memory_dir.join("memories.db.migrating")).unwrap();
+        fs::remove_file(sqlite_store::db_path(memory_dir)).unwrap();
+        assert!(MemoryMigrator::migrate_to_sqlite(memory_dir).is_err());
+        assert!(memory_dir.join("memories.json").is_file());
+        assert!(!sqlite_store::exists(memory_dir));
+    }
 }
@@ -3,29 +3,58 @@
 //! 提供全局记忆管理功能，用于存储和管理重要的开发规范、用户偏好和最佳实践
 //!
 //! ## 模块结构
-//! - `types` - 数据类型定义（MemoryEntry, MemoryStore, MemoryConfig）
+//! - `types` - 数据类型定义（MemoryEntry, MemoryLifecycle, MemoryStore, MemoryConfig）
 //! - `similarity` - 文本相似度算法
 //! - `dedup` - 去重检测器
-//! - `migration` - 旧格式迁移
+//! - `contradiction` - 矛盾检测（极性相反、取值不同）
+//! - `migration` - 旧格式迁移（Markdown → JSON → SQLite）
+//! - `sqlite_store` - SQLite 存储（事务写入、全文索引、相似度候选签名）
+//! - `json_store` - JSON 存储（团队库，位于共享仓库或网络共享目录）
 //! - `manager` - 核心管理器
+//! - `history` - 变更日志（版本历史、对比与单条撤销）
+//! - `recall` - 按任务检索记忆（回忆）
+//! - `scopes` - 分层作用域（全局 / 团队 / 项目 / 子目录）
+//! - `shared` - 仓库内共享记忆文件（`.sanshu/memory.jsonl`）
+//! - `rule_files` - 代理规则文件（AGENTS.md、CLAUDE.md、.cursorrules 等）的导入与导出
 //! - `mcp` - MCP 接口
 
 pub mod cleanup;
+pub mod contradiction;
 pub mod dedup;
+pub mod history;
+pub mod json_store;
 pub mod manager;
 pub mod mcp;
 pub mod migration;
+pub mod recall;
+pub mod rule_files;
+pub mod scopes;
+pub mod shared;
 pub mod similarity;
+pub mod sqlite_store;
 pub mod types;
 
 // 重新导出主要类型和功能
 pub use cleanup::{
-    BackupInfo, CleanupApplyGroup, CleanupApplyRequest, CleanupApplyResult, CleanupGroup,
-    CleanupGroupEntry, CleanupPreviewRequest, CleanupPreviewResult, RestoreBackupResult,
+    ArchiveCandidate, BackupInfo, CleanupApplyGroup, CleanupApplyRequest, CleanupApplyResult,
+    CleanupGroup, CleanupGroupEntry, CleanupPreviewRequest, CleanupPreviewResult,
+    RestoreBackupResult,
 };
+pub use contradiction::{ConflictPolicy, ContradictionConflict};
 pub use dedup::{DedupResult, DuplicateInfo, MemoryDeduplicator};
-pub use manager::{AddOutcome, MemoryManager};
+pub use history::{ChangeKind, FieldDiff, MemoryChange, MemoryVersion, UndoResult, VersionDiff};
+pub use manager::{AddOutcome, MemoryManager, UpsertReport};
 pub use mcp::MemoryTool;
-pub use migration::{MemoryMigrator, MigrationResult};
+pub use migration::{MemoryMigrator, MigrationResult, StoreMigrationResult};
+pub use recall::{RecallRequest, RecallResult, RecalledMemory};
+pub use rule_files::{RuleExportResult, RuleFileFormat, RuleImportResult};
+pub use scopes::{MemoryScope, OverriddenMemory, ScopedMemory};
+pub use shared::{
+    SharedConflict, SharedConflictResolution, SharedExportResult, SharedPullPreview,
+    SharedPullRequest, SharedPullResult,
+};
 pub use similarity::TextSimilarity;
-pub use types::{MemoryCategory, MemoryConfig, MemoryEntry, MemoryMetadata, MemoryStore};
+pub use types::{
+    LifecycleUpdate, MemoryCategory, MemoryConfig, MemoryEntry, MemoryLifecycle, MemoryMetadata,
+    MemorySource, MemoryStore,
+};
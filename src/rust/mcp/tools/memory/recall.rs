@@ -0,0 +1,483 @@
+//! 按任务检索记忆（回忆）
+//!
+//! 旧版「回忆」直接返回按分类拼接的全部记忆，记忆数以百计时会挤占调用方上下文。
+//! 本模块按当前任务描述为记忆排序：
+//! - BM25：在 `content_normalized` 上计算，英文按单词、中文按相邻二字切分
+//! - `TextSimilarity::calculate_enhanced`：补充改写、子串包含等字符级信号
+//!
+//! 固定（pinned）的记忆不受分类、条数与 token 预算限制，总是返回。
+//! 输入为各作用域合并后的记忆（见 `scopes`），结果标注来源作用域与被覆盖的条目。
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use serde::Serialize;
+
+use super::scopes::{scope_display, OverriddenMemory, ScopedMemory, SCOPE_PROJECT};
+use super::similarity::TextSimilarity;
+use super::types::{MemoryCategory, MemoryEntry};
+
+/// 未指定 limit 时最多返回的非固定记忆条数
+pub const DEFAULT_RECALL_LIMIT: usize = 20;
+/// 未指定 token_budget 时的输出预算（估算 token）
+pub const DEFAULT_RECALL_TOKEN_BUDGET: usize = 1500;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+/// 综合得分中 BM25（按本次最高分归一化）的权重，其余为文本相似度
+const BM25_WEIGHT: f64 = 0.6;
+/// BM25 无命中时，文本相似度至少达到该值才视为相关
+const MIN_SIMILARITY_WITHOUT_TERMS: f64 = 0.5;
+
+/// 回忆请求
+#[derive(Debug, Clone, Default)]
+pub struct RecallRequest {
+    /// 当前任务描述；为空时按分类优先级与更新时间排序
+    pub query: Option<String>,
+    /// 分类过滤；为空表示全部分类
+    pub categories: Vec<MemoryCategory>,
+    pub limit: usize,
+    pub token_budget: usize,
+}
+
+/// 单条回忆结果
+#[derive(Debug, Clone, Serialize)]
+pub struct RecalledMemory {
+    pub id: String,
+    pub content: String,
+    pub category: MemoryCategory,
+    pub pinned: bool,
+    /// 与 query 的综合相关度；无 query 或固定记忆时为空
+    pub score: Option<f64>,
+    /// 来源作用域：global | team:<名称> | project | dir:<相对路径>
+    pub scope: String,
+    /// 被本条覆盖的低优先级作用域记忆
+    pub overrides: Vec<OverriddenMemory>,
+}
+
+/// 回忆结果，`omitted_*` 合计为未返回的条数
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecallResult {
+    pub total: usize,
+    pub entries: Vec<RecalledMemory>,
+    /// 被分类过滤排除
+    pub omitted_by_category: usize,
+    /// 与 query 不相关
+    pub omitted_irrelevant: usize,
+    /// 超出 limit 或 token 预算
+    pub omitted_by_budget: usize,
+    pub estimated_tokens: usize,
+}
+
+impl RecallResult {
+    pub fn omitted(&self) -> usize {
+        self.omitted_by_category + self.omitted_irrelevant + self.omitted_by_budget
+    }
+}
+
+/// 按请求筛选并排序记忆；`memories` 为各作用域合并后的结果（见 `scopes::merge_layers`）
+pub fn recall(memories: &[ScopedMemory], request: &RecallRequest) -> RecallResult {
+    recall_with_hits(memories, request, None)
+}
+
+/// 同 [`recall`]；`hits` 为全文索引（见 `sqlite_store::search`）命中的记忆 id，
+/// 有 query 时只对命中的记忆计算得分，其余计入 `omitted_irrelevant`
+pub fn recall_with_hits(
+    memories: &[ScopedMemory],
+    request: &RecallRequest,
+    hits: Option<&HashSet<String>>,
+) -> RecallResult {
+    let mut result = RecallResult {
+        total: memories.len(),
+        ..Default::default()
+    };
+
+    let (pinned, others): (Vec<&ScopedMemory>, Vec<&ScopedMemory>) =
+        memories.iter().partition(|memory| memory.entry.pinned);
+    for memory in pinned {
+        result.estimated_tokens += estimate_tokens(&memory.entry.content);
+        result.entries.push(to_recalled(memory, None));
+    }
+
+    let candidates = others
+        .into_iter()
+        .filter(|memory| {
+            request.categories.is_empty() || request.categories.contains(&memory.entry.category)
+        })
+        .collect::<Vec<_>>();
+    result.omitted_by_category = memories.len() - result.entries.len() - candidates.len();
+
+    let query = request
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|query| !query.is_empty());
+    let ranked = match query {
+        Some(query) => {
+            let scored = candidates
+                .iter()
+                .copied()
+                .filter(|memory| hits.is_none_or(|hits| hits.contains(&memory.entry.id)))
+                .collect::<Vec<_>>();
+            let ranked = rank_by_query(query, &scored);
+            result.omitted_irrelevant = candidates.len() - ranked.len();
+            ranked
+        }
+        None => rank_by_default(&candidates),
+    };
+
+    let mut included = 0usize;
+    for (memory, score) in ranked {
+        let tokens = estimate_tokens(&memory.entry.content);
+        // 预算按条整体取舍，不截断单条记忆；至少返回一条，避免预算过小时结果为空
+        let over_budget = included > 0 && result.estimated_tokens + tokens > request.token_budget;
+        if included >= request.limit || over_budget {
+            result.omitted_by_budget += 1;
+            continue;
+        }
+        included += 1;
+        result.estimated_tokens += tokens;
+        result.entries.push(to_recalled(memory, score));
+    }
+    result
+}
+
+/// 仅含项目作用域的回忆，供单一记忆库直接调用；已归档或已过期的条目不参与
+pub fn recall_entries(entries: &[MemoryEntry], request: &RecallRequest) -> RecallResult {
+    let now = Utc::now();
+    let memories = entries
+        .iter()
+        .filter(|entry| entry.is_active(now))
+        .cloned()
+        .map(|entry| ScopedMemory::new(entry, SCOPE_PROJECT))
+        .collect::<Vec<_>>();
+    recall(&memories, request)
+}
+
+fn to_recalled(memory: &ScopedMemory, score: Option<f64>) -> RecalledMemory {
+    let entry = &memory.entry;
+    RecalledMemory {
+        id: entry.id.clone(),
+        content: entry
+            .content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        category: entry.category,
+        pinned: entry.pinned,
+        score,
+        scope: memory.scope.clone(),
+        overrides: memory.overrides.clone(),
+    }
+}
+
+/// 无 query：规范 > 偏好 > 模式 > 背景，同类按更新时间倒序
+fn rank_by_default<'a>(candidates: &[&'a ScopedMemory]) -> Vec<(&'a ScopedMemory, Option<f64>)> {
+    let mut ranked = candidates.to_vec();
+    ranked.sort_by(|left, right| {
+        category_priority(left.entry.category)
+            .cmp(&category_priority(right.entry.category))
+            .then(right.entry.updated_at.cmp(&left.entry.updated_at))
+    });
+    ranked.into_iter().map(|entry| (entry, None)).collect()
+}
+
+fn category_priority(category: MemoryCategory) -> u8 {
+    match category {
+        MemoryCategory::Rule => 0,
+        MemoryCategory::Preference => 1,
+        MemoryCategory::Pattern => 2,
+        MemoryCategory::Context => 3,
+    }
+}
+
+fn rank_by_query<'a>(
+    query: &str,
+    candidates: &[&'a ScopedMemory],
+) -> Vec<(&'a ScopedMemory, Option<f64>)> {
+    let documents = candidates
+        .iter()
+        .map(|memory| tokenize(&normalized_content(&memory.entry)))
+        .collect::<Vec<_>>();
+    let bm25 = bm25_scores(&tokenize(&TextSimilarity::normalize(query)), &documents);
+    let max_bm25 = bm25.iter().copied().fold(0.0_f64, f64::max);
+
+    let mut ranked = candidates
+        .iter()
+        .zip(bm25)
+        .filter_map(|(memory, bm25)| {
+            let similarity = TextSimilarity::calculate_enhanced(query, &memory.entry.content);
+            if bm25 <= 0.0 && similarity < MIN_SIMILARITY_WITHOUT_TERMS {
+                return None;
+            }
+            let lexical = if max_bm25 > 0.0 { bm25 / max_bm25 } else { 0.0 };
+            let score = BM25_WEIGHT * lexical + (1.0 - BM25_WEIGHT) * similarity;
+            Some((*memory, Some(score)))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|left, right| {
+        right
+            .1
+            .partial_cmp(&left.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(right.0.entry.updated_at.cmp(&left.0.entry.updated_at))
+    });
+    ranked
+}
+
+fn normalized_content(entry: &MemoryEntry) -> String {
+    if entry.content_normalized.is_empty() {
+        TextSimilarity::normalize(&entry.content)
+    } else {
+        entry.content_normalized.clone()
+    }
+}
+
+/// 英文、数字按连续字符切词（至少 2 个字符），中文按相邻二字切分，单个汉字保留为一元词
+pub(crate) fn tokenize(normalized: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk_run: Vec<char> = Vec::new();
+
+    let flush_word = |word: &mut String, tokens: &mut Vec<String>| {
+        if word.chars().count() >= 2 {
+            tokens.push(std::mem::take(word));
+        } else {
+            word.clear();
+        }
+    };
+    let flush_cjk = |run: &mut Vec<char>, tokens: &mut Vec<String>| {
+        match run.len() {
+            0 => {}
+            1 => tokens.push(run[0].to_string()),
+            _ => tokens.extend(run.windows(2).map(|pair| pair.iter().collect::<String>())),
+        }
+        run.clear();
+    };
+
+    for ch in normalized.chars() {
+        if is_cjk(ch) {
+            flush_word(&mut word, &mut tokens);
+            cjk_run.push(ch);
+        } else if ch.is_alphanumeric() || ch == '_' {
+            flush_cjk(&mut cjk_run, &mut tokens);
+            word.extend(ch.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_cjk(&mut cjk_run, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_cjk(&mut cjk_run, &mut tokens);
+    tokens
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+fn bm25_scores(query_tokens: &[String], documents: &[Vec<String>]) -> Vec<f64> {
+    if documents.is_empty() {
+        return Vec::new();
+    }
+    let doc_count = documents.len() as f64;
+    let avgdl = documents.iter().map(Vec::len).sum::<usize>() as f64 / doc_count;
+    let mut doc_freqs: HashMap<&str, usize> = HashMap::new();
+    for document in documents {
+        for token in document.iter().map(String::as_str).collect::<HashSet<_>>() {
+            *doc_freqs.entry(token).or_default() += 1;
+        }
+    }
+    let query_terms = query_tokens
+        .iter()
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+
+    documents
+        .iter()
+        .map(|document| {
+            if avgdl == 0.0 {
+                return 0.0;
+            }
+            let mut term_freqs: HashMap<&str, usize> = HashMap::new();
+            for token in document {
+                *term_freqs.entry(token.as_str()).or_default() += 1;
+            }
+            let doc_len = document.len() as f64;
+            query_terms
+                .iter()
+                .filter_map(|term| {
+                    let tf = *term_freqs.get(term)? as f64;
+                    let df = *doc_freqs.get(term)? as f64;
+                    let idf = ((doc_count - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    let denominator = tf + BM25_K1 * (1.0 - BM25_B + BM25_B * doc_len / avgdl);
+                    Some(idf * tf * (BM25_K1 + 1.0) / denominator)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// 粗略估算 token：汉字按 1 个计，其余字符按 4 个计 1 个
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut cjk = 0usize;
+    let mut other = 0usize;
+    for ch in text.chars() {
+        if is_cjk(ch) {
+            cjk += 1;
+        } else {
+            other += 1;
+        }
+    }
+    cjk + other.div_ceil(4)
+}
+
+/// 供 MCP 调用方阅读的文本格式
+pub fn format_recall(result: &RecallResult, has_query: bool) -> String {
+    if result.total == 0 {
+        return "📭 暂无项目记忆".to_string();
+    }
+    if result.entries.is_empty() {
+        return format!(
+            "📭 没有与当前任务相关的记忆（共 {} 条，已全部省略）\n💡 可调整 query / categories，或使用「列表」查看全部记忆",
+            result.total
+        );
+    }
+
+    let mut lines = vec![format!(
+        "📚 项目记忆（{}，返回 {} / {} 条，约 {} tokens）",
+        if has_query {
+            "按与当前任务的相关度排序"
+        } else {
+            "按分类与更新时间排序"
+        },
+        result.entries.len(),
+        result.total,
+        result.estimated_tokens
+    )];
+    for entry in &result.entries {
+        // 项目作用域为默认来源，不额外标注
+        let scope = if entry.scope == SCOPE_PROJECT {
+            String::new()
+        } else {
+            format!("·{}", scope_display(&entry.scope))
+        };
+        lines.push(format!(
+            "- {}[{}{}] {}",
+            if entry.pinned { "📌" } else { "" },
+            entry.category.display_name(),
+            scope,
+            entry.content
+        ));
+        for overridden in &entry.overrides {
+            lines.push(format!(
+                "  ⚠️ 覆盖{}：{}",
+                scope_display(&overridden.scope),
+                overridden.content
+            ));
+        }
+    }
+    let omitted = result.omitted();
+    if omitted > 0 {
+        lines.push(format!(
+            "💡 已省略 {} 条（分类过滤 {}，不相关 {}，超出条数或预算 {}）。可调整 query / categories / limit / token_budget，或使用「列表」查看全部",
+            omitted,
+            result.omitted_by_category,
+            result.omitted_irrelevant,
+            result.omitted_by_budget
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::types::test_entry;
+
+    fn entry(id: &str, content: &str, category: MemoryCategory, pinned: bool) -> MemoryEntry {
+        MemoryEntry {
+            category,
+            pinned,
+            ..test_entry(id, content)
+        }
+    }
+
+    fn request(query: &str, limit: usize) -> RecallRequest {
+        RecallRequest {
+            query: Some(query.to_string()),
+            categories: Vec::new(),
+            limit,
+            token_budget: DEFAULT_RECALL_TOKEN_BUDGET,
+        }
+    }
+
+    #[test]
+    fn tokenize_splits_ascii_words_and_cjk_bigrams() {
+        assert_eq!(
+            tokenize(&TextSimilarity::normalize("使用 pnpm 安装依赖")),
+            vec!["使用", "pnpm", "安装", "装依", "依赖"]
+        );
+    }
+
+    #[test]
+    fn recall_ranks_relevant_entries_and_keeps_pinned() {
+        let entries = vec![
+            entry(
+                "a",
+                "数据库迁移必须使用 sqlx migrate",
+                MemoryCategory::Rule,
+                false,
+            ),
+            entry(
+                "b",
+                "前端组件统一使用 Naive UI",
+                MemoryCategory::Pattern,
+                false,
+            ),
+            entry("c", "提交信息使用中文", MemoryCategory::Preference, false),
+            entry(
+                "d",
+                "不要运行 cargo build，用户自己编译",
+                MemoryCategory::Rule,
+                true,
+            ),
+        ];
+        let result = recall_entries(&entries, &request("新增数据库迁移脚本", 5));
+        let ids = result
+            .entries
+            .iter()
+            .map(|entry| entry.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["d", "a"]);
+        assert_eq!(result.omitted_irrelevant, 2);
+        assert_eq!(result.omitted(), 2);
+    }
+
+    #[test]
+    fn recall_applies_category_filter_and_limit() {
+        let entries = vec![
+            entry(
+                "a",
+                "规则一：日志使用 log_important",
+                MemoryCategory::Rule,
+                false,
+            ),
+            entry("b", "规则二：错误使用 anyhow", MemoryCategory::Rule, false),
+            entry("c", "偏好：回复使用中文", MemoryCategory::Preference, false),
+        ];
+        let result = recall_entries(
+            &entries,
+            &RecallRequest {
+                query: None,
+                categories: vec![MemoryCategory::Rule],
+                limit: 1,
+                token_budget: DEFAULT_RECALL_TOKEN_BUDGET,
+            },
+        );
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.omitted_by_category, 1);
+        assert_eq!(result.omitted_by_budget, 1);
+        assert!(format_recall(&result, false).contains("已省略 2 条"));
+    }
+}
@@ -0,0 +1,695 @@
+//! 代理规则文件的导入与导出
+//!
+//! 仓库里常见的代理指令文件（AGENTS.md、CLAUDE.md、.cursorrules 等）和记忆库互相转换：
+//! - 导入：解析 Markdown 列表项（没有列表时按段落），按标题和关键词推断分类
+//! - 导出：把记忆渲染进这些文件中由标记包围的区块，区块外用户手写的内容保持不变
+//!
+//! 导出区块在导入时跳过，避免同一份记忆来回导入。
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+
+use super::types::{MemoryCategory, MemoryEntry};
+
+pub const EXPORT_START: &str = "<!-- sanshu-memory:start -->";
+pub const EXPORT_END: &str = "<!-- sanshu-memory:end -->";
+
+/// 支持的规则文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleFileFormat {
+    /// AGENTS.md
+    Agents,
+    /// CLAUDE.md
+    Claude,
+    /// GEMINI.md
+    Gemini,
+    /// .cursorrules
+    CursorRules,
+    /// .cursor/rules/*.mdc（带 YAML front matter）
+    CursorMdc,
+    /// .github/copilot-instructions.md
+    Copilot,
+    /// .windsurfrules
+    Windsurf,
+    /// 其他 Markdown 文件
+    Markdown,
+}
+
+impl RuleFileFormat {
+    /// 未指定文件时，导入会查找、导出缺省写入的已知格式
+    pub const KNOWN: [Self; 7] = [
+        Self::Agents,
+        Self::Claude,
+        Self::Gemini,
+        Self::CursorRules,
+        Self::CursorMdc,
+        Self::Copilot,
+        Self::Windsurf,
+    ];
+
+    /// 导出时的默认路径（相对项目根目录）
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            Self::Agents => "AGENTS.md",
+            Self::Claude => "CLAUDE.md",
+            Self::Gemini => "GEMINI.md",
+            Self::CursorRules => ".cursorrules",
+            Self::CursorMdc => ".cursor/rules/sanshu-memory.mdc",
+            Self::Copilot => ".github/copilot-instructions.md",
+            Self::Windsurf => ".windsurfrules",
+            Self::Markdown => "MEMORY.md",
+        }
+    }
+
+    /// 按格式名解析（agents | claude | gemini | cursorrules | cursor | copilot | windsurf）
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "agents" | "agents.md" => Some(Self::Agents),
+            "claude" | "claude.md" => Some(Self::Claude),
+            "gemini" | "gemini.md" => Some(Self::Gemini),
+            "cursorrules" | ".cursorrules" => Some(Self::CursorRules),
+            "cursor" | "mdc" => Some(Self::CursorMdc),
+            "copilot" => Some(Self::Copilot),
+            "windsurf" | "windsurfrules" | ".windsurfrules" => Some(Self::Windsurf),
+            _ => None,
+        }
+    }
+
+    /// 按文件路径识别格式，无法识别时视为普通 Markdown
+    pub fn detect(path: &Path) -> Self {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match file_name.as_str() {
+            "agents.md" => Self::Agents,
+            "claude.md" | "claude.local.md" => Self::Claude,
+            "gemini.md" => Self::Gemini,
+            ".cursorrules" => Self::CursorRules,
+            "copilot-instructions.md" => Self::Copilot,
+            ".windsurfrules" => Self::Windsurf,
+            name if name.ends_with(".mdc") => Self::CursorMdc,
+            _ => Self::Markdown,
+        }
+    }
+}
+
+/// 从规则文件解析出的一条记忆
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedRule {
+    pub content: String,
+    pub category: MemoryCategory,
+    /// 所在标题，用于推断分类
+    pub heading: Option<String>,
+}
+
+/// 单个文件的导入统计
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleFileImport {
+    pub path: String,
+    pub format: RuleFileFormat,
+    pub parsed_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedRule {
+    pub id: String,
+    pub content: String,
+    pub category: String,
+    pub source_file: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedRule {
+    pub content: String,
+    pub source_file: String,
+    /// duplicate（与已有记忆重复）| conflict（与已有记忆矛盾）
+    pub reason: String,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuleImportResult {
+    pub files: Vec<RuleFileImport>,
+    pub added: Vec<ImportedRule>,
+    /// 合并进已有同类记忆的条目
+    pub updated: Vec<ImportedRule>,
+    pub skipped: Vec<SkippedRule>,
+    pub backup_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleFileExport {
+    pub path: String,
+    pub format: RuleFileFormat,
+    pub entry_count: usize,
+    /// created（新建文件）| updated（替换导出区块）| appended（在已有文件末尾追加区块）| unchanged
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RuleExportResult {
+    pub files: Vec<RuleFileExport>,
+}
+
+/// 把用户给出的相对路径解析到项目根目录下；拒绝绝对路径、`..` 以及经符号链接逃出项目根目录的路径
+pub fn resolve_rule_path(project_root: &Path, relative: &str) -> Result<PathBuf> {
+    let relative = Path::new(relative.trim());
+    if relative.as_os_str().is_empty()
+        || relative.is_absolute()
+        || relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(anyhow::anyhow!(
+            "规则文件路径必须是项目根目录下的相对路径: {}",
+            relative.display()
+        ));
+    }
+    let path = project_root.join(relative);
+
+    // 目标文件可能尚不存在：解析最深的已存在祖先（含悬空符号链接本身），再确认仍在项目根目录下
+    let root = project_root
+        .canonicalize()
+        .with_context(|| format!("无法解析项目根目录: {}", project_root.display()))?;
+    let mut existing = path.as_path();
+    while fs::symlink_metadata(existing).is_err() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => break,
+        }
+    }
+    let resolved = existing
+        .canonicalize()
+        .with_context(|| format!("无法解析规则文件路径: {}", existing.display()))?;
+    if !resolved.starts_with(&root) {
+        return Err(anyhow::anyhow!(
+            "规则文件路径经符号链接指向项目根目录之外: {}",
+            relative.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// 项目根目录下已存在的已知规则文件；`.cursor/rules` 下的每个 `.mdc` 单独列出
+pub fn discover_rule_files(project_root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for format in RuleFileFormat::KNOWN {
+        if format == RuleFileFormat::CursorMdc {
+            let Ok(entries) = fs::read_dir(project_root.join(".cursor").join("rules")) else {
+                continue;
+            };
+            let mut mdc_files = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "mdc"))
+                .collect::<Vec<_>>();
+            mdc_files.sort();
+            files.extend(mdc_files);
+        } else {
+            let path = project_root.join(format.default_path());
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// 解析规则文件内容
+///
+/// - 跳过 YAML front matter、代码块、HTML 注释行和导出区块
+/// - 列表项（`-`、`*`、`+`、`1.`）各为一条，缩进的续行并入上一条
+/// - 整个文件没有列表项时（如纯文本的 `.cursorrules`），每个段落为一条
+pub fn parse_rule_file(content: &str) -> Vec<ParsedRule> {
+    let mut items: Vec<(Option<String>, String)> = Vec::new();
+    let mut paragraphs: Vec<(Option<String>, String)> = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut in_code = false;
+    let mut in_export = false;
+    let mut current_item: Option<String> = None;
+    let mut current_paragraph: Option<String> = None;
+
+    let mut lines = content.lines().peekable();
+    if lines.peek().map(|line| line.trim()) == Some("---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+        }
+    }
+
+    let flush = |target: &mut Vec<(Option<String>, String)>,
+                 current: &mut Option<String>,
+                 heading: &Option<String>| {
+        if let Some(text) = current.take() {
+            target.push((heading.clone(), text));
+        }
+    };
+
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed == EXPORT_START {
+            in_export = true;
+            continue;
+        }
+        if trimmed == EXPORT_END {
+            in_export = false;
+            continue;
+        }
+        if in_export {
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            flush(&mut items, &mut current_item, &heading);
+            flush(&mut paragraphs, &mut current_paragraph, &heading);
+            continue;
+        }
+        if in_code || (trimmed.starts_with("<!--") && trimmed.ends_with("-->")) {
+            continue;
+        }
+
+        if let Some(title) = trimmed.strip_prefix('#') {
+            flush(&mut items, &mut current_item, &heading);
+            flush(&mut paragraphs, &mut current_paragraph, &heading);
+            heading = Some(title.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut items, &mut current_item, &heading);
+            flush(&mut paragraphs, &mut current_paragraph, &heading);
+            continue;
+        }
+        if let Some(text) = strip_list_marker(trimmed) {
+            flush(&mut items, &mut current_item, &heading);
+            flush(&mut paragraphs, &mut current_paragraph, &heading);
+            current_item = Some(text.to_string());
+            continue;
+        }
+        // 缩进的续行并入上一条列表项，其余归入段落
+        match current_item.as_mut() {
+            Some(item) if line.starts_with(' ') || line.starts_with('\t') => {
+                item.push(' ');
+                item.push_str(trimmed);
+            }
+            _ => {
+                flush(&mut items, &mut current_item, &heading);
+                match current_paragraph.as_mut() {
+                    Some(paragraph) => {
+                        paragraph.push(' ');
+                        paragraph.push_str(trimmed);
+                    }
+                    None => current_paragraph = Some(trimmed.to_string()),
+                }
+            }
+        }
+    }
+    flush(&mut items, &mut current_item, &heading);
+    flush(&mut paragraphs, &mut current_paragraph, &heading);
+
+    let source = if items.is_empty() { paragraphs } else { items };
+    source
+        .into_iter()
+        .map(|(heading, text)| (heading, clean_text(&text)))
+        .filter(|(_, text)| text.chars().count() >= 2)
+        .map(|(heading, content)| ParsedRule {
+            category: infer_category(heading.as_deref(), &content),
+            content,
+            heading,
+        })
+        .collect()
+}
+
+fn strip_list_marker(line: &str) -> Option<&str> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return Some(rest.trim());
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some(rest.trim());
+        }
+    }
+    None
+}
+
+/// 去掉任务框与整句加粗，保留行内代码
+fn clean_text(text: &str) -> String {
+    let mut text = text.trim();
+    for checkbox in ["[ ] ", "[x] ", "[X] "] {
+        if let Some(rest) = text.strip_prefix(checkbox) {
+            text = rest.trim();
+        }
+    }
+    if text.len() > 4 && text.starts_with("**") && text.ends_with("**") {
+        text = &text[2..text.len() - 2];
+    }
+    text.trim().to_string()
+}
+
+/// 分类关键词，按优先级排列
+const CATEGORY_KEYWORDS: &[(MemoryCategory, &[&str])] = &[
+    (
+        MemoryCategory::Context,
+        &[
+            "背景",
+            "上下文",
+            "概述",
+            "简介",
+            "技术栈",
+            "环境",
+            "目录结构",
+            "context",
+            "overview",
+            "about",
+            "background",
+            "stack",
+            "environment",
+            "structure",
+            "setup",
+        ],
+    ),
+    (
+        MemoryCategory::Preference,
+        &[
+            "偏好",
+            "风格",
+            "习惯",
+            "沟通",
+            "语气",
+            "喜欢",
+            "倾向",
+            "preference",
+            "prefer",
+            "style",
+            "tone",
+            "communication",
+        ],
+    ),
+    (
+        MemoryCategory::Pattern,
+        &[
+            "模式",
+            "最佳实践",
+            "架构",
+            "流程",
+            "示例",
+            "pattern",
+            "best practice",
+            "architecture",
+            "workflow",
+            "example",
+        ],
+    ),
+    (
+        MemoryCategory::Rule,
+        &[
+            "规范",
+            "规则",
+            "约定",
+            "要求",
+            "必须",
+            "禁止",
+            "不要",
+            "不得",
+            "rule",
+            "convention",
+            "guideline",
+            "must",
+            "never",
+            "always",
+            "don't",
+            "do not",
+        ],
+    ),
+];
+
+/// 先按所在标题推断分类，标题没有线索时按条目内容，都没有时视为规范
+pub fn infer_category(heading: Option<&str>, content: &str) -> MemoryCategory {
+    let matches = |text: &str| {
+        let lower = text.to_lowercase();
+        CATEGORY_KEYWORDS
+            .iter()
+            .find(|(_, keywords)| keywords.iter().any(|keyword| lower.contains(keyword)))
+            .map(|(category, _)| *category)
+    };
+    heading
+        .and_then(matches)
+        .or_else(|| {
+            // 条目内容中的约束性措辞优先于描述性措辞
+            let lower = content.to_lowercase();
+            CATEGORY_KEYWORDS
+                .iter()
+                .rev()
+                .find(|(_, keywords)| keywords.iter().any(|keyword| lower.contains(keyword)))
+                .map(|(category, _)| *category)
+        })
+        .unwrap_or(MemoryCategory::Rule)
+}
+
+/// 渲染导出区块：按分类分节，固定的记忆排在前面
+pub fn render_export_block(entries: &[&MemoryEntry]) -> String {
+    let mut block = String::new();
+    block.push_str(EXPORT_START);
+    block.push('\n');
+    block.push_str("<!-- 由三术记忆导出生成，请通过 ji 修改，手动改动会在下次导出时被覆盖 -->\n");
+    for category in [
+        MemoryCategory::Rule,
+        MemoryCategory::Preference,
+        MemoryCategory::Pattern,
+        MemoryCategory::Context,
+    ] {
+        let mut section = entries
+            .iter()
+            .filter(|entry| entry.category == category)
+            .collect::<Vec<_>>();
+        if section.is_empty() {
+            continue;
+        }
+        section.sort_by(|left, right| {
+            right
+                .pinned
+                .cmp(&left.pinned)
+                .then(left.created_at.cmp(&right.created_at))
+        });
+        block.push_str(&format!("\n## {}\n\n", category.display_name()));
+        for entry in section {
+            let content = entry
+                .content
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            block.push_str(&format!("- {}\n", content));
+        }
+    }
+    block.push_str(EXPORT_END);
+    block.push('\n');
+    block
+}
+
+/// 把导出区块写入已有内容：替换原区块，没有区块时追加到末尾；返回新内容与状态
+pub fn merge_export_block(
+    existing: Option<&str>,
+    block: &str,
+    format: RuleFileFormat,
+) -> Result<(String, &'static str)> {
+    let Some(existing) = existing else {
+        let mut content = String::new();
+        if format == RuleFileFormat::CursorMdc {
+            content.push_str("---\ndescription: 三术项目记忆\nalwaysApply: true\n---\n\n");
+        }
+        content.push_str("# 项目记忆\n\n");
+        content.push_str(block);
+        return Ok((content, "created"));
+    };
+
+    let Some(start) = existing.find(EXPORT_START) else {
+        let mut content = existing.trim_end().to_string();
+        content.push_str("\n\n");
+        content.push_str(block);
+        return Ok((content, "appended"));
+    };
+
+    // 开始标记之后、下一个开始标记（或文件末尾）之前必须有结束标记，否则拒绝写入，避免再追加一个区块
+    let body_start = start + EXPORT_START.len();
+    let next_start = existing[body_start..]
+        .find(EXPORT_START)
+        .map_or(existing.len(), |offset| body_start + offset);
+    let end = existing[body_start..next_start]
+        .find(EXPORT_END)
+        .map(|offset| body_start + offset + EXPORT_END.len())
+        .ok_or_else(|| anyhow::anyhow!("规则文件中的导出区块缺少结束标记 {}", EXPORT_END))?;
+    let end = if existing[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    };
+    let content = format!("{}{}{}", &existing[..start], block, &existing[end..]);
+    if content == existing {
+        Ok((content, "unchanged"))
+    } else {
+        Ok((content, "updated"))
+    }
+}
+
+/// 写入导出内容，没有变化时不触碰文件
+pub fn write_export(
+    path: &Path,
+    entries: &[&MemoryEntry],
+    format: RuleFileFormat,
+) -> Result<&'static str> {
+    let existing = if path.exists() {
+        Some(
+            fs::read_to_string(path)
+                .with_context(|| format!("读取规则文件失败: {}", path.display()))?,
+        )
+    } else {
+        None
+    };
+    let block = render_export_block(entries);
+    let (content, status) = merge_export_block(existing.as_deref(), &block, format)?;
+    if status != "unchanged" {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+            .with_context(|| format!("写入规则文件失败: {}", path.display()))?;
+    }
+    Ok(status)
+}
+
+/// 导出的记忆：未归档、未过期
+pub fn exportable_entries(entries: &[MemoryEntry]) -> Vec<&MemoryEntry> {
+    let now = Utc::now();
+    entries
+        .iter()
+        .filter(|entry| entry.is_active(now))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::similarity::TextSimilarity;
+
+    #[test]
+    fn parse_markdown_bullets_with_heading_categories() {
+        let content = r#"# AGENTS
+
+本文件给代理使用。
+
+## 项目概述
+
+- 后端使用 Rust + Tauri
+  前端使用 Vue 3
+
+## Coding conventions
+
+1. 提交前运行 cargo fmt
+2. **不要修改 Cargo.lock**
+
+```bash
+- 这是代码块里的内容
+```
+
+<!-- sanshu-memory:start -->
+- 已导出的记忆
+<!-- sanshu-memory:end -->
+"#;
+        let rules = parse_rule_file(content);
+        let summary = rules
+            .iter()
+            .map(|rule| (rule.content.as_str(), rule.category))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "后端使用 Rust + Tauri 前端使用 Vue 3",
+                    MemoryCategory::Context
+                ),
+                ("提交前运行 cargo fmt", MemoryCategory::Rule),
+                ("不要修改 Cargo.lock", MemoryCategory::Rule),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_plain_text_falls_back_to_paragraphs() {
+        let content = "---\ndescription: x\n---\nAlways answer in Chinese.\n\nPrefer composition API\nover options API.\n";
+        let rules = parse_rule_file(content);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].content, "Always answer in Chinese.");
+        assert_eq!(rules[0].category, MemoryCategory::Rule);
+        assert_eq!(rules[1].content, "Prefer composition API over options API.");
+        assert_eq!(rules[1].category, MemoryCategory::Preference);
+    }
+
+    #[test]
+    fn export_block_replaces_only_managed_section() {
+        let entry = MemoryEntry {
+            id: "a".to_string(),
+            content: "提交信息使用中文".to_string(),
+            content_normalized: TextSimilarity::normalize("提交信息使用中文"),
+            category: MemoryCategory::Preference,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            pinned: false,
+            lifecycle: Default::default(),
+        };
+        let block = render_export_block(&[&entry]);
+        let (created, status) = merge_export_block(None, &block, RuleFileFormat::Agents).unwrap();
+        assert_eq!(status, "created");
+        assert!(created.contains("## 偏好\n\n- 提交信息使用中文\n"));
+
+        let handwritten = format!("# 手写说明\n\n- 保留我\n\n{}\n尾部说明\n", block);
+        let (merged, status) =
+            merge_export_block(Some(&handwritten), &block, RuleFileFormat::Agents).unwrap();
+        assert_eq!(status, "unchanged");
+        assert_eq!(merged, handwritten);
+
+        let (appended, status) = merge_export_block(
+            Some("# 手写说明\n- 保留我\n"),
+            &block,
+            RuleFileFormat::Agents,
+        )
+        .unwrap();
+        assert_eq!(status, "appended");
+        assert!(appended.starts_with("# 手写说明\n- 保留我\n\n<!-- sanshu-memory:start -->"));
+        assert!(parse_rule_file(&appended)
+            .iter()
+            .all(|rule| rule.content == "保留我"));
+
+        let unterminated = format!("# 手写说明\n\n{}\n- 旧条目\n", EXPORT_START);
+        assert!(merge_export_block(Some(&unterminated), &block, RuleFileFormat::Agents).is_err());
+        let nested = format!("{}\n- 旧条目\n{}", EXPORT_START, block);
+        assert!(merge_export_block(Some(&nested), &block, RuleFileFormat::Agents).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rule_paths_must_not_escape_through_symlinks() {
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), project.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("AGENTS.md"),
+            project.path().join("AGENTS.md"),
+        )
+        .unwrap();
+
+        assert!(resolve_rule_path(project.path(), "linked/AGENTS.md").is_err());
+        assert!(resolve_rule_path(project.path(), "AGENTS.md").is_err());
+        let nested = resolve_rule_path(project.path(), ".cursor/rules/memory.mdc").unwrap();
+        assert_eq!(nested, project.path().join(".cursor/rules/memory.mdc"));
+    }
+}
@@ -0,0 +1,379 @@
+//! 记忆分层作用域
+//!
+//! 记忆按作用域分层存放，回忆时合并，越具体的作用域优先：
+//! 子目录 > 项目 > 团队 > 全局
+//!
+//! - 全局：`<配置目录>/sanshu/memory`，存放与项目无关的个人偏好（如「回复使用中文」）
+//! - 团队：`memory_team_stores` 配置的共享目录，按配置顺序排列
+//! - 项目：git 根目录下的 `.sanshu-memory`（原有行为）
+//! - 子目录：monorepo 子包目录下的 `.sanshu-memory`，越深越优先
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::similarity::TextSimilarity;
+use super::sqlite_store;
+use super::types::{MemoryEntry, MemoryStore};
+use crate::config::MemoryTeamStore;
+use crate::log_debug;
+
+pub const SCOPE_GLOBAL: &str = "global";
+pub const SCOPE_PROJECT: &str = "project";
+const TEAM_PREFIX: &str = "team:";
+const DIRECTORY_PREFIX: &str = "dir:";
+
+/// 写入记忆时选择的作用域
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryScope {
+    Global,
+    /// 团队名；只配置了一个团队库时可省略
+    Team(Option<String>),
+    Project,
+    /// 以请求中的 `project_path` 作为子目录
+    Directory,
+}
+
+impl MemoryScope {
+    /// 解析 `scope` 参数，缺省为项目作用域
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        let value = value.map(str::trim).unwrap_or_default();
+        // 前缀只比较 ASCII，团队名保持原样；to_lowercase 可能改变字节长度，不能用于切片
+        if value
+            .get(..TEAM_PREFIX.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(TEAM_PREFIX))
+        {
+            let name = value[TEAM_PREFIX.len()..].trim();
+            return Ok(Self::Team(
+                Some(name.to_string()).filter(|name| !name.is_empty()),
+            ));
+        }
+        match value.to_lowercase().as_str() {
+            "" | "project" | "项目" => Ok(Self::Project),
+            "global" | "user" | "全局" | "用户" => Ok(Self::Global),
+            "team" | "团队" => Ok(Self::Team(None)),
+            "directory" | "dir" | "目录" | "子目录" => Ok(Self::Directory),
+            _ => Err(anyhow::anyhow!(
+                "未知的记忆作用域: {}。支持: global | team | team:<名称> | project | directory",
+                value
+            )),
+        }
+    }
+}
+
+/// 合并后带作用域标注的记忆
+#[derive(Debug, Clone)]
+pub struct ScopedMemory {
+    pub entry: MemoryEntry,
+    /// global | team:<名称> | project | dir:<相对路径>
+    pub scope: String,
+    /// 被本条覆盖的低优先级作用域中的近似记忆
+    pub overrides: Vec<OverriddenMemory>,
+}
+
+impl ScopedMemory {
+    pub fn new(entry: MemoryEntry, scope: impl Into<String>) -> Self {
+        Self {
+            entry,
+            scope: scope.into(),
+            overrides: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverriddenMemory {
+    pub scope: String,
+    pub id: String,
+    pub content: String,
+}
+
+/// 合并结果；`duplicates` 为跨作用域内容完全相同而折叠的条数
+#[derive(Debug, Clone, Default)]
+pub struct MergedMemories {
+    pub memories: Vec<ScopedMemory>,
+    pub duplicates: usize,
+}
+
+/// 作用域的中文显示名
+pub fn scope_display(scope: &str) -> String {
+    if let Some(name) = scope.strip_prefix(TEAM_PREFIX) {
+        return format!("团队 {}", name);
+    }
+    if let Some(dir) = scope.strip_prefix(DIRECTORY_PREFIX) {
+        return format!("目录 {}", dir);
+    }
+    match scope {
+        SCOPE_GLOBAL => "全局".to_string(),
+        SCOPE_PROJECT => "项目".to_string(),
+        other => other.to_string(),
+    }
+}
+
+pub fn team_scope(name: &str) -> String {
+    format!("{}{}", TEAM_PREFIX, name)
+}
+
+pub fn directory_scope(relative: &str) -> String {
+    format!("{}{}", DIRECTORY_PREFIX, relative)
+}
+
+pub fn is_team_scope(scope: &str) -> bool {
+    scope.starts_with(TEAM_PREFIX)
+}
+
+pub fn is_directory_scope(scope: &str) -> bool {
+    scope.starts_with(DIRECTORY_PREFIX)
+}
+
+/// 用户全局记忆目录
+pub fn global_memory_dir() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?
+        .join("sanshu")
+        .join("memory"))
+}
+
+pub fn team_stores() -> Vec<MemoryTeamStore> {
+    crate::config::load_standalone_config()
+        .ok()
+        .and_then(|config| config.mcp_config.memory_team_stores)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|store| !store.name.trim().is_empty() && !store.path.trim().is_empty())
+        .collect()
+}
+
+/// 按名称选择团队库；未指定名称时要求只配置了一个
+pub fn resolve_team_store(name: Option<&str>) -> Result<MemoryTeamStore> {
+    let stores = team_stores();
+    match name {
+        Some(name) => stores
+            .into_iter()
+            .find(|store| store.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("未配置名为 {} 的团队记忆库", name)),
+        None => match stores.len() {
+            0 => Err(anyhow::anyhow!(
+                "未配置团队记忆库，请先在 memory_team_stores 中添加"
+            )),
+            1 => Ok(stores.into_iter().next().expect("len checked")),
+            _ => Err(anyhow::anyhow!(
+                "配置了多个团队记忆库，请使用 team:<名称> 指定: {}",
+                stores
+                    .iter()
+                    .map(|store| store.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        },
+    }
+}
+
+/// 只读加载某一层的记忆；不存在或无法解析时视为空层，不做迁移与去重
+///
+/// 团队层只读取 memories.json（见 `json_store`）；其他层优先读取 SQLite 记忆库，
+/// 尚未迁移的层读取 memories.json
+pub fn read_layer_entries(scope: &str, memory_dir: &Path) -> Vec<MemoryEntry> {
+    if is_team_scope(scope) {
+        return read_json_layer(memory_dir);
+    }
+    match sqlite_store::load_entries(memory_dir) {
+        Ok(Some(entries)) => return entries,
+        Ok(None) => {}
+        Err(error) => {
+            log_debug!(
+                "读取记忆层失败，已跳过: {} ({})",
+                memory_dir.display(),
+                error
+            );
+            return Vec::new();
+        }
+    }
+    read_json_layer(memory_dir)
+}
+
+fn read_json_layer(memory_dir: &Path) -> Vec<MemoryEntry> {
+    let path = memory_dir.join("memories.json");
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<MemoryStore>(&content) {
+        Ok(store) => store.entries,
+        Err(error) => {
+            log_debug!("读取记忆层失败，已跳过: {} ({})", path.display(), error);
+            Vec::new()
+        }
+    }
+}
+
+/// 请求目录到项目根之间（不含项目根）已存在的子目录记忆层，越深越靠前
+pub fn directory_layers(project_root: &Path, requested_dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut layers = Vec::new();
+    if !requested_dir.starts_with(project_root) {
+        return layers;
+    }
+    let mut current = requested_dir;
+    while current != project_root {
+        let memory_dir = current.join(".sanshu-memory");
+        if sqlite_store::exists(&memory_dir) || memory_dir.join("memories.json").is_file() {
+            let relative = current
+                .strip_prefix(project_root)
+                .map(|path| path.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            layers.push((directory_scope(&relative), memory_dir));
+        }
+        match current.parent() {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    layers
+}
+
+/// 按优先级（高到低）合并各层记忆
+///
+/// 低优先级层中与已保留记忆同分类且相似度 ≥ `conflict_threshold` 的条目视为被覆盖：
+/// 内容归一化后完全相同时直接折叠，否则挂到保留条目的 `overrides` 上提示冲突。
+/// 同一层内部不比较（各层写入时已去重）。已归档或已过期的条目不参与合并。
+pub fn merge_layers(
+    layers: Vec<(String, Vec<MemoryEntry>)>,
+    conflict_threshold: f64,
+) -> MergedMemories {
+    let mut merged = MergedMemories::default();
+    let now = Utc::now();
+    for (scope, entries) in layers {
+        let higher_count = merged.memories.len();
+        for entry in entries.into_iter().filter(|entry| entry.is_active(now)) {
+            let normalized = if entry.content_normalized.is_empty() {
+                TextSimilarity::normalize(&entry.content)
+            } else {
+                entry.content_normalized.clone()
+            };
+            let winner = merged.memories[..higher_count]
+                .iter_mut()
+                .filter(|kept| kept.entry.category == entry.category)
+                .map(|kept| {
+                    let similarity =
+                        TextSimilarity::calculate_enhanced(&kept.entry.content, &entry.content);
+                    (kept, similarity)
+                })
+                .filter(|(_, similarity)| *similarity >= conflict_threshold)
+                .max_by(|left, right| {
+                    left.1
+                        .partial_cmp(&right.1)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+            match winner {
+                Some((kept, _)) if TextSimilarity::normalize(&kept.entry.content) == normalized => {
+                    kept.entry.pinned |= entry.pinned;
+                    merged.duplicates += 1;
+                }
+                Some((kept, _)) => kept.overrides.push(OverriddenMemory {
+                    scope: scope.clone(),
+                    id: entry.id.clone(),
+                    content: entry.content.clone(),
+                }),
+                None => merged
+                    .memories
+                    .push(ScopedMemory::new(entry, scope.clone())),
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::types::{test_entry, MemoryCategory};
+
+    fn entry(id: &str, content: &str, category: MemoryCategory) -> MemoryEntry {
+        MemoryEntry {
+            category,
+            ..test_entry(id, content)
+        }
+    }
+
+    #[test]
+    fn parse_scope_accepts_aliases_and_team_names() {
+        assert_eq!(MemoryScope::parse(None).unwrap(), MemoryScope::Project);
+        assert_eq!(
+            MemoryScope::parse(Some("全局")).unwrap(),
+            MemoryScope::Global
+        );
+        assert_eq!(
+            MemoryScope::parse(Some("team:Frontend")).unwrap(),
+            MemoryScope::Team(Some("Frontend".to_string()))
+        );
+        assert_eq!(
+            MemoryScope::parse(Some("dir")).unwrap(),
+            MemoryScope::Directory
+        );
+        assert!(MemoryScope::parse(Some("org")).is_err());
+        // 小写后字节长度变化的团队名不应导致切片越界
+        assert_eq!(
+            MemoryScope::parse(Some("TEAM:İstanbul")).unwrap(),
+            MemoryScope::Team(Some("İstanbul".to_string()))
+        );
+        // 开尔文符号 K 小写后从 3 字节变为 1 字节
+        assert_eq!(
+            MemoryScope::parse(Some("team:\u{212A}\u{212A}x")).unwrap(),
+            MemoryScope::Team(Some("\u{212A}\u{212A}x".to_string()))
+        );
+    }
+
+    #[test]
+    fn merge_prefers_specific_scope_and_marks_conflicts() {
+        let layers = vec![
+            (
+                SCOPE_PROJECT.to_string(),
+                vec![entry(
+                    "p1",
+                    "本项目使用 npm 安装依赖",
+                    MemoryCategory::Preference,
+                )],
+            ),
+            (
+                SCOPE_GLOBAL.to_string(),
+                vec![
+                    entry("g1", "使用 pnpm 安装依赖", MemoryCategory::Preference),
+                    entry("g2", "回复使用中文", MemoryCategory::Preference),
+                    entry(
+                        "g3",
+                        "本项目使用 npm 安装依赖。",
+                        MemoryCategory::Preference,
+                    ),
+                ],
+            ),
+        ];
+        let merged = merge_layers(layers, 0.55);
+        let ids = merged
+            .memories
+            .iter()
+            .map(|memory| memory.entry.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["p1", "g2"]);
+        assert_eq!(merged.duplicates, 1);
+        assert_eq!(merged.memories[0].overrides.len(), 1);
+        assert_eq!(merged.memories[0].overrides[0].id, "g1");
+        assert_eq!(merged.memories[0].overrides[0].scope, SCOPE_GLOBAL);
+    }
+
+    #[test]
+    fn directory_layers_walk_up_to_project_root() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let package = root.join("packages").join("web");
+        let nested = package.join("src");
+        fs::create_dir_all(package.join(".sanshu-memory")).unwrap();
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(package.join(".sanshu-memory").join("memories.json"), "{}").unwrap();
+
+        let layers = directory_layers(root, &nested);
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].0, "dir:packages/web");
+        assert!(directory_layers(root, root).is_empty());
+    }
+}
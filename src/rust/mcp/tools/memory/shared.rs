@@ -0,0 +1,445 @@
+//! 团队共享记忆文件
+//!
+//! 可选地把项目记忆同步到仓库内的 `.sanshu/memory.jsonl`，随代码一起提交，让队友也能拿到同一套规则。
+//! - 每行一条记忆，按 id 排序，id 跨机器保持不变，便于 git 合并
+//! - 导出：本地新增或更新较新的条目写入文件；文件中已有、本地没有的条目原样保留
+//! - 拉取：同 id 内容不一致时只在预览中列出冲突，由调用方逐条选择保留哪一边，不会静默覆盖
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::cleanup::CleanupGroupEntry;
+use super::similarity::TextSimilarity;
+use super::types::{MemoryCategory, MemoryEntry, MemoryLifecycle, MemorySource};
+
+/// 共享记忆文件相对项目根目录的位置
+pub const SHARED_DIR: &str = ".sanshu";
+pub const SHARED_FILE: &str = "memory.jsonl";
+
+/// 共享文件中的一行；不含 `content_normalized`，导入时重新计算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedMemoryLine {
+    pub id: String,
+    pub content: String,
+    pub category: MemoryCategory,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&MemoryEntry> for SharedMemoryLine {
+    fn from(entry: &MemoryEntry) -> Self {
+        Self {
+            id: entry.id.clone(),
+            content: entry.content.clone(),
+            category: entry.category,
+            pinned: entry.pinned,
+            confidence: entry.lifecycle.confidence,
+            expires_at: entry.lifecycle.expires_at,
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+        }
+    }
+}
+
+impl SharedMemoryLine {
+    /// 转为本地条目，来源记为导入；使用统计与归档状态不随共享文件同步
+    pub fn to_entry(&self) -> MemoryEntry {
+        MemoryEntry {
+            id: self.id.clone(),
+            content: self.content.clone(),
+            content_normalized: TextSimilarity::normalize(&self.content),
+            category: self.category,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            pinned: self.pinned,
+            lifecycle: MemoryLifecycle {
+                confidence: self.confidence,
+                expires_at: self.expires_at,
+                ..MemoryLifecycle::from_source(MemorySource::Import)
+            },
+        }
+    }
+}
+
+/// 解析后的共享文件；`invalid_lines` 记录无法解析的行（如未解决的合并冲突标记）
+#[derive(Debug, Clone, Default)]
+pub struct SharedFile {
+    pub lines: Vec<SharedMemoryLine>,
+    pub invalid_lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SharedConflict {
+    pub id: String,
+    pub local: CleanupGroupEntry,
+    pub shared: CleanupGroupEntry,
+    /// 更新时间较新的一边：local | shared
+    pub recommended_keep: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct SharedPullPreview {
+    pub shared_file: String,
+    pub shared_count: usize,
+    pub local_count: usize,
+    /// 共享文件中有、本地没有，拉取时新增
+    pub new_entries: Vec<CleanupGroupEntry>,
+    /// 同 id 内容或分类不一致，需要逐条选择
+    pub conflicts: Vec<SharedConflict>,
+    pub unchanged_count: usize,
+    /// 仅本地存在，可通过导出写入共享文件
+    pub local_only_count: usize,
+    /// 无法解析的行（行号: 内容），存在时拒绝拉取与导出
+    pub invalid_lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SharedConflictResolution {
+    pub id: String,
+    /// local（保留本地，下次导出时覆盖共享文件）| shared（采用共享文件内容）
+    pub keep: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct SharedPullRequest {
+    #[serde(default)]
+    pub resolutions: Vec<SharedConflictResolution>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct SharedPullResult {
+    pub backup_file: Option<String>,
+    pub added_ids: Vec<String>,
+    pub replaced_ids: Vec<String>,
+    pub kept_local_ids: Vec<String>,
+    /// 未给出处理方式的冲突，本地内容保持不变
+    pub unresolved_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct SharedExportResult {
+    pub shared_file: String,
+    pub written_count: usize,
+    pub added_ids: Vec<String>,
+    pub updated_ids: Vec<String>,
+    /// 共享文件中的版本更新，需先拉取处理冲突
+    pub skipped_conflict_ids: Vec<String>,
+}
+
+pub fn shared_file_path(project_root: &Path) -> PathBuf {
+    project_root.join(SHARED_DIR).join(SHARED_FILE)
+}
+
+/// 读取共享文件；文件不存在时视为空
+pub fn read_shared_file(path: &Path) -> Result<SharedFile> {
+    let mut shared = SharedFile::default();
+    if !path.exists() {
+        return Ok(shared);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("读取共享记忆文件失败: {}", path.display()))?;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<SharedMemoryLine>(line) {
+            Ok(parsed) => shared.lines.push(parsed),
+            Err(_) => shared.invalid_lines.push(format!(
+                "{}: {}",
+                index + 1,
+                line.chars().take(80).collect::<String>()
+            )),
+        }
+    }
+    Ok(shared)
+}
+
+/// 按 id 排序写入，每行一条，末尾保留换行
+pub fn write_shared_file(path: &Path, lines: &[SharedMemoryLine]) -> Result<()> {
+    let mut sorted = lines.iter().collect::<Vec<_>>();
+    sorted.sort_by(|left, right| left.id.cmp(&right.id));
+    let mut content = String::new();
+    for line in sorted {
+        content.push_str(&serde_json::to_string(line)?);
+        content.push('\n');
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("jsonl.tmp");
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("写入共享记忆文件失败: {}", path.display()))?;
+    Ok(())
+}
+
+pub fn ensure_parseable(shared: &SharedFile, path: &Path) -> Result<()> {
+    if shared.invalid_lines.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "共享记忆文件 {} 存在 {} 行无法解析（可能是未解决的 git 合并冲突），请先修复:\n{}",
+        path.display(),
+        shared.invalid_lines.len(),
+        shared.invalid_lines.join("\n")
+    ))
+}
+
+/// 同 id 的两条记忆是否需要人工处理：分类不同或归一化内容不同
+fn diverges(local: &MemoryEntry, shared: &SharedMemoryLine) -> bool {
+    local.category != shared.category
+        || TextSimilarity::normalize(&local.content) != TextSimilarity::normalize(&shared.content)
+}
+
+/// 对比共享文件与本地记忆，生成拉取预览
+pub fn preview_pull(
+    local: &[MemoryEntry],
+    shared: &SharedFile,
+    shared_file: &str,
+) -> SharedPullPreview {
+    let local_by_id = local
+        .iter()
+        .map(|entry| (entry.id.as_str(), entry))
+        .collect::<HashMap<_, _>>();
+    let mut preview = SharedPullPreview {
+        shared_file: shared_file.to_string(),
+        shared_count: shared.lines.len(),
+        local_count: local.len(),
+        invalid_lines: shared.invalid_lines.clone(),
+        ..Default::default()
+    };
+
+    let mut shared_ids = HashSet::new();
+    for line in &shared.lines {
+        shared_ids.insert(line.id.as_str());
+        match local_by_id.get(line.id.as_str()) {
+            None => preview
+                .new_entries
+                .push(CleanupGroupEntry::from(&line.to_entry())),
+            Some(entry) if diverges(entry, line) => preview.conflicts.push(SharedConflict {
+                id: line.id.clone(),
+                local: CleanupGroupEntry::from(*entry),
+                shared: CleanupGroupEntry::from(&line.to_entry()),
+                recommended_keep: if line.updated_at > entry.updated_at {
+                    "shared"
+                } else {
+                    "local"
+                }
+                .to_string(),
+            }),
+            Some(_) => preview.unchanged_count += 1,
+        }
+    }
+    preview.local_only_count = local
+        .iter()
+        .filter(|entry| !shared_ids.contains(entry.id.as_str()))
+        .count();
+    preview
+}
+
+/// 按预览与冲突处理方式更新本地记忆，返回是否有改动
+///
+/// 选择保留本地的冲突会把 `updated_at` 刷新为当前时间，使下次导出能覆盖共享文件中的旧版本。
+pub fn apply_pull(
+    local: &mut Vec<MemoryEntry>,
+    shared: &SharedFile,
+    request: &SharedPullRequest,
+    now: DateTime<Utc>,
+    result: &mut SharedPullResult,
+) -> Result<bool> {
+    let mut resolutions = BTreeMap::new();
+    for resolution in &request.resolutions {
+        let keep = resolution.keep.trim().to_lowercase();
+        if keep != "local" && keep != "shared" {
+            return Err(anyhow::anyhow!(
+                "冲突处理方式无效 ({}): {}，可选 local | shared",
+                resolution.id,
+                resolution.keep
+            ));
+        }
+        resolutions.insert(resolution.id.as_str(), keep);
+    }
+
+    let mut changed = false;
+    for line in &shared.lines {
+        let Some(entry) = local.iter_mut().find(|entry| entry.id == line.id) else {
+            local.push(line.to_entry());
+            result.added_ids.push(line.id.clone());
+            changed = true;
+            continue;
+        };
+        if !diverges(entry, line) {
+            continue;
+        }
+        match resolutions.get(line.id.as_str()).map(String::as_str) {
+            Some("shared") => {
+                let created_at = entry.created_at;
+                let local_lifecycle = entry.lifecycle.clone();
+                *entry = line.to_entry();
+                entry.created_at = created_at.min(line.created_at);
+                // 使用统计与归档状态属于本地，不被共享文件覆盖
+                entry.lifecycle.archived = local_lifecycle.archived;
+                entry.lifecycle.archived_at = local_lifecycle.archived_at;
+                entry.lifecycle.last_recalled_at = local_lifecycle.last_recalled_at;
+                entry.lifecycle.recall_count = local_lifecycle.recall_count;
+                result.replaced_ids.push(line.id.clone());
+                changed = true;
+            }
+            Some(_) => {
+                entry.updated_at = now.max(line.updated_at);
+                result.kept_local_ids.push(line.id.clone());
+                changed = true;
+            }
+            None => result.unresolved_ids.push(line.id.clone()),
+        }
+    }
+    Ok(changed)
+}
+
+/// 把本地记忆合并进共享文件内容：本地新增的写入，本地较新的覆盖，共享文件较新的跳过
+pub fn merge_for_export(
+    local: &[MemoryEntry],
+    shared: &SharedFile,
+) -> (Vec<SharedMemoryLine>, SharedExportResult) {
+    let mut result = SharedExportResult::default();
+    let mut lines = shared.lines.clone();
+    let index_by_id = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| (line.id.clone(), index))
+        .collect::<HashMap<_, _>>();
+
+    for entry in local {
+        match index_by_id.get(&entry.id) {
+            None => {
+                lines.push(SharedMemoryLine::from(entry));
+                result.added_ids.push(entry.id.clone());
+            }
+            Some(&index) if diverges(entry, &lines[index]) => {
+                if entry.updated_at >= lines[index].updated_at {
+                    lines[index] = SharedMemoryLine::from(entry);
+                    result.updated_ids.push(entry.id.clone());
+                } else {
+                    result.skipped_conflict_ids.push(entry.id.clone());
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    result.written_count = lines.len();
+    (lines, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::types::test_entry;
+    use chrono::Duration;
+
+    fn entry(id: &str, content: &str, updated_at: DateTime<Utc>) -> MemoryEntry {
+        MemoryEntry {
+            created_at: updated_at,
+            updated_at,
+            ..test_entry(id, content)
+        }
+    }
+
+    fn shared_of(entries: &[MemoryEntry]) -> SharedFile {
+        SharedFile {
+            lines: entries.iter().map(SharedMemoryLine::from).collect(),
+            invalid_lines: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn write_and_read_round_trip_sorted_by_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = shared_file_path(dir.path());
+        let now = Utc::now();
+        let lines = shared_of(&[entry("b", "规则二", now), entry("a", "规则一", now)]).lines;
+        write_shared_file(&path, &lines).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(content.lines().next().unwrap().contains("\"id\":\"a\""));
+
+        fs::write(&path, format!("{}<<<<<<< HEAD\n", content)).unwrap();
+        let parsed = read_shared_file(&path).unwrap();
+        assert_eq!(parsed.lines.len(), 2);
+        assert_eq!(parsed.invalid_lines, vec!["3: <<<<<<< HEAD".to_string()]);
+        assert!(ensure_parseable(&parsed, &path).is_err());
+    }
+
+    #[test]
+    fn pull_surfaces_conflicts_until_resolved() {
+        let now = Utc::now();
+        let mut local = vec![entry("a", "使用 npm", now), entry("c", "只在本地", now)];
+        let shared = shared_of(&[
+            entry("a", "使用 pnpm", now + Duration::minutes(5)),
+            entry("b", "队友新增的规则", now),
+        ]);
+
+        let preview = preview_pull(&local, &shared, ".sanshu/memory.jsonl");
+        assert_eq!(preview.new_entries.len(), 1);
+        assert_eq!(preview.conflicts.len(), 1);
+        assert_eq!(preview.conflicts[0].recommended_keep, "shared");
+        assert_eq!(preview.local_only_count, 1);
+
+        let mut result = SharedPullResult::default();
+        apply_pull(
+            &mut local,
+            &shared,
+            &SharedPullRequest::default(),
+            now,
+            &mut result,
+        )
+        .unwrap();
+        assert_eq!(result.added_ids, vec!["b"]);
+        assert_eq!(result.unresolved_ids, vec!["a"]);
+        assert_eq!(local[0].content, "使用 npm");
+
+        let request = SharedPullRequest {
+            resolutions: vec![SharedConflictResolution {
+                id: "a".to_string(),
+                keep: "shared".to_string(),
+            }],
+        };
+        let mut result = SharedPullResult::default();
+        apply_pull(&mut local, &shared, &request, now, &mut result).unwrap();
+        assert_eq!(result.replaced_ids, vec!["a"]);
+        assert_eq!(local[0].content, "使用 pnpm");
+    }
+
+    #[test]
+    fn export_skips_entries_newer_in_shared_file() {
+        let now = Utc::now();
+        let local = vec![
+            entry("a", "使用 npm", now),
+            entry("b", "本地更新后的规则", now + Duration::minutes(5)),
+            entry("c", "本地新增", now),
+        ];
+        let shared = shared_of(&[
+            entry("a", "使用 pnpm", now + Duration::minutes(1)),
+            entry("b", "旧规则", now),
+        ]);
+
+        let (lines, result) = merge_for_export(&local, &shared);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(result.added_ids, vec!["c"]);
+        assert_eq!(result.updated_ids, vec!["b"]);
+        assert_eq!(result.skipped_conflict_ids, vec!["a"]);
+    }
+}
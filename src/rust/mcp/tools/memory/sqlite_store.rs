@@ -0,0 +1,662 @@
+//! SQLite 记忆存储
+//!
+//! 每个记忆库一个 `memories.db`（WAL 模式），取代整文件重写的 `memories.json`：
+//! - `memories`：每条记忆一行，`entry` 列保存完整条目 JSON
+//! - `memory_meta`：存储版本、项目路径、去重配置等
+//! - `memory_fts`：FTS5 全文索引，词项与回忆的 BM25 相同（英文单词、中文相邻二字）
+//! - `memory_signatures`：归一化内容的字符二元组签名，用于查找相似度候选
+//! - `memory_history`：变更日志（见 `history.rs`），与条目在同一事务中写入
+//!
+//! 写入只提交与上次加载相比发生变化的条目，并在 `BEGIN IMMEDIATE` 事务中进行；
+//! 两个进程同时写入同一个库时不会互相覆盖新增的记忆。若要修改或删除的记忆已被其他进程改动，
+//! 整个事务回滚并返回错误，由调用方重新加载后重试。
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension};
+
+use super::history::{self, MemoryChange};
+use super::recall::tokenize;
+use super::similarity::TextSimilarity;
+use super::types::{MemoryConfig, MemoryEntry, MemoryStore, StoreSnapshot};
+
+pub const STORE_DB_FILE: &str = "memories.db";
+/// 相似度候选的最大数量，按共享签名数从多到少截取
+pub const MAX_SIMILARITY_CANDIDATES: usize = 200;
+
+const STORE_SCHEMA_VERSION: i64 = 2;
+
+pub fn db_path(memory_dir: &Path) -> PathBuf {
+    memory_dir.join(STORE_DB_FILE)
+}
+
+pub fn exists(memory_dir: &Path) -> bool {
+    db_path(memory_dir).is_file()
+}
+
+fn open_database(path: &Path) -> Result<Connection> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+    )
+    .with_context(|| format!("打开记忆库失败: {}", path.display()))?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    connection.execute_batch(
+        "PRAGMA journal_mode=WAL;
+         PRAGMA synchronous=NORMAL;",
+    )?;
+    let version = connection.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))?;
+    if version < STORE_SCHEMA_VERSION {
+        connection.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS memory_meta (
+                 key TEXT PRIMARY KEY,
+                 value TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS memories (
+                 id TEXT PRIMARY KEY,
+                 category TEXT NOT NULL,
+                 updated_at TEXT NOT NULL,
+                 entry TEXT NOT NULL
+             );
+             CREATE VIRTUAL TABLE IF NOT EXISTS memory_fts USING fts5(
+                 id UNINDEXED,
+                 terms,
+                 tokenize='unicode61 remove_diacritics 2'
+             );
+             CREATE TABLE IF NOT EXISTS memory_signatures (
+                 gram TEXT NOT NULL,
+                 memory_id TEXT NOT NULL,
+                 PRIMARY KEY (gram, memory_id)
+             ) WITHOUT ROWID;
+             CREATE INDEX IF NOT EXISTS memory_signatures_by_id
+                 ON memory_signatures(memory_id);
+             CREATE TABLE IF NOT EXISTS memory_history (
+                 seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                 change_id TEXT NOT NULL UNIQUE,
+                 memory_id TEXT NOT NULL,
+                 change TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS memory_history_by_memory
+                 ON memory_history(memory_id, seq);
+             PRAGMA user_version = {};",
+            STORE_SCHEMA_VERSION
+        ))?;
+    }
+    Ok(connection)
+}
+
+/// 只读打开，用于回忆时加载其他作用域；库不存在时返回 `None`
+fn open_read_only(memory_dir: &Path) -> Result<Option<Connection>> {
+    let path = db_path(memory_dir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let connection = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("打开记忆库失败: {}", path.display()))?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    Ok(Some(connection))
+}
+
+/// 加载整个记忆库；库不存在时返回 `None`
+pub fn load(memory_dir: &Path) -> Result<Option<MemoryStore>> {
+    if !exists(memory_dir) {
+        return Ok(None);
+    }
+    let connection = open_database(&db_path(memory_dir))?;
+    let meta = read_meta(&connection)?;
+    let defaults = MemoryStore::default();
+    Ok(Some(MemoryStore {
+        version: meta.get("version").cloned().unwrap_or(defaults.version),
+        project_path: meta.get("project_path").cloned().unwrap_or_default(),
+        entries: read_entries(&connection)?,
+        last_dedup_at: meta
+            .get("last_dedup_at")
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|value| value.with_timezone(&Utc))
+            .unwrap_or(defaults.last_dedup_at),
+        config: meta
+            .get("config")
+            .and_then(|value| serde_json::from_str::<MemoryConfig>(value).ok())
+            .unwrap_or(defaults.config),
+    }))
+}
+
+/// 只读加载条目；库不存在时返回 `None`
+pub fn load_entries(memory_dir: &Path) -> Result<Option<Vec<MemoryEntry>>> {
+    match open_read_only(memory_dir)? {
+        Some(connection) => Ok(Some(read_entries(&connection)?)),
+        None => Ok(None),
+    }
+}
+
+fn read_meta(connection: &Connection) -> Result<HashMap<String, String>> {
+    let mut statement = connection.prepare("SELECT key, value FROM memory_meta")?;
+    let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn read_entries(connection: &Connection) -> Result<Vec<MemoryEntry>> {
+    let mut statement = connection.prepare("SELECT id, entry FROM memories ORDER BY rowid")?;
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut entries = Vec::new();
+    for row in rows {
+        let (id, json) = row?;
+        match serde_json::from_str::<MemoryEntry>(&json) {
+            Ok(entry) => entries.push(entry),
+            Err(error) => crate::log_debug!("跳过无法解析的记忆 {}: {}", id, error),
+        }
+    }
+    Ok(entries)
+}
+
+/// 写入自 `previous` 以来发生变化的条目、存储元数据和变更记录 `changes`
+///
+/// `previous` 为上次加载或保存后的状态。要修改或删除的条目、要修改的元数据在库中的状态与
+/// `previous` 不一致时，说明已被其他进程改动，返回错误且不写入任何内容；
+/// 变更记录与条目同时提交或同时回滚。
+pub fn save(
+    memory_dir: &Path,
+    store: &MemoryStore,
+    previous: &StoreSnapshot,
+    changes: &[MemoryChange],
+) -> Result<()> {
+    let mut connection = open_database(&db_path(memory_dir))?;
+    save_with(&mut connection, store, previous, changes)
+}
+
+/// 在 `path` 新建记忆库并写入整个存储，用于迁移
+///
+/// 写完后切换为回滚日志模式，不留下 `-wal`/`-shm`，调用方可直接把文件改名为 `memories.db`；
+/// 之后正常打开时会重新启用 WAL
+pub fn create_at(path: &Path, store: &MemoryStore) -> Result<()> {
+    let mut connection = open_database(path)?;
+    save_with(&mut connection, store, &StoreSnapshot::default(), &[])?;
+    connection.execute_batch("PRAGMA journal_mode=DELETE;")?;
+    connection
+        .close()
+        .map_err(|(_, error)| error)
+        .with_context(|| format!("关闭记忆库失败: {}", path.display()))?;
+    Ok(())
+}
+
+fn save_with(
+    connection: &mut Connection,
+    store: &MemoryStore,
+    previous: &StoreSnapshot,
+    changes: &[MemoryChange],
+) -> Result<()> {
+    let transaction =
+        connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+    for (key, value) in store.meta_values()? {
+        let expected = previous.meta.get(key);
+        if expected == Some(&value) {
+            continue;
+        }
+        if let Some(expected) = expected {
+            ensure_meta_unchanged(&transaction, key, expected)?;
+        }
+        transaction.execute(
+            "INSERT INTO memory_meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+    }
+
+    for entry in &store.entries {
+        let json = serde_json::to_string(entry)?;
+        match previous.entries.get(&entry.id) {
+            Some(old) if serde_json::to_string(old)? == json => continue,
+            Some(old) => ensure_unchanged(&transaction, &entry.id, old)?,
+            None => {}
+        }
+        write_entry(&transaction, entry, &json)?;
+    }
+
+    let remaining = store
+        .entries
+        .iter()
+        .map(|entry| entry.id.as_str())
+        .collect::<HashSet<_>>();
+    for old in previous.entries.values() {
+        if remaining.contains(old.id.as_str()) {
+            continue;
+        }
+        // 其他进程已删除的条目无需再删，删除结果一致
+        if stored_entry(&transaction, &old.id)?.is_none() {
+            continue;
+        }
+        ensure_unchanged(&transaction, &old.id, old)?;
+        delete_entry(&transaction, &old.id)?;
+    }
+
+    for change in changes {
+        write_change(&transaction, change)?;
+    }
+
+    transaction.commit()?;
+    Ok(())
+}
+
+fn write_change(connection: &Connection, change: &MemoryChange) -> Result<()> {
+    connection.execute(
+        "INSERT OR IGNORE INTO memory_history (change_id, memory_id, change) VALUES (?1, ?2, ?3)",
+        params![
+            change.change_id,
+            change.memory_id,
+            serde_json::to_string(change)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// 某条记忆的变更记录，按写入顺序；无法解析的记录跳过
+pub fn read_changes(memory_dir: &Path, memory_id: &str) -> Result<Vec<MemoryChange>> {
+    let Some(connection) = open_read_only(memory_dir)? else {
+        return Ok(Vec::new());
+    };
+    let mut statement = connection
+        .prepare("SELECT change FROM memory_history WHERE memory_id = ?1 ORDER BY seq")?;
+    let rows = statement.query_map(params![memory_id], |row| row.get::<_, String>(0))?;
+    let mut changes = Vec::new();
+    for row in rows {
+        match serde_json::from_str::<MemoryChange>(&row?) {
+            Ok(change) => changes.push(change),
+            Err(error) => crate::log_debug!("跳过无法解析的记忆变更记录: {}", error),
+        }
+    }
+    Ok(changes)
+}
+
+/// 把旧版的 `history.jsonl` 导入 `memory_history`，成功后改名为 `history.jsonl.imported`
+///
+/// 文件不存在时返回 0；导入失败时文件保持原样，下次打开时重试
+pub fn import_history_file(memory_dir: &Path) -> Result<usize> {
+    let path = memory_dir.join(history::HISTORY_FILE);
+    if !path.is_file() {
+        return Ok(0);
+    }
+    let changes = history::read_change_file(&path)?;
+    let mut connection = open_database(&db_path(memory_dir))?;
+    let transaction =
+        connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    for change in &changes {
+        write_change(&transaction, change)?;
+    }
+    transaction.commit()?;
+
+    let imported = path.with_extension("jsonl.imported");
+    std::fs::rename(&path, &imported)
+        .with_context(|| format!("重命名记忆变更日志失败: {}", path.display()))?;
+    Ok(changes.len())
+}
+
+fn stored_entry(connection: &Connection, id: &str) -> Result<Option<String>> {
+    Ok(connection
+        .query_row(
+            "SELECT entry FROM memories WHERE id = ?1",
+            params![id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?)
+}
+
+/// 库中的条目须与上次加载时相同（忽略使用统计与 `updated_at`）；已被其他进程删除也视为冲突，
+/// 否则修改会让已删除的记忆重新出现
+fn ensure_unchanged(connection: &Connection, id: &str, expected: &MemoryEntry) -> Result<()> {
+    let stored = stored_entry(connection, id)?
+        .and_then(|json| serde_json::from_str::<MemoryEntry>(&json).ok());
+    if !history::same_state(stored.as_ref(), Some(expected)) {
+        return Err(anyhow::anyhow!(
+            "记忆 {} 已被其他进程修改，请重新执行本次操作",
+            id
+        ));
+    }
+    Ok(())
+}
+
+/// 要修改的元数据须与上次加载时相同；库中没有该键（早期版本未写入）时视为一致
+fn ensure_meta_unchanged(connection: &Connection, key: &str, expected: &str) -> Result<()> {
+    let stored = connection
+        .query_row(
+            "SELECT value FROM memory_meta WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    if stored.is_some_and(|stored| stored != expected) {
+        return Err(anyhow::anyhow!(
+            "记忆库元数据 {} 已被其他进程修改，请重新执行本次操作",
+            key
+        ));
+    }
+    Ok(())
+}
+
+fn write_entry(connection: &Connection, entry: &MemoryEntry, json: &str) -> Result<()> {
+    connection.execute(
+        "INSERT INTO memories (id, category, updated_at, entry) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(id) DO UPDATE SET
+             category = excluded.category,
+             updated_at = excluded.updated_at,
+             entry = excluded.entry",
+        params![
+            entry.id,
+            entry.category.display_name(),
+            entry.updated_at.to_rfc3339(),
+            json
+        ],
+    )?;
+    connection.execute("DELETE FROM memory_fts WHERE id = ?1", params![entry.id])?;
+    connection.execute(
+        "INSERT INTO memory_fts (id, terms) VALUES (?1, ?2)",
+        params![entry.id, search_terms(entry).join(" ")],
+    )?;
+    connection.execute(
+        "DELETE FROM memory_signatures WHERE memory_id = ?1",
+        params![entry.id],
+    )?;
+    for gram in signature(&normalized_content(entry)) {
+        connection.execute(
+            "INSERT OR IGNORE INTO memory_signatures (gram, memory_id) VALUES (?1, ?2)",
+            params![gram, entry.id],
+        )?;
+    }
+    Ok(())
+}
+
+fn delete_entry(connection: &Connection, id: &str) -> Result<()> {
+    connection.execute("DELETE FROM memories WHERE id = ?1", params![id])?;
+    connection.execute("DELETE FROM memory_fts WHERE id = ?1", params![id])?;
+    connection.execute(
+        "DELETE FROM memory_signatures WHERE memory_id = ?1",
+        params![id],
+    )?;
+    Ok(())
+}
+
+fn normalized_content(entry: &MemoryEntry) -> String {
+    if entry.content_normalized.is_empty() {
+        TextSimilarity::normalize(&entry.content)
+    } else {
+        entry.content_normalized.clone()
+    }
+}
+
+fn search_terms(entry: &MemoryEntry) -> Vec<String> {
+    tokenize(&normalized_content(entry))
+}
+
+/// n-gram 签名：去掉空白后的相邻二字，去重
+pub fn signature(normalized: &str) -> Vec<String> {
+    let chars = normalized
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    chars
+        .windows(2)
+        .map(|pair| pair.iter().collect::<String>())
+        .filter(|gram| seen.insert(gram.clone()))
+        .collect()
+}
+
+/// 与 `content` 至少共享一个签名的记忆 id，按共享数从多到少，最多 `limit` 条；
+/// 没有签名的短记忆总是包含在内。`content` 过短无法生成签名时返回 `None`，调用方应全量比较。
+pub fn similarity_candidates(
+    memory_dir: &Path,
+    content: &str,
+    limit: usize,
+) -> Result<Option<HashSet<String>>> {
+    let grams = signature(&TextSimilarity::normalize(content));
+    if grams.is_empty() {
+        return Ok(None);
+    }
+    let Some(connection) = open_read_only(memory_dir)? else {
+        return Ok(None);
+    };
+    let placeholders = vec!["?"; grams.len()].join(", ");
+    let mut statement = connection.prepare(&format!(
+        "SELECT memory_id FROM memory_signatures WHERE gram IN ({})
+         GROUP BY memory_id ORDER BY COUNT(*) DESC LIMIT {}",
+        placeholders, limit
+    ))?;
+    let mut candidates = statement
+        .query_map(params_from_iter(grams.iter()), |row| {
+            row.get::<_, String>(0)
+        })?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+
+    let mut statement = connection.prepare(
+        "SELECT id FROM memories
+         WHERE id NOT IN (SELECT memory_id FROM memory_signatures)",
+    )?;
+    for id in statement.query_map([], |row| row.get::<_, String>(0))? {
+        candidates.insert(id?);
+    }
+    Ok(Some(candidates))
+}
+
+/// 全文检索命中 `query` 任一词项的记忆 id；库不存在或 `query` 没有词项时返回 `None`
+pub fn search(memory_dir: &Path, query: &str) -> Result<Option<HashSet<String>>> {
+    let terms = tokenize(&TextSimilarity::normalize(query));
+    if terms.is_empty() {
+        return Ok(None);
+    }
+    let Some(connection) = open_read_only(memory_dir)? else {
+        return Ok(None);
+    };
+    let expression = terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let mut statement =
+        connection.prepare("SELECT id FROM memory_fts WHERE memory_fts MATCH ?1")?;
+    let hits = statement
+        .query_map(params![expression], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    Ok(Some(hits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::types::test_entry;
+    use tempfile::TempDir;
+
+    #[test]
+    fn concurrent_writers_keep_each_others_entries() {
+        let dir = TempDir::new().unwrap();
+        let store = MemoryStore {
+            entries: vec![test_entry("a", "使用 pnpm 安装依赖")],
+            ..Default::default()
+        };
+        save(dir.path(), &store, &StoreSnapshot::default(), &[]).unwrap();
+
+        // 两个进程各自加载后分别新增一条
+        let base = StoreSnapshot::of(&store).unwrap();
+        let mut first = load(dir.path()).unwrap().unwrap();
+        let mut second = load(dir.path()).unwrap().unwrap();
+        first.entries.push(test_entry("b", "回复使用中文"));
+        second
+            .entries
+            .push(test_entry("c", "数据库迁移必须使用 sqlx migrate"));
+        save(dir.path(), &first, &base, &[]).unwrap();
+        save(dir.path(), &second, &base, &[]).unwrap();
+
+        let ids = load(dir.path())
+            .unwrap()
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+
+        // 另一进程已改写 a，基于旧状态的修改被拒绝
+        let mut edited = first.clone();
+        edited.entries[0].content = "使用 npm 安装依赖".to_string();
+        save(
+            dir.path(),
+            &edited,
+            &StoreSnapshot::of(&first).unwrap(),
+            &[],
+        )
+        .unwrap();
+        let mut stale = second.clone();
+        stale.entries[0].pinned = true;
+        assert!(save(
+            dir.path(),
+            &stale,
+            &StoreSnapshot::of(&second).unwrap(),
+            &[]
+        )
+        .is_err());
+
+        // 另一进程已删除 b：基于旧状态修改 b 被拒绝，只删除 b 则视为一致
+        let loaded = load(dir.path()).unwrap().unwrap();
+        let mut removed = loaded.clone();
+        removed.entries.retain(|entry| entry.id != "b");
+        save(
+            dir.path(),
+            &removed,
+            &StoreSnapshot::of(&loaded).unwrap(),
+            &[],
+        )
+        .unwrap();
+        let mut resurrected = loaded.clone();
+        resurrected
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == "b")
+            .unwrap()
+            .pinned = true;
+        assert!(save(
+            dir.path(),
+            &resurrected,
+            &StoreSnapshot::of(&loaded).unwrap(),
+            &[]
+        )
+        .is_err());
+        save(
+            dir.path(),
+            &removed,
+            &StoreSnapshot::of(&loaded).unwrap(),
+            &[],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn only_changed_meta_is_written() {
+        let dir = TempDir::new().unwrap();
+        save(
+            dir.path(),
+            &MemoryStore::default(),
+            &StoreSnapshot::default(),
+            &[],
+        )
+        .unwrap();
+
+        // 一个进程修改配置，另一个进程只新增条目：配置不会被旧值覆盖
+        let mut first = load(dir.path()).unwrap().unwrap();
+        let mut second = load(dir.path()).unwrap().unwrap();
+        let base = StoreSnapshot::of(&first).unwrap();
+        first.config.similarity_threshold = 0.9;
+        save(dir.path(), &first, &base, &[]).unwrap();
+        second.entries.push(test_entry("a", "回复使用中文"));
+        save(dir.path(), &second, &base, &[]).unwrap();
+        let stored = load(dir.path()).unwrap().unwrap();
+        assert_eq!(stored.config.similarity_threshold, 0.9);
+        assert_eq!(stored.entries.len(), 1);
+
+        // 两个进程都修改配置：后保存的一方被拒绝
+        second.config.similarity_threshold = 0.5;
+        let error = save(dir.path(), &second, &base, &[]).unwrap_err();
+        assert!(error.to_string().contains("config"));
+    }
+
+    #[test]
+    fn history_commits_and_rolls_back_with_entries() {
+        let dir = TempDir::new().unwrap();
+        let context = history::ChangeContext::default();
+        let store = MemoryStore {
+            entries: vec![test_entry("a", "使用 pnpm 安装依赖")],
+            ..Default::default()
+        };
+        let changes = history::collect_changes(
+            &HashMap::new(),
+            &store.entries,
+            "upsert",
+            &context,
+            Utc::now(),
+        );
+        save(dir.path(), &store, &StoreSnapshot::default(), &changes).unwrap();
+        assert_eq!(read_changes(dir.path(), "a").unwrap().len(), 1);
+
+        // 另一进程已改写 a：基于旧状态的保存被拒绝，变更记录也不写入
+        let base = StoreSnapshot::of(&store).unwrap();
+        let mut other = store.clone();
+        other.entries[0].content = "使用 npm 安装依赖".to_string();
+        save(dir.path(), &other, &base, &[]).unwrap();
+        let mut stale = store.clone();
+        stale.entries[0].pinned = true;
+        let changes =
+            history::collect_changes(&base.entries, &stale.entries, "pin", &context, Utc::now());
+        assert!(save(dir.path(), &stale, &base, &changes).is_err());
+        assert_eq!(read_changes(dir.path(), "a").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn legacy_history_file_is_imported_once() {
+        let dir = TempDir::new().unwrap();
+        let context = history::ChangeContext::default();
+        let entries = vec![test_entry("a", "使用 pnpm 安装依赖")];
+        let legacy =
+            history::collect_changes(&HashMap::new(), &entries, "upsert", &context, Utc::now());
+        let lines = legacy
+            .iter()
+            .map(|change| serde_json::to_string(change).unwrap() + "\n")
+            .collect::<String>();
+        let path = dir.path().join(history::HISTORY_FILE);
+        std::fs::write(&path, lines).unwrap();
+
+        assert_eq!(import_history_file(dir.path()).unwrap(), 1);
+        assert!(!path.exists());
+        assert_eq!(import_history_file(dir.path()).unwrap(), 0);
+        let changes = read_changes(dir.path(), "a").unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change_id, legacy[0].change_id);
+    }
+
+    #[test]
+    fn signature_and_fts_find_candidates() {
+        let dir = TempDir::new().unwrap();
+        let store = MemoryStore {
+            entries: vec![
+                test_entry("a", "使用 pnpm 安装依赖"),
+                test_entry("b", "数据库迁移必须使用 sqlx migrate"),
+                test_entry("c", "x"),
+            ],
+            ..Default::default()
+        };
+        save(dir.path(), &store, &StoreSnapshot::default(), &[]).unwrap();
+
+        let candidates = similarity_candidates(dir.path(), "用 pnpm 装依赖", 10)
+            .unwrap()
+            .unwrap();
+        assert!(candidates.contains("a"));
+        assert!(!candidates.contains("b"));
+        assert!(candidates.contains("c"));
+
+        let hits = search(dir.path(), "新增数据库迁移脚本").unwrap().unwrap();
+        assert_eq!(hits, HashSet::from(["b".to_string()]));
+    }
+}
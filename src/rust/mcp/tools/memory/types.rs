@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +19,133 @@ pub struct MemoryEntry {
     pub created_at: DateTime<Utc>,
     /// 更新时间
     pub updated_at: DateTime<Utc>,
+    /// 固定记忆：回忆时总是返回，不受分类、条数与 token 预算限制
+    #[serde(default)]
+    pub pinned: bool,
+    /// 生命周期元数据（来源、置信度、过期、归档、使用统计），平铺在条目 JSON 中
+    #[serde(flatten)]
+    pub lifecycle: MemoryLifecycle,
+}
+
+impl MemoryEntry {
+    /// 未归档且未过期的记忆参与回忆与跨作用域合并
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        !self.lifecycle.archived && !self.lifecycle.is_expired(now)
+    }
+}
+
+/// 记忆来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MemorySource {
+    /// AI 代理通过 ji 主动记录（旧数据默认视为此来源）
+    #[default]
+    Agent,
+    /// 用户在弹窗中明确要求记住
+    User,
+    /// 弹窗上下文块（`ResponseContextBlock`，`memory_policy=save`）
+    ContextBlock,
+    /// 从旧格式、共享文件等外部导入
+    Import,
+    /// 工具自动写入（如 sou 索引配置摘要）
+    System,
+}
+
+impl MemorySource {
+    /// 从字符串解析来源，无法识别时返回 None
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "agent" | "ai" | "代理" => Some(Self::Agent),
+            "user" | "popup" | "用户" => Some(Self::User),
+            "context_block" | "context" | "上下文块" => Some(Self::ContextBlock),
+            "import" | "导入" => Some(Self::Import),
+            "system" | "系统" => Some(Self::System),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Agent => "agent",
+            Self::User => "user",
+            Self::ContextBlock => "context_block",
+            Self::Import => "import",
+            Self::System => "system",
+        }
+    }
+
+    /// 未显式给出置信度时按来源取默认值：用户明确要求的最可信，自动写入的最低
+    pub fn default_confidence(&self) -> f64 {
+        match self {
+            Self::User => 1.0,
+            Self::ContextBlock => 0.9,
+            Self::Import => 0.8,
+            Self::Agent => 0.7,
+            Self::System => 0.6,
+        }
+    }
+}
+
+/// 记忆生命周期元数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MemoryLifecycle {
+    #[serde(default)]
+    pub source: MemorySource,
+    /// 置信度（0.0 ~ 1.0）；为空时取来源默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// 过期时间；过期后不再参与回忆，由清理预览提议归档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 已归档：保留在存储中但不再参与回忆
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<DateTime<Utc>>,
+    /// 最近一次在回忆结果中返回的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_recalled_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub recall_count: u64,
+    /// 被本条取代（已归档）的矛盾记忆 id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supersedes: Vec<String>,
+}
+
+/// 新增或更新记忆时调用方给出的生命周期信息，未给出的字段保持不变
+#[derive(Debug, Clone, Default)]
+pub struct LifecycleUpdate {
+    pub source: Option<MemorySource>,
+    pub confidence: Option<f64>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl MemoryLifecycle {
+    pub fn from_source(source: MemorySource) -> Self {
+        Self {
+            source,
+            ..Default::default()
+        }
+    }
+
+    pub fn effective_confidence(&self) -> f64 {
+        self.confidence
+            .unwrap_or_else(|| self.source.default_confidence())
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// 归档或取消归档，返回状态是否发生变化
+    pub fn set_archived(&mut self, archived: bool, now: DateTime<Utc>) -> bool {
+        if self.archived == archived {
+            return false;
+        }
+        self.archived = archived;
+        self.archived_at = archived.then_some(now);
+        true
+    }
 }
 
 /// 记忆分类
@@ -84,6 +213,41 @@ impl Default for MemoryStore {
     }
 }
 
+impl MemoryStore {
+    /// 元数据的存储形式：键与序列化后的值，与 SQLite `memory_meta` 表一致
+    pub fn meta_values(&self) -> serde_json::Result<Vec<(&'static str, String)>> {
+        Ok(vec![
+            ("version", self.version.clone()),
+            ("project_path", self.project_path.clone()),
+            ("last_dedup_at", self.last_dedup_at.to_rfc3339()),
+            ("config", serde_json::to_string(&self.config)?),
+        ])
+    }
+}
+
+/// 上次加载或保存后的记忆库状态
+///
+/// 保存时据此只写入发生变化的条目与元数据，并检查它们在此期间未被其他进程修改
+#[derive(Debug, Clone, Default)]
+pub struct StoreSnapshot {
+    pub entries: HashMap<String, MemoryEntry>,
+    /// 元数据（见 `MemoryStore::meta_values`）；记忆库尚未写入时为空，保存时写入全部元数据
+    pub meta: HashMap<&'static str, String>,
+}
+
+impl StoreSnapshot {
+    pub fn of(store: &MemoryStore) -> serde_json::Result<Self> {
+        Ok(Self {
+            entries: store
+                .entries
+                .iter()
+                .map(|entry| (entry.id.clone(), entry.clone()))
+                .collect(),
+            meta: store.meta_values()?.into_iter().collect(),
+        })
+    }
+}
+
 /// 记忆去重配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
@@ -141,3 +305,18 @@ pub struct MemoryMetadata {
     pub total_entries: usize,
     pub version: String,
 }
+
+/// 测试用的最小记忆条目：规范分类、未固定、生命周期取默认值
+#[cfg(test)]
+pub(crate) fn test_entry(id: &str, content: &str) -> MemoryEntry {
+    MemoryEntry {
+        id: id.to_string(),
+        content: content.to_string(),
+        content_normalized: super::similarity::TextSimilarity::normalize(content),
+        category: MemoryCategory::Rule,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        pinned: false,
+        lifecycle: Default::default(),
+    }
+}
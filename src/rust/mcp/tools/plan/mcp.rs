@@ -17,25 +17,49 @@ impl PlanTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["set", "update", "get", "clear"],
-                    "description": "计划动作：set 完整替换，update 更新单项状态，get 查询，clear 清空"
+                    "enum": ["set", "update", "get", "clear", "list", "archive"],
+                    "description": "计划动作：set 完整替换，update 更新单项状态，get 查询，clear 清空，list 列出工作区全部计划，archive 归档计划"
                 },
                 "workspace": {
                     "type": "string",
                     "minLength": 1,
                     "description": "工作区根目录绝对路径"
                 },
+                "plan_id": {
+                    "type": "string",
+                    "minLength": 1,
+                    "maxLength": 64,
+                    "pattern": "^[A-Za-z0-9_-]+$",
+                    "description": "计划名称，省略时为 default；多个实例并行工作时各自使用独立的 plan_id"
+                },
+                "agent_label": {
+                    "type": "string",
+                    "minLength": 1,
+                    "description": "调用方实例标识；首次 set 时成为计划归属，之后只有同一实例可以修改该计划"
+                },
+                "revision": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "修改所基于的计划版本；与当前版本不一致时拒绝修改并返回最新计划。省略时不做版本检查，直接在最新计划上生效，多个实例共享计划时应始终提供"
+                },
                 "items": {
                     "type": "array",
                     "minItems": 1,
-                    "description": "set 时必填的有序计划项；同一计划最多一个 in_progress",
+                    "description": "set 时必填的有序计划项；进行中的项只能是一个步骤及其上级项",
                     "items": {
                         "type": "object",
                         "additionalProperties": false,
                         "properties": {
                             "id": { "type": "string", "minLength": 1, "description": "稳定且唯一的计划项 ID" },
                             "text": { "type": "string", "minLength": 1, "description": "计划项文本" },
-                            "status": { "type": "string", "enum": ["pending", "in_progress", "completed"] }
+                            "status": { "type": "string", "enum": ["pending", "in_progress", "completed", "blocked", "cancelled"] },
+                            "parent_id": { "type": "string", "minLength": 1, "description": "上级计划项 ID，用于拆分子步骤" },
+                            "depends_on": {
+                                "type": "array",
+                                "items": { "type": "string", "minLength": 1 },
+                                "description": "须先完成的计划项 ID；依赖未完成时不能进入 in_progress，不允许循环依赖"
+                            },
+                            "blocked_reason": { "type": "string", "minLength": 1, "description": "status 为 blocked 时必填的受阻原因" }
                         },
                         "required": ["id", "text", "status"]
                     }
@@ -47,8 +71,13 @@ impl PlanTool {
                 },
                 "status": {
                     "type": "string",
-                    "enum": ["pending", "in_progress", "completed"],
+                    "enum": ["pending", "in_progress", "completed", "blocked", "cancelled"],
                     "description": "update 时必填的目标状态"
+                },
+                "reason": {
+                    "type": "string",
+                    "minLength": 1,
+                    "description": "update 为 blocked 时必填的受阻原因"
                 }
             },
             "required": ["action", "workspace"]
@@ -60,7 +89,7 @@ impl PlanTool {
         Tool {
             name: Cow::Borrowed("plan"),
             description: Some(Cow::Borrowed(
-                "维护当前工作区的开发执行计划。开始前用 set 提交完整计划，开始和完成步骤时用 update 单向更新状态，可用 get 查询或 clear 清空。",
+                "维护当前工作区的开发执行计划。开始前用 set 提交完整计划，开始和完成步骤时用 update 单向更新状态，受阻时标记 blocked 并说明原因，不再需要的步骤标记 cancelled；可用 parent_id 拆分子步骤、depends_on 声明先后依赖，可用 get 查询或 clear 清空。多个实例并行时用 plan_id 区分各自的计划并提供 agent_label，修改时带上 revision 可避免覆盖他人的更新；list 列出工作区全部计划，archive 归档已完成的计划。",
             )),
             input_schema: Arc::new(schema_map),
             annotations: None,
@@ -79,8 +108,9 @@ impl PlanTool {
 
         log_important!(
             info,
-            "[plan] 动作完成: action={}, changed={}, completed={}, total={}, workspace={}",
+            "[plan] 动作完成: action={}, plan_id={}, changed={}, completed={}, total={}, workspace={}",
             action.as_str(),
+            result.plan_id,
             result.changed,
             result.summary.completed,
             result.summary.total,
@@ -110,6 +140,10 @@ impl PlanTool {
             PlanError::Corrupt(message) | PlanError::Storage(message) => {
                 McpError::internal_error(message, None)
             }
+            PlanError::Stale { message, snapshot } => {
+                let data = serde_json::to_value(&snapshot).ok();
+                McpError::invalid_params(message, data)
+            }
         }
     }
 }
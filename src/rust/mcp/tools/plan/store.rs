@@ -1,7 +1,9 @@
 use once_cell::sync::Lazy;
 use ring::digest::{Context, SHA256};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -9,7 +11,8 @@ use tempfile::NamedTempFile;
 use thiserror::Error;
 
 use super::types::{
-    PlanAction, PlanFile, PlanItem, PlanRequest, PlanResult, PlanStatus, PLAN_FILE_VERSION,
+    PlanAction, PlanFile, PlanItem, PlanOverview, PlanRequest, PlanResult, PlanStatus,
+    DEFAULT_PLAN_ID, PLAN_FILE_VERSION,
 };
 
 #[derive(Debug, Error)]
@@ -22,8 +25,27 @@ pub enum PlanError {
     Corrupt(String),
     #[error("计划存储失败：{0}")]
     Storage(String),
+    /// 请求的 revision 落后于当前计划；附带最新快照，调用方据此重试
+    #[error("计划版本冲突：{message}")]
+    Stale {
+        message: String,
+        snapshot: Box<PlanResult>,
+    },
 }
 
+/// 一次请求操作的计划及调用方身份
+struct PlanTarget {
+    workspace: String,
+    plan_id: String,
+    agent: Option<String>,
+    revision: Option<u64>,
+}
+
+/// 归档计划的子目录
+const ARCHIVE_DIR: &str = "archive";
+/// plan_id 的最大长度
+const MAX_PLAN_ID_LEN: usize = 64;
+
 pub struct PlanStore {
     root_dir: PathBuf,
     workspace_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
@@ -60,21 +82,32 @@ impl PlanStore {
     pub fn execute(&self, request: PlanRequest) -> Result<PlanResult, PlanError> {
         let workspace = Self::normalize_workspace(&request.workspace)?;
         Self::validate_action_fields(&request)?;
+        let target = PlanTarget {
+            workspace,
+            plan_id: Self::normalize_plan_id(request.plan_id.as_deref())?,
+            agent: Self::trimmed(request.agent_label),
+            revision: request.revision,
+        };
 
-        let workspace_lock = self.workspace_lock(&workspace)?;
+        let workspace_lock = self.workspace_lock(&target.workspace)?;
         let _guard = workspace_lock
             .lock()
             .map_err(|_| PlanError::Storage("工作区计划锁已损坏".to_string()))?;
+        // 中文说明：进程内互斥之外再加文件锁，多个 MCP 进程的读取-校验-写入也依次进行。
+        let _file_lock = self.lock_workspace_file(&target.workspace)?;
 
         match request.action {
-            PlanAction::Set => self.set_plan(workspace, request.items.unwrap_or_default()),
+            PlanAction::Set => self.set_plan(target, request.items.unwrap_or_default()),
             PlanAction::Update => self.update_plan(
-                workspace,
+                target,
                 request.id.unwrap_or_default(),
                 request.status.expect("update 已校验 status"),
+                request.reason,
             ),
-            PlanAction::Get => self.get_plan_locked(workspace),
-            PlanAction::Clear => self.clear_plan(workspace),
+            PlanAction::Get => self.get_plan_locked(target),
+            PlanAction::Clear => self.clear_plan(target),
+            PlanAction::List => self.list_plans(target.workspace),
+            PlanAction::Archive => self.archive_plan(target),
         }
     }
 
@@ -85,14 +118,17 @@ impl PlanStore {
             items: None,
             id: None,
             status: None,
+            reason: None,
+            plan_id: None,
+            agent_label: None,
+            revision: None,
         })
     }
 
+    /// 默认计划的文件路径（桌面端面板监听此文件）
     pub fn plan_file_path(&self, workspace: &str) -> Result<PathBuf, PlanError> {
         let normalized = Self::normalize_workspace(workspace)?;
-        Ok(self
-            .root_dir
-            .join(format!("{}.json", Self::workspace_hash(&normalized))))
+        Ok(self.plan_path(&normalized, DEFAULT_PLAN_ID))
     }
 
     pub fn normalize_workspace(workspace: &str) -> Result<String, PlanError> {
@@ -130,6 +166,25 @@ impl PlanStore {
         Ok(normalized)
     }
 
+    /// plan_id 用于文件名，只允许字母、数字、`-` 与 `_`；统一转为小写，
+    /// 避免大小写不敏感的文件系统上 `Plan` 与 `plan` 指向同一文件
+    fn normalize_plan_id(plan_id: Option<&str>) -> Result<String, PlanError> {
+        let Some(plan_id) = plan_id.map(str::trim).filter(|plan_id| !plan_id.is_empty()) else {
+            return Ok(DEFAULT_PLAN_ID.to_string());
+        };
+        let valid = plan_id.len() <= MAX_PLAN_ID_LEN
+            && plan_id
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+        if !valid {
+            return Err(PlanError::Validation(format!(
+                "plan_id 只能包含字母、数字、- 和 _，且不超过 {} 个字符：{}",
+                MAX_PLAN_ID_LEN, plan_id
+            )));
+        }
+        Ok(plan_id.to_ascii_lowercase())
+    }
+
     fn workspace_hash(workspace: &str) -> String {
         let mut context = Context::new(&SHA256);
         context.update(workspace.as_bytes());
@@ -147,12 +202,83 @@ impl PlanStore {
             .clone())
     }
 
+    /// 工作区的独占文件锁 `<工作区哈希>.lock`，随返回的文件关闭而释放
+    fn lock_workspace_file(&self, workspace: &str) -> Result<File, PlanError> {
+        self.ensure_root_dir()?;
+        let path = self
+            .root_dir
+            .join(format!("{}.lock", Self::workspace_hash(workspace)));
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|error| {
+                PlanError::Storage(format!("打开计划锁 {} 失败：{}", path.display(), error))
+            })?;
+        file.lock().map_err(|error| {
+            PlanError::Storage(format!("获取计划锁 {} 失败：{}", path.display(), error))
+        })?;
+        Ok(file)
+    }
+
+    /// 各 plan_id 已分配过的最大 revision，保存在 `<工作区哈希>.revisions.json`；
+    /// 清除、归档或覆盖损坏文件后仍保留，重新创建的计划不会复用旧版本号
+    fn revisions_path(&self, workspace: &str) -> PathBuf {
+        self.root_dir.join(format!(
+            "{}.revisions.json",
+            Self::workspace_hash(workspace)
+        ))
+    }
+
+    fn read_revisions(&self, workspace: &str) -> Result<HashMap<String, u64>, PlanError> {
+        let path = self.revisions_path(workspace);
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read(&path).map_err(|error| {
+            PlanError::Storage(format!(
+                "读取计划版本文件 {} 失败：{}",
+                path.display(),
+                error
+            ))
+        })?;
+        serde_json::from_slice(&content)
+            .map_err(|error| PlanError::Corrupt(format!("{} 无法解析：{}", path.display(), error)))
+    }
+
+    fn last_revision(&self, target: &PlanTarget) -> Result<u64, PlanError> {
+        Ok(self
+            .read_revisions(&target.workspace)?
+            .get(&target.plan_id)
+            .copied()
+            .unwrap_or(0))
+    }
+
+    /// 分配下一个 revision（大于 `current` 与已分配过的版本）并先记入版本文件
+    fn next_revision(&self, target: &PlanTarget, current: u64) -> Result<u64, PlanError> {
+        let mut revisions = self.read_revisions(&target.workspace)?;
+        let last = revisions.get(&target.plan_id).copied().unwrap_or(0);
+        let next = current.max(last) + 1;
+        revisions.insert(target.plan_id.clone(), next);
+        self.write_file(&self.revisions_path(&target.workspace), &revisions)?;
+        Ok(next)
+    }
+
+    /// 不存在的计划：空计划项，revision 为该 plan_id 最近分配的版本
+    fn empty_file(&self, target: &PlanTarget) -> Result<PlanFile, PlanError> {
+        let mut file = PlanFile::empty(target.workspace.clone(), target.plan_id.clone());
+        file.revision = self.last_revision(target)?;
+        Ok(file)
+    }
+
     fn validate_action_fields(request: &PlanRequest) -> Result<(), PlanError> {
         match request.action {
             PlanAction::Set => {
-                if request.id.is_some() || request.status.is_some() {
+                if request.id.is_some() || request.status.is_some() || request.reason.is_some() {
                     return Err(PlanError::Validation(
-                        "set 只允许 workspace 和 items".to_string(),
+                        "set 只允许 workspace、items、plan_id、agent_label 与 revision".to_string(),
                     ));
                 }
                 let items = request
@@ -175,26 +301,64 @@ impl PlanStore {
                 if request.status.is_none() {
                     return Err(PlanError::Validation("update 必须提供 status".to_string()));
                 }
+                let has_reason = request
+                    .reason
+                    .as_deref()
+                    .is_some_and(|reason| !reason.trim().is_empty());
+                match (request.status == Some(PlanStatus::Blocked), has_reason) {
+                    (true, false) => {
+                        return Err(PlanError::Validation(
+                            "update 为 blocked 时必须提供 reason".to_string(),
+                        ))
+                    }
+                    (false, true) => {
+                        return Err(PlanError::Validation(
+                            "只有 blocked 可以提供 reason".to_string(),
+                        ))
+                    }
+                    _ => {}
+                }
             }
-            PlanAction::Get | PlanAction::Clear => {
-                if request.items.is_some() || request.id.is_some() || request.status.is_some() {
+            PlanAction::Get | PlanAction::Clear | PlanAction::Archive | PlanAction::List => {
+                if request.items.is_some()
+                    || request.id.is_some()
+                    || request.status.is_some()
+                    || request.reason.is_some()
+                {
                     return Err(PlanError::Validation(format!(
-                        "{} 只允许提供 workspace",
+                        "{} 只允许提供 workspace、plan_id、agent_label 与 revision",
                         request.action.as_str()
                     )));
                 }
+                if request.action == PlanAction::List && request.plan_id.is_some() {
+                    return Err(PlanError::Validation(
+                        "list 返回工作区的全部计划，不接受 plan_id".to_string(),
+                    ));
+                }
             }
         }
+        if matches!(request.action, PlanAction::Get | PlanAction::List)
+            && request.revision.is_some()
+        {
+            return Err(PlanError::Validation(format!(
+                "{} 不接受 revision",
+                request.action.as_str()
+            )));
+        }
         Ok(())
     }
 
     fn validate_items(items: &mut [PlanItem]) -> Result<(), PlanError> {
         let mut ids = HashSet::new();
-        let mut in_progress_count = 0usize;
 
-        for item in items {
+        for item in items.iter_mut() {
             item.id = item.id.trim().to_string();
             item.text = item.text.trim().to_string();
+            item.parent_id = Self::trimmed(item.parent_id.take());
+            item.blocked_reason = Self::trimmed(item.blocked_reason.take());
+            for dependency in &mut item.depends_on {
+                *dependency = dependency.trim().to_string();
+            }
             if item.id.is_empty() {
                 return Err(PlanError::Validation("计划项 id 不能为空".to_string()));
             }
@@ -210,56 +374,279 @@ impl PlanStore {
                     item.id
                 )));
             }
+            match (item.status, item.blocked_reason.is_some()) {
+                (PlanStatus::Blocked, false) => {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 为 blocked 时必须提供 blocked_reason",
+                        item.id
+                    )));
+                }
+                (status, true) if status != PlanStatus::Blocked => {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 不是 blocked，不能提供 blocked_reason",
+                        item.id
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        let items = &*items;
+        let by_id = items
+            .iter()
+            .map(|item| (item.id.as_str(), item))
+            .collect::<HashMap<_, _>>();
+        for item in items {
+            if let Some(parent_id) = item.parent_id.as_deref() {
+                if parent_id == item.id || !by_id.contains_key(parent_id) {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 的上级项不存在：{}",
+                        item.id, parent_id
+                    )));
+                }
+            }
+            let mut dependencies = HashSet::new();
+            for dependency in &item.depends_on {
+                if dependency == &item.id || !by_id.contains_key(dependency.as_str()) {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 的依赖项不存在：{}",
+                        item.id, dependency
+                    )));
+                }
+                if !dependencies.insert(dependency.as_str()) {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 的依赖项重复：{}",
+                        item.id, dependency
+                    )));
+                }
+            }
+        }
+
+        Self::check_parent_cycles(items, &by_id)?;
+        Self::check_dependency_cycles(items, &by_id)?;
+
+        for item in items {
+            let ancestors = Self::ancestors(&by_id, &item.id);
+            if let Some(dependency) = item
+                .depends_on
+                .iter()
+                .find(|dependency| ancestors.contains(&dependency.as_str()))
+            {
+                return Err(PlanError::Validation(format!(
+                    "计划项 {} 不能依赖自己的上级项 {}",
+                    item.id, dependency
+                )));
+            }
             if item.status == PlanStatus::InProgress {
-                in_progress_count += 1;
+                if let Some(dependency) = Self::incomplete_dependency(&by_id, item) {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 的依赖 {} 尚未完成，不能处于 in_progress",
+                        item.id, dependency
+                    )));
+                }
+            }
+            if item.status == PlanStatus::Completed {
+                if let Some(child) = items.iter().find(|child| {
+                    !child.status.is_finished()
+                        && Self::ancestors(&by_id, &child.id).contains(&item.id.as_str())
+                }) {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 的下级项 {} 尚未结束，不能标记为 completed",
+                        item.id, child.id
+                    )));
+                }
             }
         }
 
-        if in_progress_count > 1 {
-            return Err(PlanError::Validation(
-                "同一计划最多只能有一个 in_progress 项".to_string(),
-            ));
+        // 中文说明：进行中的计划项必须位于同一条上下级链上，即只有一个进行中的叶子步骤。
+        let mut active = items
+            .iter()
+            .filter(|item| item.status == PlanStatus::InProgress)
+            .map(|item| (item.id.as_str(), Self::ancestors(&by_id, &item.id)))
+            .collect::<Vec<_>>();
+        active.sort_by_key(|(_, ancestors)| ancestors.len());
+        for pair in active.windows(2) {
+            let ((outer, _), (_, inner_ancestors)) = (&pair[0], &pair[1]);
+            if !inner_ancestors.contains(outer) {
+                return Err(PlanError::Validation(
+                    "同一计划最多只能有一个 in_progress 项（其上级项除外）".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn trimmed(value: Option<String>) -> Option<String> {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// 由近及远的全部上级项 id；调用前须已排除上下级环
+    fn ancestors<'a>(by_id: &HashMap<&'a str, &'a PlanItem>, id: &str) -> Vec<&'a str> {
+        let mut ancestors = Vec::new();
+        let mut current = by_id.get(id).and_then(|item| item.parent_id.as_deref());
+        while let Some(parent_id) = current {
+            let Some(parent) = by_id.get(parent_id) else {
+                break;
+            };
+            ancestors.push(parent.id.as_str());
+            current = parent.parent_id.as_deref();
+        }
+        ancestors
+    }
+
+    fn incomplete_dependency<'a>(
+        by_id: &HashMap<&str, &PlanItem>,
+        item: &'a PlanItem,
+    ) -> Option<&'a str> {
+        item.depends_on
+            .iter()
+            .find(|dependency| {
+                by_id
+                    .get(dependency.as_str())
+                    .is_none_or(|dependency| dependency.status != PlanStatus::Completed)
+            })
+            .map(String::as_str)
+    }
+
+    fn check_parent_cycles(
+        items: &[PlanItem],
+        by_id: &HashMap<&str, &PlanItem>,
+    ) -> Result<(), PlanError> {
+        for item in items {
+            let mut visited = HashSet::from([item.id.as_str()]);
+            let mut current = item.parent_id.as_deref();
+            while let Some(parent_id) = current {
+                if !visited.insert(parent_id) {
+                    return Err(PlanError::Validation(format!(
+                        "计划项 {} 的上级关系存在循环",
+                        item.id
+                    )));
+                }
+                current = by_id
+                    .get(parent_id)
+                    .and_then(|parent| parent.parent_id.as_deref());
+            }
+        }
+        Ok(())
+    }
+
+    fn check_dependency_cycles(
+        items: &[PlanItem],
+        by_id: &HashMap<&str, &PlanItem>,
+    ) -> Result<(), PlanError> {
+        // 中文说明：三色深度优先遍历，遇到仍在栈上的节点即为循环依赖。
+        fn visit<'a>(
+            id: &'a str,
+            by_id: &HashMap<&str, &'a PlanItem>,
+            visiting: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Result<(), PlanError> {
+            if done.contains(id) {
+                return Ok(());
+            }
+            if let Some(start) = visiting.iter().position(|current| *current == id) {
+                let mut cycle = visiting[start..].to_vec();
+                cycle.push(id);
+                return Err(PlanError::Validation(format!(
+                    "计划项存在循环依赖：{}",
+                    cycle.join(" -> ")
+                )));
+            }
+            visiting.push(id);
+            if let Some(item) = by_id.get(id) {
+                for dependency in &item.depends_on {
+                    visit(dependency, by_id, visiting, done)?;
+                }
+            }
+            visiting.pop();
+            done.insert(id);
+            Ok(())
+        }
+
+        let mut done = HashSet::new();
+        for item in items {
+            visit(&item.id, by_id, &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+
+    /// 校验调用方是否可以修改计划：归属实例一致，且 revision（若提供）与当前版本一致
+    fn check_access(target: &PlanTarget, file: &PlanFile) -> Result<(), PlanError> {
+        if let Some(owner) = file.owner.as_deref() {
+            if target.agent.as_deref() != Some(owner) {
+                return Err(PlanError::Conflict(format!(
+                    "计划 {} 属于 {}，当前调用方为 {}；请使用其他 plan_id",
+                    file.plan_id,
+                    owner,
+                    target.agent.as_deref().unwrap_or("未提供 agent_label")
+                )));
+            }
+        }
+        if let Some(expected) = target.revision {
+            if expected != file.revision {
+                return Err(PlanError::Stale {
+                    message: format!(
+                        "计划 {} 的当前版本为 {}，请求基于版本 {}，请基于返回的最新计划重试",
+                        file.plan_id, file.revision, expected
+                    ),
+                    snapshot: Box::new(PlanResult::new(PlanAction::Get, file.clone(), false)),
+                });
+            }
         }
         Ok(())
     }
 
     fn set_plan(
         &self,
-        workspace: String,
+        target: PlanTarget,
         mut items: Vec<PlanItem>,
     ) -> Result<PlanResult, PlanError> {
         Self::validate_items(&mut items)?;
-        let new_file = PlanFile {
-            version: PLAN_FILE_VERSION,
-            workspace: workspace.clone(),
-            items: items.clone(),
-        };
 
-        let path = self.path_for_normalized_workspace(&workspace);
-        let changed = match self.read_file(&path, &workspace) {
-            Ok(Some(current)) => current != new_file,
-            Ok(None) | Err(PlanError::Corrupt(_)) => true,
+        let path = self.plan_path(&target.workspace, &target.plan_id);
+        let current = match self.read_file(&path, &target.workspace, Some(&target.plan_id)) {
+            Ok(current) => current,
+            // 中文说明：损坏的计划允许直接用 set 覆盖恢复；属于其他工作区或计划的文件返回冲突，不会被覆盖。
+            Err(PlanError::Corrupt(_)) => None,
             Err(error) => return Err(error),
         };
+        let exists = current.is_some();
+        let mut file = match current {
+            Some(current) => current,
+            None => self.empty_file(&target)?,
+        };
+        Self::check_access(&target, &file)?;
 
+        let owner = file.owner.clone().or_else(|| target.agent.clone());
+        let changed = !exists || file.items != items || file.owner != owner;
         if changed {
-            self.write_file(&path, &new_file)?;
+            file.items = items;
+            file.owner = owner;
+            file.revision = self.next_revision(&target, file.revision)?;
+            self.write_file(&path, &file)?;
         }
 
-        Ok(PlanResult::new(PlanAction::Set, workspace, changed, items))
+        Ok(PlanResult::new(PlanAction::Set, file, changed))
     }
 
     fn update_plan(
         &self,
-        workspace: String,
+        target: PlanTarget,
         id: String,
         status: PlanStatus,
+        reason: Option<String>,
     ) -> Result<PlanResult, PlanError> {
         let id = id.trim().to_string();
-        let path = self.path_for_normalized_workspace(&workspace);
+        let reason = Self::trimmed(reason);
+        let path = self.plan_path(&target.workspace, &target.plan_id);
         let mut file = self
-            .read_file(&path, &workspace)?
-            .ok_or_else(|| PlanError::Conflict("当前工作区没有计划，请先调用 set".to_string()))?;
+            .read_file(&path, &target.workspace, Some(&target.plan_id))?
+            .ok_or_else(|| {
+                PlanError::Conflict(format!("计划 {} 不存在，请先调用 set", target.plan_id))
+            })?;
+        Self::check_access(&target, &file)?;
 
         let index = file
             .items
@@ -268,19 +655,23 @@ impl PlanStore {
             .ok_or_else(|| PlanError::Conflict(format!("计划项不存在：{}", id)))?;
         let current = file.items[index].status;
 
-        if current == status {
-            return Ok(PlanResult::new(
-                PlanAction::Update,
-                workspace,
-                false,
-                file.items,
-            ));
+        if current == status && file.items[index].blocked_reason == reason {
+            return Ok(PlanResult::new(PlanAction::Update, file, false));
         }
 
+        // 中文说明：completed 与 cancelled 为终态；blocked 解除后可回到 pending 或直接开始。
         let valid_transition = matches!(
             (current, status),
             (PlanStatus::Pending, PlanStatus::InProgress)
                 | (PlanStatus::InProgress, PlanStatus::Completed)
+                | (
+                    PlanStatus::Pending | PlanStatus::InProgress | PlanStatus::Blocked,
+                    PlanStatus::Blocked | PlanStatus::Cancelled
+                )
+                | (
+                    PlanStatus::Blocked,
+                    PlanStatus::Pending | PlanStatus::InProgress
+                )
         );
         if !valid_transition {
             return Err(PlanError::Conflict(format!(
@@ -291,59 +682,159 @@ impl PlanStore {
             )));
         }
 
-        if status == PlanStatus::InProgress
-            && file.items.iter().enumerate().any(|(item_index, item)| {
-                item_index != index && item.status == PlanStatus::InProgress
-            })
-        {
-            return Err(PlanError::Conflict(
-                "已有其他计划项处于 in_progress，请先完成该项".to_string(),
-            ));
-        }
-
         file.items[index].status = status;
+        file.items[index].blocked_reason = reason;
+        // 中文说明：变更后整体复核依赖、上下级与进行中约束，违反时视为状态冲突，不写入。
+        Self::validate_items(&mut file.items).map_err(|error| match error {
+            PlanError::Validation(message) => PlanError::Conflict(message),
+            other => other,
+        })?;
+        file.revision = self.next_revision(&target, file.revision)?;
         self.write_file(&path, &file)?;
-        Ok(PlanResult::new(
-            PlanAction::Update,
-            workspace,
-            true,
-            file.items,
-        ))
+        Ok(PlanResult::new(PlanAction::Update, file, true))
     }
 
-    fn get_plan_locked(&self, workspace: String) -> Result<PlanResult, PlanError> {
-        let path = self.path_for_normalized_workspace(&workspace);
-        let items = self
-            .read_file(&path, &workspace)?
-            .unwrap_or_else(|| PlanFile::empty(workspace.clone()))
-            .items;
-        Ok(PlanResult::new(PlanAction::Get, workspace, false, items))
+    fn get_plan_locked(&self, target: PlanTarget) -> Result<PlanResult, PlanError> {
+        let path = self.plan_path(&target.workspace, &target.plan_id);
+        let file = match self.read_file(&path, &target.workspace, Some(&target.plan_id))? {
+            Some(file) => file,
+            None => self.empty_file(&target)?,
+        };
+        Ok(PlanResult::new(PlanAction::Get, file, false))
     }
 
-    fn clear_plan(&self, workspace: String) -> Result<PlanResult, PlanError> {
-        let path = self.path_for_normalized_workspace(&workspace);
-        let changed = if path.exists() {
+    fn clear_plan(&self, target: PlanTarget) -> Result<PlanResult, PlanError> {
+        let path = self.plan_path(&target.workspace, &target.plan_id);
+        // 中文说明：损坏的计划无法确认归属，允许直接清除以便恢复；属于其他工作区或计划的文件返回冲突。
+        let mut current_revision = 0;
+        match self.read_file(&path, &target.workspace, Some(&target.plan_id)) {
+            Ok(Some(current)) => {
+                Self::check_access(&target, &current)?;
+                current_revision = current.revision;
+            }
+            Ok(None) | Err(PlanError::Corrupt(_)) => {}
+            Err(error) => return Err(error),
+        }
+        let changed = path.exists();
+        if changed {
+            // 中文说明：清除也占用一个版本，基于清除前版本的修改会被拒绝。
+            self.next_revision(&target, current_revision)?;
             fs::remove_file(&path).map_err(|error| {
                 PlanError::Storage(format!("删除计划文件 {} 失败：{}", path.display(), error))
             })?;
-            true
-        } else {
-            false
-        };
+        }
         Ok(PlanResult::new(
             PlanAction::Clear,
-            workspace,
+            self.empty_file(&target)?,
             changed,
-            Vec::new(),
         ))
     }
 
-    fn path_for_normalized_workspace(&self, workspace: &str) -> PathBuf {
-        self.root_dir
-            .join(format!("{}.json", Self::workspace_hash(workspace)))
+    /// 把计划移入归档目录，释放 plan_id；归档后的计划只出现在 list 中
+    fn archive_plan(&self, target: PlanTarget) -> Result<PlanResult, PlanError> {
+        let path = self.plan_path(&target.workspace, &target.plan_id);
+        let mut file = self
+            .read_file(&path, &target.workspace, Some(&target.plan_id))?
+            .ok_or_else(|| PlanError::Conflict(format!("计划 {} 不存在", target.plan_id)))?;
+        Self::check_access(&target, &file)?;
+
+        let now = chrono::Utc::now();
+        file.archived_at = Some(now.to_rfc3339());
+        let archive_dir = self.root_dir.join(ARCHIVE_DIR);
+        fs::create_dir_all(&archive_dir).map_err(|error| {
+            PlanError::Storage(format!(
+                "创建计划归档目录 {} 失败：{}",
+                archive_dir.display(),
+                error
+            ))
+        })?;
+        let archive_path = archive_dir.join(format!(
+            "{}--{}--{}.json",
+            Self::workspace_hash(&target.workspace),
+            target.plan_id,
+            now.format("%Y%m%d%H%M%S%3f")
+        ));
+        self.write_file(&archive_path, &file)?;
+        self.next_revision(&target, file.revision)?;
+        fs::remove_file(&path).map_err(|error| {
+            PlanError::Storage(format!("删除计划文件 {} 失败：{}", path.display(), error))
+        })?;
+        Ok(PlanResult::new(PlanAction::Archive, file, true))
+    }
+
+    /// 工作区的全部计划：进行中的按 plan_id 排序（default 在前），归档的按归档时间倒序
+    fn list_plans(&self, workspace: String) -> Result<PlanResult, PlanError> {
+        let mut active = self.read_workspace_files(&self.root_dir, &workspace)?;
+        active.sort_by(|a, b| {
+            (a.plan_id != DEFAULT_PLAN_ID, &a.plan_id)
+                .cmp(&(b.plan_id != DEFAULT_PLAN_ID, &b.plan_id))
+        });
+        let mut archived =
+            self.read_workspace_files(&self.root_dir.join(ARCHIVE_DIR), &workspace)?;
+        archived.sort_by(|a, b| b.archived_at.cmp(&a.archived_at));
+
+        let mut result = PlanResult::new(
+            PlanAction::List,
+            PlanFile::empty(workspace, DEFAULT_PLAN_ID.to_string()),
+            false,
+        );
+        result.plans = active
+            .iter()
+            .chain(archived.iter())
+            .map(PlanOverview::from_file)
+            .collect();
+        Ok(result)
+    }
+
+    /// 读取目录中属于该工作区的计划文件；损坏的文件跳过
+    fn read_workspace_files(
+        &self,
+        dir: &Path,
+        workspace: &str,
+    ) -> Result<Vec<PlanFile>, PlanError> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let hash = Self::workspace_hash(workspace);
+        let entries = fs::read_dir(dir).map_err(|error| {
+            PlanError::Storage(format!("读取计划目录 {} 失败：{}", dir.display(), error))
+        })?;
+        let mut files = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let belongs = name == format!("{}.json", hash)
+                || (name.starts_with(&format!("{}--", hash)) && name.ends_with(".json"));
+            if !belongs {
+                continue;
+            }
+            match self.read_file(&path, workspace, None) {
+                Ok(Some(file)) => files.push(file),
+                Ok(None) => {}
+                Err(error) => log::warn!("跳过无法读取的计划文件：{}", error),
+            }
+        }
+        Ok(files)
+    }
+
+    /// default 计划沿用早期的 `<工作区哈希>.json`，其他计划为 `<工作区哈希>--<plan_id>.json`
+    fn plan_path(&self, workspace: &str, plan_id: &str) -> PathBuf {
+        let hash = Self::workspace_hash(workspace);
+        if plan_id == DEFAULT_PLAN_ID {
+            self.root_dir.join(format!("{}.json", hash))
+        } else {
+            self.root_dir.join(format!("{}--{}.json", hash, plan_id))
+        }
     }
 
-    fn read_file(&self, path: &Path, workspace: &str) -> Result<Option<PlanFile>, PlanError> {
+    fn read_file(
+        &self,
+        path: &Path,
+        workspace: &str,
+        plan_id: Option<&str>,
+    ) -> Result<Option<PlanFile>, PlanError> {
         if !path.exists() {
             return Ok(None);
         }
@@ -362,21 +853,29 @@ impl PlanStore {
                 file.version
             )));
         }
+        // 文件可以解析但属于其他工作区或计划时返回冲突而非损坏，set 不会覆盖它。
+        // 早期版本保留了 plan_id 的大小写，这里按不区分大小写比较
         if file.workspace != workspace {
-            return Err(PlanError::Corrupt(format!(
+            return Err(PlanError::Conflict(format!(
                 "{} 的 workspace 与请求不匹配",
                 path.display()
             )));
         }
+        if plan_id.is_some_and(|plan_id| !plan_id.eq_ignore_ascii_case(&file.plan_id)) {
+            return Err(PlanError::Conflict(format!(
+                "{} 的 plan_id 与请求不匹配",
+                path.display()
+            )));
+        }
         Self::validate_items(&mut file.items).map_err(|error| {
             PlanError::Corrupt(format!("{} 的计划项无效：{}", path.display(), error))
         })?;
         Ok(Some(file))
     }
 
-    fn write_file(&self, path: &Path, file: &PlanFile) -> Result<(), PlanError> {
+    fn write_file<T: Serialize>(&self, path: &Path, value: &T) -> Result<(), PlanError> {
         self.ensure_root_dir()?;
-        let content = serde_json::to_vec_pretty(file)
+        let content = serde_json::to_vec_pretty(value)
             .map_err(|error| PlanError::Storage(format!("序列化计划失败：{}", error)))?;
 
         let mut temp_file = NamedTempFile::new_in(&self.root_dir)
@@ -424,6 +923,10 @@ mod tests {
             items: None,
             id: None,
             status: None,
+            reason: None,
+            plan_id: None,
+            agent_label: None,
+            revision: None,
         }
     }
 
@@ -433,11 +936,17 @@ mod tests {
                 id: "step-1".to_string(),
                 text: "实现存储".to_string(),
                 status: PlanStatus::Pending,
+                parent_id: None,
+                depends_on: Vec::new(),
+                blocked_reason: None,
             },
             PlanItem {
                 id: "step-2".to_string(),
                 text: "实现界面".to_string(),
                 status: PlanStatus::Pending,
+                parent_id: None,
+                depends_on: Vec::new(),
+                blocked_reason: None,
             },
         ]
     }
@@ -611,6 +1120,93 @@ mod tests {
         ));
     }
 
+    fn item(id: &str, parent_id: Option<&str>, depends_on: &[&str]) -> PlanItem {
+        PlanItem {
+            id: id.to_string(),
+            text: format!("步骤 {}", id),
+            status: PlanStatus::Pending,
+            parent_id: parent_id.map(str::to_string),
+            depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            blocked_reason: None,
+        }
+    }
+
+    #[test]
+    fn hierarchy_dependencies_and_cycles_are_validated() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = temp.path().join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        let store = PlanStore::new(temp.path().join("plans"));
+
+        let rejected = [
+            vec![item("a", None, &["b"]), item("b", None, &["a"])],
+            vec![item("a", Some("b"), &[]), item("b", Some("a"), &[])],
+            vec![item("a", None, &[]), item("b", Some("a"), &["a"])],
+            vec![item("a", None, &["missing"])],
+        ];
+        for items in rejected {
+            let mut set = request(PlanAction::Set, &workspace);
+            set.items = Some(items);
+            assert!(matches!(store.execute(set), Err(PlanError::Validation(_))));
+        }
+
+        let mut set = request(PlanAction::Set, &workspace);
+        set.items = Some(vec![
+            item("build", None, &[]),
+            item("store", Some("build"), &[]),
+            item("ui", Some("build"), &["store"]),
+            item("docs", None, &["build"]),
+        ]);
+        let result = store.execute(set).unwrap();
+        assert_eq!(result.summary.subtrees.len(), 1);
+        assert_eq!(result.summary.subtrees[0].total, 2);
+
+        let update = |id: &str, status: PlanStatus, reason: Option<&str>| {
+            let mut update = request(PlanAction::Update, &workspace);
+            update.id = Some(id.to_string());
+            update.status = Some(status);
+            update.reason = reason.map(str::to_string);
+            store.execute(update)
+        };
+
+        // 依赖未完成时不能开始；上级项与其下级可以同时进行
+        assert!(matches!(
+            update("ui", PlanStatus::InProgress, None),
+            Err(PlanError::Conflict(_))
+        ));
+        update("build", PlanStatus::InProgress, None).unwrap();
+        update("store", PlanStatus::InProgress, None).unwrap();
+        assert!(matches!(
+            update("build", PlanStatus::Completed, None),
+            Err(PlanError::Conflict(_))
+        ));
+        update("store", PlanStatus::Completed, None).unwrap();
+
+        assert!(matches!(
+            update("ui", PlanStatus::Blocked, None),
+            Err(PlanError::Validation(_))
+        ));
+        let blocked = update("ui", PlanStatus::Blocked, Some("等待设计稿")).unwrap();
+        assert_eq!(blocked.summary.blocked, 1);
+        assert_eq!(
+            blocked.items[2].blocked_reason.as_deref(),
+            Some("等待设计稿")
+        );
+
+        update("ui", PlanStatus::Cancelled, None).unwrap();
+        let completed = update("build", PlanStatus::Completed, None).unwrap();
+        assert!(completed.summary.subtrees[0].all_completed);
+        assert_eq!(completed.summary.subtrees[0].cancelled, 1);
+        assert!(matches!(
+            update("ui", PlanStatus::Pending, None),
+            Err(PlanError::Conflict(_))
+        ));
+
+        update("docs", PlanStatus::InProgress, None).unwrap();
+        let done = update("docs", PlanStatus::Completed, None).unwrap();
+        assert!(done.summary.all_completed);
+    }
+
     #[test]
     fn concurrent_duplicate_update_is_serialized() {
         let temp = tempfile::tempdir().unwrap();
@@ -645,4 +1241,183 @@ mod tests {
             .count();
         assert_eq!(changed_count, 1);
     }
+
+    #[test]
+    fn separate_stores_on_the_same_root_are_serialized_by_file_lock() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = temp.path().join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        let root = temp.path().join("plans");
+
+        let mut set = request(PlanAction::Set, &workspace);
+        set.items = Some(items());
+        PlanStore::new(root.clone()).execute(set).unwrap();
+
+        // 中文说明：两个 PlanStore 各有独立的进程内锁，模拟两个 MCP 进程。
+        let barrier = Arc::new(Barrier::new(3));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let store = PlanStore::new(root.clone());
+                let barrier = Arc::clone(&barrier);
+                let workspace = workspace.clone();
+                thread::spawn(move || {
+                    let mut update = request(PlanAction::Update, &workspace);
+                    update.id = Some("step-1".to_string());
+                    update.status = Some(PlanStatus::InProgress);
+                    update.revision = Some(1);
+                    barrier.wait();
+                    store.execute(update).is_ok()
+                })
+            })
+            .collect();
+        barrier.wait();
+        let succeeded = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|succeeded| *succeeded)
+            .count();
+        assert_eq!(succeeded, 1);
+        let current = PlanStore::new(root)
+            .execute(request(PlanAction::Get, &workspace))
+            .unwrap();
+        assert_eq!(current.revision, 2);
+    }
+
+    #[test]
+    fn revisions_are_not_reused_after_clear_or_archive() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = temp.path().join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        let store = PlanStore::new(temp.path().join("plans"));
+
+        let mut set = request(PlanAction::Set, &workspace);
+        set.items = Some(items());
+        assert_eq!(store.execute(set.clone()).unwrap().revision, 1);
+
+        let cleared = store
+            .execute(request(PlanAction::Clear, &workspace))
+            .unwrap();
+        assert_eq!(cleared.revision, 2);
+        // 中文说明：基于清除前版本的请求不能悄悄重建计划。
+        let mut stale = set.clone();
+        stale.revision = Some(1);
+        assert!(matches!(store.execute(stale), Err(PlanError::Stale { .. })));
+        assert_eq!(store.execute(set.clone()).unwrap().revision, 3);
+
+        let archived = store
+            .execute(request(PlanAction::Archive, &workspace))
+            .unwrap();
+        assert_eq!(archived.revision, 3);
+        let recreated = store.execute(set).unwrap();
+        assert_eq!(recreated.revision, 5);
+        let fresh = store.execute(request(PlanAction::Get, &workspace)).unwrap();
+        assert_eq!(fresh.revision, 5);
+    }
+
+    #[test]
+    fn named_plans_enforce_owner_and_revision() {
+        let temp = tempfile::tempdir().unwrap();
+        let workspace = temp.path().join("workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        let store = PlanStore::new(temp.path().join("plans"));
+
+        let named = |action: PlanAction, plan_id: &str, agent: &str| {
+            let mut request = request(action, &workspace);
+            request.plan_id = Some(plan_id.to_string());
+            request.agent_label = Some(agent.to_string());
+            request
+        };
+
+        let mut alpha = named(PlanAction::Set, "alpha", "agent-a");
+        alpha.items = Some(items());
+        let created = store.execute(alpha).unwrap();
+        assert_eq!(created.owner.as_deref(), Some("agent-a"));
+        assert_eq!(created.revision, 1);
+
+        let mut beta = named(PlanAction::Set, "beta", "agent-b");
+        beta.items = Some(items());
+        store.execute(beta).unwrap();
+
+        // 中文说明：默认计划与命名计划互不影响。
+        let default = store.execute(request(PlanAction::Get, &workspace)).unwrap();
+        assert_eq!(default.plan_id, DEFAULT_PLAN_ID);
+        assert!(default.items.is_empty());
+
+        let mut foreign = named(PlanAction::Update, "alpha", "agent-b");
+        foreign.id = Some("step-1".to_string());
+        foreign.status = Some(PlanStatus::InProgress);
+        assert!(matches!(
+            store.execute(foreign),
+            Err(PlanError::Conflict(_))
+        ));
+
+        // 中文说明：plan_id 不区分大小写，大小写变体不能绕过归属覆盖已有计划。
+        let mut shouting = named(PlanAction::Set, "ALPHA", "agent-b");
+        shouting.items = Some(vec![items().remove(0)]);
+        assert!(matches!(
+            store.execute(shouting),
+            Err(PlanError::Conflict(_))
+        ));
+
+        // 中文说明：能解析但 plan_id 不匹配的文件不按损坏处理，set 不会覆盖它。
+        let workspace_key = workspace.to_str().unwrap();
+        let alpha_path = store.plan_path(workspace_key, "alpha");
+        let gamma_path = store.plan_path(workspace_key, "gamma");
+        fs::copy(&alpha_path, &gamma_path).unwrap();
+        let mut gamma = named(PlanAction::Set, "gamma", "agent-b");
+        gamma.items = Some(items());
+        assert!(matches!(store.execute(gamma), Err(PlanError::Conflict(_))));
+        assert!(matches!(
+            store.execute(named(PlanAction::Clear, "gamma", "agent-b")),
+            Err(PlanError::Conflict(_))
+        ));
+        assert_eq!(
+            fs::read(&gamma_path).unwrap(),
+            fs::read(&alpha_path).unwrap()
+        );
+        fs::remove_file(&gamma_path).unwrap();
+
+        let mut start = named(PlanAction::Update, "alpha", "agent-a");
+        start.id = Some("step-1".to_string());
+        start.status = Some(PlanStatus::InProgress);
+        start.revision = Some(1);
+        assert_eq!(store.execute(start.clone()).unwrap().revision, 2);
+
+        start.id = Some("step-2".to_string());
+        start.status = Some(PlanStatus::Cancelled);
+        match store.execute(start) {
+            Err(PlanError::Stale { snapshot, .. }) => {
+                assert_eq!(snapshot.revision, 2);
+                assert_eq!(snapshot.items[0].status, PlanStatus::InProgress);
+                assert_eq!(snapshot.items[1].status, PlanStatus::Pending);
+            }
+            other => panic!("期望版本冲突，实际为 {:?}", other),
+        }
+
+        let archived = store
+            .execute(named(PlanAction::Archive, "beta", "agent-b"))
+            .unwrap();
+        assert!(archived.changed);
+        let beta_get = store
+            .execute(named(PlanAction::Get, "beta", "agent-a"))
+            .unwrap();
+        assert!(beta_get.items.is_empty());
+
+        let listed = store
+            .execute(request(PlanAction::List, &workspace))
+            .unwrap();
+        let overview: Vec<_> = listed
+            .plans
+            .iter()
+            .map(|plan| (plan.plan_id.as_str(), plan.archived_at.is_some()))
+            .collect();
+        assert_eq!(overview, vec![("alpha", false), ("beta", true)]);
+
+        let mut invalid = request(PlanAction::Get, &workspace);
+        invalid.plan_id = Some("../escape".to_string());
+        assert!(matches!(
+            store.execute(invalid),
+            Err(PlanError::Validation(_))
+        ));
+    }
 }
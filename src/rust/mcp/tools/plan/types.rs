@@ -1,7 +1,14 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub const PLAN_FILE_VERSION: u32 = 1;
+/// 未指定 plan_id 时使用的计划，对应早期每个工作区唯一的计划文件
+pub const DEFAULT_PLAN_ID: &str = "default";
+
+fn default_plan_id() -> String {
+    DEFAULT_PLAN_ID.to_string()
+}
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -10,6 +17,8 @@ pub enum PlanAction {
     Update,
     Get,
     Clear,
+    List,
+    Archive,
 }
 
 impl PlanAction {
@@ -19,6 +28,8 @@ impl PlanAction {
             Self::Update => "update",
             Self::Get => "get",
             Self::Clear => "clear",
+            Self::List => "list",
+            Self::Archive => "archive",
         }
     }
 }
@@ -29,6 +40,10 @@ pub enum PlanStatus {
     Pending,
     InProgress,
     Completed,
+    /// 受阻，需附带原因
+    Blocked,
+    /// 已取消，与 completed 一样视为结束
+    Cancelled,
 }
 
 impl PlanStatus {
@@ -37,8 +52,15 @@ impl PlanStatus {
             Self::Pending => "pending",
             Self::InProgress => "in_progress",
             Self::Completed => "completed",
+            Self::Blocked => "blocked",
+            Self::Cancelled => "cancelled",
         }
     }
+
+    /// 已完成或已取消，不再推进
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Cancelled)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
@@ -47,6 +69,15 @@ pub struct PlanItem {
     pub id: String,
     pub text: String,
     pub status: PlanStatus,
+    /// 上级计划项；为空表示顶层
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// 须先完成的计划项
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// blocked 时的受阻原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
@@ -60,20 +91,47 @@ pub struct PlanRequest {
     pub id: Option<String>,
     #[serde(default)]
     pub status: Option<PlanStatus>,
+    /// update 为 blocked 时必填的受阻原因
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// 计划名；为空时使用 default
+    #[serde(default)]
+    pub plan_id: Option<String>,
+    /// 调用方 AI 实例名称，与 zhi 的 agent_label 相同；计划归首次 set 的实例所有
+    #[serde(default)]
+    pub agent_label: Option<String>,
+    /// 调用方读取到的计划版本；与当前版本不一致时拒绝写入并返回最新计划
+    #[serde(default)]
+    pub revision: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PlanFile {
     pub version: u32,
     pub workspace: String,
+    #[serde(default = "default_plan_id")]
+    pub plan_id: String,
+    /// 所属 AI 实例；为空表示任何调用方都可以修改
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// 每次写入递增，用于乐观并发校验
+    #[serde(default)]
+    pub revision: u64,
+    /// 归档时间（RFC 3339）；仅归档目录中的计划有值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
     pub items: Vec<PlanItem>,
 }
 
 impl PlanFile {
-    pub fn empty(workspace: String) -> Self {
+    pub fn empty(workspace: String, plan_id: String) -> Self {
         Self {
             version: PLAN_FILE_VERSION,
             workspace,
+            plan_id,
+            owner: None,
+            revision: 0,
+            archived_at: None,
             items: Vec::new(),
         }
     }
@@ -83,42 +141,139 @@ impl PlanFile {
 pub struct PlanSummary {
     pub completed: usize,
     pub total: usize,
+    /// 全部计划项已完成或已取消
     pub all_completed: bool,
+    pub blocked: usize,
+    pub cancelled: usize,
+    /// 有下级的计划项按子树汇总，按计划项顺序排列
+    pub subtrees: Vec<PlanSubtreeSummary>,
+}
+
+/// 某计划项全部下级（不含自身）的状态汇总
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PlanSubtreeSummary {
+    pub id: String,
+    pub completed: usize,
+    pub total: usize,
+    pub all_completed: bool,
+    pub blocked: usize,
+    pub cancelled: usize,
 }
 
 impl PlanSummary {
     pub fn from_items(items: &[PlanItem]) -> Self {
-        let completed = items
-            .iter()
-            .filter(|item| item.status == PlanStatus::Completed)
-            .count();
+        let (completed, blocked, cancelled) = count_statuses(items.iter());
         let total = items.len();
+
+        let mut children: HashMap<&str, Vec<&PlanItem>> = HashMap::new();
+        for item in items {
+            if let Some(parent_id) = item.parent_id.as_deref() {
+                children.entry(parent_id).or_default().push(item);
+            }
+        }
+        let subtrees = items
+            .iter()
+            .filter(|item| children.contains_key(item.id.as_str()))
+            .map(|item| {
+                let descendants = descendants(&children, &item.id);
+                let (completed, blocked, cancelled) = count_statuses(descendants.iter().copied());
+                PlanSubtreeSummary {
+                    id: item.id.clone(),
+                    completed,
+                    total: descendants.len(),
+                    all_completed: completed + cancelled == descendants.len(),
+                    blocked,
+                    cancelled,
+                }
+            })
+            .collect();
+
         Self {
             completed,
             total,
-            all_completed: total > 0 && completed == total,
+            all_completed: total > 0 && completed + cancelled == total,
+            blocked,
+            cancelled,
+            subtrees,
         }
     }
 }
 
+fn count_statuses<'a>(items: impl Iterator<Item = &'a PlanItem>) -> (usize, usize, usize) {
+    items.fold(
+        (0, 0, 0),
+        |(completed, blocked, cancelled), item| match item.status {
+            PlanStatus::Completed => (completed + 1, blocked, cancelled),
+            PlanStatus::Blocked => (completed, blocked + 1, cancelled),
+            PlanStatus::Cancelled => (completed, blocked, cancelled + 1),
+            PlanStatus::Pending | PlanStatus::InProgress => (completed, blocked, cancelled),
+        },
+    )
+}
+
+/// 深度优先收集全部下级；计划项已通过校验，不存在环
+fn descendants<'a>(children: &HashMap<&str, Vec<&'a PlanItem>>, id: &str) -> Vec<&'a PlanItem> {
+    let mut result = Vec::new();
+    let mut stack = vec![id];
+    while let Some(current) = stack.pop() {
+        for child in children.get(current).into_iter().flatten() {
+            result.push(*child);
+            stack.push(child.id.as_str());
+        }
+    }
+    result
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PlanResult {
     pub action: String,
     pub workspace: String,
+    pub plan_id: String,
+    pub owner: Option<String>,
+    pub revision: u64,
     pub changed: bool,
     pub items: Vec<PlanItem>,
     pub summary: PlanSummary,
+    /// list 时返回工作区的全部计划（含已归档）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plans: Vec<PlanOverview>,
 }
 
 impl PlanResult {
-    pub fn new(action: PlanAction, workspace: String, changed: bool, items: Vec<PlanItem>) -> Self {
-        let summary = PlanSummary::from_items(&items);
+    pub fn new(action: PlanAction, file: PlanFile, changed: bool) -> Self {
+        let summary = PlanSummary::from_items(&file.items);
         Self {
             action: action.as_str().to_string(),
-            workspace,
+            workspace: file.workspace,
+            plan_id: file.plan_id,
+            owner: file.owner,
+            revision: file.revision,
             changed,
-            items,
+            items: file.items,
             summary,
+            plans: Vec::new(),
+        }
+    }
+}
+
+/// list 返回的单个计划概览
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PlanOverview {
+    pub plan_id: String,
+    pub owner: Option<String>,
+    pub revision: u64,
+    pub archived_at: Option<String>,
+    pub summary: PlanSummary,
+}
+
+impl PlanOverview {
+    pub fn from_file(file: &PlanFile) -> Self {
+        Self {
+            plan_id: file.plan_id.clone(),
+            owner: file.owner.clone(),
+            revision: file.revision,
+            archived_at: file.archived_at.clone(),
+            summary: PlanSummary::from_items(&file.items),
         }
     }
 }
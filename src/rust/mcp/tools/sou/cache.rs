@@ -0,0 +1,195 @@
+// sou 查询结果缓存
+// 同一会话内 agent 经常重复近似的 sou 查询；fast-context 需要多轮远端调用，both 要跑两个后端。
+// 缓存按项目隔离，键包含规范化查询、后端策略、排除项、检索参数与索引代次；
+// 本地索引或 ACE blob 集合一变，代次随之变化，旧条目自然失效。
+
+use once_cell::sync::Lazy;
+use rmcp::model::CallToolResult;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 兜底过期时间：未启用本地 watcher 时代次无法感知工作区改动，靠 TTL 限制陈旧程度。
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_ENTRIES_PER_PROJECT: usize = 64;
+
+static QUERY_CACHE: Lazy<Mutex<HashMap<String, ProjectQueryCache>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct QueryCacheKey {
+    pub query: String,
+    pub backend: String,
+    pub excludes: String,
+    pub params: String,
+    pub generation: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CacheStatus {
+    Hit,
+    Miss,
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Bypass => "bypass",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Default)]
+struct ProjectQueryCache {
+    entries: HashMap<QueryCacheKey, CachedEntry>,
+    stats: CacheStats,
+}
+
+struct CachedEntry {
+    result: CallToolResult,
+    stored_at: Instant,
+}
+
+/// 规范化查询：大小写与空白差异不应导致缓存未命中。
+pub(super) fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// 排除项与顺序无关；未显式传入时用 `default` 标记走配置默认值。
+pub(super) fn normalize_excludes(excludes: Option<&[String]>) -> String {
+    let Some(excludes) = excludes else {
+        return "default".to_string();
+    };
+    let mut values = excludes
+        .iter()
+        .map(|value| value.trim().trim_matches('/').replace('\\', "/"))
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+    values.sort();
+    values.dedup();
+    values.join(",")
+}
+
+pub(super) fn lookup(project: &str, key: &QueryCacheKey) -> (Option<CallToolResult>, CacheStats) {
+    let Ok(mut cache) = QUERY_CACHE.lock() else {
+        return (None, CacheStats::default());
+    };
+    let project_cache = cache.entry(project.to_string()).or_default();
+    let fresh = project_cache
+        .entries
+        .get(key)
+        .filter(|entry| entry.stored_at.elapsed() < CACHE_TTL)
+        .map(|entry| entry.result.clone());
+    if fresh.is_some() {
+        project_cache.stats.hits += 1;
+    } else {
+        project_cache.entries.remove(key);
+        project_cache.stats.misses += 1;
+    }
+    (fresh, project_cache.stats)
+}
+
+pub(super) fn store(project: &str, key: QueryCacheKey, result: &CallToolResult) {
+    let Ok(mut cache) = QUERY_CACHE.lock() else {
+        return;
+    };
+    let project_cache = cache.entry(project.to_string()).or_default();
+    // 代次变化后旧代次条目不会再命中，写入时顺手清掉。
+    project_cache.entries.retain(|existing, entry| {
+        existing.generation == key.generation && entry.stored_at.elapsed() < CACHE_TTL
+    });
+    if project_cache.entries.len() >= MAX_ENTRIES_PER_PROJECT {
+        if let Some(oldest) = project_cache
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.stored_at)
+            .map(|(key, _)| key.clone())
+        {
+            project_cache.entries.remove(&oldest);
+        }
+    }
+    project_cache.entries.insert(
+        key,
+        CachedEntry {
+            result: result.clone(),
+            stored_at: Instant::now(),
+        },
+    );
+}
+
+pub(super) fn stats(project: &str) -> CacheStats {
+    QUERY_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(project).map(|project_cache| project_cache.stats))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    fn key(query: &str, generation: &str) -> QueryCacheKey {
+        QueryCacheKey {
+            query: normalize_query(query),
+            backend: "local".to_string(),
+            excludes: normalize_excludes(None),
+            params: String::new(),
+            generation: generation.to_string(),
+        }
+    }
+
+    fn result(text: &str) -> CallToolResult {
+        CallToolResult {
+            content: vec![Content::text(text)],
+            is_error: Some(false),
+            meta: None,
+            structured_content: None,
+        }
+    }
+
+    #[test]
+    fn normalization_ignores_case_whitespace_and_exclude_order() {
+        assert_eq!(normalize_query("  Parse   Config\n"), "parse config");
+        assert_eq!(
+            normalize_excludes(Some(&["target/".to_string(), "node_modules".to_string()])),
+            normalize_excludes(Some(&["node_modules".to_string(), "target".to_string()]))
+        );
+        assert_ne!(normalize_excludes(None), normalize_excludes(Some(&[])));
+    }
+
+    #[test]
+    fn lookup_hits_same_generation_and_misses_after_generation_change() {
+        let project = "cache-test-project";
+        let (cached, stats) = lookup(project, &key("Parse Config", "g1"));
+        assert!(cached.is_none());
+        assert_eq!(stats, CacheStats { hits: 0, misses: 1 });
+
+        store(project, key("Parse Config", "g1"), &result("hit"));
+        let (cached, stats) = lookup(project, &key("parse  config", "g1"));
+        assert!(cached.is_some());
+        assert_eq!(stats, CacheStats { hits: 1, misses: 1 });
+
+        let (cached, _) = lookup(project, &key("parse config", "g2"));
+        assert!(cached.is_none());
+        store(project, key("other", "g2"), &result("new"));
+        let (cached, stats) = lookup(project, &key("parse config", "g1"));
+        assert!(cached.is_none(), "写入新代次后旧代次条目应被清理");
+        assert_eq!(stats, CacheStats { hits: 1, misses: 3 });
+        assert_eq!(self::stats(project), stats);
+    }
+}
@@ -0,0 +1,442 @@
+// sou 检索质量评测
+// 读取 JSONL 用例（每行 `{query, expected_paths}`），逐个后端调用 SouTool::search_sections，
+// 统计 recall@k、MRR 与延迟。切换 sou_default_backend 或调整 score_hit 前后可用同一组用例对比。
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::Path;
+use std::time::Instant;
+
+use super::{normalize_backend, SouRequest, SouSection, SouTool};
+
+pub const DEFAULT_EVAL_K: usize = 10;
+
+/// 单条评测用例；`expected_paths` 为相对项目根目录的文件路径。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    pub expected_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EvalOptions {
+    pub project_root: String,
+    pub cases: Vec<EvalCase>,
+    pub backends: Vec<String>,
+    pub k: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendEvalReport {
+    pub backend: String,
+    pub cases: usize,
+    pub errors: usize,
+    pub recall_at_k: f64,
+    pub mrr: f64,
+    pub latency_mean_ms: u64,
+    pub latency_p50_ms: u64,
+    pub latency_p95_ms: u64,
+    /// 前 k 个结果中没有任何期望文件的查询，便于逐条排查
+    pub missed_queries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub project_root: String,
+    pub k: usize,
+    pub backends: Vec<BackendEvalReport>,
+}
+
+/// 读取 JSONL 用例文件；空行与 `#` 开头的注释行会被跳过。
+pub fn load_cases(path: &Path) -> Result<Vec<EvalCase>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("读取评测用例失败: {}", path.display()))?;
+    let mut cases = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let case: EvalCase = serde_json::from_str(trimmed)
+            .with_context(|| format!("解析评测用例失败: 第 {} 行", index + 1))?;
+        if case.query.trim().is_empty() || case.expected_paths.is_empty() {
+            return Err(anyhow!(
+                "评测用例第 {} 行缺少 query 或 expected_paths",
+                index + 1
+            ));
+        }
+        cases.push(case);
+    }
+    if cases.is_empty() {
+        return Err(anyhow!("评测用例文件为空: {}", path.display()));
+    }
+    Ok(cases)
+}
+
+/// 解析并校验后端列表（逗号分隔），未知后端直接报错而不是静默跳过。
+pub fn parse_backends(raw: &str) -> Result<Vec<String>> {
+    let mut backends = Vec::new();
+    for value in raw
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        let backend = normalize_backend(value).ok_or_else(|| anyhow!("未知后端: {}", value))?;
+        if !backends.contains(&backend) {
+            backends.push(backend);
+        }
+    }
+    if backends.is_empty() {
+        return Err(anyhow!("至少需要一个评测后端"));
+    }
+    Ok(backends)
+}
+
+pub async fn run(options: EvalOptions) -> EvalReport {
+    let project_root = options.project_root.clone();
+    let max_results = options.k.clamp(1, u8::MAX as usize) as u8;
+    evaluate(
+        &options.project_root,
+        &options.cases,
+        &options.backends,
+        options.k,
+        |backend, query| {
+            let request = SouRequest {
+                project_root_path: project_root.clone(),
+                query,
+                action: None,
+                path: None,
+                backend: Some(backend),
+                tree_depth: None,
+                max_turns: None,
+                max_results: Some(max_results),
+                max_commands: None,
+                timeout_ms: None,
+                exclude_paths: None,
+                workspace_group: None,
+                no_cache: Some(true),
+                context_lines: None,
+                max_output_chars: None,
+            };
+            async move {
+                SouTool::search_sections(request)
+                    .await
+                    .map(|sections| ranked_paths(&sections))
+            }
+        },
+    )
+    .await
+}
+
+async fn evaluate<F, Fut>(
+    project_root: &str,
+    cases: &[EvalCase],
+    backends: &[String],
+    k: usize,
+    search: F,
+) -> EvalReport
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<Vec<String>, String>>,
+{
+    let k = k.max(1);
+    let mut reports = Vec::new();
+    for backend in backends {
+        let mut recall_sum = 0.0;
+        let mut reciprocal_sum = 0.0;
+        let mut latencies = Vec::new();
+        let mut errors = 0usize;
+        let mut missed_queries = Vec::new();
+
+        for case in cases {
+            let started_at = Instant::now();
+            let result = search(backend.clone(), case.query.clone()).await;
+            let elapsed_ms = started_at.elapsed().as_millis() as u64;
+            let ranked = match result {
+                Ok(ranked) => {
+                    latencies.push(elapsed_ms);
+                    ranked
+                }
+                Err(message) => {
+                    // 后端报错按零召回计入，保证不同后端的分母一致
+                    crate::log_important!(
+                        warn,
+                        "[sou-eval] 查询失败: backend={}, query={}, error={}",
+                        backend,
+                        case.query,
+                        message
+                    );
+                    errors += 1;
+                    Vec::new()
+                }
+            };
+            let (recall, reciprocal_rank) = case_metrics(&ranked, &case.expected_paths, k);
+            if reciprocal_rank == 0.0 {
+                missed_queries.push(case.query.clone());
+            }
+            recall_sum += recall;
+            reciprocal_sum += reciprocal_rank;
+        }
+
+        let count = cases.len().max(1) as f64;
+        latencies.sort_unstable();
+        reports.push(BackendEvalReport {
+            backend: backend.clone(),
+            cases: cases.len(),
+            errors,
+            recall_at_k: recall_sum / count,
+            mrr: reciprocal_sum / count,
+            latency_mean_ms: if latencies.is_empty() {
+                0
+            } else {
+                latencies.iter().sum::<u64>() / latencies.len() as u64
+            },
+            latency_p50_ms: percentile(&latencies, 0.50),
+            latency_p95_ms: percentile(&latencies, 0.95),
+            missed_queries,
+        });
+    }
+
+    EvalReport {
+        project_root: project_root.to_string(),
+        k,
+        backends: reports,
+    }
+}
+
+/// 把片段按出现顺序折叠为去重后的文件路径列表，同一文件的多个片段只算一次排名。
+fn ranked_paths(sections: &[SouSection]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    sections
+        .iter()
+        .map(|section| normalize_eval_path(strip_line_suffix(&section.location)))
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+fn strip_line_suffix(location: &str) -> &str {
+    match location.rsplit_once(':') {
+        Some((path, range))
+            if !range.is_empty() && range.chars().all(|ch| ch.is_ascii_digit() || ch == '-') =>
+        {
+            path
+        }
+        _ => location,
+    }
+}
+
+fn normalize_eval_path(path: &str) -> String {
+    path.trim()
+        .replace('\\', "/")
+        .trim_start_matches("./")
+        .to_string()
+}
+
+fn path_matches(ranked: &str, expected: &str) -> bool {
+    ranked == expected || ranked.ends_with(&format!("/{}", expected))
+}
+
+/// 返回 (recall@k, reciprocal rank)；倒数排名只看前 k 个结果中第一个命中的期望文件。
+fn case_metrics(ranked: &[String], expected: &[String], k: usize) -> (f64, f64) {
+    let expected = expected
+        .iter()
+        .map(|path| normalize_eval_path(path))
+        .collect::<Vec<_>>();
+    if expected.is_empty() {
+        return (0.0, 0.0);
+    }
+    let top = &ranked[..ranked.len().min(k)];
+    let found = expected
+        .iter()
+        .filter(|path| top.iter().any(|ranked| path_matches(ranked, path)))
+        .count();
+    let reciprocal_rank = top
+        .iter()
+        .position(|ranked| expected.iter().any(|path| path_matches(ranked, path)))
+        .map(|index| 1.0 / (index + 1) as f64)
+        .unwrap_or(0.0);
+    (found as f64 / expected.len() as f64, reciprocal_rank)
+}
+
+fn percentile(sorted: &[u64], ratio: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((sorted.len() as f64) * ratio).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub fn format_report(report: &EvalReport) -> String {
+    let mut lines = vec![
+        format!("sou 评测: project={}, k={}", report.project_root, report.k),
+        format!(
+            "{:<14}{:>7}{:>8}{:>11}{:>8}{:>10}{:>10}{:>10}",
+            "backend", "cases", "errors", "recall@k", "mrr", "mean_ms", "p50_ms", "p95_ms"
+        ),
+    ];
+    for backend in &report.backends {
+        lines.push(format!(
+            "{:<14}{:>7}{:>8}{:>11.3}{:>8.3}{:>10}{:>10}{:>10}",
+            backend.backend,
+            backend.cases,
+            backend.errors,
+            backend.recall_at_k,
+            backend.mrr,
+            backend.latency_mean_ms,
+            backend.latency_p50_ms,
+            backend.latency_p95_ms
+        ));
+    }
+    for backend in &report.backends {
+        if backend.missed_queries.is_empty() {
+            continue;
+        }
+        lines.push(String::new());
+        lines.push(format!("[{}] 前 k 个结果未命中:", backend.backend));
+        lines.extend(
+            backend
+                .missed_queries
+                .iter()
+                .map(|query| format!("  - {}", query)),
+        );
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{local, parse_sou_sections};
+    use super::*;
+    use tempfile::tempdir;
+
+    fn case(query: &str, expected: &[&str]) -> EvalCase {
+        EvalCase {
+            query: query.to_string(),
+            expected_paths: expected.iter().map(|path| path.to_string()).collect(),
+        }
+    }
+
+    fn section(location: &str) -> SouSection {
+        SouSection {
+            backend: "local".to_string(),
+            location: location.to_string(),
+            excerpt: String::new(),
+            section: None,
+            repo: None,
+        }
+    }
+
+    #[test]
+    fn ranked_paths_strip_line_ranges_and_dedupe_files() {
+        let ranked = ranked_paths(&[
+            section("src\\config.rs:10-40"),
+            section("src/config.rs:60-90"),
+            section("./docs/guide.md"),
+        ]);
+        assert_eq!(ranked, vec!["src/config.rs", "docs/guide.md"]);
+    }
+
+    #[test]
+    fn case_metrics_compute_recall_and_reciprocal_rank_within_k() {
+        let ranked = ["a.rs", "b.rs", "c.rs", "d.rs"].map(String::from);
+        let expected = ["c.rs", "d.rs"].map(String::from);
+        assert_eq!(case_metrics(&ranked, &expected, 3), (0.5, 1.0 / 3.0));
+        assert_eq!(case_metrics(&ranked, &expected, 2), (0.0, 0.0));
+        assert_eq!(case_metrics(&ranked, &expected, 10), (1.0, 1.0 / 3.0));
+    }
+
+    #[tokio::test]
+    async fn evaluate_aggregates_metrics_per_backend_and_counts_errors() {
+        let cases = vec![
+            case("parse config", &["src/config.rs"]),
+            case("watcher", &["src/watch.rs"]),
+        ];
+        let backends = vec!["local".to_string(), "ace".to_string()];
+        let report = evaluate("/repo", &cases, &backends, 5, |backend, query| async move {
+            match (backend.as_str(), query.as_str()) {
+                ("ace", _) => Err("ACE 未配置".to_string()),
+                (_, "parse config") => Ok(vec!["src/config.rs".to_string()]),
+                _ => Ok(vec!["src/lib.rs".to_string(), "src/watch.rs".to_string()]),
+            }
+        })
+        .await;
+
+        let local = &report.backends[0];
+        assert_eq!(local.recall_at_k, 1.0);
+        assert_eq!(local.mrr, 0.75);
+        assert_eq!(local.errors, 0);
+        assert!(local.missed_queries.is_empty());
+        let ace = &report.backends[1];
+        assert_eq!(ace.errors, 2);
+        assert_eq!(ace.recall_at_k, 0.0);
+        assert_eq!(ace.missed_queries.len(), 2);
+        assert!(format_report(&report).contains("recall@k"));
+    }
+
+    #[test]
+    fn load_cases_skips_comments_and_rejects_incomplete_lines() {
+        let temp = tempdir().expect("评测临时目录应创建成功");
+        let path = temp.path().join("cases.jsonl");
+        std::fs::write(
+            &path,
+            "# fixture\n{\"query\":\"parse config\",\"expected_paths\":[\"src/config.rs\"]}\n\n",
+        )
+        .expect("评测用例应写入成功");
+        assert_eq!(load_cases(&path).expect("用例应可解析").len(), 1);
+
+        std::fs::write(&path, "{\"query\":\"x\",\"expected_paths\":[]}\n").expect("写入失败");
+        assert!(load_cases(&path).is_err());
+        assert_eq!(
+            parse_backends("local, ACE,local").unwrap(),
+            vec!["local", "ace"]
+        );
+        assert!(parse_backends("bing").is_err());
+    }
+
+    #[tokio::test]
+    async fn fixture_cases_are_found_by_local_backend_end_to_end() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("sou_eval");
+        let cases = load_cases(&fixture.join("cases.jsonl")).expect("示例用例应可解析");
+        let project_root = fixture.join("repo");
+        // 中文说明：直接调用本地后端，索引放在临时目录；不读取用户配置、不写入系统索引目录，也不启动 watcher。
+        let index_dir = tempdir().expect("索引临时目录应创建成功");
+        let index_path = index_dir.path().join("sou_eval.sqlite3");
+        let report = evaluate(
+            &project_root.to_string_lossy(),
+            &cases,
+            &["local".to_string()],
+            3,
+            |_, query| {
+                let options = local::LocalSearchOptions {
+                    project_root: project_root.clone(),
+                    query,
+                    max_results: 3,
+                    exclude_paths: Vec::new(),
+                };
+                let index_path = index_path.clone();
+                async move {
+                    local::search_for_test(options, index_path)
+                        .await
+                        .map(|output| ranked_paths(&parse_sou_sections(&output.text, "local")))
+                        .map_err(|error| error.to_string())
+                }
+            },
+        )
+        .await;
+
+        let local = &report.backends[0];
+        assert_eq!(local.cases, 4);
+        assert_eq!(local.errors, 0);
+        assert!(
+            local.missed_queries.is_empty(),
+            "未命中: {:?}",
+            local.missed_queries
+        );
+        assert_eq!(local.recall_at_k, 1.0);
+    }
+}
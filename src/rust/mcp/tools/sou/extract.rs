@@ -0,0 +1,336 @@
+// sou 本地索引的文档抽取器
+// 代码文件继续按固定行窗口分块；Markdown / Jupyter Notebook / PDF 等非代码文档
+// 先抽取可检索文本并记录所属章节（标题路径、单元格、页码），再交给 FTS5 建索引。
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+pub(super) const CHUNK_LINES: usize = 80;
+pub(super) const CHUNK_OVERLAP: usize = 20;
+/// PDF 往往远大于源码文件，单独放宽体积上限。
+const MAX_PDF_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_NOTEBOOK_OUTPUT_LINES: usize = 40;
+
+/// 抽取后的文档片段；行号是抽取文本中的行号，Markdown 与原文件一致。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DocumentChunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub section: Option<String>,
+    pub text: String,
+}
+
+/// 非代码文档抽取器。新增格式只需实现该 trait 并登记到 EXTRACTORS。
+pub(super) trait DocumentExtractor: Sync {
+    fn name(&self) -> &'static str;
+    fn extensions(&self) -> &'static [&'static str];
+    fn max_bytes(&self) -> u64 {
+        super::local::MAX_FILE_BYTES
+    }
+    fn extract(&self, bytes: &[u8]) -> Result<Vec<DocumentChunk>>;
+}
+
+struct MarkdownExtractor;
+struct NotebookExtractor;
+struct PdfExtractor;
+
+static EXTRACTORS: &[&dyn DocumentExtractor] =
+    &[&MarkdownExtractor, &NotebookExtractor, &PdfExtractor];
+
+pub(super) fn extractor_for(path: &Path) -> Option<&'static dyn DocumentExtractor> {
+    let extension = path
+        .extension()
+        .and_then(|value| value.to_str())?
+        .to_ascii_lowercase();
+    EXTRACTORS
+        .iter()
+        .copied()
+        .find(|extractor| extractor.extensions().contains(&extension.as_str()))
+}
+
+/// 普通文本按固定窗口分块；`first_line` 为窗口起始的 1 基行号。
+pub(super) fn chunk_lines(
+    lines: &[&str],
+    first_line: usize,
+    section: Option<&str>,
+) -> Vec<DocumentChunk> {
+    let mut chunks = Vec::new();
+    let step = CHUNK_LINES - CHUNK_OVERLAP;
+    let mut start = 0usize;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        let text = lines[start..end].join("\n");
+        if !text.trim().is_empty() {
+            chunks.push(DocumentChunk {
+                start_line: first_line + start,
+                end_line: first_line + end - 1,
+                section: section.map(str::to_string),
+                text,
+            });
+        }
+        if end == lines.len() {
+            break;
+        }
+        start += step;
+    }
+    chunks
+}
+
+impl DocumentExtractor for MarkdownExtractor {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md", "mdx", "markdown"]
+    }
+
+    /// 按标题分节，章节名取完整标题路径（如 `设计 > 索引`），超长章节再按窗口细分。
+    fn extract(&self, bytes: &[u8]) -> Result<Vec<DocumentChunk>> {
+        let content = String::from_utf8_lossy(bytes);
+        let lines = content.lines().collect::<Vec<_>>();
+        let mut sections: Vec<(Option<String>, usize, usize)> = Vec::new();
+        let mut headings: Vec<(usize, String)> = Vec::new();
+        let mut current: Option<String> = None;
+        let mut section_start = 0usize;
+        let mut in_fence = false;
+
+        for (index, line) in lines.iter().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                continue;
+            }
+            if in_fence {
+                continue;
+            }
+            let Some((level, title)) = parse_heading(line) else {
+                continue;
+            };
+            if index > section_start {
+                sections.push((current.clone(), section_start, index));
+            }
+            headings.retain(|(existing, _)| *existing < level);
+            headings.push((level, title));
+            current = Some(
+                headings
+                    .iter()
+                    .map(|(_, title)| title.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" > "),
+            );
+            section_start = index;
+        }
+        if section_start < lines.len() {
+            sections.push((current, section_start, lines.len()));
+        }
+
+        Ok(sections
+            .into_iter()
+            .flat_map(|(section, start, end)| {
+                chunk_lines(&lines[start..end], start + 1, section.as_deref())
+            })
+            .collect())
+    }
+}
+
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|ch| *ch == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim();
+    if title.is_empty() {
+        None
+    } else {
+        Some((level, title.to_string()))
+    }
+}
+
+impl DocumentExtractor for NotebookExtractor {
+    fn name(&self) -> &'static str {
+        "notebook"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ipynb"]
+    }
+
+    /// 每个单元格（源码 + 文本输出）作为一节；行号按抽取后的连续文本编号。
+    fn extract(&self, bytes: &[u8]) -> Result<Vec<DocumentChunk>> {
+        let notebook: Value = serde_json::from_slice(bytes).context("解析 Notebook JSON 失败")?;
+        let cells = notebook
+            .get("cells")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("Notebook 缺少 cells 字段"))?;
+
+        let mut chunks = Vec::new();
+        let mut next_line = 1usize;
+        for (index, cell) in cells.iter().enumerate() {
+            let kind = cell
+                .get("cell_type")
+                .and_then(Value::as_str)
+                .unwrap_or("code");
+            let mut text = join_notebook_text(cell.get("source"));
+            let outputs = notebook_outputs(cell);
+            if !outputs.is_empty() {
+                text.push_str("\n# output\n");
+                text.push_str(&outputs);
+            }
+            let lines = text.lines().collect::<Vec<_>>();
+            let section = format!("cell {} ({})", index + 1, kind);
+            chunks.extend(chunk_lines(&lines, next_line, Some(&section)));
+            next_line += lines.len();
+        }
+        Ok(chunks)
+    }
+}
+
+fn join_notebook_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .concat(),
+        _ => String::new(),
+    }
+}
+
+/// 只保留 stream 与 text/plain 输出；图片等二进制输出对检索没有价值。
+fn notebook_outputs(cell: &Value) -> String {
+    let Some(outputs) = cell.get("outputs").and_then(Value::as_array) else {
+        return String::new();
+    };
+    let text = outputs
+        .iter()
+        .map(|output| {
+            let text = join_notebook_text(output.get("text"));
+            if text.is_empty() {
+                join_notebook_text(output.get("data").and_then(|data| data.get("text/plain")))
+            } else {
+                text
+            }
+        })
+        .filter(|text| !text.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    text.lines()
+        .take(MAX_NOTEBOOK_OUTPUT_LINES)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl DocumentExtractor for PdfExtractor {
+    fn name(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    fn max_bytes(&self) -> u64 {
+        MAX_PDF_BYTES
+    }
+
+    /// 按页抽取文本，章节记为 `page N`；行号按抽取后的连续文本编号。
+    fn extract(&self, bytes: &[u8]) -> Result<Vec<DocumentChunk>> {
+        // pdf-extract 遇到不规范的 PDF 可能 panic，这里兜住以免拖垮整个索引同步任务。
+        let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+            .map_err(|_| anyhow!("PDF 文本抽取异常"))?
+            .map_err(|error| anyhow!("PDF 文本抽取失败: {}", error))?;
+
+        let mut chunks = Vec::new();
+        let mut next_line = 1usize;
+        for (index, page) in pages.iter().enumerate() {
+            let lines = page
+                .lines()
+                .map(str::trim_end)
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>();
+            let section = format!("page {}", index + 1);
+            chunks.extend(chunk_lines(&lines, next_line, Some(&section)));
+            next_line += lines.len();
+        }
+        Ok(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_chunks_carry_heading_path_and_skip_fenced_hashes() {
+        let text =
+            "intro\n# Design\nbody\n## Index\n```sh\n# not a heading\n```\n## Search\nquery\n";
+        let chunks = MarkdownExtractor
+            .extract(text.as_bytes())
+            .expect("Markdown 应可抽取");
+
+        let sections = chunks
+            .iter()
+            .map(|chunk| chunk.section.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                None,
+                Some("Design"),
+                Some("Design > Index"),
+                Some("Design > Search")
+            ]
+        );
+        assert_eq!(chunks[2].start_line, 4);
+        assert_eq!(chunks[2].end_line, 7);
+        assert!(chunks[2].text.contains("# not a heading"));
+    }
+
+    #[test]
+    fn notebook_cells_include_sources_and_text_outputs() {
+        let notebook = serde_json::json!({
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Loader\n", "reads parquet"]},
+                {
+                    "cell_type": "code",
+                    "source": "load_frame()",
+                    "outputs": [
+                        {"output_type": "stream", "text": ["rows=42\n"]},
+                        {"output_type": "display_data", "data": {"image/png": "AAAA"}}
+                    ]
+                }
+            ]
+        });
+        let chunks = NotebookExtractor
+            .extract(notebook.to_string().as_bytes())
+            .expect("Notebook 应可抽取");
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].section.as_deref(), Some("cell 1 (markdown)"));
+        assert_eq!(chunks[1].section.as_deref(), Some("cell 2 (code)"));
+        assert_eq!(chunks[1].start_line, 3);
+        assert!(chunks[1].text.contains("rows=42"));
+        assert!(!chunks[1].text.contains("AAAA"));
+    }
+
+    #[test]
+    fn extractors_are_selected_by_extension() {
+        assert_eq!(
+            extractor_for(Path::new("docs/ADR-001.MD")).map(|value| value.name()),
+            Some("markdown")
+        );
+        assert_eq!(
+            extractor_for(Path::new("spec.pdf")).map(|value| value.name()),
+            Some("pdf")
+        );
+        assert!(extractor_for(Path::new("src/main.rs")).is_none());
+    }
+}
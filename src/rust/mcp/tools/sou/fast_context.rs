@@ -6,6 +6,7 @@ use flate2::Compression;
 use futures_util::future::join_all;
 use globset::GlobBuilder;
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use rand::Rng;
 use regex::Regex;
 use reqwest::Client;
 use rusqlite::{Connection, OpenFlags};
@@ -26,6 +27,10 @@ use tokio::process::Command;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+use super::why_not_indexed::RuleCheck;
+use crate::mcp::tools::acemcp::secret_scan::{ScanDecision, SecretScanner, CHANNEL_FAST_CONTEXT};
+use crate::network::resilience::{self, UpstreamError};
+
 const API_BASE: &str = "https://server.self-serve.windsurf.com/exa.api_server_pb.ApiServerService";
 const AUTH_BASE: &str = "https://server.self-serve.windsurf.com/exa.auth_pb.AuthService";
 const WS_APP: &str = "windsurf";
@@ -41,6 +46,8 @@ const FAST_CONTEXT_IGNORE_FILES: [&str; 4] = [
 const MAX_TREE_BYTES: usize = 250 * 1024;
 const RESULT_MAX_LINES: usize = 50;
 const LINE_MAX_CHARS: usize = 250;
+/// 流式请求遵循 Retry-After 的等待上限；服务端要求更久时直接失败
+const MAX_STREAMING_RETRY_AFTER: Duration = Duration::from_secs(10);
 const FINAL_FORCE_ANSWER: &str =
     "You have no turns left. Now you MUST provide your final ANSWER, even if it's not complete.";
 
@@ -101,6 +108,10 @@ pub struct SearchOptions {
     pub max_commands: u8,
     pub timeout_ms: u64,
     pub exclude_paths: Vec<String>,
+    /// 读文件 / rg 输出发往远端前的密钥扫描设置
+    pub secret_scan_enabled: bool,
+    pub secret_scan_policy: Option<String>,
+    pub secret_scan_custom_patterns: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -357,14 +368,22 @@ pub async fn search(opts: SearchOptions) -> Result<SearchResult> {
         repo_map.size_bytes,
         repo_map.fell_back
     );
+    let secret_scanner = SecretScanner::new(
+        Some(opts.secret_scan_enabled),
+        opts.secret_scan_policy.as_deref(),
+        &opts.secret_scan_custom_patterns,
+    );
     // #2 Repo Map 智能化：附加 README / manifest 摘要，提升 LLM 首轮命中率
-    let project_summary = build_project_summary(&project_root, &ignore_matcher);
+    let project_summary = build_project_summary(&project_root, &ignore_matcher, &secret_scanner);
     // 并发版 ToolExecutor：Arc<Mutex<状态>> 让多条 restricted_exec 命令可并行，并统一应用默认排除目录。
-    let executor = Arc::new(ToolExecutor::new(
-        project_root.clone(),
-        opts.exclude_paths.clone(),
-        ignore_matcher,
-    ));
+    let executor = Arc::new(
+        ToolExecutor::new(
+            project_root.clone(),
+            opts.exclude_paths.clone(),
+            ignore_matcher,
+        )
+        .with_secret_scanner(secret_scanner),
+    );
     let tool_defs = build_tool_definitions(opts.max_commands);
     let system_prompt = build_system_prompt(opts.max_turns, opts.max_commands, opts.max_results);
     // [D] 中文 query 提示：当中文字符占比超过 30%，user prompt 内追加翻译提醒
@@ -905,10 +924,24 @@ async fn streaming_request(
     let url = format!("{API_BASE}/GetDevstralStream");
     let base_timeout_ms = timeout_ms.max(1000);
     let abort_ms = base_timeout_ms + 5000;
+    let endpoint = resilience::endpoint_key(resilience::SERVICE_FAST_CONTEXT, API_BASE);
     let mut last_error = None;
 
     for attempt in 0..=max_retries {
+        // 熔断打开期间直接失败，避免在远端限流/故障时继续消耗配额
+        // 探测位由 `_probe` 持有到本轮结束，请求中途被取消或提前返回时随之释放
+        let _probe = match resilience::check_circuit(&endpoint) {
+            Ok(probe) => probe,
+            Err(open) => {
+                return Err(FastContextError {
+                    code: "CIRCUIT_OPEN".to_string(),
+                    message: open.message,
+                    status: None,
+                })
+            }
+        };
         let started_at = Instant::now();
+        let mut retry_after = None;
         let trace_id = Uuid::new_v4().simple().to_string();
         let span_id = Uuid::new_v4().simple().to_string()[..16].to_string();
         let response = client
@@ -940,6 +973,7 @@ async fn streaming_request(
                     .await
                     .map(|bytes| bytes.to_vec())
                     .map_err(classify_reqwest_error)?;
+                resilience::record_success(&endpoint);
                 log::info!(
                     "[fast-context] 流式请求成功: attempt={}, bytes={}, elapsed_ms={}",
                     attempt + 1,
@@ -950,6 +984,9 @@ async fn streaming_request(
             }
             Ok(resp) => {
                 let err = FastContextError::status(resp.status());
+                let failure = UpstreamError::from_status(resp.status(), resp.headers(), "");
+                retry_after = failure.retry_after;
+                resilience::record_failure(&endpoint, &failure);
                 log::warn!(
                     "[fast-context] 流式请求 HTTP 失败: attempt={}, status={:?}, code={}, elapsed_ms={}",
                     attempt + 1,
@@ -964,6 +1001,7 @@ async fn streaming_request(
                 last_error = Some(err);
             }
             Err(err) => {
+                resilience::record_failure(&endpoint, &UpstreamError::from_reqwest(&err));
                 let err = classify_reqwest_error(err);
                 log::warn!(
                     "[fast-context] 流式请求网络失败: attempt={}, code={}, elapsed_ms={}, message={}",
@@ -978,8 +1016,18 @@ async fn streaming_request(
 
         if attempt < max_retries {
             // #4 指数退避 + jitter：避免雷霆群与服务器同步震荡
-            let jitter_ms = pseudo_jitter_ms(attempt);
-            tokio::time::sleep(Duration::from_millis(retry_delay_ms(attempt, jitter_ms))).await;
+            // 服务端给出 Retry-After 时以其为准；超过等待上限时直接失败，不让 sou 长时间挂起
+            if retry_after.is_some_and(|delay| delay > MAX_STREAMING_RETRY_AFTER) {
+                log::warn!(
+                    "[fast-context] Retry-After 超过等待上限，放弃重试: retry_after_s={}, max_s={}",
+                    retry_after.unwrap_or_default().as_secs(),
+                    MAX_STREAMING_RETRY_AFTER.as_secs()
+                );
+                break;
+            }
+            let delay = retry_after
+                .unwrap_or_else(|| Duration::from_millis(retry_delay_ms(attempt, jitter_ms())));
+            tokio::time::sleep(delay).await;
         }
     }
 
@@ -991,19 +1039,9 @@ fn should_retry_streaming_error(err: &FastContextError) -> bool {
     !matches!(err.status, Some(400..=499))
 }
 
-/// 基于纳秒时间戳的轻量 jitter（0~400ms），无需引入 rand 依赖
-fn pseudo_jitter_ms(attempt: usize) -> u64 {
-    let nanos = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.subsec_nanos() as u64)
-        .unwrap_or(0);
-    jitter_ms_from_seed(attempt, nanos)
-}
-
-/// 从可控 seed 计算 jitter，保留随机扰动逻辑同时让边界可测试。
-fn jitter_ms_from_seed(attempt: usize, nanos: u64) -> u64 {
-    let seed = nanos.wrapping_add(attempt as u64 * 7919);
-    seed % 400
+/// 重试 jitter（0~400ms）
+fn jitter_ms() -> u64 {
+    rand::thread_rng().gen_range(0..400)
 }
 
 /// 计算最终重试等待时间，保证指数退避基础值与 jitter 组合逻辑可测试。
@@ -1316,6 +1354,70 @@ fn fast_context_ignore_files(root: &Path) -> Vec<PathBuf> {
         .collect()
 }
 
+/// why-not-indexed：fast_context 不预建索引，这里复核目录树与本地命令共用的过滤规则
+pub(super) fn explain_path(root: &Path, path: &Path, exclude_paths: &[String]) -> Vec<RuleCheck> {
+    let mut checks = vec![RuleCheck::info(
+        "检索方式",
+        "实时检索，无需预建索引；不限制扩展名与文件大小",
+    )];
+    let ignore_files = fast_context_ignore_files(root)
+        .iter()
+        .filter_map(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .collect::<Vec<_>>();
+    let matcher = build_fast_context_ignore(root);
+    checks.push(
+        match matcher.matched_path_or_any_parents(path, path.is_dir()) {
+            ignore::Match::Ignore(glob) => RuleCheck::fail(
+                "ignore 文件",
+                format!(
+                    "规则 `{}` 命中（来自 {}）",
+                    glob.original(),
+                    glob.from()
+                        .and_then(|from| from.file_name())
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "ignore 文件".to_string())
+                ),
+            ),
+            ignore::Match::Whitelist(glob) => RuleCheck::pass(
+                "ignore 文件",
+                format!("规则 `{}` 显式放行", glob.original()),
+            ),
+            ignore::Match::None if ignore_files.is_empty() => {
+                RuleCheck::pass("ignore 文件", "项目根目录没有 ignore 文件")
+            }
+            ignore::Match::None => RuleCheck::pass(
+                "ignore 文件",
+                format!("未命中 {} 中的规则", ignore_files.join(" / ")),
+            ),
+        },
+    );
+
+    // 目录树逐层按名称过滤，rg/glob 命令还会按相对路径匹配
+    let relative = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    let matched = exclude_paths.iter().find(|pattern| {
+        let pattern = std::slice::from_ref(*pattern);
+        matches_exclude(&relative, pattern)
+            || relative
+                .split('/')
+                .any(|segment| matches_exclude(segment, pattern))
+    });
+    checks.push(match matched {
+        Some(pattern) => RuleCheck::fail("exclude_paths", format!("命中排除项 `{}`", pattern)),
+        None => RuleCheck::pass(
+            "exclude_paths",
+            format!("未命中 {} 条排除项", exclude_paths.len()),
+        ),
+    });
+    checks
+}
+
 fn is_ignored_path(ignore_matcher: &Gitignore, path: &Path) -> bool {
     ignore_matcher
         .matched_path_or_any_parents(path, path.is_dir())
@@ -1444,8 +1546,23 @@ fn entry_count(dir: &Path, exclude_paths: &[String], ignore_matcher: &Gitignore)
 
 /// #2 构建项目简介：README 首段 + manifest 顶层信息
 /// 目的：让 LLM 第一轮就知道项目用什么技术栈、入口在哪，省一轮 ls/tree 探查
-fn build_project_summary(root: &Path, ignore_matcher: &Gitignore) -> String {
+fn build_project_summary(
+    root: &Path,
+    ignore_matcher: &Gitignore,
+    scanner: &SecretScanner,
+) -> String {
     let mut sections = Vec::new();
+    // 摘要与 readfile 一样发往远端，按同一密钥扫描策略处置；skip / block 时省略该段
+    let root_slash = normalize_path(root);
+    let scan = |name: &str, head: String| match scanner.apply(
+        CHANNEL_FAST_CONTEXT,
+        &root_slash,
+        name,
+        head,
+    ) {
+        ScanDecision::Allow(content) => Some(content),
+        ScanDecision::Skipped | ScanDecision::Blocked(_) => None,
+    };
 
     // README 首 30 行（截断）
     for candidate in ["README.md", "README.MD", "Readme.md", "readme.md", "README"] {
@@ -1456,9 +1573,11 @@ fn build_project_summary(root: &Path, ignore_matcher: &Gitignore) -> String {
         if let Ok(content) = fs::read_to_string(&path) {
             let head = content.lines().take(30).collect::<Vec<_>>().join("\n");
             if !head.trim().is_empty() {
-                sections.push(format!(
-                    "### README ({candidate}, first 30 lines)\n```\n{head}\n```"
-                ));
+                if let Some(head) = scan(candidate, head) {
+                    sections.push(format!(
+                        "### README ({candidate}, first 30 lines)\n```\n{head}\n```"
+                    ));
+                }
                 break;
             }
         }
@@ -1470,9 +1589,11 @@ fn build_project_summary(root: &Path, ignore_matcher: &Gitignore) -> String {
         if let Ok(content) = fs::read_to_string(&cargo_toml) {
             let head = content.lines().take(40).collect::<Vec<_>>().join("\n");
             if !head.trim().is_empty() {
-                sections.push(format!(
-                    "### Cargo.toml (first 40 lines)\n```toml\n{head}\n```"
-                ));
+                if let Some(head) = scan("Cargo.toml", head) {
+                    sections.push(format!(
+                        "### Cargo.toml (first 40 lines)\n```toml\n{head}\n```"
+                    ));
+                }
             }
         }
     }
@@ -1483,9 +1604,11 @@ fn build_project_summary(root: &Path, ignore_matcher: &Gitignore) -> String {
         if let Ok(content) = fs::read_to_string(&package_json) {
             let head = content.lines().take(40).collect::<Vec<_>>().join("\n");
             if !head.trim().is_empty() {
-                sections.push(format!(
-                    "### package.json (first 40 lines)\n```json\n{head}\n```"
-                ));
+                if let Some(head) = scan("package.json", head) {
+                    sections.push(format!(
+                        "### package.json (first 40 lines)\n```json\n{head}\n```"
+                    ));
+                }
             }
         }
     }
@@ -1496,9 +1619,11 @@ fn build_project_summary(root: &Path, ignore_matcher: &Gitignore) -> String {
         if let Ok(content) = fs::read_to_string(&pyproject_toml) {
             let head = content.lines().take(30).collect::<Vec<_>>().join("\n");
             if !head.trim().is_empty() {
-                sections.push(format!(
-                    "### pyproject.toml (first 30 lines)\n```toml\n{head}\n```"
-                ));
+                if let Some(head) = scan("pyproject.toml", head) {
+                    sections.push(format!(
+                        "### pyproject.toml (first 30 lines)\n```toml\n{head}\n```"
+                    ));
+                }
             }
         }
     }
@@ -2065,6 +2190,8 @@ struct ToolExecutor {
     exclude_paths: Vec<String>,
     ignore_matcher: Gitignore,
     ignore_files: Vec<PathBuf>,
+    /// 内容发往远端前的密钥扫描；未设置时不扫描
+    secret_scanner: Option<SecretScanner>,
     state: Mutex<ToolExecutorState>,
 }
 
@@ -2077,6 +2204,8 @@ struct ToolExecutorState {
     /// readfile 完整文件缓存：(规范化绝对路径 → 文件原文)
     /// 供格式化层复用；同一文件的多个 readfile 也共享一次磁盘 IO
     read_cache: HashMap<String, String>,
+    /// 被密钥扫描策略拦截的文件（规范化绝对路径 → 返回给模型的错误信息）
+    withheld_files: HashMap<String, String>,
     /// 本次搜索的本地命令统计，用于输出命中率和诊断 LLM 工具调用质量
     stats: SearchStats,
     /// 上一次 turn 的命令指纹集合，用于 #5 重复检测
@@ -2119,10 +2248,37 @@ impl ToolExecutor {
             exclude_paths,
             ignore_matcher,
             ignore_files,
+            secret_scanner: None,
             state: Mutex::new(ToolExecutorState::default()),
         }
     }
 
+    fn with_secret_scanner(mut self, scanner: SecretScanner) -> Self {
+        self.secret_scanner = Some(scanner);
+        self
+    }
+
+    /// 首次读取文件时按密钥扫描策略处置；Err 为返回给模型的错误信息
+    fn scan_read_content(&self, path: &Path, content: String) -> Result<String, String> {
+        let Some(scanner) = &self.secret_scanner else {
+            return Ok(content);
+        };
+        let relative = path
+            .strip_prefix(&self.root)
+            .map(normalize_path)
+            .unwrap_or_else(|_| normalize_path(path));
+        match scanner.apply(CHANNEL_FAST_CONTEXT, &self.root_slash, &relative, content) {
+            ScanDecision::Allow(content) => Ok(content),
+            ScanDecision::Skipped => Err(format!(
+                "Error: file withheld by secret scan policy (skip): {relative}"
+            )),
+            ScanDecision::Blocked(rules) => Err(format!(
+                "Error: file blocked by secret scan policy ({}): {relative}",
+                rules.join(",")
+            )),
+        }
+    }
+
     async fn collected_rg_patterns(&self) -> Vec<String> {
         let state = self.state.lock().await;
         let mut seen = HashSet::new();
@@ -2577,7 +2733,14 @@ impl ToolExecutor {
 
         match tokio::time::timeout(Duration::from_secs(30), command.output()).await {
             Ok(Ok(output)) if output.status.success() => {
-                let result = truncate_output(&self.remap(&String::from_utf8_lossy(&output.stdout)));
+                let stdout = self.remap(&String::from_utf8_lossy(&output.stdout));
+                let stdout = match &self.secret_scanner {
+                    Some(scanner) => {
+                        scanner.redact_output(CHANNEL_FAST_CONTEXT, &self.root_slash, "rg", &stdout)
+                    }
+                    None => stdout,
+                };
+                let result = truncate_output(&stdout);
                 log::info!(
                     "[fast-context] rg 成功: path={}, output_len={}",
                     real_path.display(),
@@ -2684,16 +2847,27 @@ impl ToolExecutor {
         // #3 读文件缓存：同一 path 全量内容仅读盘一次，多次 readfile（不同 range）零额外 IO
         let content = {
             let state = self.state.lock().await;
+            if let Some(message) = state.withheld_files.get(&key) {
+                return message.clone();
+            }
             state.read_cache.get(&key).cloned()
         };
         let content = match content {
             Some(c) => c,
             None => match fs::read_to_string(&path) {
-                Ok(c) => {
-                    let mut state = self.state.lock().await;
-                    state.read_cache.insert(key.clone(), c.clone());
-                    c
-                }
+                // 缓存的是扫描处置后的内容，格式化层复用时同样不含密钥原文
+                Ok(c) => match self.scan_read_content(&path, c) {
+                    Ok(c) => {
+                        let mut state = self.state.lock().await;
+                        state.read_cache.insert(key.clone(), c.clone());
+                        c
+                    }
+                    Err(message) => {
+                        let mut state = self.state.lock().await;
+                        state.withheld_files.insert(key.clone(), message.clone());
+                        return message;
+                    }
+                },
                 Err(err) => {
                     log::warn!(
                         "[fast-context] readfile 读取失败: path={}, error={}",
@@ -3658,7 +3832,7 @@ mod tests {
     #[test]
     fn jitter_and_retry_delay_are_bounded_and_additive() {
         for attempt in 0..8 {
-            let jitter = jitter_ms_from_seed(attempt, u64::MAX - attempt as u64);
+            let jitter = jitter_ms();
             assert!(jitter < 400, "jitter 必须保持在 0..400ms 范围内");
             assert_eq!(
                 retry_delay_ms(attempt, jitter),
@@ -1,9 +1,10 @@
 use anyhow::{anyhow, Context, Result};
+use futures_util::future::join_all;
 use ignore::WalkBuilder;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use ring::digest::{Context as ShaContext, SHA256};
-use rusqlite::{params, Connection, OpenFlags};
+use rusqlite::{backup::Progress, params, Connection, DatabaseName, OpenFlags};
 use serde::Serialize;
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
@@ -15,14 +16,17 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::process::Command;
 
+use super::extract::{self, DocumentChunk};
+use super::why_not_indexed::RuleCheck;
+
 const INDEX_MISSING: u8 = 0;
 const INDEX_BUILDING: u8 = 1;
 const INDEX_READY: u8 = 2;
 const INDEX_ERROR: u8 = 3;
-const CHUNK_LINES: usize = 80;
-const CHUNK_OVERLAP: usize = 20;
-const MAX_FILE_BYTES: u64 = 1024 * 1024;
+pub(super) const MAX_FILE_BYTES: u64 = 1024 * 1024;
 const MAX_QUERY_TERMS: usize = 24;
+/// v2 为 chunks 表新增 section 列；旧版本索引在打开时重建。
+const INDEX_SCHEMA_VERSION: i64 = 2;
 
 static PROJECT_INDEXES: Lazy<Mutex<HashMap<PathBuf, Arc<ProjectIndex>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
@@ -45,6 +49,14 @@ pub(super) struct LocalSearchOutput {
     pub duration_ms: u64,
 }
 
+/// 工作区组中的单个仓库；exclude_paths 已合并全局与仓库级排除项。
+#[derive(Debug, Clone)]
+pub(super) struct WorkspaceRoot {
+    pub label: String,
+    pub project_root: PathBuf,
+    pub exclude_paths: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct LocalIndexStatus {
     pub project_root: String,
@@ -67,6 +79,21 @@ struct SearchHit {
     exact_match: bool,
     path_matches: usize,
     lexical_score: f64,
+    section: Option<String>,
+    repo: Option<HitRepo>,
+}
+
+/// 跨仓库检索时命中所属仓库，用于标注结果和拼接绝对路径。
+#[derive(Debug, Clone)]
+struct HitRepo {
+    label: String,
+    root: PathBuf,
+}
+
+struct CollectedHits {
+    hits: Vec<SearchHit>,
+    engine: String,
+    fallback_reason: Option<String>,
 }
 
 struct ProjectIndex {
@@ -77,6 +104,8 @@ struct ProjectIndex {
     indexed_chunks: AtomicU64,
     sync_running: AtomicBool,
     dirty: Arc<AtomicBool>,
+    /// 文件变更或索引同步完成时递增，供查询缓存判断结果是否过期。
+    generation: Arc<AtomicU64>,
     profile_hash: Mutex<String>,
     last_error: Mutex<Option<String>>,
     watcher: Mutex<Option<RecommendedWatcher>>,
@@ -94,6 +123,7 @@ impl ProjectIndex {
             sync_running: AtomicBool::new(false),
             // 进程重启后先做一次元数据对账，查询仍可读取已有索引。
             dirty: Arc::new(AtomicBool::new(state == INDEX_READY)),
+            generation: Arc::new(AtomicU64::new(0)),
             profile_hash: Mutex::new(String::new()),
             last_error: Mutex::new(error),
             watcher: Mutex::new(None),
@@ -119,10 +149,12 @@ impl ProjectIndex {
         }
 
         let dirty = Arc::clone(&self.dirty);
+        let generation = Arc::clone(&self.generation);
         let mut watcher =
             notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
                 Ok(_) => {
                     dirty.store(true, Ordering::Release);
+                    generation.fetch_add(1, Ordering::AcqRel);
                 }
                 Err(error) => log::warn!("[sou-local] 文件监听事件失败: {}", error),
             })
@@ -186,6 +218,33 @@ async fn search_with_index(
     enable_watcher: bool,
 ) -> Result<LocalSearchOutput> {
     let started_at = Instant::now();
+    let collected = collect_hits(&options, &root, &index, enable_watcher).await?;
+    let duration_ms = started_at.elapsed().as_millis() as u64;
+    let state = index.state_name().to_string();
+    let text = format_hits(
+        &root,
+        &collected.hits,
+        &collected.engine,
+        &state,
+        duration_ms,
+        collected.fallback_reason.as_deref(),
+    );
+    Ok(LocalSearchOutput {
+        text,
+        hit_count: collected.hits.len(),
+        engine: collected.engine,
+        index_state: state,
+        fallback_reason: collected.fallback_reason,
+        duration_ms,
+    })
+}
+
+async fn collect_hits(
+    options: &LocalSearchOptions,
+    root: &Path,
+    index: &Arc<ProjectIndex>,
+    enable_watcher: bool,
+) -> Result<CollectedHits> {
     let terms = extract_query_terms(&options.query);
     if terms.is_empty() {
         return Err(anyhow!("本地搜索未提取到有效关键词"));
@@ -199,14 +258,14 @@ async fn search_with_index(
             );
         }
     }
-    refresh_profile(&index, &options.exclude_paths);
+    refresh_profile(index, &options.exclude_paths);
 
     let mut fallback_reason = None;
     let (hits, engine) = if index.state.load(Ordering::Acquire) == INDEX_READY {
         if index.dirty.load(Ordering::Acquire) || index.sync_running.load(Ordering::Acquire) {
             fallback_reason = Some("本地索引存在待同步变更，本次使用即时搜索".to_string());
-            schedule_sync(Arc::clone(&index), options.exclude_paths.clone());
-            run_immediate_search(&root, &options, &terms).await?
+            schedule_sync(Arc::clone(index), options.exclude_paths.clone());
+            run_immediate_search(root, options, &terms).await?
         } else {
             let db_path = index.db_path.clone();
             let query = options.query.clone();
@@ -221,24 +280,120 @@ async fn search_with_index(
                 Ok(hits) => (hits, "fts5".to_string()),
                 Err(error) => {
                     let reason = format!("FTS5 查询失败: {}", error);
-                    mark_index_error(&index, &reason);
-                    schedule_sync(Arc::clone(&index), options.exclude_paths.clone());
+                    mark_index_error(index, &reason);
+                    schedule_sync(Arc::clone(index), options.exclude_paths.clone());
                     fallback_reason = Some(reason);
-                    run_immediate_search(&root, &options, &terms).await?
+                    run_immediate_search(root, options, &terms).await?
                 }
             }
         }
     } else {
         let state = index.state_name().to_string();
         fallback_reason = Some(format!("本地索引状态为 {}", state));
-        schedule_sync(Arc::clone(&index), options.exclude_paths.clone());
-        run_immediate_search(&root, &options, &terms).await?
+        schedule_sync(Arc::clone(index), options.exclude_paths.clone());
+        run_immediate_search(root, options, &terms).await?
     };
 
+    Ok(CollectedHits {
+        hits,
+        engine,
+        fallback_reason,
+    })
+}
+
+/// 工作区组检索：并发查询各仓库自己的 FTS5 索引，按归一化分值合并并标注仓库名。
+pub(super) async fn search_workspace(
+    query: String,
+    max_results: usize,
+    roots: Vec<WorkspaceRoot>,
+) -> Result<LocalSearchOutput> {
+    let mut indexed = Vec::new();
+    let mut skipped = Vec::new();
+    for root in roots {
+        match root.project_root.canonicalize() {
+            Ok(canonical) if canonical.is_dir() => {
+                let index = project_index(&canonical)?;
+                indexed.push((
+                    WorkspaceRoot {
+                        project_root: canonical,
+                        ..root
+                    },
+                    index,
+                ));
+            }
+            _ => skipped.push(format!(
+                "{}: 路径无效 {}",
+                root.label,
+                root.project_root.display()
+            )),
+        }
+    }
+    search_workspace_with_indexes(query, max_results, indexed, skipped, true).await
+}
+
+async fn search_workspace_with_indexes(
+    query: String,
+    max_results: usize,
+    roots: Vec<(WorkspaceRoot, Arc<ProjectIndex>)>,
+    mut notes: Vec<String>,
+    enable_watcher: bool,
+) -> Result<LocalSearchOutput> {
+    let started_at = Instant::now();
+    if roots.is_empty() {
+        return Err(anyhow!("工作区组没有可检索的仓库: {}", notes.join("；")));
+    }
+    let collected = join_all(roots.iter().map(|(root, index)| {
+        let options = LocalSearchOptions {
+            project_root: root.project_root.clone(),
+            query: query.clone(),
+            max_results,
+            exclude_paths: root.exclude_paths.clone(),
+        };
+        async move { collect_hits(&options, &root.project_root, index, enable_watcher).await }
+    }))
+    .await;
+
+    let mut hits = Vec::new();
+    let mut engines = Vec::new();
+    let mut states = Vec::new();
+    for ((root, index), result) in roots.iter().zip(collected) {
+        match result {
+            Ok(mut collected) => {
+                normalize_lexical_scores(&mut collected.hits);
+                hits.extend(collected.hits.into_iter().map(|mut hit| {
+                    hit.repo = Some(HitRepo {
+                        label: root.label.clone(),
+                        root: root.project_root.clone(),
+                    });
+                    hit
+                }));
+                if !engines.contains(&collected.engine) {
+                    engines.push(collected.engine);
+                }
+                if let Some(reason) = collected.fallback_reason {
+                    notes.push(format!("{}: {}", root.label, reason));
+                }
+            }
+            Err(error) => notes.push(format!("{}: {}", root.label, error)),
+        }
+        states.push(format!("{}={}", root.label, index.state_name()));
+    }
+
+    let hits = rank_and_limit(hits, max_results)?;
     let duration_ms = started_at.elapsed().as_millis() as u64;
-    let state = index.state_name().to_string();
+    let engine = if engines.is_empty() {
+        "none".to_string()
+    } else {
+        engines.join("+")
+    };
+    let state = states.join(",");
+    let fallback_reason = if notes.is_empty() {
+        None
+    } else {
+        Some(notes.join("；"))
+    };
     let text = format_hits(
-        &root,
+        &roots[0].0.project_root,
         &hits,
         &engine,
         &state,
@@ -265,6 +420,25 @@ pub async fn rebuild(project_root: &str, exclude_paths: Vec<String>) -> Result<L
     Ok(index.status())
 }
 
+/// 返回已加载的本地索引代次；本进程尚未使用过该项目的本地索引时返回 None。
+/// 只读取，不创建索引也不启动 watcher。
+pub(super) fn peek_generation(root: &Path) -> Option<u64> {
+    let root = root.canonicalize().ok()?;
+    let indexes = PROJECT_INDEXES.lock().ok()?;
+    indexes
+        .get(&root)
+        .map(|index| index.generation.load(Ordering::Acquire))
+}
+
+/// 与 `peek_generation` 相同，但只在 watcher 已启动时返回：此时代次才会随文件变化递增。
+pub(super) fn peek_watched_generation(root: &Path) -> Option<u64> {
+    let root = root.canonicalize().ok()?;
+    let indexes = PROJECT_INDEXES.lock().ok()?;
+    let index = indexes.get(&root)?;
+    let watching = index.watcher.lock().ok()?.is_some();
+    watching.then(|| index.generation.load(Ordering::Acquire))
+}
+
 pub fn status(project_root: &str) -> Result<LocalIndexStatus> {
     let root = PathBuf::from(project_root)
         .canonicalize()
@@ -272,6 +446,123 @@ pub fn status(project_root: &str) -> Result<LocalIndexStatus> {
     Ok(project_index(&root)?.status())
 }
 
+/// 索引快照导出：把项目本地索引复制到 `dest`，只保留大小与修改时间仍与工作区一致的文件。
+/// 返回保留的相对路径；项目尚无本地索引时返回 None。
+pub(crate) fn export_snapshot_database(root: &Path, dest: &Path) -> Result<Option<Vec<String>>> {
+    let index = project_index(root)?;
+    if !index.db_path.is_file() {
+        return Ok(None);
+    }
+    snapshot_database(&index.db_path, root, dest).map(Some)
+}
+
+fn snapshot_database(db_path: &Path, root: &Path, dest: &Path) -> Result<Vec<String>> {
+    let source = open_database(db_path)?;
+    // VACUUM INTO 生成不依赖 WAL 的一致性单文件副本
+    source
+        .execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+        .context("复制本地索引失败")?;
+    drop(source);
+    let connection = open_database(dest)?;
+    let mut kept = Vec::new();
+    let mut stale = Vec::new();
+    for (relative, signature) in load_file_metadata(&connection)? {
+        let current = fs::metadata(root.join(&relative))
+            .ok()
+            .map(|metadata| (modified_ns(&metadata), metadata.len() as i64));
+        if current == Some(signature) {
+            kept.push(relative);
+        } else {
+            stale.push(relative);
+        }
+    }
+    remove_database_files(&connection, &stale)?;
+    connection.execute_batch("PRAGMA journal_mode=DELETE;")?;
+    kept.sort();
+    Ok(kept)
+}
+
+/// 索引快照导入：用快照中的本地索引替换当前索引。
+/// 只保留 `verified`（内容哈希与工作区一致）的文件并改写为本机修改时间，其余文件由后续对账重新索引。
+pub(crate) fn import_snapshot_database(
+    root: &Path,
+    source: &Path,
+    verified: &HashSet<String>,
+) -> Result<LocalIndexStatus> {
+    import_snapshot_into(&*project_index(root)?, source, verified)
+}
+
+fn import_snapshot_into(
+    index: &ProjectIndex,
+    source: &Path,
+    verified: &HashSet<String>,
+) -> Result<LocalIndexStatus> {
+    if index
+        .sync_running
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        return Err(anyhow!("本地索引正在同步，请稍后重试"));
+    }
+    let result = apply_snapshot_database(source, &index.db_path, &index.root, verified);
+    index.sync_running.store(false, Ordering::Release);
+    let (files, chunks) = result?;
+    index.indexed_files.store(files, Ordering::Release);
+    index.indexed_chunks.store(chunks, Ordering::Release);
+    index.state.store(INDEX_READY, Ordering::Release);
+    // 工作区中快照未覆盖或内容不同的文件交给下一次对账
+    index.dirty.store(true, Ordering::Release);
+    index.generation.fetch_add(1, Ordering::AcqRel);
+    if let Ok(mut error) = index.last_error.lock() {
+        *error = None;
+    }
+    Ok(index.status())
+}
+
+fn apply_snapshot_database(
+    source: &Path,
+    db_path: &Path,
+    root: &Path,
+    verified: &HashSet<String>,
+) -> Result<(u64, u64)> {
+    let connection = open_database(source)?;
+    let mut stale = Vec::new();
+    let mut stamps = Vec::new();
+    for relative in load_file_metadata(&connection)?.into_keys() {
+        match fs::metadata(root.join(&relative)) {
+            Ok(metadata) if verified.contains(&relative) => {
+                stamps.push((relative, modified_ns(&metadata), metadata.len() as i64));
+            }
+            _ => stale.push(relative),
+        }
+    }
+    remove_database_files(&connection, &stale)?;
+    for (relative, modified, size) in &stamps {
+        connection.execute(
+            "UPDATE files SET modified_ns = ?2, size = ?3 WHERE path = ?1",
+            params![relative, modified, size],
+        )?;
+    }
+    connection.execute_batch("PRAGMA journal_mode=DELETE;")?;
+    drop(connection);
+
+    // 通过 SQLite backup API 整库替换：走 SQLite 自身的锁与 WAL，检索中的连接要么读到旧库要么读到新库，
+    // 不会像直接覆盖文件那样与残留的 -wal/-shm 错配
+    let mut target = open_database(db_path)?;
+    target
+        .restore(DatabaseName::Main, source, None::<fn(Progress)>)
+        .with_context(|| format!("写入本地索引失败: {}", db_path.display()))?;
+    index_counts(&target)
+}
+
+fn remove_database_files(connection: &Connection, paths: &[String]) -> Result<()> {
+    for path in paths {
+        connection.execute("DELETE FROM chunks WHERE path = ?1", params![path])?;
+        connection.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+    }
+    Ok(())
+}
+
 fn project_index(root: &Path) -> Result<Arc<ProjectIndex>> {
     let mut indexes = PROJECT_INDEXES
         .lock()
@@ -404,8 +695,20 @@ fn open_database(path: &Path) -> Result<Connection> {
     connection.busy_timeout(Duration::from_millis(250))?;
     connection.execute_batch(
         "PRAGMA journal_mode=WAL;
-         PRAGMA synchronous=NORMAL;
-         CREATE TABLE IF NOT EXISTS files (
+         PRAGMA synchronous=NORMAL;",
+    )?;
+    let version = connection.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))?;
+    if version < INDEX_SCHEMA_VERSION {
+        // 索引只是源码的派生数据，结构升级直接清空重建，由后续对账重新填充。
+        connection.execute_batch(&format!(
+            "DROP TABLE IF EXISTS chunks;
+             DROP TABLE IF EXISTS files;
+             PRAGMA user_version = {};",
+            INDEX_SCHEMA_VERSION
+        ))?;
+    }
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS files (
              path TEXT PRIMARY KEY,
              modified_ns INTEGER NOT NULL,
              size INTEGER NOT NULL
@@ -416,6 +719,7 @@ fn open_database(path: &Path) -> Result<Connection> {
              end_line UNINDEXED,
              search_text,
              content UNINDEXED,
+             section UNINDEXED,
              tokenize='unicode61 remove_diacritics 2'
          );",
     )?;
@@ -427,6 +731,7 @@ fn sync_index(index: &ProjectIndex, exclude_paths: &[String]) -> Result<(u64, u6
     let mut existing = load_file_metadata(&connection)?;
     let files = collect_project_files(&index.root, exclude_paths);
     let transaction = connection.transaction()?;
+    let mut changed = false;
 
     for path in files {
         let relative = relative_path(&index.root, &path)?;
@@ -438,27 +743,36 @@ fn sync_index(index: &ProjectIndex, exclude_paths: &[String]) -> Result<(u64, u6
         if existing.remove(&relative) == Some(signature) {
             continue;
         }
+        changed = true;
 
         transaction.execute("DELETE FROM chunks WHERE path = ?1", params![relative])?;
         transaction.execute("DELETE FROM files WHERE path = ?1", params![relative])?;
-        let Some(content) = read_text_file(&path, metadata.len())? else {
+        let Some(chunks) = read_document_chunks(&path, metadata.len())? else {
             continue;
         };
         transaction.execute(
             "INSERT INTO files(path, modified_ns, size) VALUES (?1, ?2, ?3)",
             params![relative, signature.0, signature.1],
         )?;
-        for (start_line, end_line, excerpt) in chunk_content(&content) {
-            let search_text = build_search_text(&relative, &excerpt);
+        for chunk in chunks {
+            let search_text = build_search_text(
+                &relative,
+                &format!(
+                    "{}\n{}",
+                    chunk.section.as_deref().unwrap_or_default(),
+                    chunk.text
+                ),
+            );
             transaction.execute(
-                "INSERT INTO chunks(path, start_line, end_line, search_text, content)
-                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                "INSERT INTO chunks(path, start_line, end_line, search_text, content, section)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
                 params![
                     relative,
-                    start_line as i64,
-                    end_line as i64,
+                    chunk.start_line as i64,
+                    chunk.end_line as i64,
                     search_text,
-                    excerpt
+                    chunk.text,
+                    chunk.section
                 ],
             )?;
         }
@@ -467,8 +781,13 @@ fn sync_index(index: &ProjectIndex, exclude_paths: &[String]) -> Result<(u64, u6
     for stale in existing.keys() {
         transaction.execute("DELETE FROM chunks WHERE path = ?1", params![stale])?;
         transaction.execute("DELETE FROM files WHERE path = ?1", params![stale])?;
+        changed = true;
     }
     transaction.commit()?;
+    // 中文说明：只有索引内容确实变化才推进代次；无变化的对账不会让查询缓存失效。
+    if changed {
+        index.generation.fetch_add(1, Ordering::AcqRel);
+    }
     index_counts(&connection)
 }
 
@@ -512,7 +831,8 @@ fn query_index(
     let fetch_limit = max_results.max(1).saturating_mul(5).min(150);
     let mut statement = connection.prepare(
         "SELECT path, start_line, end_line, content,
-                bm25(chunks, 0.0, 0.0, 0.0, 1.0, 0.0) AS lexical_score
+                bm25(chunks, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0) AS lexical_score,
+                section
          FROM chunks
          WHERE chunks MATCH ?1
          ORDER BY lexical_score
@@ -525,13 +845,14 @@ fn query_index(
             row.get::<_, i64>(2)? as usize,
             row.get::<_, String>(3)?,
             row.get::<_, f64>(4)?,
+            row.get::<_, Option<String>>(5)?,
         ))
     })?;
 
     let mut hits = Vec::new();
     for row in rows {
-        let (path, start_line, end_line, excerpt, lexical_score) = row?;
-        hits.push(score_hit(
+        let (path, start_line, end_line, excerpt, lexical_score, section) = row?;
+        let mut hit = score_hit(
             path,
             start_line,
             end_line,
@@ -539,7 +860,9 @@ fn query_index(
             lexical_score,
             query,
             terms,
-        ));
+        );
+        hit.section = section;
+        hits.push(hit);
     }
     rank_and_limit(hits, max_results)
 }
@@ -656,11 +979,36 @@ fn scan_project(
     excludes: &[String],
 ) -> Result<Vec<SearchHit>> {
     let mut matches = HashMap::new();
+    let mut document_hits = Vec::new();
     for path in collect_project_files(root, excludes) {
         let metadata = match fs::metadata(&path) {
             Ok(value) => value,
             Err(_) => continue,
         };
+        if extract::extractor_for(&path).is_some() {
+            // 文档片段带章节信息，直接按抽取块命中，不再回读原文件行号。
+            let Some(chunks) = read_document_chunks(&path, metadata.len())? else {
+                continue;
+            };
+            let relative = relative_path(root, &path)?;
+            for chunk in chunks {
+                let lower = chunk.text.to_lowercase();
+                if terms.iter().any(|term| lower.contains(term)) {
+                    let mut hit = score_hit(
+                        relative.clone(),
+                        chunk.start_line,
+                        chunk.end_line,
+                        chunk.text,
+                        0.0,
+                        query,
+                        terms,
+                    );
+                    hit.section = chunk.section;
+                    document_hits.push(hit);
+                }
+            }
+            continue;
+        }
         let Some(content) = read_text_file(&path, metadata.len())? else {
             continue;
         };
@@ -678,7 +1026,9 @@ fn scan_project(
             matches.insert(relative_path(root, &path)?, lines);
         }
     }
-    hits_from_line_matches(root, query, terms, matches, max_results)
+    let mut hits = hits_from_line_matches(root, query, terms, matches, max_results)?;
+    hits.extend(document_hits);
+    rank_and_limit(hits, max_results)
 }
 
 fn hits_from_line_matches(
@@ -745,6 +1095,8 @@ fn score_hit(
         exact_match,
         path_matches,
         lexical_score,
+        section: None,
+        repo: None,
     }
 }
 
@@ -760,15 +1112,46 @@ fn rank_and_limit(mut hits: Vec<SearchHit>, max_results: usize) -> Result<Vec<Se
                     .partial_cmp(&right.lexical_score)
                     .unwrap_or(std::cmp::Ordering::Equal)
             })
+            .then_with(|| repo_root(left).cmp(repo_root(right)))
             .then_with(|| left.relative_path.cmp(&right.relative_path))
             .then_with(|| left.start_line.cmp(&right.start_line))
     });
     let mut seen = HashSet::new();
-    hits.retain(|hit| seen.insert((hit.relative_path.clone(), hit.start_line, hit.end_line)));
+    hits.retain(|hit| {
+        seen.insert((
+            repo_root(hit).to_path_buf(),
+            hit.relative_path.clone(),
+            hit.start_line,
+            hit.end_line,
+        ))
+    });
     hits.truncate(max_results.max(1));
     Ok(hits)
 }
 
+/// bm25 分值只在同一个索引内可比；合并前对单个仓库的命中做一次 min-max 归一化到 [0, 1]
+/// （越小越相关），跨仓库合并时才不会被某个大仓库的绝对分值压制。仓库内顺序保持不变。
+fn normalize_lexical_scores(hits: &mut [SearchHit]) {
+    let (min, max) = hits.iter().fold((f64::MAX, f64::MIN), |(min, max), hit| {
+        (min.min(hit.lexical_score), max.max(hit.lexical_score))
+    });
+    for hit in hits.iter_mut() {
+        hit.lexical_score = if max > min {
+            (hit.lexical_score - min) / (max - min)
+        } else {
+            0.0
+        };
+    }
+}
+
+/// 按仓库根目录区分命中；同名仓库不会互相去重
+fn repo_root(hit: &SearchHit) -> &Path {
+    hit.repo
+        .as_ref()
+        .map(|repo| repo.root.as_path())
+        .unwrap_or_else(|| Path::new(""))
+}
+
 fn format_hits(
     root: &Path,
     hits: &[SearchHit],
@@ -782,11 +1165,22 @@ fn format_hits(
         String::new(),
     ];
     for hit in hits {
+        let base = hit
+            .repo
+            .as_ref()
+            .map(|repo| repo.root.as_path())
+            .unwrap_or(root);
         parts.push(format!(
             "Path: {}",
-            normalize_path(&root.join(&hit.relative_path))
+            normalize_path(&base.join(&hit.relative_path))
         ));
         parts.push(format!("Lines: L{}-L{}", hit.start_line, hit.end_line));
+        if let Some(repo) = hit.repo.as_ref() {
+            parts.push(format!("Repo: {}", repo.label));
+        }
+        if let Some(section) = hit.section.as_deref() {
+            parts.push(format!("Section: {}", section));
+        }
         for (offset, line) in hit.excerpt.lines().enumerate() {
             parts.push(format!("L{}:{}", hit.start_line + offset, line));
         }
@@ -818,14 +1212,106 @@ fn collect_project_files(root: &Path, excludes: &[String]) -> Vec<PathBuf> {
         .filter(|path| is_supported_file(path))
         .filter(|path| {
             fs::metadata(path)
-                .map(|metadata| metadata.len() <= MAX_FILE_BYTES)
+                .map(|metadata| metadata.len() <= max_file_bytes(path))
                 .unwrap_or(false)
         })
         .filter(|path| !is_excluded(root, path, excludes))
         .collect()
 }
 
+/// why-not-indexed：按 collect_project_files 的过滤顺序逐条复核单个路径
+pub(super) fn explain_path(root: &Path, path: &Path, excludes: &[String]) -> Vec<RuleCheck> {
+    let mut checks = Vec::new();
+    let relative = normalize_path(path.strip_prefix(root).unwrap_or(path));
+    let Ok(metadata) = fs::metadata(path) else {
+        checks.push(RuleCheck::info("文件状态", "路径不存在，仅复核排除规则"));
+        checks.push(exclude_check(root, path, excludes));
+        return checks;
+    };
+
+    // 只沿目标路径的祖先目录遍历，判定结果与索引时的 ignore 标准过滤完全一致
+    let target = path.to_path_buf();
+    let visible = WalkBuilder::new(root)
+        .standard_filters(true)
+        .filter_entry(move |entry| target.starts_with(entry.path()))
+        .build()
+        .filter_map(Result::ok)
+        .any(|entry| entry.path() == path);
+    checks.push(if visible {
+        RuleCheck::pass(
+            "ignore 标准过滤",
+            "未被 .gitignore / .ignore / 隐藏路径规则过滤",
+        )
+    } else if let Some(hidden) = relative.split('/').find(|segment| segment.starts_with('.')) {
+        RuleCheck::fail(
+            "ignore 标准过滤",
+            format!(
+                "隐藏路径 `{}` 被跳过（本地索引不收录隐藏文件/目录）",
+                hidden
+            ),
+        )
+    } else {
+        RuleCheck::fail(
+            "ignore 标准过滤",
+            "被 .gitignore / .ignore / 全局 git excludes 规则忽略",
+        )
+    });
+
+    if metadata.is_dir() {
+        checks.push(RuleCheck::info("文件状态", "目录：仅复核目录级规则"));
+        checks.push(exclude_check(root, path, excludes));
+        return checks;
+    }
+
+    checks.push(if is_supported_file(path) {
+        RuleCheck::pass("文件类型", "扩展名或文件名在本地索引支持列表中")
+    } else {
+        RuleCheck::fail("文件类型", "扩展名不在本地索引支持列表中")
+    });
+    let limit = max_file_bytes(path);
+    checks.push(if metadata.len() <= limit {
+        RuleCheck::pass(
+            "MAX_FILE_BYTES",
+            format!("{} 字节，上限 {} 字节", metadata.len(), limit),
+        )
+    } else {
+        RuleCheck::fail(
+            "MAX_FILE_BYTES",
+            format!("{} 字节超过上限 {} 字节", metadata.len(), limit),
+        )
+    });
+    checks.push(exclude_check(root, path, excludes));
+    if extract::extractor_for(path).is_none() {
+        let has_nul = fs::read(path)
+            .map(|bytes| bytes.iter().take(8192).any(|byte| *byte == 0))
+            .unwrap_or(false);
+        if has_nul {
+            checks.push(RuleCheck::fail(
+                "二进制检测",
+                "前 8KB 含 NUL 字节，按二进制文件跳过",
+            ));
+        }
+    }
+    checks
+}
+
+fn exclude_check(root: &Path, path: &Path, excludes: &[String]) -> RuleCheck {
+    match excludes
+        .iter()
+        .find(|exclude| is_excluded(root, path, std::slice::from_ref(*exclude)))
+    {
+        Some(exclude) => RuleCheck::fail("exclude_paths", format!("命中排除项 `{}`", exclude)),
+        None => RuleCheck::pass(
+            "exclude_paths",
+            format!("未命中 {} 条排除项", excludes.len()),
+        ),
+    }
+}
+
 fn is_supported_file(path: &Path) -> bool {
+    if extract::extractor_for(path).is_some() {
+        return true;
+    }
     let extension = path
         .extension()
         .and_then(|value| value.to_str())
@@ -928,30 +1414,41 @@ fn read_text_file(path: &Path, size: u64) -> Result<Option<String>> {
     Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
 }
 
-fn chunk_content(content: &str) -> Vec<(usize, usize, String)> {
-    let lines = content.lines().collect::<Vec<_>>();
-    if lines.is_empty() {
-        return Vec::new();
+fn max_file_bytes(path: &Path) -> u64 {
+    extract::extractor_for(path)
+        .map(|extractor| extractor.max_bytes())
+        .unwrap_or(MAX_FILE_BYTES)
+}
+
+/// 文档类文件交给对应抽取器，其余按纯文本窗口分块；无法抽取时返回 None 跳过该文件。
+fn read_document_chunks(path: &Path, size: u64) -> Result<Option<Vec<DocumentChunk>>> {
+    let Some(extractor) = extract::extractor_for(path) else {
+        return Ok(read_text_file(path, size)?
+            .map(|content| extract::chunk_lines(&content.lines().collect::<Vec<_>>(), 1, None)));
+    };
+    if size > extractor.max_bytes() {
+        return Ok(None);
     }
-    let mut chunks = Vec::new();
-    let step = CHUNK_LINES - CHUNK_OVERLAP;
-    let mut start = 0usize;
-    while start < lines.len() {
-        let end = (start + CHUNK_LINES).min(lines.len());
-        chunks.push((start + 1, end, lines[start..end].join("\n")));
-        if end == lines.len() {
-            break;
+    let bytes = fs::read(path).with_context(|| format!("读取文档失败: {}", path.display()))?;
+    match extractor.extract(&bytes) {
+        Ok(chunks) => Ok(Some(chunks)),
+        Err(error) => {
+            log::warn!(
+                "[sou-local] {} 文档抽取失败，已跳过: path={}, error={}",
+                extractor.name(),
+                path.display(),
+                error
+            );
+            Ok(None)
         }
-        start += step;
     }
-    chunks
 }
 
-fn build_search_text(path: &str, content: &str) -> String {
+pub(crate) fn build_search_text(path: &str, content: &str) -> String {
     tokenize_text(&format!("{}\n{}", path, content), usize::MAX).join(" ")
 }
 
-fn extract_query_terms(query: &str) -> Vec<String> {
+pub(crate) fn extract_query_terms(query: &str) -> Vec<String> {
     let stopwords = [
         "the", "and", "for", "from", "with", "this", "that", "what", "where", "when", "代码",
         "项目", "搜索", "相关", "实现", "如何", "怎么", "什么", "是否",
@@ -1207,6 +1704,213 @@ mod tests {
         assert_eq!(renamed.len(), 1);
     }
 
+    #[test]
+    fn snapshot_database_keeps_only_verified_files_with_local_stamps() {
+        let temp = tempdir().expect("临时项目应创建成功");
+        let root = temp.path().join("project");
+        fs::create_dir_all(root.join("src")).expect("源码目录应创建成功");
+        fs::write(root.join("src/kept.rs"), "pub struct KeptSnapshot;\n").unwrap();
+        fs::write(root.join("src/changed.rs"), "pub struct ChangedSnapshot;\n").unwrap();
+        let index = ProjectIndex::new(root.clone(), temp.path().join("source.sqlite3"));
+        sync_index(&index, &[]).expect("首次索引应成功");
+
+        let exported = temp.path().join("exported.sqlite3");
+        let kept = snapshot_database(&index.db_path, &root, &exported).expect("导出应成功");
+        assert_eq!(kept, vec!["src/changed.rs", "src/kept.rs"]);
+
+        // 模拟另一台机器：修改时间不同，其中一个文件内容也不同
+        std::thread::sleep(Duration::from_millis(2));
+        fs::write(root.join("src/kept.rs"), "pub struct KeptSnapshot;\n").unwrap();
+        let target = temp.path().join("target.sqlite3");
+        let verified = HashSet::from(["src/kept.rs".to_string()]);
+        let counts =
+            apply_snapshot_database(&exported, &target, &root, &verified).expect("导入应成功");
+        assert_eq!(counts.0, 1);
+
+        let connection = open_database(&target).unwrap();
+        let metadata = fs::metadata(root.join("src/kept.rs")).unwrap();
+        assert_eq!(
+            load_file_metadata(&connection).unwrap().get("src/kept.rs"),
+            Some(&(modified_ns(&metadata), metadata.len() as i64))
+        );
+        let hits = query_index(
+            &target,
+            "ChangedSnapshot",
+            &extract_query_terms("ChangedSnapshot"),
+            5,
+        )
+        .unwrap();
+        assert!(hits.iter().all(|hit| hit.relative_path == "src/kept.rs"));
+    }
+
+    #[test]
+    fn snapshot_import_replaces_live_index_while_a_reader_is_open() {
+        let temp = tempdir().expect("临时项目应创建成功");
+        let root = temp.path().join("project");
+        fs::create_dir_all(root.join("src")).expect("源码目录应创建成功");
+        fs::write(root.join("src/live.rs"), "pub struct LiveOnly;\n").unwrap();
+        let live = ProjectIndex::new(root.clone(), temp.path().join("live.sqlite3"));
+        sync_index(&live, &[]).expect("现有索引应建立成功");
+        // 检索连接保持打开，WAL 中留有未检查点的写入
+        let reader = open_database(&live.db_path).unwrap();
+        assert_eq!(index_counts(&reader).unwrap().0, 1);
+
+        fs::remove_file(root.join("src/live.rs")).unwrap();
+        fs::write(root.join("src/shared.rs"), "pub struct SharedSnapshot;\n").unwrap();
+        let source = ProjectIndex::new(root.clone(), temp.path().join("source.sqlite3"));
+        sync_index(&source, &[]).expect("快照源索引应建立成功");
+        let exported = temp.path().join("exported.sqlite3");
+        snapshot_database(&source.db_path, &root, &exported).expect("导出应成功");
+
+        live.sync_running.store(true, Ordering::Release);
+        let verified = HashSet::from(["src/shared.rs".to_string()]);
+        assert!(import_snapshot_into(&live, &exported, &verified).is_err());
+        live.sync_running.store(false, Ordering::Release);
+
+        let status = import_snapshot_into(&live, &exported, &verified).expect("导入应成功");
+        assert_eq!(status.indexed_files, 1);
+        assert!(!live.sync_running.load(Ordering::Acquire));
+        assert_eq!(
+            load_file_metadata(&reader)
+                .unwrap()
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec!["src/shared.rs".to_string()]
+        );
+        let hits = query_index(
+            &live.db_path,
+            "SharedSnapshot",
+            &extract_query_terms("SharedSnapshot"),
+            5,
+        )
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(query_index(
+            &live.db_path,
+            "LiveOnly",
+            &extract_query_terms("LiveOnly"),
+            5
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn markdown_documents_are_indexed_with_heading_sections() {
+        let temp = tempdir().expect("文档索引测试目录应创建成功");
+        let root = temp.path().join("project");
+        fs::create_dir_all(root.join("docs")).expect("文档目录应创建成功");
+        fs::write(
+            root.join("docs").join("adr-007.md"),
+            "# Storage\nintro\n## Retention\nSnapshots expire after RetentionWindow days.\n",
+        )
+        .expect("测试文档应写入成功");
+        let index = ProjectIndex::new(root.clone(), temp.path().join("docs.sqlite3"));
+        sync_index(&index, &[]).expect("文档索引应成功");
+
+        let hits = query_index(
+            &index.db_path,
+            "RetentionWindow",
+            &extract_query_terms("RetentionWindow"),
+            5,
+        )
+        .expect("文档索引查询应成功");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].section.as_deref(), Some("Storage > Retention"));
+        assert_eq!(hits[0].start_line, 3);
+        let text = format_hits(&root, &hits, "fts5", "ready", 1, None);
+        assert!(text.contains("Section: Storage > Retention"));
+    }
+
+    #[tokio::test]
+    async fn workspace_search_merges_repositories_and_labels_hits() {
+        let temp = tempdir().expect("工作区测试目录应创建成功");
+        let mut roots = Vec::new();
+        for (label, body) in [
+            ("billing", "pub struct InvoiceLedger;\n"),
+            (
+                "gateway",
+                "fn route_invoice_ledger() {}\n// InvoiceLedger proxy\n",
+            ),
+        ] {
+            let root = temp.path().join(label);
+            fs::create_dir_all(&root).expect("仓库目录应创建成功");
+            fs::write(root.join("lib.rs"), body).expect("仓库源码应写入成功");
+            let root = root.canonicalize().expect("仓库路径应可规范化");
+            let index = Arc::new(ProjectIndex::new(
+                root.clone(),
+                temp.path().join(format!("{label}.sqlite3")),
+            ));
+            sync_now(Arc::clone(&index), Vec::new())
+                .await
+                .expect("仓库索引应建立成功");
+            roots.push((
+                WorkspaceRoot {
+                    label: label.to_string(),
+                    project_root: root,
+                    exclude_paths: Vec::new(),
+                },
+                index,
+            ));
+        }
+
+        let output = search_workspace_with_indexes(
+            "InvoiceLedger".to_string(),
+            10,
+            roots,
+            vec!["legacy: 路径无效 /missing".to_string()],
+            false,
+        )
+        .await
+        .expect("工作区检索应成功");
+
+        assert_eq!(output.hit_count, 2);
+        assert!(output.text.contains("Repo: billing"));
+        assert!(output.text.contains("Repo: gateway"));
+        assert!(output.text.contains("billing/lib.rs"));
+        assert!(output.index_state.contains("gateway=ready"));
+        assert!(output
+            .fallback_reason
+            .as_deref()
+            .is_some_and(|reason| reason.contains("legacy")));
+    }
+
+    #[test]
+    fn lexical_scores_are_normalized_per_repository() {
+        let hit = |label: &str, path: &str, score: f64| SearchHit {
+            repo: Some(HitRepo {
+                label: label.to_string(),
+                root: PathBuf::from(label),
+            }),
+            ..score_hit(path.to_string(), 1, 1, String::new(), score, "q", &[])
+        };
+        // 大仓库的 bm25 绝对值更小，但归一化后与小仓库的最佳结果同级。
+        let mut big = vec![hit("big", "a.rs", -30.0), hit("big", "b.rs", -20.0)];
+        let mut small = vec![hit("small", "c.rs", -2.0), hit("small", "d.rs", -1.0)];
+        normalize_lexical_scores(&mut big);
+        normalize_lexical_scores(&mut small);
+        let hits = rank_and_limit(big.into_iter().chain(small).collect(), 10).expect("排序应成功");
+        let order = hits
+            .iter()
+            .map(|hit| hit.relative_path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(order, vec!["a.rs", "c.rs", "b.rs", "d.rs"]);
+    }
+
+    #[test]
+    fn repositories_with_same_label_are_not_deduplicated() {
+        let hit = |root: &str| SearchHit {
+            repo: Some(HitRepo {
+                label: "api".to_string(),
+                root: PathBuf::from(root),
+            }),
+            ..score_hit("src/lib.rs".to_string(), 1, 3, String::new(), 0.0, "q", &[])
+        };
+        let hits =
+            rank_and_limit(vec![hit("/work/a/api"), hit("/work/b/api")], 10).expect("排序应成功");
+        assert_eq!(hits.len(), 2);
+    }
+
     #[tokio::test]
     async fn pending_index_changes_use_current_files_instead_of_stale_fts5() {
         let temp = tempdir().expect("即时搜索测试目录应创建成功");
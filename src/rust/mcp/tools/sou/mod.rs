@@ -9,13 +9,19 @@ use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 
-use crate::config::load_standalone_config;
+use crate::config::{load_standalone_config, SouWorkspaceGroup};
 use crate::log_important;
 use crate::mcp::tools::acemcp::types::AcemcpRequest;
 use crate::mcp::tools::AcemcpTool;
+use crate::network::resilience;
 
+mod cache;
+pub(crate) mod eval;
+mod extract;
 pub(crate) mod fast_context;
 pub(crate) mod local;
+mod snippets;
+pub(crate) mod why_not_indexed;
 
 const BACKEND_ACE: &str = "ace";
 const BACKEND_FAST_CONTEXT: &str = "fast_context";
@@ -24,12 +30,24 @@ const BACKEND_AUTO: &str = "auto";
 const BACKEND_BOTH: &str = "both";
 const BACKEND_DEFAULT: &str = "default";
 const FAST_CONTEXT_FALLBACK_RETRY_DELAY_MS: u64 = 700;
+/// 为缓存标注（`cache=…, cache_hits=…, cache_misses=…`）在字符预算中预留的长度
+const CACHE_NOTE_RESERVE: usize = 96;
 
 /// sou 对外请求。旧客户端只传 project_root_path/query 时仍然可用。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SouRequest {
+    /// 使用 workspace_group 时可省略
+    #[serde(default)]
     pub project_root_path: String,
+    /// action=why_not_indexed 时可省略
+    #[serde(default)]
     pub query: String,
+    /// search（默认）、why_not_indexed 或 index_history
+    #[serde(default)]
+    pub action: Option<String>,
+    /// why_not_indexed 诊断的目标文件或目录（相对项目根或绝对路径）
+    #[serde(default)]
+    pub path: Option<String>,
     pub backend: Option<String>,
     pub tree_depth: Option<u8>,
     pub max_turns: Option<u8>,
@@ -37,6 +55,14 @@ pub struct SouRequest {
     pub max_commands: Option<u8>,
     pub timeout_ms: Option<u64>,
     pub exclude_paths: Option<Vec<String>>,
+    /// 配置中的工作区组名；设置后跨组内所有仓库做本地检索
+    pub workspace_group: Option<String>,
+    /// 为 true 时跳过查询缓存读取，本次结果仍会刷新缓存
+    pub no_cache: Option<bool>,
+    /// 每个片段向上下各扩展的行数（上限 50）
+    pub context_lines: Option<u8>,
+    /// 输出字符预算；超出时按排名截断片段
+    pub max_output_chars: Option<usize>,
 }
 
 /// crate 内部统一代码片段，供 uiux 等组合工具消费，避免重复解析 MCP 文本。
@@ -45,6 +71,10 @@ pub(crate) struct SouSection {
     pub backend: String,
     pub location: String,
     pub excerpt: String,
+    /// 文档类命中所属章节（Markdown 标题路径、Notebook 单元格、PDF 页码）
+    pub section: Option<String>,
+    /// 工作区组检索时命中所属仓库
+    pub repo: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +84,7 @@ struct SouRuntimeConfig {
     include_backend_headers: bool,
     include_failed_backend_errors: bool,
     local_enabled: bool,
+    workspace_groups: Vec<SouWorkspaceGroup>,
     fast_context: FastContextConfig,
 }
 
@@ -66,6 +97,9 @@ struct FastContextConfig {
     max_commands: u8,
     timeout_ms: u64,
     exclude_paths: Vec<String>,
+    secret_scan_enabled: bool,
+    secret_scan_policy: Option<String>,
+    secret_scan_custom_patterns: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -96,9 +130,18 @@ impl SouTool {
                     "type": "string",
                     "description": "项目根目录的绝对路径，使用正斜杠(/)作为分隔符。"
                 },
+                "action": {
+                    "type": "string",
+                    "enum": ["search", "why_not_indexed", "index_history"],
+                    "description": "search（默认）执行检索；why_not_indexed 逐后端解释 path 为何（未）被收录，并列出被排除目录的体积排行；index_history 列出最近的 ACE 索引任务（触发来源、各阶段耗时、失败批次、发送字节），project_root_path 为空时列出全部项目，条数由 max_results 控制（默认 20）。"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "action=why_not_indexed 时的目标文件或目录，可为相对项目根的路径或项目内绝对路径。"
+                },
                 "query": {
                     "type": "string",
-                    "description": "用于查找相关代码上下文的自然语言搜索查询。提示：代码标识符通常为英文，使用中文描述时建议混入英文类名/函数名/文件名（如 GestureRecognizer、ImageCodec），可以显著提升命中率与稳定性。"
+                    "description": "action=search 时必填。用于查找相关代码上下文的自然语言搜索查询。提示：代码标识符通常为英文，使用中文描述时建议混入英文类名/函数名/文件名（如 GestureRecognizer、ImageCodec），可以显著提升命中率与稳定性。"
                 },
                 "backend": {
                     "type": "string",
@@ -129,9 +172,24 @@ impl SouTool {
                     "type": "array",
                     "items": { "type": "string" },
                     "description": "fast-context 额外排除路径或 glob。"
+                },
+                "workspace_group": {
+                    "type": "string",
+                    "description": "可选工作区组名（在配置 sou_workspace_groups 中定义）。设置后跨组内所有仓库做本地 FTS5 检索并合并排序，结果标注 Repo；此时可省略 project_root_path。"
+                },
+                "no_cache": {
+                    "type": "boolean",
+                    "description": "跳过查询缓存强制重新检索（结果仍会刷新缓存）。默认复用本会话内同一项目、同一索引代次下的相同查询结果。"
+                },
+                "context_lines": {
+                    "type": "integer",
+                    "description": "每个片段向上下各扩展的上下文行数（0-50，默认 0）。扩展后相邻片段会再次合并。"
+                },
+                "max_output_chars": {
+                    "type": "integer",
+                    "description": "输出字符预算，对整个输出生效。超出时按排名保留片段，放不下的片段按行截断并追加 [sou truncated] 说明；统计与元数据行优先保留，它们本身超出预算时按顺序省略放不下的行。"
                 }
-            },
-            "required": ["project_root_path", "query"]
+            }
         });
 
         if let serde_json::Value::Object(schema_map) = schema {
@@ -153,53 +211,73 @@ impl SouTool {
     }
 
     pub async fn search_context(request: SouRequest) -> Result<CallToolResult, McpError> {
+        match request.action.as_deref().map(str::trim) {
+            None | Some("") | Some("search") => {}
+            Some("why_not_indexed") => return Ok(why_not_indexed_result(&request).await),
+            Some("index_history") => return Ok(index_history_result(&request)),
+            Some(other) => {
+                return Ok(error_result(format!("sou失败: 未知 action {}", other)));
+            }
+        }
+        if request.query.trim().is_empty() {
+            return Ok(error_result("sou搜索失败: 缺少 query".to_string()));
+        }
         let config = SouRuntimeConfig::load()
             .map_err(|e| McpError::internal_error(format!("读取 sou 配置失败: {}", e), None))?;
         let strategy = resolve_strategy(request.backend.as_deref(), &config);
 
         log_important!(
             info,
-            "[sou] 搜索请求: backend={}, project_root_path={}, query={}",
+            "[sou] 搜索请求: backend={}, project_root_path={}, workspace_group={:?}, query={}",
             strategy,
             request.project_root_path,
+            request.workspace_group,
             request.query
         );
 
-        match strategy.as_str() {
-            BACKEND_ACE => result_to_call_tool(
-                run_ace(&request).await.map_err(|e| BackendRunError {
-                    backend: BACKEND_ACE.to_string(),
-                    message: e,
-                }),
-                BACKEND_ACE,
-            ),
-            BACKEND_FAST_CONTEXT => result_to_call_tool(
-                run_fast_context(
-                    &request,
-                    &config.fast_context,
-                    config.include_backend_headers,
-                )
-                .await
-                .map_err(|e| BackendRunError {
-                    backend: BACKEND_FAST_CONTEXT.to_string(),
-                    message: e,
-                }),
-                BACKEND_FAST_CONTEXT,
-            ),
-            BACKEND_LOCAL if config.local_enabled => result_to_call_tool(
-                run_local(&request, &config.fast_context)
-                    .await
-                    .map_err(|e| BackendRunError {
-                        backend: BACKEND_LOCAL.to_string(),
-                        message: e,
-                    }),
-                BACKEND_LOCAL,
-            ),
-            BACKEND_LOCAL => Ok(error_result("Local搜索失败: 本地兜底已禁用".to_string())),
-            BACKEND_BOTH => run_both(&request, &config).await,
-            BACKEND_AUTO => run_auto(&request, &config).await,
-            other => Ok(error_result(format!("sou搜索失败: 未知后端策略 {}", other))),
+        if request.workspace_group.is_none() && request.project_root_path.trim().is_empty() {
+            return Ok(error_result(
+                "sou搜索失败: 缺少 project_root_path 或 workspace_group".to_string(),
+            ));
         }
+
+        let scope = cache_scope(&request);
+        let key = cache_key(&request, &config, &strategy);
+        let status = if request.no_cache.unwrap_or(false) {
+            cache::CacheStatus::Bypass
+        } else if let Some(key) = key {
+            let (cached, stats) = cache::lookup(&scope, &key);
+            if let Some(result) = cached {
+                log_important!(info, "[sou] 查询缓存命中: scope={}", scope);
+                return Ok(annotate_cache(
+                    result,
+                    cache::CacheStatus::Hit,
+                    stats,
+                    request.max_output_chars,
+                ));
+            }
+            cache::CacheStatus::Miss
+        } else {
+            // 没有可用的索引代次（如 fast_context 查询且未监听项目目录），结果无法判断是否过期，不走缓存
+            cache::CacheStatus::Bypass
+        };
+
+        let result = refine_result(
+            search_uncached(&request, &config, &strategy).await?,
+            &request,
+        );
+        if is_cacheable(&result) {
+            // 中文说明：搜索本身可能首次建立本地索引，按搜索后的代次写入，下一次同样的查询才能命中。
+            if let Some(key) = cache_key(&request, &config, &strategy) {
+                cache::store(&scope, key, &result);
+            }
+        }
+        Ok(annotate_cache(
+            result,
+            status,
+            cache::stats(&scope),
+            request.max_output_chars,
+        ))
     }
 
     /// 内部结构化搜索入口；对外 MCP 文本协议继续由 search_context 保持兼容。
@@ -207,6 +285,10 @@ impl SouTool {
         let config =
             SouRuntimeConfig::load().map_err(|error| format!("读取 sou 配置失败: {}", error))?;
         let strategy = resolve_strategy(request.backend.as_deref(), &config);
+        if request.workspace_group.is_some() {
+            let result = run_workspace(&request, &config, &strategy).await?;
+            return Ok(parse_sou_sections(&result.text, &result.backend));
+        }
         let results = match strategy.as_str() {
             BACKEND_ACE => vec![run_ace(&request).await?],
             BACKEND_FAST_CONTEXT => vec![
@@ -248,6 +330,246 @@ impl SouTool {
     }
 }
 
+/// 查询缓存按项目隔离；工作区组整体作为一个作用域。
+fn cache_scope(request: &SouRequest) -> String {
+    match request.workspace_group.as_deref() {
+        Some(group) => format!("group:{}", group.trim().to_lowercase()),
+        None => canonical_root_string(&request.project_root_path),
+    }
+}
+
+fn canonical_root_string(path: &str) -> String {
+    PathBuf::from(path)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(path))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// 查询缓存键；任一根目录没有可用的索引代次时返回 None，本次不读写缓存。
+fn cache_key(
+    request: &SouRequest,
+    config: &SouRuntimeConfig,
+    strategy: &str,
+) -> Option<cache::QueryCacheKey> {
+    let roots = match request.workspace_group.as_deref() {
+        Some(name) => config
+            .workspace_groups
+            .iter()
+            .find(|group| group.name.trim().eq_ignore_ascii_case(name.trim()))
+            .map(|group| {
+                group
+                    .roots
+                    .iter()
+                    .map(|root| canonical_root_string(&root.path))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
+        None => vec![canonical_root_string(&request.project_root_path)],
+    };
+    let generation = roots
+        .iter()
+        .map(|root| index_generation(root, request, config, strategy))
+        .collect::<Option<Vec<_>>>()?
+        .join(";");
+    Some(cache::QueryCacheKey {
+        query: cache::normalize_query(&request.query),
+        backend: strategy.to_string(),
+        excludes: cache::normalize_excludes(request.exclude_paths.as_deref()),
+        params: format!(
+            "tree_depth={:?},max_turns={:?},max_results={:?},max_commands={:?},group={:?},context_lines={:?},max_output_chars={:?}",
+            request.tree_depth,
+            request.max_turns,
+            request.max_results,
+            request.max_commands,
+            request.workspace_group,
+            request.context_lines,
+            request.max_output_chars
+        ),
+        generation,
+    })
+}
+
+/// 索引代次：本地索引代次 + ACE blob 集合指纹，任一变化都会让旧缓存失效。
+/// 只读取本次策略实际会用到的后端，不会为纯远端查询创建本地索引或启动 watcher。
+///
+/// fast_context 直接读取工作区文件，没有自己的代次：本地索引未参与时借用已启动的 watcher 代次，
+/// 本进程没有监听该项目时返回 None，结果不缓存。
+fn index_generation(
+    root: &str,
+    request: &SouRequest,
+    config: &SouRuntimeConfig,
+    strategy: &str,
+) -> Option<String> {
+    let uses = |backend: &str| {
+        strategy == backend
+            || (strategy == BACKEND_AUTO && config.auto_order.iter().any(|item| item == backend))
+    };
+    let local =
+        if config.local_enabled && (request.workspace_group.is_some() || uses(BACKEND_LOCAL)) {
+            local::peek_generation(Path::new(root))
+                .map(|generation| generation.to_string())
+                .unwrap_or_else(|| "na".to_string())
+        } else if uses(BACKEND_FAST_CONTEXT) || strategy == BACKEND_BOTH {
+            format!("watch:{}", local::peek_watched_generation(Path::new(root))?)
+        } else if !config.local_enabled {
+            "off".to_string()
+        } else {
+            "unused".to_string()
+        };
+    let ace = if uses(BACKEND_ACE) || strategy == BACKEND_BOTH {
+        crate::mcp::tools::acemcp::mcp::project_blob_fingerprint(root)
+            .unwrap_or_else(|| "none".to_string())
+    } else {
+        "unused".to_string()
+    };
+    Some(format!("{}|local:{}|ace:{}", root, local, ace))
+}
+
+/// 合并重叠片段、扩展上下文、标注所在符号并按字符预算截断；错误结果原样返回。
+/// 预算中预留缓存标注的长度，标注追加后输出仍不超过 `max_output_chars`。
+fn refine_result(result: CallToolResult, request: &SouRequest) -> CallToolResult {
+    if result.is_error == Some(true) {
+        return result;
+    }
+    let text = snippets::refine_text(
+        &call_result_text(&result),
+        &request.project_root_path,
+        snippets::SnippetOptions {
+            context_lines: request.context_lines.unwrap_or(0) as usize,
+            max_output_chars: request
+                .max_output_chars
+                .map(|limit| limit.saturating_sub(CACHE_NOTE_RESERVE)),
+        },
+    );
+    CallToolResult {
+        content: vec![Content::text(text)],
+        ..result
+    }
+}
+
+/// 错误与降级结果不缓存，避免一次临时故障在 TTL 内反复返回。
+fn is_cacheable(result: &CallToolResult) -> bool {
+    result.is_error != Some(true)
+        && !result
+            .structured_content
+            .as_ref()
+            .and_then(|value| value.get("degraded"))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+}
+
+/// 在 `[sou metadata]` 行与结构化元数据中标注缓存状态。
+/// 文本标注会使输出超出 `max_output_chars` 时（预算小于预留长度）只写入结构化元数据。
+fn annotate_cache(
+    result: CallToolResult,
+    status: cache::CacheStatus,
+    stats: cache::CacheStats,
+    max_output_chars: Option<usize>,
+) -> CallToolResult {
+    if result.is_error == Some(true) {
+        return result;
+    }
+    let suffix = format!(
+        "cache={}, cache_hits={}, cache_misses={}",
+        status.as_str(),
+        stats.hits,
+        stats.misses
+    );
+    let text = call_result_text(&result);
+    let mut lines = text.lines().map(str::to_string).collect::<Vec<_>>();
+    match lines
+        .iter_mut()
+        .rev()
+        .find(|line| line.starts_with("[sou metadata]"))
+    {
+        Some(line) => {
+            line.push_str(", ");
+            line.push_str(&suffix);
+        }
+        None => lines.push(format!("[sou metadata] {}", suffix)),
+    }
+    let mut annotated = lines.join("\n");
+    if max_output_chars.is_some_and(|limit| annotated.chars().count() > limit) {
+        annotated = text;
+    }
+
+    let mut metadata = result
+        .structured_content
+        .clone()
+        .unwrap_or_else(|| serde_json::json!({}));
+    if let Some(object) = metadata.as_object_mut() {
+        object.insert(
+            "cache".to_string(),
+            serde_json::json!({
+                "status": status.as_str(),
+                "hits": stats.hits,
+                "misses": stats.misses,
+            }),
+        );
+    }
+    CallToolResult {
+        content: vec![Content::text(annotated)],
+        is_error: result.is_error,
+        meta: result.meta,
+        structured_content: Some(metadata),
+    }
+}
+
+async fn search_uncached(
+    request: &SouRequest,
+    config: &SouRuntimeConfig,
+    strategy: &str,
+) -> Result<CallToolResult, McpError> {
+    if request.workspace_group.is_some() {
+        return result_to_call_tool(
+            run_workspace(request, config, strategy)
+                .await
+                .map_err(|message| BackendRunError {
+                    backend: BACKEND_LOCAL.to_string(),
+                    message,
+                }),
+            strategy,
+        );
+    }
+
+    match strategy {
+        BACKEND_ACE => result_to_call_tool(
+            run_ace(request).await.map_err(|e| BackendRunError {
+                backend: BACKEND_ACE.to_string(),
+                message: e,
+            }),
+            BACKEND_ACE,
+        ),
+        BACKEND_FAST_CONTEXT => result_to_call_tool(
+            run_fast_context(
+                request,
+                &config.fast_context,
+                config.include_backend_headers,
+            )
+            .await
+            .map_err(|e| BackendRunError {
+                backend: BACKEND_FAST_CONTEXT.to_string(),
+                message: e,
+            }),
+            BACKEND_FAST_CONTEXT,
+        ),
+        BACKEND_LOCAL if config.local_enabled => result_to_call_tool(
+            run_local(request, &config.fast_context)
+                .await
+                .map_err(|e| BackendRunError {
+                    backend: BACKEND_LOCAL.to_string(),
+                    message: e,
+                }),
+            BACKEND_LOCAL,
+        ),
+        BACKEND_LOCAL => Ok(error_result("Local搜索失败: 本地兜底已禁用".to_string())),
+        BACKEND_BOTH => run_both(request, config).await,
+        BACKEND_AUTO => run_auto(request, config).await,
+        other => Ok(error_result(format!("sou搜索失败: 未知后端策略 {}", other))),
+    }
+}
+
 impl SouRuntimeConfig {
     fn load() -> Result<Self> {
         let app_config =
@@ -263,6 +585,7 @@ impl SouRuntimeConfig {
             include_backend_headers: mcp.sou_include_backend_headers.unwrap_or(true),
             include_failed_backend_errors: mcp.sou_include_failed_backend_errors.unwrap_or(true),
             local_enabled: mcp.sou_local_enabled.unwrap_or(true),
+            workspace_groups: mcp.sou_workspace_groups.unwrap_or_default(),
             fast_context: FastContextConfig {
                 api_key: mcp.fast_context_api_key.and_then(|s| {
                     if s.trim().is_empty() {
@@ -282,6 +605,9 @@ impl SouRuntimeConfig {
                 exclude_paths: mcp
                     .fast_context_exclude_paths
                     .unwrap_or_else(default_fast_excludes),
+                secret_scan_enabled: mcp.secret_scan_enabled.unwrap_or(true),
+                secret_scan_policy: mcp.secret_scan_policy,
+                secret_scan_custom_patterns: mcp.secret_scan_custom_patterns.unwrap_or_default(),
             },
         })
     }
@@ -366,6 +692,100 @@ fn normalize_auto_order(value: Option<Vec<String>>) -> Vec<String> {
     out
 }
 
+/// 工作区组检索只走本地 FTS5/rg：远端后端的索引与配额均按单项目维护，
+/// 扇出到十几个仓库会放大延迟与费用。显式请求其他后端时降级并说明原因。
+async fn run_workspace(
+    request: &SouRequest,
+    config: &SouRuntimeConfig,
+    strategy: &str,
+) -> Result<BackendRunResult, String> {
+    let name = request
+        .workspace_group
+        .as_deref()
+        .unwrap_or_default()
+        .trim();
+    let group = config
+        .workspace_groups
+        .iter()
+        .find(|group| group.name.trim().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            format!(
+                "未找到工作区组 {}，可用: {}",
+                name,
+                config
+                    .workspace_groups
+                    .iter()
+                    .map(|group| group.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+    if !config.local_enabled {
+        return Err("工作区组检索依赖本地兜底，但本地兜底已禁用".to_string());
+    }
+
+    let base_excludes = request
+        .exclude_paths
+        .clone()
+        .unwrap_or_else(|| config.fast_context.exclude_paths.clone());
+    let roots = group
+        .roots
+        .iter()
+        .map(|root| local::WorkspaceRoot {
+            label: workspace_root_label(root),
+            project_root: PathBuf::from(&root.path),
+            exclude_paths: base_excludes
+                .iter()
+                .chain(root.exclude_paths.iter())
+                .cloned()
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    let output = local::search_workspace(
+        request.query.clone(),
+        request
+            .max_results
+            .unwrap_or(config.fast_context.max_results) as usize,
+        roots,
+    )
+    .await
+    .map_err(|error| error.to_string())?;
+
+    let ignored_backend = (!matches!(strategy, BACKEND_LOCAL | BACKEND_AUTO)).then(|| {
+        format!(
+            "workspace_group 仅支持 local 后端，已忽略 backend={}",
+            strategy
+        )
+    });
+    let fallback_reason = match (ignored_backend, output.fallback_reason) {
+        (Some(ignored), Some(reason)) => Some(format!("{}；{}", ignored, reason)),
+        (ignored, reason) => ignored.or(reason),
+    };
+    Ok(BackendRunResult {
+        backend: BACKEND_LOCAL.to_string(),
+        text: output.text,
+        hit_count: output.hit_count,
+        duration_ms: output.duration_ms,
+        engine: Some(output.engine),
+        index_state: Some(output.index_state),
+        fallback_reason,
+    })
+}
+
+fn workspace_root_label(root: &crate::config::SouWorkspaceRoot) -> String {
+    root.label
+        .as_deref()
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            Path::new(root.path.trim_end_matches(['/', '\\']))
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.path.clone())
+        })
+}
+
 async fn run_auto(
     request: &SouRequest,
     config: &SouRuntimeConfig,
@@ -383,6 +803,15 @@ async fn run_auto(
     }
 }
 
+/// 远端后端的熔断器处于打开状态时返回剩余冷却时间
+fn breaker_open_remaining(backend: &str) -> Option<Duration> {
+    match backend {
+        BACKEND_ACE => resilience::open_remaining(resilience::SERVICE_ACE),
+        BACKEND_FAST_CONTEXT => resilience::open_remaining(resilience::SERVICE_FAST_CONTEXT),
+        _ => None,
+    }
+}
+
 async fn run_auto_result(
     request: &SouRequest,
     config: &SouRuntimeConfig,
@@ -392,6 +821,7 @@ async fn run_auto_result(
         .auto_order
         .iter()
         .filter(|backend| matches!(backend.as_str(), BACKEND_ACE | BACKEND_FAST_CONTEXT))
+        .filter(|backend| breaker_open_remaining(backend).is_none())
         .count()
         .max(1) as u64;
     let total_remote_budget_ms = request
@@ -401,6 +831,14 @@ async fn run_auto_result(
     let per_remote_timeout_ms = (total_remote_budget_ms / remote_backend_count).max(1000);
 
     for backend in &config.auto_order {
+        if let Some(remaining) = breaker_open_remaining(backend) {
+            log_important!(info, "[sou] auto 跳过熔断中的后端: {}", backend);
+            errors.push(BackendRunError {
+                backend: backend.clone(),
+                message: format!("熔断中，{}s 后恢复探测，已跳过", remaining.as_secs().max(1)),
+            });
+            continue;
+        }
         log_important!(info, "[sou] auto 尝试后端: {}", backend);
         let result = match backend.as_str() {
             BACKEND_ACE => match tokio::time::timeout(
@@ -634,7 +1072,11 @@ async fn run_fast_context(
     let first = run_fast_context_once(request, config, include_header, false).await;
     match first {
         Ok(result) => Ok(result),
-        Err(message) if should_retry_fast_context_search(&message) => {
+        // 熔断打开时兜底重试也会被拒绝，直接返回首轮错误
+        Err(message)
+            if should_retry_fast_context_search(&message)
+                && breaker_open_remaining(BACKEND_FAST_CONTEXT).is_none() =>
+        {
             log_important!(
                 warn,
                 "[sou] fast-context 触发独立兜底重试: delay_ms={}, first_error={}",
@@ -702,6 +1144,9 @@ async fn run_fast_context_once(
             max_commands,
             timeout_ms: effective_timeout_ms,
             exclude_paths,
+            secret_scan_enabled: config.secret_scan_enabled,
+            secret_scan_policy: config.secret_scan_policy.clone(),
+            secret_scan_custom_patterns: config.secret_scan_custom_patterns.clone(),
         }),
     )
     .await
@@ -939,6 +1384,8 @@ fn parse_sou_sections(text: &str, default_backend: &str) -> Vec<SouSection> {
     let mut sections = Vec::new();
     let mut backend = default_backend.to_string();
     let mut current_location: Option<String> = None;
+    let mut current_section: Option<String> = None;
+    let mut current_repo: Option<String> = None;
     let mut current_lines = Vec::new();
 
     for line in text.lines() {
@@ -947,6 +1394,8 @@ fn parse_sou_sections(text: &str, default_backend: &str) -> Vec<SouSection> {
                 &mut sections,
                 &backend,
                 &mut current_location,
+                &mut current_section,
+                &mut current_repo,
                 &mut current_lines,
             );
             backend = value.trim().to_string();
@@ -957,6 +1406,8 @@ fn parse_sou_sections(text: &str, default_backend: &str) -> Vec<SouSection> {
                 &mut sections,
                 &backend,
                 &mut current_location,
+                &mut current_section,
+                &mut current_repo,
                 &mut current_lines,
             );
             current_location = Some(normalize_sou_location(path));
@@ -968,6 +1419,20 @@ fn parse_sou_sections(text: &str, default_backend: &str) -> Vec<SouSection> {
             }
             continue;
         }
+        // 只认紧跟在 Path/Lines 头之后的 Repo/Section 行，避免误吞正文
+        if current_location.is_some() && current_lines.is_empty() {
+            if let Some(section) = line.strip_prefix("Section: ") {
+                current_section = Some(section.trim().to_string());
+                continue;
+            }
+            if let Some(repo) = line.strip_prefix("Repo: ") {
+                current_repo = Some(repo.trim().to_string());
+                continue;
+            }
+            if line.starts_with("Symbol: ") {
+                continue;
+            }
+        }
         if line.starts_with("The following code sections were retrieved:") {
             continue;
         }
@@ -975,6 +1440,7 @@ fn parse_sou_sections(text: &str, default_backend: &str) -> Vec<SouSection> {
             || line.starts_with("[sou fallback]")
             || line.starts_with("[sou-local]")
             || line.starts_with("[sou-local fallback]")
+            || line.starts_with("[sou truncated]")
             || line.starts_with("[fast-context stats]")
             || line.starts_with("[fast-context config]")
             || line.starts_with("grep keywords:")
@@ -990,6 +1456,8 @@ fn parse_sou_sections(text: &str, default_backend: &str) -> Vec<SouSection> {
         &mut sections,
         &backend,
         &mut current_location,
+        &mut current_section,
+        &mut current_repo,
         &mut current_lines,
     );
     sections
@@ -1011,8 +1479,12 @@ fn flush_sou_section(
     sections: &mut Vec<SouSection>,
     backend: &str,
     current_location: &mut Option<String>,
+    current_section: &mut Option<String>,
+    current_repo: &mut Option<String>,
     current_lines: &mut Vec<String>,
 ) {
+    let section = current_section.take();
+    let repo = current_repo.take();
     let Some(location) = current_location.take() else {
         current_lines.clear();
         return;
@@ -1031,6 +1503,8 @@ fn flush_sou_section(
         backend: backend.to_string(),
         location,
         excerpt,
+        section,
+        repo,
     });
 }
 
@@ -1112,6 +1586,54 @@ fn diagnostic_summary(message: &str) -> String {
     format!("{}...", single_line.chars().take(320).collect::<String>())
 }
 
+async fn why_not_indexed_result(request: &SouRequest) -> CallToolResult {
+    let Some(path) = request
+        .path
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return error_result("why_not_indexed 失败: 缺少 path".to_string());
+    };
+    if request.project_root_path.trim().is_empty() {
+        return error_result("why_not_indexed 失败: 缺少 project_root_path".to_string());
+    }
+    match why_not_indexed::explain(
+        &request.project_root_path,
+        path,
+        request.exclude_paths.clone(),
+        why_not_indexed::DEFAULT_TOP_EXCLUDED,
+    )
+    .await
+    {
+        Ok(report) => CallToolResult {
+            content: vec![Content::text(why_not_indexed::format_report(&report))],
+            is_error: Some(false),
+            meta: None,
+            structured_content: serde_json::to_value(&report).ok(),
+        },
+        Err(error) => error_result(format!("why_not_indexed 失败: {}", error)),
+    }
+}
+
+fn index_history_result(request: &SouRequest) -> CallToolResult {
+    use crate::mcp::tools::acemcp::job_history;
+
+    let project_root = Some(request.project_root_path.trim())
+        .filter(|root| !root.is_empty())
+        .map(crate::mcp::tools::acemcp::scope_guard::normalize_root);
+    let records = job_history::query_history(&job_history::HistoryQuery {
+        project_root,
+        limit: request.max_results.unwrap_or(20).max(1) as usize,
+        ..Default::default()
+    });
+    CallToolResult {
+        content: vec![Content::text(job_history::format_history(&records))],
+        is_error: Some(false),
+        meta: None,
+        structured_content: serde_json::to_value(&records).ok(),
+    }
+}
+
 fn error_result(text: String) -> CallToolResult {
     CallToolResult {
         content: vec![Content::text(text)],
@@ -1270,6 +1792,41 @@ mod tests {
         assert_eq!(sections[0].excerpt, "L2:fn local_search() {}");
     }
 
+    #[test]
+    fn typed_sections_record_workspace_repo() {
+        let text = "Path: /srv/billing/lib.rs\nLines: L1-L1\nRepo: billing\nL1:pub struct InvoiceLedger;\n";
+        let sections = parse_sou_sections(text, "local");
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].repo.as_deref(), Some("billing"));
+        assert_eq!(sections[0].excerpt, "L1:pub struct InvoiceLedger;");
+    }
+
+    #[test]
+    fn workspace_root_label_defaults_to_directory_name() {
+        let root = crate::config::SouWorkspaceRoot {
+            path: "E:/work/payments-api/".to_string(),
+            label: None,
+            exclude_paths: Vec::new(),
+        };
+        assert_eq!(workspace_root_label(&root), "payments-api");
+        let labeled = crate::config::SouWorkspaceRoot {
+            label: Some(" billing ".to_string()),
+            ..root
+        };
+        assert_eq!(workspace_root_label(&labeled), "billing");
+    }
+
+    #[test]
+    fn typed_sections_record_document_section() {
+        let text = "Path: E:/demo/docs/adr.md\nLines: L4-L9\nSection: Design > Index\nL4:## Index\nL5:Section: not a header\n";
+        let sections = parse_sou_sections(text, "local");
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].section.as_deref(), Some("Design > Index"));
+        assert_eq!(sections[0].excerpt, "L4:## Index\nL5:Section: not a header");
+    }
+
     #[tokio::test]
     async fn explicit_local_backend_returns_hits_and_structured_metadata() {
         let temp = tempdir().expect("Local 路由临时项目应创建成功");
@@ -1281,6 +1838,8 @@ mod tests {
         let request = SouRequest {
             project_root_path: temp.path().to_string_lossy().to_string(),
             query: "LocalIndexStatus backendSuccessResult".to_string(),
+            action: None,
+            path: None,
             backend: Some(BACKEND_LOCAL.to_string()),
             tree_depth: None,
             max_turns: None,
@@ -1288,6 +1847,10 @@ mod tests {
             max_commands: None,
             timeout_ms: None,
             exclude_paths: Some(Vec::new()),
+            workspace_group: None,
+            no_cache: None,
+            context_lines: None,
+            max_output_chars: None,
         };
         let defaults = FastContextConfig {
             api_key: None,
@@ -1297,6 +1860,9 @@ mod tests {
             max_commands: 8,
             timeout_ms: 30_000,
             exclude_paths: Vec::new(),
+            secret_scan_enabled: true,
+            secret_scan_policy: None,
+            secret_scan_custom_patterns: Vec::new(),
         };
 
         let output = local::search_for_test(
@@ -1331,4 +1897,105 @@ mod tests {
         assert!(metadata["hit_count"].as_u64().unwrap_or_default() >= 1);
         assert_eq!(call_result.is_error, Some(false));
     }
+
+    #[test]
+    fn fast_context_results_are_not_cached_without_a_watched_generation() {
+        let temp = tempdir().expect("缓存键临时项目应创建成功");
+        let config = SouRuntimeConfig {
+            default_backend: BACKEND_AUTO.to_string(),
+            auto_order: vec![BACKEND_FAST_CONTEXT.to_string()],
+            include_backend_headers: false,
+            include_failed_backend_errors: false,
+            local_enabled: true,
+            workspace_groups: Vec::new(),
+            fast_context: FastContextConfig {
+                api_key: None,
+                tree_depth: 3,
+                max_turns: 4,
+                max_results: 10,
+                max_commands: 8,
+                timeout_ms: 30_000,
+                exclude_paths: Vec::new(),
+                secret_scan_enabled: true,
+                secret_scan_policy: None,
+                secret_scan_custom_patterns: Vec::new(),
+            },
+        };
+        let request: SouRequest = serde_json::from_value(json!({
+            "project_root_path": temp.path().to_string_lossy(),
+            "query": "parse config",
+        }))
+        .expect("测试请求应能解析");
+
+        // 中文说明：fast_context 直接读磁盘，本进程未监听该项目时无法判断结果是否过期。
+        assert!(cache_key(&request, &config, BACKEND_FAST_CONTEXT).is_none());
+        assert!(cache_key(&request, &config, BACKEND_AUTO).is_none());
+        assert!(cache_key(&request, &config, BACKEND_BOTH).is_none());
+        let local = cache_key(&request, &config, BACKEND_LOCAL).expect("本地检索应可缓存");
+        assert!(local.generation.contains("local:na"));
+    }
+
+    #[test]
+    fn cache_annotation_extends_metadata_line_and_structured_content() {
+        let result = success_result_with_metadata(
+            "Path: src/lib.rs\nL1:fn main() {}\n[sou metadata] requested_backend=local, actual_backend=local, degraded=false".to_string(),
+            serde_json::json!({"degraded": false}),
+        );
+        assert!(is_cacheable(&result));
+
+        let annotated = annotate_cache(
+            result,
+            cache::CacheStatus::Hit,
+            cache::CacheStats { hits: 2, misses: 1 },
+            None,
+        );
+        let text = call_result_text(&annotated);
+        assert!(text.ends_with("degraded=false, cache=hit, cache_hits=2, cache_misses=1"));
+        let metadata = annotated.structured_content.expect("应带结构化元数据");
+        assert_eq!(metadata["cache"]["status"], "hit");
+        assert_eq!(metadata["cache"]["misses"], 1);
+
+        // 预留长度足以容纳标注：按预算截断后的结果加上标注仍不超过 max_output_chars
+        let body = (1..=40)
+            .map(|number| {
+                format!(
+                    "L{}:    let value_{} = compute({});",
+                    number, number, number
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request: SouRequest = serde_json::from_value(serde_json::json!({
+            "query": "compute",
+            "max_output_chars": 400,
+        }))
+        .expect("测试请求应能解析");
+        let refined = refine_result(
+            success_result_with_metadata(
+                format!(
+                    "Path: src/lib.rs\n{}\n[sou metadata] actual_backend=local",
+                    body
+                ),
+                serde_json::json!({"degraded": false}),
+            ),
+            &request,
+        );
+        let annotated = annotate_cache(
+            refined,
+            cache::CacheStatus::Bypass,
+            cache::CacheStats {
+                hits: u64::MAX,
+                misses: u64::MAX,
+            },
+            request.max_output_chars,
+        );
+        assert!(call_result_text(&annotated).chars().count() <= 400);
+
+        let degraded = success_result_with_metadata(
+            "Path: a".to_string(),
+            serde_json::json!({"degraded": true}),
+        );
+        assert!(!is_cacheable(&degraded));
+        assert!(!is_cacheable(&error_result("失败".to_string())));
+    }
 }
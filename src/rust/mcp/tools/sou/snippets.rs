@@ -0,0 +1,689 @@
+// sou 输出片段整理
+// 各后端返回的片段经常重叠：本地索引分块自带 20 行重叠，both 模式下 ACE 与 fast-context
+// 也会命中同一段代码。这里在文本协议层按文件合并重叠行区间、按需向两侧扩展上下文、
+// 标注所在符号（函数/类型等），并按字符预算截断，保证输出能确定性地放进 agent 上下文。
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::{extract, local};
+
+/// 上下文扩展上限，避免一次请求把整文件拉回来
+const MAX_CONTEXT_LINES: usize = 50;
+/// 向上查找所在符号的最大行数
+const SYMBOL_SCAN_LINES: usize = 500;
+const MAX_SYMBOL_CHARS: usize = 120;
+/// 截断说明行（含 omitted_lines）的预留长度
+const TRUNCATION_NOTE_RESERVE: usize = 112;
+
+static NUMBERED_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^L(\d+):").expect("行号正则应合法"));
+static DEFINITION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"^(?:(?:pub(?:\([^)]*\))?|export|default|async|static|public|private|protected|internal|abstract|final|override|unsafe|extern(?:\s+"[^"]*")?|inline|virtual|data|sealed|open)\s+)*(?:fn|struct|enum|trait|impl|mod|union|macro_rules!|class|interface|def|func|function|object|namespace|module)\b"#,
+    )
+    .expect("符号正则应合法")
+});
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct SnippetOptions {
+    pub context_lines: usize,
+    pub max_output_chars: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Snippet {
+    path: String,
+    repo: Option<String>,
+    section: Option<String>,
+    symbol: Option<String>,
+    /// `Lines:` 头给出的区间；带行号片段以实际行号为准
+    range: Option<(usize, usize)>,
+    /// 带 `L<n>:` 行号的片段按行存储，可合并与扩展；其他格式（如 ACE 原文）原样保留
+    numbered: Option<BTreeMap<usize, String>>,
+    raw: Vec<String>,
+    truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Text(String),
+    Snippet(Snippet),
+}
+
+/// 整理 sou 文本输出；没有可识别的片段时原样返回。
+pub(super) fn refine_text(text: &str, project_root: &str, options: SnippetOptions) -> String {
+    let mut items = parse_items(text);
+    if !items.iter().any(|item| matches!(item, Item::Snippet(_))) {
+        return text.to_string();
+    }
+
+    let mut files = FileCache::new(project_root);
+    let context_lines = options.context_lines.min(MAX_CONTEXT_LINES);
+    if context_lines > 0 {
+        for item in items.iter_mut() {
+            if let Item::Snippet(snippet) = item {
+                expand_context(snippet, context_lines, &mut files);
+            }
+        }
+    }
+    merge_overlapping(&mut items);
+    for item in items.iter_mut() {
+        if let Item::Snippet(snippet) = item {
+            fill_numbered_gaps(snippet, &mut files);
+            if snippet.section.is_none() {
+                snippet.symbol = enclosing_symbol(snippet, &mut files);
+            }
+        }
+    }
+
+    match options.max_output_chars {
+        Some(limit) => apply_budget(items, limit),
+        None => render(&items),
+    }
+}
+
+fn parse_items(text: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut current: Option<Snippet> = None;
+    let mut range: Option<(usize, usize)> = None;
+
+    for line in text.lines() {
+        if let Some(path) = line.strip_prefix("Path: ") {
+            push_snippet(&mut items, current.take(), range.take());
+            current = Some(Snippet {
+                path: path.trim().to_string(),
+                repo: None,
+                section: None,
+                symbol: None,
+                range: None,
+                numbered: None,
+                raw: Vec::new(),
+                truncated: false,
+            });
+            continue;
+        }
+        let Some(snippet) = current.as_mut() else {
+            items.push(Item::Text(line.to_string()));
+            continue;
+        };
+        if snippet.raw.is_empty() {
+            if let Some(value) = line.strip_prefix("Lines: L") {
+                range = parse_range(value);
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("Repo: ") {
+                snippet.repo = Some(value.trim().to_string());
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("Section: ") {
+                snippet.section = Some(value.trim().to_string());
+                continue;
+            }
+            if line.starts_with("Symbol: ") {
+                continue;
+            }
+        }
+        if is_terminator(line) {
+            push_snippet(&mut items, current.take(), range.take());
+            items.push(Item::Text(line.to_string()));
+            continue;
+        }
+        snippet.raw.push(line.to_string());
+    }
+    push_snippet(&mut items, current, range);
+    items
+}
+
+fn push_snippet(items: &mut Vec<Item>, snippet: Option<Snippet>, range: Option<(usize, usize)>) {
+    let Some(mut snippet) = snippet else {
+        return;
+    };
+    while snippet
+        .raw
+        .last()
+        .is_some_and(|line| line.trim().is_empty())
+    {
+        snippet.raw.pop();
+    }
+    snippet.range = range;
+    if range.is_some() && !snippet.raw.is_empty() {
+        let mut lines = BTreeMap::new();
+        let all_numbered = snippet.raw.iter().all(|line| {
+            let Some(captures) = NUMBERED_LINE.captures(line) else {
+                return false;
+            };
+            let Ok(number) = captures[1].parse::<usize>() else {
+                return false;
+            };
+            lines.insert(number, line[captures[0].len()..].to_string());
+            true
+        });
+        if all_numbered {
+            snippet.numbered = Some(lines);
+            snippet.raw.clear();
+        }
+    }
+    items.push(Item::Snippet(snippet));
+    // 片段之间原有的空行由 render 统一补回
+    items.push(Item::Text(String::new()));
+}
+
+fn parse_range(value: &str) -> Option<(usize, usize)> {
+    let (start, end) = value.trim().split_once("-L")?;
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+}
+
+fn is_terminator(line: &str) -> bool {
+    line.trim() == "---"
+        || [
+            "### sou backend: ",
+            "[sou",
+            "[fast-context",
+            "grep keywords:",
+            "No relevant files found.",
+            "The following code sections were retrieved:",
+            "后端诊断",
+        ]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// 同一文件（及同一仓库）行区间重叠或相邻的片段合并到最先出现的位置；
+/// 原文格式片段只去除完全相同的重复。
+fn merge_overlapping(items: &mut Vec<Item>) {
+    let mut index = 0;
+    while index < items.len() {
+        let Item::Snippet(candidate) = &items[index] else {
+            index += 1;
+            continue;
+        };
+        let target = items[..index].iter().position(|item| match item {
+            Item::Snippet(existing) => can_merge(existing, candidate),
+            Item::Text(_) => false,
+        });
+        let Some(target) = target else {
+            index += 1;
+            continue;
+        };
+        let Item::Snippet(candidate) = items.remove(index) else {
+            unreachable!("候选项必为片段");
+        };
+        if index < items.len() && matches!(&items[index], Item::Text(line) if line.is_empty()) {
+            items.remove(index);
+        }
+        if let Item::Snippet(existing) = &mut items[target] {
+            if let (Some(lines), Some(extra)) = (existing.numbered.as_mut(), candidate.numbered) {
+                for (number, line) in extra {
+                    lines.entry(number).or_insert(line);
+                }
+            }
+        }
+        // 合并后的区间可能与更早的片段重新相交，从头再扫一遍
+        index = 0;
+    }
+}
+
+fn can_merge(existing: &Snippet, candidate: &Snippet) -> bool {
+    if existing.path != candidate.path || existing.repo != candidate.repo {
+        return false;
+    }
+    match (existing.numbered.as_ref(), candidate.numbered.as_ref()) {
+        (Some(left), Some(right)) => {
+            let (left_start, left_end) = bounds(left);
+            let (right_start, right_end) = bounds(right);
+            left_start <= right_end + 1 && right_start <= left_end + 1
+        }
+        (None, None) => existing.raw == candidate.raw,
+        _ => false,
+    }
+}
+
+fn bounds(lines: &BTreeMap<usize, String>) -> (usize, usize) {
+    (
+        lines.keys().next().copied().unwrap_or_default(),
+        lines.keys().next_back().copied().unwrap_or_default(),
+    )
+}
+
+fn expand_context(snippet: &mut Snippet, context_lines: usize, files: &mut FileCache) {
+    let Some(lines) = snippet.numbered.as_mut() else {
+        return;
+    };
+    let Some(content) = files.lines(&snippet.path) else {
+        return;
+    };
+    let (start, end) = bounds(lines);
+    let from = start.saturating_sub(context_lines).max(1);
+    let to = (end + context_lines).min(content.len());
+    for number in from..=to {
+        lines
+            .entry(number)
+            .or_insert_with(|| content[number - 1].clone());
+    }
+}
+
+/// 合并后区间内若有缺行（来自不同后端的片段首尾相接时不会出现，这里只作兜底），从磁盘补齐。
+fn fill_numbered_gaps(snippet: &mut Snippet, files: &mut FileCache) {
+    let Some(lines) = snippet.numbered.as_mut() else {
+        return;
+    };
+    let (start, end) = bounds(lines);
+    if lines.len() == end + 1 - start {
+        return;
+    }
+    let Some(content) = files.lines(&snippet.path) else {
+        return;
+    };
+    // 后端可能给出 `L0:`，与 expand_context 一样从第 1 行开始补齐
+    for number in start.max(1)..=end.min(content.len()) {
+        lines
+            .entry(number)
+            .or_insert_with(|| content[number - 1].clone());
+    }
+}
+
+/// 从片段首行向上找缩进更浅的定义行，作为片段所在符号。
+fn enclosing_symbol(snippet: &Snippet, files: &mut FileCache) -> Option<String> {
+    let lines = snippet.numbered.as_ref()?;
+    let (start, _) = bounds(lines);
+    let content = files.lines(&snippet.path)?;
+    let first = content.get(start.checked_sub(1)?)?;
+    if is_definition(first) {
+        return Some(symbol_label(first));
+    }
+    let mut limit = content[start - 1..]
+        .iter()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| indentation(line))?;
+    for line in content[..start - 1].iter().rev().take(SYMBOL_SCAN_LINES) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = indentation(line);
+        if indent >= limit {
+            continue;
+        }
+        if is_definition(line) {
+            return Some(symbol_label(line));
+        }
+        if indent == 0 {
+            return None;
+        }
+        limit = indent;
+    }
+    None
+}
+
+fn is_definition(line: &str) -> bool {
+    DEFINITION.is_match(line.trim_start())
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|ch| ch.is_whitespace())
+        .map(|ch| if ch == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn symbol_label(line: &str) -> String {
+    let label = line
+        .trim()
+        .trim_end_matches('{')
+        .trim_end_matches(':')
+        .trim();
+    if label.chars().count() <= MAX_SYMBOL_CHARS {
+        return label.to_string();
+    }
+    format!(
+        "{}...",
+        label.chars().take(MAX_SYMBOL_CHARS).collect::<String>()
+    )
+}
+
+fn render_snippet(snippet: &Snippet, body: &[String]) -> Vec<String> {
+    let mut out = vec![format!("Path: {}", snippet.path)];
+    let range = snippet.numbered.as_ref().map(bounds).or(snippet.range);
+    if let Some((start, end)) = range {
+        out.push(format!("Lines: L{}-L{}", start, end));
+    }
+    if let Some(repo) = snippet.repo.as_deref() {
+        out.push(format!("Repo: {}", repo));
+    }
+    if let Some(section) = snippet.section.as_deref() {
+        out.push(format!("Section: {}", section));
+    }
+    if let Some(symbol) = snippet.symbol.as_deref() {
+        out.push(format!("Symbol: {}", symbol));
+    }
+    out.extend(body.iter().cloned());
+    out
+}
+
+fn snippet_body(snippet: &Snippet) -> Vec<String> {
+    match snippet.numbered.as_ref() {
+        Some(lines) => lines
+            .iter()
+            .map(|(number, line)| format!("L{}:{}", number, line))
+            .collect(),
+        None => snippet.raw.clone(),
+    }
+}
+
+fn render(items: &[Item]) -> String {
+    let mut out = Vec::new();
+    for item in items {
+        match item {
+            Item::Text(line) => out.push(line.clone()),
+            Item::Snippet(snippet) => {
+                out.extend(render_snippet(snippet, &snippet_body(snippet)));
+                if snippet.truncated {
+                    out.push("...".to_string());
+                }
+            }
+        }
+    }
+    out.join("\n")
+}
+
+/// 超出预算时按排名顺序保留片段：放不下的片段按行截断，其后的片段全部省略。
+/// 非片段行（统计、诊断、元数据）优先占用预算；它们本身就超出预算时按顺序省略放不下的行，
+/// 输出总长度不超过 `limit`。
+fn apply_budget(items: Vec<Item>, limit: usize) -> String {
+    let full = render(&items);
+    if full.chars().count() <= limit {
+        return full;
+    }
+
+    let total = items
+        .iter()
+        .filter(|item| matches!(item, Item::Snippet(_)))
+        .count();
+    // 预留截断说明行
+    let mut remaining = limit.saturating_sub(TRUNCATION_NOTE_RESERVE);
+    let mut omitted_lines = 0usize;
+    let items = items
+        .into_iter()
+        .filter(|item| {
+            let Item::Text(line) = item else {
+                return true;
+            };
+            let cost = line.chars().count() + 1;
+            if cost <= remaining {
+                remaining -= cost;
+                true
+            } else {
+                omitted_lines += 1;
+                false
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut kept = Vec::new();
+    let mut shown = 0usize;
+    let mut exhausted = false;
+
+    for item in items {
+        let Item::Snippet(mut snippet) = item else {
+            kept.push(item);
+            continue;
+        };
+        if exhausted {
+            continue;
+        }
+        let body = snippet_body(&snippet);
+        let header_cost = render_snippet(&snippet, &[])
+            .iter()
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>();
+        let body_cost = body
+            .iter()
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>();
+        if header_cost + body_cost <= remaining {
+            remaining -= header_cost + body_cost;
+            shown += 1;
+            kept.push(Item::Snippet(snippet));
+            continue;
+        }
+
+        exhausted = true;
+        // 截断标记 `...` 也计入预算
+        let mut budget = remaining.saturating_sub(header_cost + 4);
+        let mut fitted = 0usize;
+        for line in &body {
+            let cost = line.chars().count() + 1;
+            if cost > budget {
+                break;
+            }
+            budget -= cost;
+            fitted += 1;
+        }
+        if fitted == 0 {
+            continue;
+        }
+        match snippet.numbered.as_mut() {
+            Some(lines) => {
+                let keep = lines.keys().take(fitted).copied().collect::<Vec<_>>();
+                lines.retain(|number, _| keep.contains(number));
+            }
+            None => snippet.raw.truncate(fitted),
+        }
+        snippet.truncated = true;
+        shown += 1;
+        kept.push(Item::Snippet(snippet));
+    }
+
+    let mut text = render(&kept);
+    text.push_str(&format!(
+        "\n[sou truncated] shown_snippets={}, omitted_snippets={}, max_output_chars={}",
+        shown,
+        total - shown,
+        limit
+    ));
+    if omitted_lines > 0 {
+        text.push_str(&format!(", omitted_lines={}", omitted_lines));
+    }
+    // 中文说明：预算小于截断说明行本身时只能硬截断。
+    if text.chars().count() > limit {
+        text = text.chars().take(limit).collect();
+    }
+    text
+}
+
+/// 同一次整理内的文件内容缓存；读不到或不适合按行定位的文件返回 None。
+struct FileCache {
+    root: PathBuf,
+    files: HashMap<String, Option<Vec<String>>>,
+}
+
+impl FileCache {
+    fn new(project_root: &str) -> Self {
+        Self {
+            root: PathBuf::from(project_root),
+            files: HashMap::new(),
+        }
+    }
+
+    fn lines(&mut self, display_path: &str) -> Option<&Vec<String>> {
+        if !self.files.contains_key(display_path) {
+            let loaded = self.load(display_path);
+            self.files.insert(display_path.to_string(), loaded);
+        }
+        self.files.get(display_path)?.as_ref()
+    }
+
+    fn load(&self, display_path: &str) -> Option<Vec<String>> {
+        let path = Path::new(display_path);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        };
+        // Notebook / PDF 片段的行号是抽取文本中的行号，与磁盘文件对不上
+        if extract::extractor_for(&path).is_some_and(|extractor| extractor.name() != "markdown") {
+            return None;
+        }
+        let metadata = std::fs::metadata(&path).ok()?;
+        if !metadata.is_file() || metadata.len() > local::MAX_FILE_BYTES {
+            return None;
+        }
+        let content = std::fs::read_to_string(&path).ok()?;
+        Some(content.lines().map(str::to_string).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn source() -> String {
+        (1..=40)
+            .map(|number| match number {
+                1 => "impl Indexer {".to_string(),
+                2 => "    pub fn sync(&self) -> Result<()> {".to_string(),
+                20 => "    }".to_string(),
+                40 => "}".to_string(),
+                _ => format!("        step_{}();", number),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn numbered(path: &str, start: usize, end: usize) -> String {
+        let content = source();
+        let lines = content.lines().collect::<Vec<_>>();
+        let mut out = vec![
+            format!("Path: {}", path),
+            format!("Lines: L{}-L{}", start, end),
+        ];
+        out.extend((start..=end).map(|number| format!("L{}:{}", number, lines[number - 1])));
+        out.push(String::new());
+        out.join("\n")
+    }
+
+    #[test]
+    fn overlapping_ranges_merge_across_backends_and_gain_symbol() {
+        let temp = tempdir().expect("临时目录应创建成功");
+        let file = temp.path().join("indexer.rs");
+        std::fs::write(&file, source()).expect("源码应写入成功");
+        let path = file.to_string_lossy().to_string();
+        let text = format!(
+            "### sou backend: ace\n\n{}\n### sou backend: fast_context\n\n{}\n{}[fast-context stats] commands_seen=1",
+            numbered(&path, 5, 10),
+            numbered(&path, 9, 14),
+            numbered(&path, 30, 31),
+        );
+
+        let refined = refine_text(&text, "", SnippetOptions::default());
+
+        assert_eq!(refined.matches(&format!("Path: {}", path)).count(), 2);
+        assert!(refined.contains("Lines: L5-L14"));
+        assert!(refined.contains("Symbol: pub fn sync(&self) -> Result<()>"));
+        assert!(refined.contains("Symbol: impl Indexer"));
+        assert_eq!(refined.matches("L9:").count(), 1);
+        assert!(refined.ends_with("[fast-context stats] commands_seen=1"));
+    }
+
+    #[test]
+    fn context_lines_expand_ranges_and_remerge_neighbours() {
+        let temp = tempdir().expect("临时目录应创建成功");
+        std::fs::write(temp.path().join("indexer.rs"), source()).expect("源码应写入成功");
+        let text = format!(
+            "{}{}",
+            numbered("indexer.rs", 5, 6),
+            numbered("indexer.rs", 10, 11)
+        );
+
+        let refined = refine_text(
+            &text,
+            &temp.path().to_string_lossy(),
+            SnippetOptions {
+                context_lines: 2,
+                max_output_chars: None,
+            },
+        );
+
+        assert_eq!(refined.matches("Path: indexer.rs").count(), 1);
+        assert!(refined.contains("Lines: L3-L13"));
+    }
+
+    #[test]
+    fn zero_line_numbers_do_not_panic_when_filling_gaps() {
+        let temp = tempdir().expect("临时目录应创建成功");
+        std::fs::write(temp.path().join("indexer.rs"), source()).expect("源码应写入成功");
+        let text = "Path: indexer.rs\nLines: L0-L3\nL0:impl Indexer {\nL3:        step_3();\n";
+
+        let refined = refine_text(
+            text,
+            &temp.path().to_string_lossy(),
+            SnippetOptions {
+                context_lines: 0,
+                max_output_chars: None,
+            },
+        );
+
+        assert!(refined.contains("L2:    pub fn sync(&self) -> Result<()> {"));
+    }
+
+    #[test]
+    fn budget_truncates_by_rank_and_keeps_metadata() {
+        let text = format!(
+            "{}{}[sou metadata] requested_backend=local",
+            numbered("missing_a.rs", 1, 20),
+            numbered("missing_b.rs", 1, 20)
+        );
+
+        let refined = refine_text(
+            &text,
+            "",
+            SnippetOptions {
+                context_lines: 0,
+                max_output_chars: Some(600),
+            },
+        );
+
+        assert!(refined.chars().count() <= 600);
+        assert!(refined.contains("Path: missing_a.rs"));
+        assert!(!refined.contains("Path: missing_b.rs"));
+        assert!(refined.contains("[sou metadata] requested_backend=local"));
+        assert!(refined.contains("[sou truncated] shown_snippets=1, omitted_snippets=1"));
+        assert_eq!(
+            refine_text(&text, "", SnippetOptions::default()),
+            refine_text(&text, "", SnippetOptions::default())
+        );
+    }
+
+    #[test]
+    fn budget_also_drops_fixed_lines_when_they_exceed_the_limit() {
+        let diagnostics = (0..20)
+            .map(|index| format!("[sou diagnostics] line={} {}", index, "x".repeat(40)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = format!("{}{}", numbered("missing_a.rs", 1, 5), diagnostics);
+
+        let refined = refine_text(
+            &text,
+            "",
+            SnippetOptions {
+                context_lines: 0,
+                max_output_chars: Some(400),
+            },
+        );
+
+        assert!(refined.chars().count() <= 400);
+        assert!(refined.contains("[sou diagnostics] line=0 "));
+        assert!(!refined.contains("[sou diagnostics] line=19 "));
+        assert!(refined.contains("omitted_lines="));
+    }
+
+    #[test]
+    fn opaque_snippets_are_kept_verbatim_and_exact_duplicates_dropped() {
+        let text = "Path: src/a.rs\n   fn a() {}\n\nPath: src/a.rs\n   fn a() {}\n\nNo relevant files found.";
+        let refined = refine_text(text, "", SnippetOptions::default());
+        assert_eq!(refined.matches("Path: src/a.rs").count(), 1);
+        assert!(refined.contains("   fn a() {}"));
+        assert!(!refined.contains("Symbol:"));
+    }
+}
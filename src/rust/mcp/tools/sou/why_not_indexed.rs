@@ -0,0 +1,377 @@
+// why-not-indexed 诊断
+// 文件能否被搜索到取决于多套互相独立的规则：ACE 的 exclude_patterns / 根目录 .gitignore /
+// text_extensions，本地 FTS 的 ignore 标准过滤 / 支持类型 / MAX_FILE_BYTES，
+// fast_context 的 ignore 文件与 exclude_paths。这里按后端逐条复核给定路径，
+// 并列出被排除目录的体积排行，便于有依据地调整排除规则。
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{fast_context, local, SouRuntimeConfig};
+use crate::mcp::tools::acemcp::mcp as acemcp;
+use crate::mcp::tools::acemcp::types::AcemcpConfig;
+use crate::mcp::tools::AcemcpTool;
+
+/// 体积排行默认条数
+pub(crate) const DEFAULT_TOP_EXCLUDED: usize = 10;
+
+/// 体积排行的总耗时预算（遍历与各目录统计合计）
+const TOP_EXCLUDED_BUDGET: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOutcome {
+    /// 规则放行
+    Pass,
+    /// 规则排除该路径
+    Fail,
+    /// 仅供参考，不影响是否收录
+    Info,
+}
+
+/// 单条规则的复核结果
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleCheck {
+    pub rule: String,
+    pub outcome: RuleOutcome,
+    pub detail: String,
+}
+
+impl RuleCheck {
+    pub(crate) fn pass(rule: &str, detail: impl Into<String>) -> Self {
+        Self::new(rule, RuleOutcome::Pass, detail)
+    }
+
+    pub(crate) fn fail(rule: &str, detail: impl Into<String>) -> Self {
+        Self::new(rule, RuleOutcome::Fail, detail)
+    }
+
+    pub(crate) fn info(rule: &str, detail: impl Into<String>) -> Self {
+        Self::new(rule, RuleOutcome::Info, detail)
+    }
+
+    fn new(rule: &str, outcome: RuleOutcome, detail: impl Into<String>) -> Self {
+        Self {
+            rule: rule.to_string(),
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendVerdict {
+    pub backend: String,
+    /// 所有规则均放行时为 true
+    pub included: bool,
+    pub checks: Vec<RuleCheck>,
+}
+
+impl BackendVerdict {
+    fn new(backend: &str, checks: Vec<RuleCheck>) -> Self {
+        Self {
+            backend: backend.to_string(),
+            included: checks
+                .iter()
+                .all(|check| check.outcome != RuleOutcome::Fail),
+            checks,
+        }
+    }
+}
+
+/// 被排除目录（不再向下展开）及其体积
+#[derive(Debug, Clone, Serialize)]
+pub struct ExcludedDirectory {
+    pub path: String,
+    pub bytes: u64,
+    pub files: u64,
+    pub rule: String,
+    /// 统计因条目上限提前结束
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhyNotIndexedReport {
+    pub project_root: String,
+    /// 相对项目根的路径（正斜杠）
+    pub path: String,
+    pub exists: bool,
+    pub backends: Vec<BackendVerdict>,
+    pub top_excluded_dirs: Vec<ExcludedDirectory>,
+    /// 排行因耗时预算或条目上限提前结束，可能遗漏部分目录
+    pub top_excluded_truncated: bool,
+}
+
+/// 解析诊断目标：相对路径按项目根拼接，绝对路径须位于项目根之内；
+/// 目标可能尚不存在而无法 canonicalize，因此直接拒绝 `..` 段，避免越出项目根
+fn resolve_target(project_root: &Path, path: &str) -> Result<(PathBuf, String)> {
+    let trimmed = path.trim().trim_start_matches("./");
+    let candidate = PathBuf::from(trimmed);
+    if candidate
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        anyhow::bail!("路径不能包含 `..`: {}", path);
+    }
+    let absolute = if candidate.is_absolute() {
+        candidate.canonicalize().unwrap_or(candidate)
+    } else {
+        project_root.join(trimmed)
+    };
+    let relative = absolute
+        .strip_prefix(project_root)
+        .with_context(|| format!("路径不在项目目录内: {}", path))?
+        .to_string_lossy()
+        .replace('\\', "/");
+    if relative.is_empty() {
+        anyhow::bail!("请提供项目内的文件或目录路径，而不是项目根目录");
+    }
+    Ok((absolute, relative))
+}
+
+/// 逐后端复核路径的收录规则；`exclude_paths` 为空时使用配置中的 fast_context/local 排除项
+pub(crate) async fn explain(
+    project_root: &str,
+    path: &str,
+    exclude_paths: Option<Vec<String>>,
+    top_excluded: usize,
+) -> Result<WhyNotIndexedReport> {
+    let root = PathBuf::from(project_root)
+        .canonicalize()
+        .with_context(|| format!("无法解析项目路径: {}", project_root))?;
+    let (absolute, relative) = resolve_target(&root, path)?;
+    let sou_config = SouRuntimeConfig::load()?;
+    let exclude_paths =
+        exclude_paths.unwrap_or_else(|| sou_config.fast_context.exclude_paths.clone());
+    let ace_config = AcemcpTool::get_acemcp_config().await?;
+
+    // 规则复核与目录遍历都是同步文件系统操作，放到阻塞线程池执行
+    tokio::task::spawn_blocking(move || {
+        build_report(
+            &root,
+            absolute,
+            relative,
+            &ace_config,
+            &sou_config,
+            &exclude_paths,
+            top_excluded,
+        )
+    })
+    .await
+    .context("why-not-indexed 诊断任务异常退出")
+}
+
+fn build_report(
+    root: &Path,
+    absolute: PathBuf,
+    relative: String,
+    ace_config: &AcemcpConfig,
+    sou_config: &SouRuntimeConfig,
+    exclude_paths: &[String],
+    top_excluded: usize,
+) -> WhyNotIndexedReport {
+    let backends = vec![
+        BackendVerdict::new("ace", acemcp::explain_ace_path(ace_config, root, &absolute)),
+        BackendVerdict::new(
+            "local",
+            if sou_config.local_enabled {
+                local::explain_path(root, &absolute, exclude_paths)
+            } else {
+                vec![RuleCheck::fail(
+                    "sou_local_enabled",
+                    "本地兜底已在配置中禁用",
+                )]
+            },
+        ),
+        BackendVerdict::new(
+            "fast_context",
+            fast_context::explain_path(root, &absolute, exclude_paths),
+        ),
+    ];
+    let (top_excluded_dirs, top_excluded_truncated) = if top_excluded == 0 {
+        (Vec::new(), false)
+    } else {
+        acemcp::top_excluded_directories(
+            ace_config,
+            root,
+            top_excluded,
+            Instant::now() + TOP_EXCLUDED_BUDGET,
+        )
+    };
+    WhyNotIndexedReport {
+        project_root: root.to_string_lossy().replace('\\', "/"),
+        path: relative,
+        exists: absolute.exists(),
+        backends,
+        top_excluded_dirs,
+        top_excluded_truncated,
+    }
+}
+
+/// MCP 文本输出
+pub(crate) fn format_report(report: &WhyNotIndexedReport) -> String {
+    let mut output = String::new();
+    let _ = writeln!(output, "[why-not-indexed] {}", report.path);
+    let _ = writeln!(output, "Project: {}", report.project_root);
+    if !report.exists {
+        let _ = writeln!(output, "注意: 路径当前不存在，以下仅按规则推断");
+    }
+    for verdict in &report.backends {
+        let _ = writeln!(
+            output,
+            "\n## {}: {}",
+            verdict.backend,
+            if verdict.included { "收录" } else { "排除" }
+        );
+        for check in &verdict.checks {
+            let mark = match check.outcome {
+                RuleOutcome::Pass => "✓",
+                RuleOutcome::Fail => "✗",
+                RuleOutcome::Info => "·",
+            };
+            let _ = writeln!(output, "{} {}: {}", mark, check.rule, check.detail);
+        }
+    }
+    if !report.top_excluded_dirs.is_empty() {
+        let _ = writeln!(output, "\n## 被排除目录体积排行（ACE 规则）");
+        for dir in &report.top_excluded_dirs {
+            let _ = writeln!(
+                output,
+                "- {} {} ({} 个文件{}) ← {}",
+                dir.path,
+                format_bytes(dir.bytes),
+                dir.files,
+                if dir.truncated { "，已截断" } else { "" },
+                dir.rule
+            );
+        }
+        if report.top_excluded_truncated {
+            let _ = writeln!(output, "（排行已按耗时预算截断，可能遗漏部分目录）");
+        }
+    }
+    output.trim_end().to_string()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn outcome_of<'a>(checks: &'a [RuleCheck], rule_prefix: &str) -> Option<&'a RuleCheck> {
+        checks
+            .iter()
+            .find(|check| check.rule.starts_with(rule_prefix))
+    }
+
+    #[test]
+    fn backends_report_the_rule_that_excludes_a_path() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        // ignore 只在 git 仓库内应用 .gitignore，与索引行为一致
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "generated/\n").unwrap();
+        fs::create_dir_all(root.join("generated")).unwrap();
+        fs::write(root.join("generated/api.rs"), "fn api() {}\n").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn lib() {}\n").unwrap();
+        fs::write(root.join("src/image.bin"), [0u8; 16]).unwrap();
+
+        let ignored = root.join("generated/api.rs");
+        let local_checks = local::explain_path(&root, &ignored, &[]);
+        assert_eq!(
+            outcome_of(&local_checks, "ignore").unwrap().outcome,
+            RuleOutcome::Fail
+        );
+        let fast_checks = fast_context::explain_path(&root, &ignored, &[]);
+        let ignore_check = outcome_of(&fast_checks, "ignore").unwrap();
+        assert_eq!(ignore_check.outcome, RuleOutcome::Fail);
+        assert!(ignore_check.detail.contains("generated/"));
+
+        let kept = root.join("src/lib.rs");
+        assert!(BackendVerdict::new("local", local::explain_path(&root, &kept, &[])).included);
+        let excluded = fast_context::explain_path(&root, &kept, &["src".to_string()]);
+        assert!(!BackendVerdict::new("fast_context", excluded).included);
+
+        let binary = local::explain_path(&root, &root.join("src/image.bin"), &[]);
+        assert!(!BackendVerdict::new("local", binary).included);
+    }
+
+    #[test]
+    fn ace_rules_and_excluded_directory_ranking() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::write(root.join("node_modules/pkg/index.js"), "x".repeat(4096)).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn lib() {}\n").unwrap();
+        fs::write(root.join("src/notes.xyz"), "plain\n").unwrap();
+        let config: crate::mcp::tools::acemcp::types::AcemcpConfig =
+            serde_json::from_value(serde_json::json!({ "text_extensions": [".rs", ".js"] }))
+                .unwrap();
+
+        let checks =
+            acemcp::explain_ace_path(&config, &root, &root.join("node_modules/pkg/index.js"));
+        let exclude = outcome_of(&checks, "exclude_patterns").unwrap();
+        assert_eq!(exclude.outcome, RuleOutcome::Fail);
+        assert!(exclude.detail.contains("node_modules"));
+        assert!(
+            BackendVerdict::new(
+                "ace",
+                acemcp::explain_ace_path(&config, &root, &root.join("src/lib.rs"))
+            )
+            .included
+        );
+        let unknown = acemcp::explain_ace_path(&config, &root, &root.join("src/notes.xyz"));
+        assert_eq!(
+            outcome_of(&unknown, "text_extensions").unwrap().outcome,
+            RuleOutcome::Fail
+        );
+
+        let (top, truncated) = acemcp::top_excluded_directories(
+            &config,
+            &root,
+            5,
+            Instant::now() + Duration::from_secs(30),
+        );
+        assert!(!truncated);
+        assert_eq!(
+            top.first().map(|dir| dir.path.as_str()),
+            Some("node_modules")
+        );
+        assert_eq!(top[0].files, 1);
+        assert_eq!(top[0].bytes, 4096);
+    }
+
+    #[test]
+    fn targets_outside_the_project_are_rejected() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let (absolute, relative) = resolve_target(&root, "./src/main.rs").unwrap();
+        assert_eq!(relative, "src/main.rs");
+        assert_eq!(absolute, root.join("src/main.rs"));
+        assert!(resolve_target(&root, "/definitely/outside.rs").is_err());
+        assert!(resolve_target(&root, "").is_err());
+        assert!(resolve_target(&root, "../sibling/secret.rs").is_err());
+        assert!(resolve_target(&root, "src/../../outside.rs").is_err());
+        let escaped = format!("{}/../outside.rs", root.display());
+        assert!(resolve_target(&root, &escaped).is_err());
+    }
+}
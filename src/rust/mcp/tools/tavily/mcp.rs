@@ -10,8 +10,12 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use super::types::*;
+use crate::network::resilience::{self, ResilienceError, RetryPolicy};
 use crate::{log_debug, log_important};
 
+/// 搜索/提取按次计费，只做少量重试；Retry-After 超过 10s 时直接返回错误
+const TAVILY_RETRY_POLICY: RetryPolicy = RetryPolicy::new(3, 500, 10_000);
+
 /// Tavily AI 搜索工具
 pub struct TavilyTool;
 
@@ -112,14 +116,21 @@ impl TavilyTool {
         log_debug!("Tavily Search 请求 URL: {}", url);
 
         // 发送请求
-        let response = client
+        let request_builder = client
             .post(&url)
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", api_key))
-            .json(&body)
-            .send()
+            .json(&body);
+        let endpoint = resilience::endpoint_key(resilience::SERVICE_TAVILY, &url);
+        let response = resilience::send_with_retry(&endpoint, TAVILY_RETRY_POLICY, request_builder)
             .await
-            .map_err(|e| {
+            .map_err(|error| {
+                let e = match error {
+                    ResilienceError::CircuitOpen(open) => {
+                        return McpError::internal_error(format!("Tavily 暂不可用: {}", open), None)
+                    }
+                    ResilienceError::Request(e) => e,
+                };
                 let msg = if e.is_timeout() {
                     "Tavily 搜索请求超时（30s）".to_string()
                 } else if e.is_connect() {
@@ -223,12 +234,13 @@ impl TavilyTool {
         log_debug!("Tavily Extract 请求 URL: {}", url);
 
         // 发送请求
-        let response = client
+        let request_builder = client
             .post(&url)
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", api_key))
-            .json(&body)
-            .send()
+            .json(&body);
+        let endpoint = resilience::endpoint_key(resilience::SERVICE_TAVILY, &url);
+        let response = resilience::send_with_retry(&endpoint, TAVILY_RETRY_POLICY, request_builder)
             .await
             .map_err(|e| McpError::internal_error(format!("Tavily 提取请求失败: {}", e), None))?;
 
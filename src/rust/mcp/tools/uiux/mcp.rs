@@ -476,6 +476,8 @@ async fn search_knowledge_via_fast_context(
     let sections = SouTool::search_sections(SouRequest {
         project_root_path: kb_dir,
         query: query.to_string(),
+        action: None,
+        path: None,
         backend: Some("fast_context".to_string()),
         tree_depth: Some(KB_FAST_CONTEXT_TREE_DEPTH),
         max_turns: Some(KB_FAST_CONTEXT_MAX_TURNS),
@@ -483,6 +485,10 @@ async fn search_knowledge_via_fast_context(
         max_commands: Some(KB_FAST_CONTEXT_MAX_COMMANDS),
         timeout_ms: None,
         exclude_paths: None,
+        workspace_group: None,
+        no_cache: None,
+        context_lines: None,
+        max_output_chars: None,
     })
     .await
     .map_err(|e| format!("sou 调用失败: {}", e))?;
@@ -580,6 +586,8 @@ async fn search_sou_sections(
     SouTool::search_sections(SouRequest {
         project_root_path: project_root_path.to_string(),
         query: query.to_string(),
+        action: None,
+        path: None,
         backend: None,
         tree_depth: None,
         max_turns: None,
@@ -587,6 +595,10 @@ async fn search_sou_sections(
         max_commands: None,
         timeout_ms: None,
         exclude_paths: None,
+        workspace_group: None,
+        no_cache: None,
+        context_lines: None,
+        max_output_chars: None,
     })
     .await
     .map_err(|e| format!("sou 调用失败: {}", e))
@@ -951,6 +963,8 @@ mod tests {
                 backend: "ace".to_string(),
                 location: "E:/demo/README.md:1-10".to_string(),
                 excerpt: "说明".to_string(),
+                section: None,
+                repo: None,
             }]),
             None,
             3,
@@ -966,6 +980,8 @@ mod tests {
                 backend: "fast_context".to_string(),
                 location: "E:/demo/Panel.vue:12-24".to_string(),
                 excerpt: "const open = ref(false)".to_string(),
+                section: None,
+                repo: None,
             }]),
             Some("Panel.vue"),
             3,
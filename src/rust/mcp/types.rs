@@ -1,7 +1,7 @@
 use chrono;
 use serde::{Deserialize, Serialize};
 
-use crate::mcp::tools::memory::CleanupApplyRequest;
+use crate::mcp::tools::memory::{CleanupApplyRequest, SharedPullRequest};
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct ZhiRequest {
@@ -50,7 +50,7 @@ pub struct MemoryConfigRequest {
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct JiyiRequest {
     #[schemars(
-        description = "操作类型：记忆(添加) | 回忆(查询) | 整理(去重) | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆)"
+        description = "操作类型：记忆(添加) | 回忆(按任务查询) | 整理(去重) | 列表(全部记忆) | 预览相似(检测相似度) | 配置(获取/更新) | 删除(移除记忆) | 固定(固定/取消固定记忆)"
     )]
     pub action: String,
     #[schemars(description = "项目路径（必需）")]
@@ -66,13 +66,13 @@ pub struct JiyiRequest {
     #[schemars(description = "配置参数（配置操作时使用）")]
     #[serde(default)]
     pub config: Option<MemoryConfigRequest>,
-    #[schemars(description = "记忆ID（删除操作时必需）")]
+    #[schemars(description = "记忆ID（删除、固定、归档、历史、对比、撤销操作时必需）")]
     #[serde(default)]
     pub memory_id: Option<String>,
     #[schemars(description = "清理阈值（预览整理时可选，默认使用同类更新阈值）")]
     #[serde(default)]
     pub threshold: Option<f64>,
-    #[schemars(description = "清理分类过滤（预览整理时可选）")]
+    #[schemars(description = "分类过滤（回忆、预览整理时可选）")]
     #[serde(default)]
     pub categories: Vec<String>,
     #[schemars(description = "是否允许跨分类清理（默认 false）")]
@@ -84,6 +84,70 @@ pub struct JiyiRequest {
     #[schemars(description = "备份文件名（恢复备份/导出备份时必需）")]
     #[serde(default)]
     pub backup_file: Option<String>,
+    #[schemars(description = "当前任务描述（回忆时可选），按相关度返回记忆")]
+    #[serde(default)]
+    pub query: Option<String>,
+    #[schemars(description = "回忆返回的非固定记忆条数上限（默认 20）")]
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[schemars(description = "回忆输出的估算 token 预算（默认 1500）")]
+    #[serde(default)]
+    pub token_budget: Option<usize>,
+    #[schemars(description = "是否固定记忆（记忆/固定操作时可选），固定记忆在回忆时总是返回")]
+    #[serde(default)]
+    pub pinned: Option<bool>,
+    #[schemars(
+        description = "记忆作用域：global（全局）| team 或 team:<名称>（团队）| project（项目，默认）| directory（project_path 所在子目录）。回忆时不传则合并全部作用域"
+    )]
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[schemars(
+        description = "共享记忆冲突处理（拉取共享时可选）：resolutions 为 [{id, keep: local|shared}]"
+    )]
+    #[serde(default)]
+    pub shared_pull: Option<SharedPullRequest>,
+    #[schemars(
+        description = "记忆来源（记忆操作时可选）：agent（默认）| user（用户在弹窗中要求记住）| context_block（memory_actions 中的条目）| import"
+    )]
+    #[serde(default)]
+    pub memory_source: Option<String>,
+    #[schemars(description = "记忆置信度 0~1（记忆操作时可选），默认按来源取值")]
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    #[schemars(
+        description = "过期时间（记忆操作时可选），RFC3339 或 YYYY-MM-DD；过期后不再参与回忆"
+    )]
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[schemars(description = "是否归档（归档操作时可选，默认 true）")]
+    #[serde(default)]
+    pub archived: Option<bool>,
+    #[schemars(
+        description = "超过该天数未被回忆的记忆提议归档（预览整理时可选，默认 90，0 表示不提议）"
+    )]
+    #[serde(default)]
+    pub unused_days: Option<u32>,
+    #[schemars(
+        description = "新记忆与已有记忆矛盾时的处理（记忆操作时可选）：不传则只返回矛盾报告不写入；supersede 取代并归档旧记忆；keep_both 两条都保留"
+    )]
+    #[serde(default)]
+    pub on_conflict: Option<String>,
+    #[schemars(description = "本次修改的原因（可选），记入变更日志")]
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[schemars(
+        description = "版本号（对比时为目标版本，默认最新；撤销时为要恢复到的版本，默认上一版本；0 表示第一次变更之前）"
+    )]
+    #[serde(default)]
+    pub version: Option<u32>,
+    #[schemars(description = "对比的起始版本（对比操作时可选，默认目标版本的上一版本）")]
+    #[serde(default)]
+    pub from_version: Option<u32>,
+    #[schemars(
+        description = "规则文件（导入规则、导出规则时可选）：项目根目录下的相对路径，导出时也可用格式名 agents | claude | gemini | cursorrules | cursor | copilot | windsurf"
+    )]
+    #[serde(default)]
+    pub rule_files: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
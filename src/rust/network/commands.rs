@@ -1,4 +1,5 @@
-// 代理配置相关的 Tauri 命令
+// 代理配置与远端调用诊断相关的 Tauri 命令
+use super::resilience::{self, BreakerStatus};
 use super::{proxy::ProxyType, ProxyDetector, ProxyInfo};
 use crate::config::{save_config, AppState, ProxyConfig};
 use crate::{log_debug, log_important};
@@ -104,3 +105,9 @@ pub async fn detect_available_proxy() -> Result<Option<ProxyInfo>, String> {
 
     Ok(proxy_info)
 }
+
+/// 获取远端调用熔断器状态（ACE / FastContext / Tavily / Context7 / Iconfont）
+#[tauri::command]
+pub async fn get_resilience_diagnostics() -> Result<Vec<BreakerStatus>, String> {
+    Ok(resilience::snapshot())
+}
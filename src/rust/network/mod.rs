@@ -1,11 +1,12 @@
 // 网络相关模块
-// 包含地理位置检测、代理检测和HTTP客户端构建功能
+// 包含地理位置检测、代理检测、HTTP客户端构建以及远端调用重试/熔断功能
 
 pub mod client;
 pub mod commands;
 pub mod geo;
 pub mod github_strategy;
 pub mod proxy;
+pub mod resilience;
 
 pub use client::{create_download_client, create_http_client, create_update_client};
 pub use geo::detect_geo_location;
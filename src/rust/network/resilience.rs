@@ -0,0 +1,723 @@
+// 远端调用韧性层
+// ACE、FastContext、Tavily、Context7、Iconfont 共用：
+// - 重试：指数退避 + jitter，优先遵循服务端 Retry-After
+// - 熔断：按端点（服务名@主机）统计连续失败，打开期间直接拒绝请求，冷却后放行一次探测
+// - 诊断：熔断器快照通过 get_resilience_diagnostics 暴露给前端
+//
+// 认证失败、参数错误等 4xx 说明服务本身可用，不计入熔断，也不重试。
+
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::log_important;
+
+pub const SERVICE_ACE: &str = "ace";
+pub const SERVICE_FAST_CONTEXT: &str = "fast_context";
+pub const SERVICE_TAVILY: &str = "tavily";
+pub const SERVICE_CONTEXT7: &str = "context7";
+pub const SERVICE_ICONFONT: &str = "iconfont";
+
+/// 连续失败达到该次数后打开熔断器
+const FAILURE_THRESHOLD: u32 = 5;
+/// 熔断打开后的默认冷却时间；Retry-After 更长时以其为准
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+/// 冷却时间上限，避免异常的 Retry-After 长期封禁端点
+const MAX_OPEN_DURATION: Duration = Duration::from_secs(600);
+
+/// 重试策略
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// 总尝试次数（含首次）
+    pub max_attempts: usize,
+    pub base_delay: Duration,
+    /// 单次等待上限；Retry-After 超过该值时不再等待，直接返回错误
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const fn new(max_attempts: usize, base_delay_ms: u64, max_delay_ms: u64) -> Self {
+        Self {
+            max_attempts,
+            base_delay: Duration::from_millis(base_delay_ms),
+            max_delay: Duration::from_millis(max_delay_ms),
+        }
+    }
+
+    /// 第 attempt 次失败（从 1 开始）后的退避时间：指数增长，落在 [50%, 100%] 区间的随机位置
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16) as u32;
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max_delay);
+        let half = ceiling / 2;
+        let jitter_range = (ceiling - half).as_millis() as u64;
+        half + Duration::from_millis(rand::thread_rng().gen_range(0..jitter_range.max(1)))
+    }
+}
+
+/// 失败类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    RateLimited,
+    Auth,
+    Client,
+    Server,
+    Timeout,
+    Network,
+    CircuitOpen,
+}
+
+impl FailureKind {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            429 => Self::RateLimited,
+            401 | 403 => Self::Auth,
+            408 => Self::Timeout,
+            400..=499 => Self::Client,
+            _ => Self::Server,
+        }
+    }
+
+    fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited | Self::Server | Self::Timeout | Self::Network
+        )
+    }
+
+    /// 是否说明端点不可用，需要计入熔断
+    fn trips_breaker(self) -> bool {
+        self.is_retryable()
+    }
+}
+
+/// 已分类的上游错误；各工具可直接返回它，供重试与熔断判断
+#[derive(Debug, Clone)]
+pub struct UpstreamError {
+    pub kind: FailureKind,
+    pub status: Option<u16>,
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+impl UpstreamError {
+    pub fn from_status(status: StatusCode, headers: &HeaderMap, body: &str) -> Self {
+        let body = body.trim();
+        Self {
+            kind: FailureKind::from_status(status.as_u16()),
+            status: Some(status.as_u16()),
+            retry_after: parse_retry_after(headers),
+            message: if body.is_empty() {
+                format!("HTTP {}", status.as_u16())
+            } else {
+                format!("HTTP {} {}", status.as_u16(), body)
+            },
+        }
+    }
+
+    pub fn from_reqwest(error: &reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            FailureKind::Timeout
+        } else if let Some(status) = error.status() {
+            FailureKind::from_status(status.as_u16())
+        } else {
+            FailureKind::Network
+        };
+        Self {
+            kind,
+            status: error.status().map(|status| status.as_u16()),
+            retry_after: None,
+            message: format!("{}", error),
+        }
+    }
+
+    fn circuit_open(endpoint: &str, remaining: Duration) -> Self {
+        Self {
+            kind: FailureKind::CircuitOpen,
+            status: None,
+            retry_after: Some(remaining),
+            message: format!(
+                "{} 熔断中，{}s 后重试（连续失败过多）",
+                endpoint,
+                remaining.as_secs().max(1)
+            ),
+        }
+    }
+}
+
+/// 非 anyhow 调用方（reqwest 原始错误需要保留）的错误类型
+#[derive(Debug)]
+pub enum ResilienceError {
+    CircuitOpen(UpstreamError),
+    Request(reqwest::Error),
+}
+
+impl fmt::Display for ResilienceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CircuitOpen(error) => write!(f, "{}", error),
+            Self::Request(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ResilienceError {}
+
+/// 从 anyhow 错误中提取分类；未分类错误按历史关键字兜底判断
+fn classify(error: &anyhow::Error) -> (Option<FailureKind>, Option<Duration>) {
+    if let Some(upstream) = error.downcast_ref::<UpstreamError>() {
+        return (Some(upstream.kind), upstream.retry_after);
+    }
+    if let Some(request) = error.downcast_ref::<reqwest::Error>() {
+        return (Some(UpstreamError::from_reqwest(request).kind), None);
+    }
+    let message = error.to_string().to_lowercase();
+    let kind = if message.contains("timeout") || message.contains("超时") {
+        Some(FailureKind::Timeout)
+    } else if message.contains("connection")
+        || message.contains("network")
+        || message.contains("temporary")
+        || message.contains("连接失败")
+    {
+        Some(FailureKind::Network)
+    } else {
+        None
+    };
+    (kind, None)
+}
+
+// ---------------- 熔断器 ----------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// 半开状态下是否已有探测请求在途
+    probe_in_flight: bool,
+    /// 当前探测请求的编号，用于判断 `ProbeGuard` 释放的是否仍是自己的探测位
+    probe_id: u64,
+    trips: u64,
+    last_error: Option<String>,
+    last_failure_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            open_until: None,
+            probe_in_flight: false,
+            probe_id: 0,
+            trips: 0,
+            last_error: None,
+            last_failure_at: None,
+        }
+    }
+}
+
+/// 熔断器诊断快照
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub endpoint: String,
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// 打开状态剩余冷却时间
+    pub open_remaining_ms: Option<u64>,
+    pub trips: u64,
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn breakers() -> &'static Mutex<HashMap<String, Breaker>> {
+    static BREAKERS: OnceLock<Mutex<HashMap<String, Breaker>>> = OnceLock::new();
+    BREAKERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 端点标识：服务名@主机，切换服务地址后使用新的熔断器
+pub fn endpoint_key(service: &str, url: &str) -> String {
+    match reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+    {
+        Some(host) => format!("{}@{}", service, host),
+        None => service.to_string(),
+    }
+}
+
+/// 半开探测位的持有凭证
+///
+/// 探测请求的 future 可能在记录结果前被丢弃（外层 `tokio::time::timeout`、调用方提前返回等），
+/// 此时探测位永远不会被 `record_success` / `record_failure` 清除，熔断器会一直拒绝请求。
+/// 凭证在 Drop 时释放仍属于自己的探测位；已记录结果时释放为空操作。
+#[must_use = "探测位在凭证被丢弃时释放，应持有到记录请求结果之后"]
+#[derive(Debug)]
+pub struct ProbeGuard {
+    probe: Option<(String, u64)>,
+}
+
+impl ProbeGuard {
+    fn none() -> Self {
+        Self { probe: None }
+    }
+
+    fn claim(endpoint: &str, breaker: &mut Breaker) -> Self {
+        breaker.probe_in_flight = true;
+        breaker.probe_id += 1;
+        Self {
+            probe: Some((endpoint.to_string(), breaker.probe_id)),
+        }
+    }
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        let Some((endpoint, probe_id)) = self.probe.take() else {
+            return;
+        };
+        let Ok(mut breakers) = breakers().lock() else {
+            return;
+        };
+        if let Some(breaker) = breakers.get_mut(&endpoint) {
+            if breaker.probe_in_flight && breaker.probe_id == probe_id {
+                breaker.probe_in_flight = false;
+            }
+        }
+    }
+}
+
+/// 请求前检查熔断器；打开期间返回剩余冷却时间
+///
+/// 半开状态放行的探测请求由返回的 `ProbeGuard` 持有探测位，调用方须持有到记录结果之后。
+pub fn check_circuit(endpoint: &str) -> Result<ProbeGuard, UpstreamError> {
+    let mut breakers = breakers().lock().unwrap();
+    let breaker = breakers.entry(endpoint.to_string()).or_default();
+    match breaker.state {
+        BreakerState::Closed => Ok(ProbeGuard::none()),
+        BreakerState::Open => {
+            let now = Instant::now();
+            match breaker.open_until {
+                Some(until) if until > now => {
+                    Err(UpstreamError::circuit_open(endpoint, until - now))
+                }
+                _ => {
+                    breaker.state = BreakerState::HalfOpen;
+                    log_important!(
+                        info,
+                        "[resilience] 熔断冷却结束，放行探测请求: {}",
+                        endpoint
+                    );
+                    Ok(ProbeGuard::claim(endpoint, breaker))
+                }
+            }
+        }
+        BreakerState::HalfOpen if breaker.probe_in_flight => Err(UpstreamError::circuit_open(
+            endpoint,
+            Duration::from_secs(1),
+        )),
+        BreakerState::HalfOpen => Ok(ProbeGuard::claim(endpoint, breaker)),
+    }
+}
+
+pub fn record_success(endpoint: &str) {
+    let mut breakers = breakers().lock().unwrap();
+    let breaker = breakers.entry(endpoint.to_string()).or_default();
+    if breaker.state != BreakerState::Closed {
+        log_important!(info, "[resilience] 端点恢复，关闭熔断: {}", endpoint);
+    }
+    breaker.state = BreakerState::Closed;
+    breaker.consecutive_failures = 0;
+    breaker.open_until = None;
+    breaker.probe_in_flight = false;
+}
+
+/// 记录失败；只有端点不可用类失败计入熔断。限流且带 Retry-After 时直接打开到指定时间
+pub fn record_failure(endpoint: &str, error: &UpstreamError) {
+    let mut breakers = breakers().lock().unwrap();
+    let breaker = breakers.entry(endpoint.to_string()).or_default();
+    breaker.probe_in_flight = false;
+    if !error.kind.trips_breaker() {
+        // 4xx 说明端点可达；半开探测得到该结果也视为恢复
+        if breaker.state == BreakerState::HalfOpen {
+            breaker.state = BreakerState::Closed;
+            breaker.consecutive_failures = 0;
+        }
+        return;
+    }
+    breaker.consecutive_failures += 1;
+    breaker.last_error = Some(error.message.chars().take(200).collect());
+    breaker.last_failure_at = Some(chrono::Utc::now());
+
+    // Retry-After: 0 表示可以立即重试，不能因此打开熔断，否则紧接着的重试会被自己拦下
+    let rate_limited_pause = error
+        .retry_after
+        .filter(|pause| error.kind == FailureKind::RateLimited && !pause.is_zero());
+    let should_open = breaker.state == BreakerState::HalfOpen
+        || breaker.consecutive_failures >= FAILURE_THRESHOLD
+        || rate_limited_pause.is_some();
+    if should_open {
+        // 冷却时间与重试等待一致，不额外放大，重试醒来时熔断器已进入半开
+        let duration = rate_limited_pause
+            .unwrap_or(OPEN_DURATION)
+            .min(MAX_OPEN_DURATION);
+        if breaker.state != BreakerState::Open {
+            breaker.trips += 1;
+        }
+        breaker.state = BreakerState::Open;
+        breaker.open_until = Some(Instant::now() + duration);
+        log_important!(
+            warn,
+            "[resilience] 打开熔断: endpoint={}, failures={}, cooldown={}s, error={}",
+            endpoint,
+            breaker.consecutive_failures,
+            duration.as_secs(),
+            error.message
+        );
+    }
+}
+
+/// 服务下任一端点处于打开状态（冷却未结束）时返回剩余时间；供 sou auto 跳过后端
+pub fn open_remaining(service: &str) -> Option<Duration> {
+    let prefix = format!("{}@", service);
+    let now = Instant::now();
+    let breakers = breakers().lock().ok()?;
+    breakers
+        .iter()
+        .filter(|(endpoint, _)| endpoint.as_str() == service || endpoint.starts_with(&prefix))
+        .filter(|(_, breaker)| breaker.state == BreakerState::Open)
+        .filter_map(|(_, breaker)| breaker.open_until.filter(|until| *until > now))
+        .map(|until| until - now)
+        .max()
+}
+
+pub fn snapshot() -> Vec<BreakerStatus> {
+    let now = Instant::now();
+    let Ok(breakers) = breakers().lock() else {
+        return Vec::new();
+    };
+    let mut statuses = breakers
+        .iter()
+        .map(|(endpoint, breaker)| BreakerStatus {
+            endpoint: endpoint.clone(),
+            state: breaker.state,
+            consecutive_failures: breaker.consecutive_failures,
+            open_remaining_ms: breaker
+                .open_until
+                .filter(|until| breaker.state == BreakerState::Open && *until > now)
+                .map(|until| (until - now).as_millis() as u64),
+            trips: breaker.trips,
+            last_error: breaker.last_error.clone(),
+            last_failure_at: breaker.last_failure_at,
+        })
+        .collect::<Vec<_>>();
+    statuses.sort_by(|left, right| left.endpoint.cmp(&right.endpoint));
+    statuses
+}
+
+// ---------------- 重试执行 ----------------
+
+/// 计算下一次等待时间；返回 None 表示不应再等待（Retry-After 超过策略上限）
+fn next_delay(
+    policy: &RetryPolicy,
+    attempt: usize,
+    retry_after: Option<Duration>,
+) -> Option<Duration> {
+    match retry_after {
+        Some(delay) if delay > policy.max_delay => None,
+        Some(delay) => Some(delay),
+        None => Some(policy.backoff(attempt)),
+    }
+}
+
+/// 带熔断与重试执行任意异步调用；错误可为 `UpstreamError`、`reqwest::Error` 或其他 anyhow 错误
+pub async fn call<T, F, Fut>(endpoint: &str, policy: RetryPolicy, mut f: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut attempt = 0usize;
+    loop {
+        let probe = check_circuit(endpoint)?;
+        attempt += 1;
+        let error = match f().await {
+            Ok(value) => {
+                record_success(endpoint);
+                return Ok(value);
+            }
+            Err(error) => error,
+        };
+        let (kind, retry_after) = classify(&error);
+        if let Some(kind) = kind {
+            record_failure(
+                endpoint,
+                &UpstreamError {
+                    kind,
+                    status: None,
+                    retry_after,
+                    message: error.to_string(),
+                },
+            );
+        }
+        // 未分类错误（解析失败、业务错误等）不影响熔断，但需释放半开探测位
+        drop(probe);
+        let retryable = kind.map(FailureKind::is_retryable).unwrap_or(false);
+        if !retryable || attempt >= policy.max_attempts {
+            return Err(error);
+        }
+        let Some(delay) = next_delay(&policy, attempt, retry_after) else {
+            return Err(error);
+        };
+        log::debug!(
+            "[resilience] 请求失败，准备重试({}/{}): endpoint={}, wait={}ms, error={}",
+            attempt,
+            policy.max_attempts,
+            endpoint,
+            delay.as_millis(),
+            error
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// 发送 HTTP 请求：网络错误、429 与 5xx 按策略重试；最终的非成功响应原样返回，由调用方格式化错误信息
+pub async fn send_with_retry(
+    endpoint: &str,
+    policy: RetryPolicy,
+    request: RequestBuilder,
+) -> Result<Response, ResilienceError> {
+    let mut request = request;
+    let mut attempt = 0usize;
+    loop {
+        let _probe = check_circuit(endpoint).map_err(ResilienceError::CircuitOpen)?;
+        attempt += 1;
+        // 无法克隆（流式请求体）时只发送一次
+        let next_request = if attempt < policy.max_attempts {
+            request.try_clone()
+        } else {
+            None
+        };
+        let (failure, outcome) = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                record_success(endpoint);
+                return Ok(response);
+            }
+            Ok(response) => {
+                let failure = UpstreamError::from_status(response.status(), response.headers(), "");
+                (failure, Ok(response))
+            }
+            Err(error) => (UpstreamError::from_reqwest(&error), Err(error)),
+        };
+        record_failure(endpoint, &failure);
+        let delay = next_delay(&policy, attempt, failure.retry_after);
+        match (failure.kind.is_retryable(), next_request, delay) {
+            (true, Some(next_request), Some(delay)) => {
+                log::debug!(
+                    "[resilience] 请求失败，准备重试({}/{}): endpoint={}, wait={}ms, error={}",
+                    attempt,
+                    policy.max_attempts,
+                    endpoint,
+                    delay.as_millis(),
+                    failure
+                );
+                tokio::time::sleep(delay).await;
+                request = next_request;
+            }
+            _ => return outcome.map_err(ResilienceError::Request),
+        }
+    }
+}
+
+/// 解析 Retry-After：支持秒数与 HTTP-date 两种格式
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delta.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn failure(kind: FailureKind, retry_after: Option<Duration>) -> UpstreamError {
+        UpstreamError {
+            kind,
+            status: None,
+            retry_after,
+            message: format!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_bounded_jitter() {
+        let policy = RetryPolicy::new(5, 1000, 8000);
+        for attempt in 1..=6 {
+            let ceiling = Duration::from_millis((1000u64 << (attempt - 1)).min(8000));
+            let delay = policy.backoff(attempt);
+            assert!(
+                delay >= ceiling / 2 && delay <= ceiling,
+                "attempt={} delay={:?}",
+                attempt,
+                delay
+            );
+        }
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(7)));
+
+        let future = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&future).unwrap());
+        let parsed = parse_retry_after(&headers).unwrap();
+        assert!(parsed > Duration::from_secs(25) && parsed <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_ignores_client_errors() {
+        let endpoint = "test-breaker@example.invalid";
+        for _ in 0..10 {
+            record_failure(endpoint, &failure(FailureKind::Auth, None));
+        }
+        assert!(check_circuit(endpoint).is_ok());
+
+        for _ in 0..FAILURE_THRESHOLD {
+            record_failure(endpoint, &failure(FailureKind::Server, None));
+        }
+        let error = check_circuit(endpoint).unwrap_err();
+        assert_eq!(error.kind, FailureKind::CircuitOpen);
+        assert!(open_remaining("test-breaker").is_some());
+        assert!(snapshot()
+            .iter()
+            .any(|status| status.endpoint == endpoint && status.state == BreakerState::Open));
+
+        record_success(endpoint);
+        assert!(check_circuit(endpoint).is_ok());
+        assert!(open_remaining("test-breaker").is_none());
+    }
+
+    #[test]
+    fn rate_limit_with_retry_after_opens_breaker_immediately() {
+        let endpoint = "test-rate-limit@example.invalid";
+        record_failure(
+            endpoint,
+            &failure(FailureKind::RateLimited, Some(Duration::from_secs(20))),
+        );
+        let remaining = open_remaining("test-rate-limit").unwrap();
+        assert!(remaining > Duration::from_secs(15));
+    }
+
+    #[tokio::test]
+    async fn retry_after_zero_retries_without_opening_breaker() {
+        let policy = RetryPolicy::new(3, 1, 5);
+        let mut attempts = 0;
+        let result = call("test-retry-after-zero", policy, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt == 1 {
+                    Err(anyhow::Error::new(failure(
+                        FailureKind::RateLimited,
+                        Some(Duration::ZERO),
+                    )))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+        assert!(open_remaining("test-retry-after-zero").is_none());
+    }
+
+    #[tokio::test]
+    async fn call_retries_transient_errors_but_not_client_errors() {
+        let policy = RetryPolicy::new(3, 1, 5);
+        let mut attempts = 0;
+        let result = call("test-call-transient", policy, || {
+            attempts += 1;
+            let current = attempts;
+            async move {
+                if current < 3 {
+                    Err(anyhow::Error::new(failure(FailureKind::Network, None)))
+                } else {
+                    Ok(current)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = call("test-call-client", policy, || {
+            attempts += 1;
+            async { Err(anyhow::Error::new(failure(FailureKind::Client, None))) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn dropped_probe_releases_half_open_breaker() {
+        let endpoint = "test-dropped-probe@example.invalid";
+        for _ in 0..FAILURE_THRESHOLD {
+            record_failure(endpoint, &failure(FailureKind::Server, None));
+        }
+        breakers()
+            .lock()
+            .unwrap()
+            .get_mut(endpoint)
+            .unwrap()
+            .open_until = Some(Instant::now());
+
+        // 探测请求挂起，外层超时丢弃 future，结果未被记录
+        let policy = RetryPolicy::new(1, 1, 5);
+        let timed_out = tokio::time::timeout(
+            Duration::from_millis(10),
+            call(endpoint, policy, || {
+                std::future::pending::<anyhow::Result<()>>()
+            }),
+        )
+        .await;
+        assert!(timed_out.is_err());
+
+        let result = call(endpoint, policy, || async { Ok(1) }).await;
+        assert_eq!(result.unwrap(), 1);
+        assert!(snapshot()
+            .iter()
+            .any(|status| status.endpoint == endpoint && status.state == BreakerState::Closed));
+    }
+}
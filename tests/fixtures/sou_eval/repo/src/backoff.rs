@@ -0,0 +1,7 @@
+use std::time::Duration;
+
+/// 指数退避：第 attempt 次重试的等待时间，上限为 max_delay
+pub fn exponential_backoff(attempt: u32, base: Duration, max_delay: Duration) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt))
+        .min(max_delay)
+}
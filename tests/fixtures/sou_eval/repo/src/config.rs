@@ -0,0 +1,11 @@
+use std::collections::HashMap;
+
+/// 解析 `key = value` 形式的配置文本，忽略空行与 `#` 注释
+pub fn parse_config(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
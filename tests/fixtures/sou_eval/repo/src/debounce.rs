@@ -0,0 +1,26 @@
+use std::time::{Duration, Instant};
+
+/// 文件变更防抖：窗口内的连续事件只触发一次
+pub struct Debouncer {
+    window: Duration,
+    last_fired: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_fired: None,
+        }
+    }
+
+    pub fn should_fire(&mut self, now: Instant) -> bool {
+        let fire = self
+            .last_fired
+            .map_or(true, |last| now.duration_since(last) >= self.window);
+        if fire {
+            self.last_fired = Some(now);
+        }
+        fire
+    }
+}
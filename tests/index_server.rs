@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use sanshu::mcp::tools::acemcp::index_server::{IndexServer, IndexServerOptions};
+use sanshu::mcp::tools::acemcp::remote::{BlobItem, RemoteIndexBackend, SelfHostedBackend};
+
+#[tokio::test]
+async fn self_hosted_backend_round_trips_against_index_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = Arc::new(
+        IndexServer::bind(
+            "127.0.0.1:0",
+            IndexServerOptions {
+                db_path: dir.path().join("index.db"),
+                token: Some("secret".to_string()),
+                embedding: None,
+            },
+        )
+        .unwrap(),
+    );
+    let base_url = format!("http://{}", server.local_addr().unwrap());
+    let handle = {
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || server.serve())
+    };
+
+    let backend = SelfHostedBackend::new(
+        base_url.clone(),
+        "secret".to_string(),
+        reqwest::Client::new(),
+    );
+    let blobs = vec![
+        BlobItem {
+            path: "src/payment.rs".to_string(),
+            content: "pub fn settle_invoice(amount: u64) -> u64 {\n    amount\n}\n".to_string(),
+        },
+        BlobItem {
+            path: "src/readme.md".to_string(),
+            content: "unrelated notes\n".to_string(),
+        },
+    ];
+    let names = backend.upload_blobs(&blobs).await.unwrap();
+    assert_eq!(names.len(), 2);
+
+    let mut probe = names.clone();
+    probe.push("missing-blob".to_string());
+    assert_eq!(
+        backend.find_missing(&probe).await.unwrap(),
+        vec!["missing-blob".to_string()]
+    );
+
+    let text = backend.search("settle_invoice", &names).await.unwrap();
+    assert!(text.contains("Path: src/payment.rs"), "{}", text);
+
+    assert_eq!(backend.delete_blobs(&names[..1]).await.unwrap(), 1);
+    assert!(backend
+        .search("settle_invoice", &names)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let unauthorized =
+        SelfHostedBackend::new(base_url, "wrong".to_string(), reqwest::Client::new());
+    let error = unauthorized.find_missing(&names).await.unwrap_err();
+    assert!(error.to_string().contains("401"), "{}", error);
+
+    server.unblock();
+    handle.join().unwrap();
+}
@@ -92,6 +92,8 @@ async fn bench_fast_context_against_real_project() {
         let result = SouTool::search_context(SouRequest {
             project_root_path: target.clone(),
             query: case.query.to_string(),
+            action: None,
+            path: None,
             backend: Some("fast_context".to_string()),
             tree_depth: Some(3),
             max_turns: Some(3),
@@ -105,6 +107,10 @@ async fn bench_fast_context_against_real_project() {
                 "logs".to_string(),
                 "node_modules".to_string(),
             ]),
+            workspace_group: None,
+            no_cache: Some(true),
+            context_lines: None,
+            max_output_chars: None,
         })
         .await
         .expect("sou fast_context 调用不应出现 MCP 内部错误");
@@ -38,6 +38,8 @@ async fn live_fast_context_search_smoke() {
     let result = SouTool::search_context(SouRequest {
         project_root_path: project_root,
         query,
+        action: None,
+        path: None,
         backend: Some("fast_context".to_string()),
         tree_depth: Some(tree_depth),
         max_turns: Some(1),
@@ -50,6 +52,10 @@ async fn live_fast_context_search_smoke() {
             "dist".to_string(),
             ".git".to_string(),
         ]),
+        workspace_group: None,
+        no_cache: Some(true),
+        context_lines: None,
+        max_output_chars: None,
     })
     .await
     .expect("sou fast_context 调用不应出现 MCP 内部错误");